@@ -298,12 +298,20 @@ public class DDlogRecord {
         return fromHandle(DDlogAPI.ddlog_map(handles));
     }
 
+    public boolean isBool() {
+        return DDlogAPI.ddlog_is_bool(this.checkHandle());
+    }
+
     public boolean getBoolean() {
         if (!DDlogAPI.ddlog_is_bool(this.checkHandle()))
             throw new RuntimeException("Value is not boolean");
         return DDlogAPI.ddlog_get_bool(this.handle);
     }
 
+    public boolean isInt() {
+        return DDlogAPI.ddlog_is_int(this.checkHandle());
+    }
+
     public BigInteger getInt() {
         if (!DDlogAPI.ddlog_is_int(this.checkHandle()))
             throw new RuntimeException("Value is not an integer type (bigint, bit<>, or signed<>)");
@@ -315,6 +323,10 @@ public class DDlogRecord {
         return new BigInteger(buf);
     }
 
+    public boolean isTuple() {
+        return DDlogAPI.ddlog_is_tuple(this.checkHandle());
+    }
+
     public int getTupleSize() {
         if (!DDlogAPI.ddlog_is_tuple(this.checkHandle()))
             throw new RuntimeException("Value is not a tuple");
@@ -327,6 +339,10 @@ public class DDlogRecord {
         return fromSharedHandle(DDlogAPI.ddlog_get_tuple_field(this.handle, index));
     }
 
+    public boolean isVector() {
+        return DDlogAPI.ddlog_is_vector(this.checkHandle());
+    }
+
     public int getVectorSize() {
         if (!DDlogAPI.ddlog_is_vector(this.checkHandle()))
             throw new RuntimeException("Value is not a vector");
@@ -339,6 +355,10 @@ public class DDlogRecord {
         return fromSharedHandle(DDlogAPI.ddlog_get_vector_elem(this.handle, index));
     }
 
+    public boolean isSet() {
+        return DDlogAPI.ddlog_is_set(this.checkHandle());
+    }
+
     public int getSetSize() {
         if (!DDlogAPI.ddlog_is_set(this.checkHandle()))
             throw new RuntimeException("Value is not a set");
@@ -351,6 +371,10 @@ public class DDlogRecord {
         return fromSharedHandle(DDlogAPI.ddlog_get_set_elem(this.handle, index));
     }
 
+    public boolean isMap() {
+        return DDlogAPI.ddlog_is_map(this.checkHandle());
+    }
+
     public int getMapSize() {
         if (!DDlogAPI.ddlog_is_map(this.checkHandle()))
             throw new RuntimeException("Value is not a map");
@@ -369,18 +393,30 @@ public class DDlogRecord {
         return fromSharedHandle(DDlogAPI.ddlog_get_map_val(this.handle, index));
     }
 
+    public boolean isString() {
+        return DDlogAPI.ddlog_is_string(this.checkHandle());
+    }
+
     public String getString() {
         if (!DDlogAPI.ddlog_is_string(this.checkHandle()))
             throw new RuntimeException("Value is not a string");
         return DDlogAPI.ddlog_get_str(this.handle);
     }
 
+    public boolean isFloat() {
+        return DDlogAPI.ddlog_is_float(this.checkHandle());
+    }
+
     public float getFloat() {
         if (!DDlogAPI.ddlog_is_float(this.checkHandle()))
             throw new RuntimeException("Value is not a float");
         return DDlogAPI.ddlog_get_float(this.handle);
     }
 
+    public boolean isDouble() {
+        return DDlogAPI.ddlog_is_double(this.checkHandle());
+    }
+
     public double getDouble() {
         if (!DDlogAPI.ddlog_is_double(this.checkHandle()))
             throw new RuntimeException("Value is not a double");
@@ -397,6 +433,18 @@ public class DDlogRecord {
         return DDlogAPI.ddlog_get_constructor(this.handle);
     }
 
+    /**
+     * Number of fields in a struct.
+     */
+    public int getStructSize() throws DDlogException {
+        if (!this.isStruct())
+            throw new RuntimeException("Value is not a struct");
+        int size = 0;
+        while (DDlogAPI.ddlog_get_struct_field(this.handle, size) != 0)
+            size++;
+        return size;
+    }
+
     public DDlogRecord getStructField(int index) throws DDlogException {
         if (!this.isStruct())
             throw new RuntimeException("Value is not a struct");
@@ -566,6 +566,8 @@ public class Test {
         }
         builder.insert_LI(true, (byte)-1, "something");
         builder.insert_L0I(false, (byte)-2, "else");
+        builder.insert_or_update_L0I(true, (byte)1, "one");
+        builder.modify_L0I(true, DDlogRecord.makeNamedStruct("L0I", new String[]{"s"}, new DDlogRecord("modified")));
         {
             Boolean[] v = new Boolean[] {true, false, true};
             builder.insert_MI(Arrays.asList(v));
@@ -751,6 +753,8 @@ public class Test {
             builder.insert_ZI20(b);
         }
         builder.insert_ZI21(13);
+        builder.insert_ZI21(14);
+        builder.delete_key_ZI21(14);
         builder.insert_ZJ(5.0);
         builder.insert_ZK(5.0f);
         builder.applyUpdates(this.api);
@@ -0,0 +1,167 @@
+//! Tab completion for the interactive command prompt.
+
+use rustyline::completion::Completer;
+
+/// Keywords that can start a command.
+const COMMAND_KEYWORDS: &[&str] = &[
+    "clear",
+    "commit",
+    "delete",
+    "delete_key",
+    "dump",
+    "dump_index",
+    "echo",
+    "exit",
+    "include",
+    "insert",
+    "insert_or_update",
+    "log_level",
+    "modify",
+    "mssleep",
+    "profile",
+    "query_index",
+    "rollback",
+    "start",
+    "timestamp",
+];
+
+/// Keywords that must be followed by a relation name.
+const RELATION_KEYWORDS: &[&str] = &[
+    "clear",
+    "delete",
+    "delete_key",
+    "dump",
+    "insert",
+    "insert_or_update",
+    "modify",
+];
+
+/// Keywords that must be followed by an index name.
+const INDEX_KEYWORDS: &[&str] = &["dump_index", "query_index"];
+
+/// Completes command keywords at the start of a command, and relation and
+/// index names in positions where the parser expects them.
+#[derive(Clone, Debug, Default)]
+pub struct CommandCompleter {
+    relations: Vec<String>,
+    indexes: Vec<String>,
+}
+
+impl CommandCompleter {
+    /// Create a completer for a program with the given relation and index
+    /// names.
+    pub fn new<R, I>(relations: R, indexes: I) -> Self
+    where
+        R: IntoIterator,
+        R::Item: Into<String>,
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut relations: Vec<String> = relations.into_iter().map(Into::into).collect();
+        relations.sort();
+        relations.dedup();
+        let mut indexes: Vec<String> = indexes.into_iter().map(Into::into).collect();
+        indexes.sort();
+        indexes.dedup();
+        Self { relations, indexes }
+    }
+
+    /// Compute completion candidates for the word that ends at byte
+    /// offset `pos` in `line`.  Returns the offset where the word starts
+    /// along with candidates sorted alphabetically.
+    pub fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let line = &line[..pos];
+        let start = line
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !is_ident_char(*c))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let prefix = &line[start..];
+
+        // Only look at the command the word belongs to.
+        let command = &line[..start];
+        let command = command.rfind(';').map_or(command, |i| &command[i + 1..]);
+        let prev = command.split_whitespace().last();
+
+        // A comma outside of a record separates updates in a list.
+        let new_command = prev.map_or(true, |p| p.ends_with(',') && nesting_depth(command) == 0);
+
+        let names: Vec<&str> = match prev {
+            _ if new_command => COMMAND_KEYWORDS.to_vec(),
+            Some(p) if INDEX_KEYWORDS.contains(&p) => {
+                self.indexes.iter().map(String::as_str).collect()
+            }
+            Some(p) if RELATION_KEYWORDS.contains(&p) => {
+                self.relations.iter().map(String::as_str).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        let candidates = names
+            .into_iter()
+            .filter(|n| n.starts_with(prefix))
+            .map(str::to_string)
+            .collect();
+        (start, candidates)
+    }
+}
+
+impl Completer for CommandCompleter {
+    fn complete(&self, line: &str, pos: usize) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+/// Characters that can appear in a (possibly scoped) relation or index name.
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == ':'
+}
+
+/// Number of brackets left open in `text`.
+fn nesting_depth(text: &str) -> isize {
+    text.chars().fold(0, |depth, c| match c {
+        '(' | '[' | '{' => depth + 1,
+        ')' | ']' | '}' => depth - 1,
+        _ => depth,
+    })
+}
+
+#[test]
+fn test_complete_keywords() {
+    let completer = CommandCompleter::default();
+    assert_eq!(
+        completer.candidates("com", 3),
+        (0, vec!["commit".to_string()])
+    );
+    assert_eq!(
+        completer.candidates("start; dump_", 12),
+        (7, vec!["dump_index".to_string()])
+    );
+    assert_eq!(
+        completer.candidates("insert R(1), del", 16),
+        (13, vec!["delete".to_string(), "delete_key".to_string()])
+    );
+}
+
+#[test]
+fn test_complete_names() {
+    let completer = CommandCompleter::new(
+        vec!["Rel1", "Rel2", "NB::Logical_Router"],
+        vec!["Rel1_by_id"],
+    );
+    assert_eq!(
+        completer.candidates("insert Re", 9),
+        (7, vec!["Rel1".to_string(), "Rel2".to_string()])
+    );
+    assert_eq!(
+        completer.candidates("dump NB::L", 10),
+        (5, vec!["NB::Logical_Router".to_string()])
+    );
+    assert_eq!(
+        completer.candidates("query_index R", 13),
+        (12, vec!["Rel1_by_id".to_string()])
+    );
+    // No completion inside records.
+    assert_eq!(completer.candidates("insert Rel1(R", 13), (12, vec![]));
+    assert_eq!(completer.candidates("insert Rel1(1, R", 16), (15, vec![]));
+}
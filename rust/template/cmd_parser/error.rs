@@ -0,0 +1,196 @@
+//! Parse errors annotated with their location in the input.
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use nom::Context;
+use nom::Err;
+
+/// Maximum number of characters of the offending line included in an
+/// error message.
+const MAX_SNIPPET_LEN: usize = 80;
+
+/// Error reported when the input does not parse as a command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Name of the input, e.g., a file name or `<stdin>`.
+    pub source: String,
+    /// Description of the error.
+    pub message: String,
+    /// Line of the error, starting from 1.
+    pub line: usize,
+    /// Column of the error in characters, starting from 1.
+    pub column: usize,
+    /// Text of the line containing the error, shortened to at most
+    /// `MAX_SNIPPET_LEN` characters around the error.
+    pub snippet: String,
+    /// Column of the error within `snippet`.
+    snippet_column: usize,
+}
+
+/// Position in the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Position {
+    /// Line, starting from 1.
+    pub line: usize,
+    /// Column in characters, starting from 1.
+    pub column: usize,
+}
+
+impl Position {
+    /// Position of the first character of the input.
+    pub fn start() -> Self {
+        Self { line: 1, column: 1 }
+    }
+
+    /// Position following `text`, given that `text` starts at `self`.
+    pub fn advance(self, text: &[u8]) -> Self {
+        match text.iter().rposition(|b| *b == b'\n') {
+            Some(i) => Self {
+                line: self.line + count_lines(text),
+                column: char_count(&text[i + 1..]) + 1,
+            },
+            None => Self {
+                line: self.line,
+                column: self.column + char_count(text),
+            },
+        }
+    }
+
+    /// Position of the first command in `buf`, given that `buf` starts at
+    /// `self`.
+    pub fn skip_whitespace(self, buf: &[u8]) -> Self {
+        let start = buf
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or_else(|| buf.len());
+        self.advance(&buf[..start])
+    }
+}
+
+impl ParseError {
+    /// Describe failure `e` returned by `parse_command(buf)`, where `buf`
+    /// starts at position `start` of `source`.
+    pub(crate) fn new<S, E>(source: S, buf: &[u8], start: Position, e: &Err<&[u8], E>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::at(source, buf, start, error_offset(buf, e))
+    }
+
+    /// Create an error pointing at byte `offset` of `buf`, where `buf`
+    /// starts at position `start` of `source`.
+    pub(crate) fn at<S>(source: S, buf: &[u8], start: Position, offset: usize) -> Self
+    where
+        S: Into<String>,
+    {
+        let offset = offset.min(buf.len());
+        let line_start = buf[..offset]
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        let line_end = buf[offset..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(buf.len(), |i| offset + i);
+
+        let position = start.advance(&buf[..offset]);
+        // `buf` may not contain the start of the line.
+        let column = char_count(&buf[line_start..offset]) + 1;
+        let text: Vec<char> = String::from_utf8_lossy(&buf[line_start..line_end])
+            .trim_end()
+            .chars()
+            .collect();
+        // Center the snippet around the error in long lines.
+        let snippet_start = if text.len() <= MAX_SNIPPET_LEN {
+            0
+        } else {
+            (column - 1)
+                .saturating_sub(MAX_SNIPPET_LEN / 2)
+                .min(text.len() - MAX_SNIPPET_LEN)
+        };
+
+        Self {
+            source: source.into(),
+            message: "invalid input".to_string(),
+            line: position.line,
+            column: position.column,
+            snippet: text[snippet_start..].iter().take(MAX_SNIPPET_LEN).collect(),
+            snippet_column: column - snippet_start,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}:{}:{}: {}",
+            self.source, self.line, self.column, self.message
+        )?;
+        writeln!(f, "    {}", self.snippet)?;
+        write!(f, "    {:>width$}", "^", width = self.snippet_column)
+    }
+}
+
+/// Offset in `buf` at which `parse_command(buf)` failed with `e`.
+pub(crate) fn error_offset<E>(buf: &[u8], e: &Err<&[u8], E>) -> usize {
+    match e {
+        Err::Error(Context::Code(rest, _)) | Err::Failure(Context::Code(rest, _)) => {
+            buf.len() - rest.len()
+        }
+        _ => buf.len(),
+    }
+}
+
+/// Number of newline characters in `buf`.
+fn count_lines(buf: &[u8]) -> usize {
+    buf.iter().filter(|b| **b == b'\n').count()
+}
+
+/// Number of characters in `buf`.
+fn char_count(buf: &[u8]) -> usize {
+    String::from_utf8_lossy(buf).chars().count()
+}
+
+#[test]
+fn test_error_location() {
+    let buf = b"start;\ninsert Foo(1),\n  insert Bar(;\n";
+    let offset = buf.len() - b"insert Bar(;\n".len();
+    let start = Position {
+        line: 10,
+        column: 5,
+    };
+    let err = ParseError::at("test.dat", buf, start, offset);
+    assert_eq!(err.line, 12);
+    assert_eq!(err.column, 3);
+    assert_eq!(err.snippet, "  insert Bar(;");
+    assert_eq!(
+        err.to_string(),
+        "test.dat:12:3: invalid input\n      insert Bar(;\n      ^"
+    );
+}
+
+#[test]
+fn test_error_long_line() {
+    let mut buf = vec![b'x'; 200];
+    buf.extend_from_slice(b"!;");
+    let err = ParseError::at("<stdin>", &buf, Position::start(), 200);
+    assert_eq!(err.column, 201);
+    assert_eq!(err.snippet.chars().count(), MAX_SNIPPET_LEN);
+    assert_eq!(err.snippet_column, 79);
+    assert_eq!(err.snippet.chars().nth(err.snippet_column - 1), Some('!'));
+}
+
+#[test]
+fn test_error_mid_line() {
+    // The buffer starts in the middle of line 2.
+    let start = Position { line: 2, column: 8 };
+    let err = ParseError::at("<stdin>", b"foo;", start, 0);
+    assert_eq!((err.line, err.column), (2, 8));
+    assert_eq!(
+        err.to_string(),
+        "<stdin>:2:8: invalid input\n    foo;\n    ^"
+    );
+}
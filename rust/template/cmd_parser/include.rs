@@ -0,0 +1,179 @@
+//! Execution of `include` commands.
+
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::parse::Command;
+use crate::CommandReader;
+
+/// Execute an `include` command: parse the file at `path` and invoke `cb`
+/// on every command in it, recursively expanding nested `include`s.
+///
+/// A relative `path` is resolved against the directory of `including`,
+/// the file containing the `include` command, or against the current
+/// directory if the command did not come from a file.
+///
+/// `cb` returns `Ok(false)` to stop processing, in which case `include`
+/// returns `Ok(false)` as well.  Errors are prefixed with the name of the
+/// file and the line number of the command that caused them.
+pub fn include<F>(path: &Path, including: Option<&Path>, cb: &mut F) -> Result<bool, String>
+where
+    F: FnMut(Command) -> Result<bool, String>,
+{
+    let mut stack: Vec<PathBuf> = including
+        .and_then(|f| f.canonicalize().ok())
+        .into_iter()
+        .collect();
+    include_file(&resolve(path, including), &mut stack, cb)
+}
+
+/// Resolve path in an `include` command found in file `including`.
+fn resolve(path: &Path, including: Option<&Path>) -> PathBuf {
+    match including.and_then(Path::parent) {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `stack` contains canonical paths of all files currently being included
+/// and is used to detect cycles.
+fn include_file<F>(path: &Path, stack: &mut Vec<PathBuf>, cb: &mut F) -> Result<bool, String>
+where
+    F: FnMut(Command) -> Result<bool, String>,
+{
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| format!("failed to open included file {}: {}", path.display(), e))?;
+    if stack.contains(&canonical) {
+        let cycle: Vec<String> = stack
+            .iter()
+            .chain(Some(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(format!("include cycle detected: {}", cycle.join(" -> ")));
+    }
+    let file = File::open(&canonical)
+        .map_err(|e| format!("failed to open included file {}: {}", path.display(), e))?;
+
+    stack.push(canonical);
+    let reader = CommandReader::new(path.display().to_string(), BufReader::new(file));
+    let result = read_commands(path, reader, stack, cb);
+    let _ = stack.pop();
+    result
+}
+
+fn read_commands<R, F>(
+    path: &Path,
+    mut reader: CommandReader<R>,
+    stack: &mut Vec<PathBuf>,
+    cb: &mut F,
+) -> Result<bool, String>
+where
+    R: BufRead,
+    F: FnMut(Command) -> Result<bool, String>,
+{
+    loop {
+        let cont = match reader.next_command() {
+            Ok(Some(Command::Include(nested))) => {
+                include_file(&resolve(&nested, Some(path)), stack, cb)
+            }
+            Ok(Some(command)) => cb(command),
+            Ok(None) => return reader.finish().map(|_| true).map_err(|e| e.to_string()),
+            Err(e) => return Err(e.to_string()),
+        }
+        .map_err(|e| format!("{}:{}: {}", path.display(), reader.line(), e))?;
+        if !cont {
+            return Ok(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env::temp_dir;
+    use std::fs::create_dir_all;
+    use std::fs::remove_dir_all;
+    use std::fs::write;
+    use std::process;
+
+    /// Create a fresh directory with the given files in it.
+    fn test_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = temp_dir().join(format!("cmd_parser_{}_{}", name, process::id()));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        for (fname, content) in files {
+            let path = dir.join(fname);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn run(path: &Path) -> Result<Vec<Command>, String> {
+        let mut commands = Vec::new();
+        include(path, None, &mut |cmd| {
+            commands.push(cmd);
+            Ok(true)
+        })
+        .map(|_| commands)
+    }
+
+    #[test]
+    fn nested_include() {
+        let dir = test_dir(
+            "nested",
+            &[
+                ("main.dat", "start;\ninclude \"sub/setup.dat\";\ncommit;\n"),
+                ("sub/setup.dat", "include \"echo.dat\";\n"),
+                ("sub/echo.dat", "echo hello;\n"),
+            ],
+        );
+
+        assert_eq!(
+            run(&dir.join("main.dat")),
+            Ok(vec![
+                Command::Start,
+                Command::Echo("hello".to_string()),
+                Command::Commit(false)
+            ])
+        );
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_cycle() {
+        let dir = test_dir(
+            "cycle",
+            &[
+                ("a.dat", "start;\ninclude \"b.dat\";\n"),
+                ("b.dat", "\n\ninclude \"a.dat\";\n"),
+            ],
+        );
+
+        let err = run(&dir.join("a.dat")).unwrap_err();
+        assert!(err.contains("a.dat:2: "), "{}", err);
+        assert!(err.contains("b.dat:3: include cycle detected"), "{}", err);
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_error_location() {
+        let dir = test_dir(
+            "error",
+            &[("bad.dat", "start;\n\ninsert Foo(1),\n  insert Bar(;\n")],
+        );
+
+        let err = run(&dir.join("bad.dat")).unwrap_err();
+        assert!(
+            err.starts_with(&format!("{}:4:3: ", dir.join("bad.dat").display())),
+            "{}",
+            err
+        );
+        remove_dir_all(&dir).unwrap();
+    }
+}
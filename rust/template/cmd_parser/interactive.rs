@@ -0,0 +1,169 @@
+//! Interactive command input from a terminal, with line editing,
+//! history, and tab completion.  Only available with the `interactive`
+//! feature, so that users that merely parse commands, e.g., the C API,
+//! do not depend on a line editor.
+
+use std::env;
+use std::io;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+
+use nom::Err;
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+
+use crate::completion::CommandCompleter;
+use crate::error::Position;
+use crate::include_commands;
+use crate::is_blank;
+use crate::parse_command;
+use crate::read_commands;
+use crate::stdin_is_tty;
+use crate::Command;
+use crate::ParseError;
+use crate::READ_BUFFER_SIZE;
+use crate::STDIN;
+
+/// Name of the history file, stored in the user's home directory.
+const HISTORY_FILE: &str = ".ddlog_history";
+/// Environment variable that overrides the location of the history file.
+const HISTORY_FILE_ENV: &str = "DDLOG_HISTORY";
+
+const PROMPT: &str = ">> ";
+/// Prompt shown while the user is in the middle of a multi-line command.
+const CONT_PROMPT: &str = ".. ";
+
+/// Parse commands from stdio.
+pub fn interact<F>(cb: F) -> Result<(), String>
+where
+    F: Fn(Command, bool) -> (Result<(), String>, bool),
+{
+    interact_with(CommandCompleter::default(), false, cb)
+}
+
+/// Parse commands from stdio.  When stdin is a terminal, `completer` is
+/// used to provide tab completion.
+///
+/// Invalid input read from a pipe terminates parsing, unless `keep_going`
+/// is set, in which case the error is reported and the invalid command is
+/// skipped up to the next `;`.
+pub fn interact_with<F>(completer: CommandCompleter, keep_going: bool, cb: F) -> Result<(), String>
+where
+    F: Fn(Command, bool) -> (Result<(), String>, bool),
+{
+    // We handle stdin differently depending on whether it is a user terminal or a pipe.
+    if !stdin_is_tty() {
+        let stdin = BufReader::with_capacity(READ_BUFFER_SIZE, io::stdin());
+        return read_commands(STDIN, stdin, keep_going, cb);
+    }
+
+    let history = history_file();
+    let mut rl = Editor::<CommandCompleter>::new();
+    rl.set_completer(Some(completer));
+    let _ = rl.load_history(&history);
+
+    let result = read_tty(&mut rl, cb);
+
+    if let Err(e) = rl.save_history(&history) {
+        eprintln!(
+            "Failed to save command history to {}: {}",
+            history.display(),
+            e
+        );
+    }
+    result
+}
+
+fn read_tty<F>(rl: &mut Editor<CommandCompleter>, cb: F) -> Result<(), String>
+where
+    F: Fn(Command, bool) -> (Result<(), String>, bool),
+{
+    let mut buf: Vec<u8> = Vec::new();
+    // Position of the first byte of `buf` in the input.
+    let mut buf_pos = Position::start();
+    // Command being typed at the terminal, possibly spanning multiple
+    // lines.  It is added to the history as a single entry once complete.
+    let mut entry = String::new();
+
+    loop {
+        let prompt = if is_blank(&buf) { PROMPT } else { CONT_PROMPT };
+        let line = match rl.readline(prompt) {
+            Ok(mut line) => {
+                if !entry.is_empty() {
+                    entry.push(' ');
+                }
+                entry.push_str(line.trim());
+                //println!("Line: {}", line);
+                // If `line` happens to be a comment, it must contain an `\n`, so that the
+                // parser can recognize its end.
+                line.push('\n');
+                line
+            }
+            Err(ReadlineError::Interrupted) => {
+                println!("CTRL-C");
+                // Discard the partially entered command.
+                buf_pos = buf_pos.advance(&buf);
+                buf.clear();
+                entry.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => {
+                println!("CTRL-D");
+                return Ok(());
+            }
+            Err(err) => {
+                return Err(format!("Readline failure: {}", err));
+            }
+        };
+
+        buf.extend_from_slice(line.as_bytes());
+
+        while !is_blank(&buf) {
+            let consumed = match parse_command(buf.as_slice()) {
+                Ok((rest, Command::Include(path))) => {
+                    let consumed = buf.len() - rest.len();
+                    match include_commands(&path, &cb) {
+                        Ok(true) => {}
+                        Ok(false) => return Ok(()),
+                        Err(err) => eprintln!("Error: {}", err),
+                    };
+                    consumed
+                }
+                Ok((rest, cmd)) => {
+                    let consumed = buf.len() - rest.len();
+                    let (result, cont) = cb(cmd, true);
+                    if !cont {
+                        return result;
+                    };
+                    consumed
+                }
+                Err(Err::Incomplete(_)) => break,
+                Err(e) => {
+                    eprintln!("{}", ParseError::new(STDIN, &buf, buf_pos, &e));
+                    // Discard the rest of the input.
+                    buf.len()
+                }
+            };
+            buf_pos = buf_pos.advance(&buf[..consumed]);
+            let _ = buf.drain(..consumed);
+        }
+
+        if is_blank(&buf) && !entry.is_empty() {
+            rl.add_history_entry(entry.as_str());
+            entry.clear();
+        }
+    }
+}
+
+/// Location of the command history file: `$DDLOG_HISTORY` if set,
+/// `~/.ddlog_history` otherwise.
+fn history_file() -> PathBuf {
+    if let Some(path) = env::var_os(HISTORY_FILE_ENV) {
+        return PathBuf::from(path);
+    }
+    match env::var_os("HOME") {
+        Some(home) => Path::new(&home).join(HISTORY_FILE),
+        None => PathBuf::from(HISTORY_FILE),
+    }
+}
@@ -1,125 +1,102 @@
 #![warn(missing_debug_implementations)]
 
+#[cfg(feature = "interactive")]
+mod completion;
+mod error;
+mod include;
+#[cfg(feature = "interactive")]
+mod interactive;
 mod parse;
+mod reader;
 
-use std::io;
 use std::io::BufRead;
-use std::io::BufReader;
+use std::path::Path;
 
+#[cfg(feature = "interactive")]
+pub use completion::CommandCompleter;
+pub use error::ParseError;
+pub use include::include;
+#[cfg(feature = "interactive")]
+pub use interactive::{interact, interact_with};
 pub use parse::*;
+pub use reader::CommandReader;
+pub use reader::ReadError;
+pub use reader::Throughput;
 
 use nom::*;
-use rustyline::error::ReadlineError;
-use rustyline::Editor;
 
-const HISTORY_FILE: &str = "cmd_parser_history.txt";
+/// Name used for standard input in error messages.
+pub const STDIN: &str = "<stdin>";
+/// Size of the buffer used when reading commands from a pipe.
+pub const READ_BUFFER_SIZE: usize = 1 << 16;
 
-// We handle stdin differently depending on whether it is a user terminal or a pipe.
-enum Input {
-    TTY(Editor<()>),
-    Pipe(BufReader<io::Stdin>),
+/// Returns `true` if stdin is a user terminal rather than a pipe or a file.
+pub fn stdin_is_tty() -> bool {
+    unsafe {
+        libc::isatty(/*libc::STDIN_FILENO*/ 0 as i32) != 0
+    }
 }
 
-/// Parse commands from stdio.
-pub fn interact<F>(cb: F) -> Result<(), String>
+/// Parse commands from `reader`, which is not a terminal.  `source` names
+/// the input in error messages.  Invalid input is handled as described in
+/// `interact_with`.
+pub fn read_commands<R, F>(source: &str, reader: R, keep_going: bool, cb: F) -> Result<(), String>
 where
+    R: BufRead,
     F: Fn(Command, bool) -> (Result<(), String>, bool),
 {
-    let mut buf: Vec<u8> = Vec::new();
+    read_pipe(CommandReader::new(source, reader), keep_going, cb)
+}
 
-    let istty = unsafe {
-        libc::isatty(/*libc::STDIN_FILENO*/ 0 as i32)
-    } != 0;
-    let mut input = if istty {
-        let mut rl = Editor::<()>::new();
-        let _ = rl.load_history(HISTORY_FILE);
-        Input::TTY(rl)
-    } else {
-        Input::Pipe(BufReader::new(io::stdin()))
-    };
+/// Execute commands in the file included by an `include` command.
+/// Commands in the file are executed non-interactively: the first error
+/// aborts the include.
+pub(crate) fn include_commands<F>(path: &Path, cb: &F) -> Result<bool, String>
+where
+    F: Fn(Command, bool) -> (Result<(), String>, bool),
+{
+    include(path, None, &mut |cmd| {
+        let (result, cont) = cb(cmd, false);
+        result.map(|_| cont)
+    })
+}
 
+fn read_pipe<R, F>(mut reader: CommandReader<R>, keep_going: bool, cb: F) -> Result<(), String>
+where
+    R: BufRead,
+    F: Fn(Command, bool) -> (Result<(), String>, bool),
+{
     loop {
-        let line = match &mut input {
-            Input::TTY(rl) => {
-                let readline = rl.readline(">> ");
-                match readline {
-                    Ok(mut line) => {
-                        rl.add_history_entry(line.as_ref());
-                        //println!("Line: {}", line);
-                        // If `line` happens to be a comment, it must contain an `\n`, so that the
-                        // parser can recognize its end.
-                        line.push('\n');
-                        line
-                    }
-                    Err(ReadlineError::Interrupted) => {
-                        println!("CTRL-C");
-                        continue;
-                    }
-                    Err(ReadlineError::Eof) => {
-                        println!("CTRL-D");
-                        save_history(&rl);
-                        return Ok(());
-                    }
-                    Err(err) => {
-                        save_history(&rl);
-                        return Err(format!("Readline failure: {}", err));
-                    }
+        match reader.next_command() {
+            Ok(Some(Command::Include(path))) => {
+                if !include_commands(&path, &cb)? {
+                    return Ok(());
                 }
             }
-            Input::Pipe(reader) => {
-                let mut line = String::new();
-                let res = reader.read_line(&mut line);
-                match res {
-                    Ok(0) => {
-                        return Ok(());
-                    }
-                    Ok(_) => {}
-                    Err(err) => {
-                        return Err(format!("Failed to read stdin: {}", err));
-                    }
-                };
-                line
-            }
-        };
-
-        buf.extend_from_slice(line.as_bytes());
-
-        loop {
-            let interactive = istty;
-            let (rest, more) = match parse_command(buf.as_slice()) {
-                Ok((rest, cmd)) => {
-                    let (result, cont) = cb(cmd, interactive);
-                    if !cont {
-                        return result;
-                    };
-                    let rest = rest.to_owned();
-                    let more = !rest.is_empty();
-                    (Some(rest), more)
+            Ok(Some(cmd)) => {
+                let (result, cont) = cb(cmd, false);
+                if !cont {
+                    return result;
                 }
-                Err(Err::Incomplete(_)) => (None, false),
-                Err(e) => {
-                    let err = format!("Invalid input: {}, ", err_str(&e));
-                    if !istty {
-                        return Err(err);
-                    } else {
-                        eprintln!("{}", err);
-                    };
-                    (Some(Vec::new()), false)
-                    //return -1;
+            }
+            // Input that ends in the middle of a command is ignored.
+            Ok(None) => return Ok(()),
+            Err(ReadError::Parse(err)) => {
+                if !keep_going {
+                    return Err(err.to_string());
                 }
-            };
-            if let Some(rest) = rest {
-                buf = rest
-            };
-            if !more {
-                break;
+                eprintln!("{}", err);
+                reader
+                    .skip_command()
+                    .map_err(|e| format!("Failed to read stdin: {}", e))?;
             }
+            Err(ReadError::Io(err)) => return Err(format!("Failed to read stdin: {}", err)),
         }
     }
+}
 
-    fn save_history(rl: &Editor<()>) {
-        rl.save_history(HISTORY_FILE).unwrap()
-    }
+pub(crate) fn is_blank(buf: &[u8]) -> bool {
+    buf.iter().all(u8::is_ascii_whitespace)
 }
 
 pub fn err_str<E>(e: &Err<&[u8], E>) -> String {
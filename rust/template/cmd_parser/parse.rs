@@ -1,5 +1,8 @@
 //! nom-based parser for Datalog values.
 
+use crate::error::error_offset;
+use crate::error::Position;
+use crate::ParseError;
 use differential_datalog::record::*;
 use nom::*;
 use num::bigint::*;
@@ -8,6 +11,7 @@ use num::Num;
 use num::ToPrimitive;
 use ordered_float::OrderedFloat;
 use std::borrow::Cow;
+use std::path::PathBuf;
 
 #[derive(Copy, Debug, PartialEq, Eq, Clone)]
 pub enum ProfileCmd {
@@ -32,6 +36,7 @@ pub enum Command {
     Update(UpdCmd, bool),
     QueryIndex(String, Record),
     DumpIndex(String),
+    Include(PathBuf),
 }
 
 named!(spaces<&[u8], ()>,
@@ -129,6 +134,10 @@ named!(pub parse_command<&[u8], Command>,
                             idx: identifier                                   >>
                             apply!(sym,";")                                   >>
                             (Command::DumpIndex(idx)))                                          |
+                  do_parse!(apply!(sym,"include")   >>
+                            path: string_literal    >>
+                            apply!(sym,";")         >>
+                            (Command::Include(PathBuf::from(path))))                            |
                   do_parse!(upd:  update >>
                             last: alt!(map!(apply!(sym,";"), |_|true) | map!(apply!(sym, ","), |_|false)) >>
                             (Command::Update(upd, last)))) >>
@@ -196,6 +205,10 @@ fn test_command() {
         parse_command(br"rollback;"),
         Ok((&br""[..], Command::Rollback))
     );
+    assert_eq!(
+        parse_command(br#"include "setup.dat";"#),
+        Ok((&br""[..], Command::Include(PathBuf::from("setup.dat"))))
+    );
     assert_eq!(
         parse_command(br"insert Rel1(true);"),
         Ok((
@@ -330,6 +343,61 @@ named!(record<&[u8], Record>,
     alt!(bool_val | string_val | serialized_val | tuple_val | array_val | struct_val | float_val | int_val )
 );
 
+named!(spaced_record<&[u8], Record>,
+    do_parse!(spaces >> rec: record >> (rec))
+);
+
+/// Parse `text` as a single record, e.g., `Foo{1, "bar"}`.  `source` names
+/// the input in error messages.
+pub fn parse_record(source: &str, text: &str) -> Result<Record, ParseError> {
+    let input = text.as_bytes();
+    // The parser cannot tell whether a record like `5` ends at the end of
+    // input or continues in the next chunk of input; terminate it with `;`.
+    let mut buf = Vec::with_capacity(input.len() + 1);
+    buf.extend_from_slice(input);
+    buf.push(b';');
+
+    let (offset, message) = match spaced_record(&buf) {
+        Ok((rest, rec)) if rest == b";" => return Ok(rec),
+        Ok((rest, _)) => (buf.len() - rest.len(), "unexpected input after record"),
+        Err(Err::Incomplete(_)) => (input.len(), "incomplete record at end of input"),
+        Err(e) => (error_offset(&buf, &e), "invalid record"),
+    };
+    let mut error = ParseError::at(source, input, Position::start(), offset);
+    error.message = message.to_string();
+    Err(error)
+}
+
+#[test]
+fn test_parse_record() {
+    assert_eq!(
+        parse_record("test", " Foo{5, \"bar\"}\n"),
+        Ok(Record::PosStruct(
+            Cow::from("Foo"),
+            vec![
+                Record::Int(5_i32.to_bigint().unwrap()),
+                Record::String("bar".to_string())
+            ]
+        ))
+    );
+    assert_eq!(
+        parse_record("test", "5"),
+        Ok(Record::Int(5_i32.to_bigint().unwrap()))
+    );
+
+    let err = parse_record("test", "Foo{5} 6").unwrap_err();
+    assert_eq!((err.line, err.column), (1, 8));
+    assert_eq!(err.message, "unexpected input after record");
+
+    let err = parse_record("test", "Foo{5,\n  \"bar").unwrap_err();
+    assert_eq!(err.message, "incomplete record at end of input");
+    assert_eq!((err.line, err.column), (2, 7));
+
+    let err = parse_record("test", "\n  ]").unwrap_err();
+    assert_eq!(err.message, "invalid record");
+    assert_eq!((err.line, err.column), (2, 3));
+}
+
 named!(named_record<&[u8], (Name, Record)>,
     do_parse!(apply!(sym,".") >>
               fname: identifier >>
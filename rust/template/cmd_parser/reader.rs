@@ -0,0 +1,565 @@
+//! Incremental parsing of commands from a `BufRead`.
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::io;
+use std::io::BufRead;
+use std::time::Instant;
+
+use nom::Err;
+
+use crate::error::Position;
+use crate::is_blank;
+use crate::parse::parse_command;
+use crate::parse::Command;
+use crate::ParseError;
+
+/// Error returned by `CommandReader`.
+#[derive(Debug)]
+pub enum ReadError {
+    /// Failed to read the input.
+    Io(io::Error),
+    /// The input does not parse as a command.
+    Parse(ParseError),
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "failed to read input: {}", e),
+            ReadError::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+/// Parsing throughput of a `CommandReader`, averaged over its lifetime.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Throughput {
+    /// Bytes of input parsed per second.
+    pub bytes_per_sec: f64,
+    /// Commands parsed per second.
+    pub commands_per_sec: f64,
+}
+
+impl Display for Throughput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1} MB/s, {:.0} commands/s",
+            self.bytes_per_sec / 1_000_000.0,
+            self.commands_per_sec
+        )
+    }
+}
+
+/// The kinds of commands that differ in how they end.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    /// A comment, which ends in a newline.
+    Comment,
+    /// An `echo` command, which ends in the first `;`.
+    Echo,
+    /// Any other command, which ends in a `;` or, if it is an update, in
+    /// a `,`.
+    Other,
+}
+
+/// Tracks the syntactic context of an incomplete command to find the
+/// points at which it may end without parsing it.
+///
+/// Separators inside of records and string literals are not considered,
+/// so that a large update is only parsed once it is complete.
+#[derive(Debug, Default)]
+struct Scanner {
+    /// The kind of the command; `None` until enough of it has been seen
+    /// to tell.
+    kind: Option<Kind>,
+    /// The leading non-whitespace bytes of the command while its kind is
+    /// unknown.
+    prefix: Vec<u8>,
+    /// Nesting depth of parentheses, brackets and braces.
+    depth: usize,
+    /// Whether we are inside of a string literal.
+    string: bool,
+    /// Whether the previous byte was a backslash inside of a string
+    /// literal.
+    escape: bool,
+}
+
+impl Scanner {
+    /// Feed the next byte of the command.  Returns whether the command
+    /// may end with it.
+    fn feed(&mut self, byte: u8) -> bool {
+        const ECHO: &[u8] = b"echo";
+
+        match self.kind {
+            None if self.prefix.is_empty() && byte.is_ascii_whitespace() => false,
+            None if self.prefix.is_empty() && byte == b'#' => {
+                self.kind = Some(Kind::Comment);
+                false
+            }
+            None => {
+                self.prefix.push(byte);
+                if self.prefix == ECHO {
+                    self.kind = Some(Kind::Echo);
+                } else if !ECHO.starts_with(&self.prefix) {
+                    self.kind = Some(Kind::Other);
+                    self.prefix.clear();
+                    return self.feed_other(byte);
+                }
+                false
+            }
+            Some(Kind::Comment) => byte == b'\n',
+            Some(Kind::Echo) => byte == b';',
+            Some(Kind::Other) => self.feed_other(byte),
+        }
+    }
+
+    /// Feed the next byte of a command of kind `Kind::Other`.
+    fn feed_other(&mut self, byte: u8) -> bool {
+        if self.string {
+            if self.escape {
+                self.escape = false;
+            } else if byte == b'\\' {
+                self.escape = true;
+            } else if byte == b'"' {
+                self.string = false;
+            }
+            return false;
+        }
+
+        match byte {
+            b'"' => self.string = true,
+            b'(' | b'[' | b'{' => self.depth += 1,
+            b')' | b']' | b'}' => self.depth = self.depth.saturating_sub(1),
+            b',' => return self.depth == 0,
+            b';' => return true,
+            _ => (),
+        }
+        false
+    }
+}
+
+/// Parses commands from a `BufRead` one at a time.
+///
+/// Input is read in chunks and discarded as soon as it has been parsed, so
+/// that memory use is bounded by the size of the largest command rather
+/// than the size of the input.
+#[derive(Debug)]
+pub struct CommandReader<R> {
+    reader: R,
+    /// Name of the input used in error messages.
+    source: String,
+    /// Input read so far; everything before `pos` has been parsed.
+    buf: Vec<u8>,
+    pos: usize,
+    /// The position of `buf[pos]` in the input.
+    position: Position,
+    /// The line on which the most recently returned command starts.
+    cmd_line: usize,
+    /// Offset in `buf` of the most recently returned command.
+    cmd_start: usize,
+    /// Set when the input at `pos` is known to be an incomplete command,
+    /// i.e., we need to find a point at which it may end before attempting
+    /// to parse it again.
+    incomplete: bool,
+    /// Offset in `buf` up to which the incomplete command has been
+    /// scanned for its end.
+    scan: usize,
+    /// The state of scanning the incomplete command.
+    scanner: Scanner,
+    /// The time at which the reader was created.
+    started: Instant,
+    /// Number of bytes parsed so far.
+    bytes: u64,
+    /// Number of commands parsed so far.
+    commands: u64,
+}
+
+impl<R> CommandReader<R>
+where
+    R: BufRead,
+{
+    /// Create a reader parsing commands from `reader`.  `source` names the
+    /// input in error messages.
+    pub fn new<S>(source: S, reader: R) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            reader,
+            source: source.into(),
+            buf: Vec::new(),
+            pos: 0,
+            position: Position::start(),
+            cmd_line: 1,
+            cmd_start: 0,
+            incomplete: false,
+            scan: 0,
+            scanner: Scanner::default(),
+            started: Instant::now(),
+            bytes: 0,
+            commands: 0,
+        }
+    }
+
+    /// Parse the next command.
+    ///
+    /// Returns `Ok(None)` when the end of input is reached before a
+    /// complete command.  An incomplete command is kept, so reading can
+    /// continue if more data is appended to the input later; use `finish`
+    /// to check that the input did not end in the middle of a command.
+    ///
+    /// After a parse error, the reader stays at the invalid command;
+    /// `skip_command` moves past it.
+    pub fn next_command(&mut self) -> Result<Option<Command>, ReadError> {
+        loop {
+            if !self.incomplete {
+                let input = &self.buf[self.pos..];
+                if !is_blank(input) {
+                    match parse_command(input) {
+                        Ok((rest, command)) => {
+                            let consumed = input.len() - rest.len();
+                            self.cmd_line = self.position.skip_whitespace(input).line;
+                            self.cmd_start = self.pos;
+                            self.advance(consumed);
+                            self.commands += 1;
+                            return Ok(Some(command));
+                        }
+                        Err(Err::Incomplete(_)) => self.incomplete = true,
+                        Err(e) => {
+                            let source = self.source.as_str();
+                            let error = ParseError::new(source, input, self.position, &e);
+                            return Err(ReadError::Parse(error));
+                        }
+                    }
+                }
+            }
+            if self.incomplete && self.scan_for_end() {
+                self.incomplete = false;
+                continue;
+            }
+            if !self.fill()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Skip the input up to and including the next `;`.
+    pub fn skip_command(&mut self) -> io::Result<()> {
+        loop {
+            if let Some(i) = self.buf[self.pos..].iter().position(|b| *b == b';') {
+                self.advance(i + 1);
+                self.incomplete = false;
+                return Ok(());
+            }
+            self.advance(self.buf.len() - self.pos);
+            if !self.fill()? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Check that the input read so far does not end with an incomplete
+    /// command.
+    pub fn finish(&self) -> Result<(), ParseError> {
+        let input = &self.buf[self.pos..];
+        match input.iter().position(|b| !b.is_ascii_whitespace()) {
+            None => Ok(()),
+            Some(start) => {
+                let mut error = ParseError::at(self.source.as_str(), input, self.position, start);
+                error.message = "incomplete command at end of input".to_string();
+                Err(error)
+            }
+        }
+    }
+
+    /// Read the next chunk of input.  Returns `false` at the end of input.
+    fn fill(&mut self) -> io::Result<bool> {
+        // Only keep the command being parsed in memory.
+        if self.pos > 0 {
+            let _ = self.buf.drain(..self.pos);
+            self.scan -= self.pos;
+            self.pos = 0;
+            self.cmd_start = 0;
+        }
+
+        let chunk = match self.reader.fill_buf() {
+            Ok(chunk) => chunk,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(true),
+            Err(e) => return Err(e),
+        };
+        if chunk.is_empty() {
+            return Ok(false);
+        }
+
+        self.buf.extend_from_slice(chunk);
+        let len = chunk.len();
+        self.reader.consume(len);
+        Ok(true)
+    }
+
+    /// Scan the input read since the last call for a point at which the
+    /// incomplete command at `pos` may end.  Returns whether one was found.
+    ///
+    /// Scanning resumes where it left off, so every byte of a command is
+    /// only looked at once, no matter how many chunks it spans.
+    fn scan_for_end(&mut self) -> bool {
+        let scanner = &mut self.scanner;
+        match self.buf[self.scan..].iter().position(|b| scanner.feed(*b)) {
+            Some(i) => {
+                self.scan += i + 1;
+                true
+            }
+            None => {
+                self.scan = self.buf.len();
+                false
+            }
+        }
+    }
+
+    /// Mark the next `len` bytes of input as parsed.
+    fn advance(&mut self, len: usize) {
+        self.position = self.position.advance(&self.buf[self.pos..self.pos + len]);
+        self.pos += len;
+        self.bytes += len as u64;
+        self.scan = self.pos;
+        self.scanner = Scanner::default();
+    }
+}
+
+impl<R> CommandReader<R> {
+    /// The name of the input.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The line on which the most recently returned command starts.
+    pub fn line(&self) -> usize {
+        self.cmd_line
+    }
+
+    /// The text of the most recently returned command, without surrounding
+    /// whitespace.  Only available until the next call to `next_command`
+    /// or `skip_command`.
+    pub fn command_text(&self) -> &[u8] {
+        let text = &self.buf[self.cmd_start.min(self.pos)..self.pos];
+        let start = text
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(text.len());
+        let end = text
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+            .map_or(start, |i| i + 1);
+        &text[start..end]
+    }
+
+    /// Number of bytes of input parsed so far.
+    pub fn bytes_parsed(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Number of commands parsed so far.
+    pub fn commands_parsed(&self) -> u64 {
+        self.commands
+    }
+
+    /// The rate at which input has been parsed since the reader was
+    /// created.
+    pub fn throughput(&self) -> Throughput {
+        let secs = self.started.elapsed().as_secs_f64().max(f64::EPSILON);
+        Throughput {
+            bytes_per_sec: self.bytes as f64 / secs,
+            commands_per_sec: self.commands as f64 / secs,
+        }
+    }
+
+    /// Number of bytes of input currently held in memory.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Unwrap the underlying reader.  Input that has been read, but not
+    /// parsed yet, is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::BufReader;
+    use std::io::Cursor;
+    use std::io::Read;
+
+    const TRANSACTION: &str =
+        "start;\n# comment\ninsert Foo(1, \"bar\"),\ndelete Foo(2, \"baz\");\ncommit;\n";
+
+    /// Generates `count` copies of `text` without holding them in memory.
+    struct Repeat {
+        text: Vec<u8>,
+        count: usize,
+        pending: Cursor<Vec<u8>>,
+    }
+
+    impl Repeat {
+        fn new(text: Vec<u8>, count: usize) -> Self {
+            Self {
+                text,
+                count,
+                pending: Cursor::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Read for Repeat {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pending.position() as usize == self.pending.get_ref().len() {
+                if self.count == 0 {
+                    return Ok(0);
+                }
+                self.count -= 1;
+                self.pending = Cursor::new(self.text.clone());
+            }
+            self.pending.read(buf)
+        }
+    }
+
+    fn commands(input: &str) -> Result<Vec<Command>, String> {
+        let mut reader = CommandReader::new("test", BufReader::with_capacity(3, input.as_bytes()));
+        let mut commands = Vec::new();
+        while let Some(command) = reader.next_command().map_err(|e| e.to_string())? {
+            commands.push(command);
+        }
+        reader.finish().map_err(|e| e.to_string())?;
+        Ok(commands)
+    }
+
+    #[test]
+    fn small_chunks() {
+        // A buffer of 3 bytes splits commands at arbitrary points.
+        assert_eq!(
+            commands("start;\n# comment\necho hello world;\ncommit dump_changes;"),
+            Ok(vec![
+                Command::Start,
+                Command::Comment,
+                Command::Echo("hello world".to_string()),
+                Command::Commit(true)
+            ])
+        );
+    }
+
+    #[test]
+    fn incomplete_command() {
+        let err = commands("start;\n\n  echo unterminated").unwrap_err();
+        assert!(
+            err.starts_with("test:3:3: incomplete command at end of input"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn skip_invalid_command() {
+        let input = "start;\nfoo bar;\ncommit;\n";
+        let mut reader = CommandReader::new("test", input.as_bytes());
+        assert_eq!(reader.next_command().unwrap(), Some(Command::Start));
+        match reader.next_command() {
+            Err(ReadError::Parse(e)) => assert_eq!((e.line, e.column), (2, 1)),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        reader.skip_command().unwrap();
+        assert_eq!(reader.next_command().unwrap(), Some(Command::Commit(false)));
+        assert_eq!(reader.line(), 3);
+        assert_eq!(reader.next_command().unwrap(), None);
+    }
+
+    #[test]
+    fn command_text() {
+        let input = "start;\n  insert Foo(1, \"a;b\"),\ncommit;";
+        let mut reader = CommandReader::new("test", input.as_bytes());
+        let mut texts = Vec::new();
+        while reader.next_command().unwrap().is_some() {
+            texts.push(String::from_utf8(reader.command_text().to_vec()).unwrap());
+        }
+        assert_eq!(texts, vec!["start;", "insert Foo(1, \"a;b\"),", "commit;"]);
+    }
+
+    #[test]
+    fn bounded_memory() {
+        let transactions = Repeat::new(TRANSACTION.as_bytes().to_vec(), 20_000);
+        let mut reader = CommandReader::new("test", BufReader::new(transactions));
+        let mut max_buffered = 0;
+        while reader.next_command().unwrap().is_some() {
+            max_buffered = max_buffered.max(reader.buffered());
+        }
+        reader.finish().unwrap();
+
+        assert_eq!(reader.commands_parsed(), 20_000 * 5);
+        assert_eq!(reader.bytes_parsed(), 20_000 * TRANSACTION.len() as u64);
+        assert!(max_buffered <= 2 * 8192, "{}", max_buffered);
+
+        let throughput = reader.throughput();
+        assert!(throughput.bytes_per_sec > 0.0);
+        assert!(throughput.commands_per_sec > 0.0);
+    }
+
+    #[test]
+    fn bounded_memory_large_updates() {
+        // Large updates only terminated by `,`, with commas, semicolons
+        // and brackets inside of the record.
+        let mut update = b"insert Foo(1, [".to_vec();
+        for _ in 0..20_000 {
+            update.extend_from_slice(b"\"a,b;c)\", ");
+        }
+        update.extend_from_slice(b"\"\\\"\"]),\n");
+
+        let updates = Repeat::new(update.clone(), 50);
+        let mut reader = CommandReader::new("test", BufReader::new(updates));
+        let mut max_buffered = 0;
+        while let Some(command) = reader.next_command().unwrap() {
+            assert!(
+                matches!(command, Command::Update(_, false)),
+                "{:?}",
+                command
+            );
+            max_buffered = max_buffered.max(reader.buffered());
+        }
+        reader.finish().unwrap();
+
+        assert_eq!(reader.commands_parsed(), 50);
+        assert!(
+            max_buffered <= update.len() + 8192,
+            "{} > {}",
+            max_buffered,
+            update.len() + 8192
+        );
+    }
+
+    #[test]
+    fn scanner() {
+        let ends = |text: &str| {
+            let mut scanner = Scanner::default();
+            text.bytes()
+                .enumerate()
+                .filter(|(_, b)| scanner.feed(*b))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ends("insert Foo(1, [2, 3], {a: 4}),"), vec![29]);
+        assert_eq!(ends("insert Foo(\"a,\\\";b\"),"), vec![20]);
+        assert_eq!(ends("  # a, \"b; c\nstart;"), vec![12]);
+        assert_eq!(ends(" echo \"a, b;"), vec![11]);
+        assert_eq!(ends("ech;"), vec![3]);
+    }
+}
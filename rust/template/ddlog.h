@@ -1,10 +1,15 @@
 #ifndef __DDLOG_H__
 #define __DDLOG_H__
 
+/*
+ * This file is generated from the Rust sources of the C API by cbindgen.
+ * Do not edit it manually: edit doc comments in the Rust sources and run
+ * `DDLOG_UPDATE_HEADER=1 cargo build` to regenerate it.
+ */
+
 #include <stdint.h>
 #include <stdbool.h>
 #include <sys/types.h>
-
 /*
  * *Note:* all functions in this library, with the exception of
  * `ddlog_record_commands()` and `ddlog_stop()`, and `ddlog_delta_XXX`
@@ -17,6 +22,9 @@
  * transactions.  An attempt to start a transaction while another
  * transaction is in progress (in the same or different thread) will
  * return an error.
+ *
+ * Errors are reported to the calling thread only: `ddlog_last_error()`
+ * describes the last failed call made by the thread that invokes it.
  */
 
 /*
@@ -76,19 +84,14 @@ typedef void * ddlog_prog;
 typedef struct ddlog_delta_struct * ddlog_delta;
 
 /*
- * Insert or delete command to be passed to the `ddlog_apply_updates()`
- */
-typedef void ddlog_cmd;
-
-/*
- * Unique DDlog table identifier
+ * NOTE: This API is _not_ thread-safe. It is the callers responsibility to ensure
+ * that at most one thread can access `ddlog_delta` at the same time.
  */
-typedef size_t table_id;
 
 /*
- * Unique DDlog index identifier
+ * Insert or delete command to be passed to the `ddlog_apply_updates()`
  */
-typedef size_t index_id;
+typedef void ddlog_cmd;
 
 /*
  * The record type represents DDlog values that can be written to and
@@ -107,13 +110,217 @@ typedef size_t index_id;
  */
 typedef void ddlog_record;
 
+/*
+ * The record API is intended solely for passing values to and from
+ * DDlog.  It allows constructing and reading values, but does not
+ * provide methods to otherwise manipulate them, e.g., to lookup an
+ * element in a set or map.
+ *
+ * This API supports the following ownership policy (see additional
+ * details in individual function description):
+ *
+ * - The client obtains ownership of a record by creating it using
+ *   `ddlog_bool()`, `ddlog_string()`, `ddlog_tuple()`, etc.  This is
+ *   currently the only way to obtain ownership.
+ *
+ * - The client yields ownership by either passing the record to DDlog
+ *   as part of an update command (see `ddlog_apply_updates()`) or by
+ *   attaching it to another owned object (e.g., appending it to a
+ *   vector using `ddlog_vector_push()`).
+ *
+ * - Each owned record encapsulates some dynamically allocated memory.
+ *   To avoid memory leaks, the client must transfer the ownership of
+ *   every record they own or deallocate the record using
+ *   `ddlog_free()`.
+ *
+ * - There is a limited API for modifying _owned_ records, e.g., by
+ *   appending elements to an array.
+ *
+ * - In addition to owned records, the client can also obtain pointers to
+ *   *borrowed* records in one of two ways:
+ *
+ *    1. By invoking `ddlog_dump_table()` or `ddlog_dump_index()` to
+ *       enumerate the content of an output table or index.  These functions
+ *       take a user callback and invoke it once for each record in the table
+ *       or index.  The record, passed as argument to the callback is owned
+ *       by DDlog and is only valid for the duration of the callback.
+ *       (TODO: the only reason for this is convenience, so the client
+ *       does not need to worry about deallocating the record later.
+ *       The API could be changed to return owned records.)
+ *
+ *    2. By querying another record of type tuple, vector, set or map.
+ *       For instance, `ddlog_get_vector_elem()` returns a borrowed
+ *       reference to an element of the vector.
+ *
+ * - The client may inspect a borrowed record, but not modify it, attach
+ *   to another records or pass to DDlog as part of an update.
+ *
+ * - Owned records are represented in the API by mutable pointers
+ *   (`ddlog_record*`), whereas borrowed records are represented by
+ *   immutable pointers (`const ddlog_record*`).
+ *
+ * Type checking:
+ *
+ * The Record API does not perform any type checking, e.g., one can
+ * create a vector with elements of different types or a struct whose
+ * fields don't match declarations in the DDlog program. Type checking
+ * is performed by DDlog before inserting records to the database, e.g.,
+ * in the `ddlog_apply_updates()` function.  The function will fail if
+ * it detects type mismatch between relation type and the record
+ * supplied by the client.
+ *
+ * The client can rely on records read from the database to have types
+ * that matches the corresponding DDlog output table declaration.
+ */
+
+
+/**
+ * Error codes.  Functions in this library signal failure through their
+ * return value (typically, -1 or NULL); the code and a human-readable
+ * description of the error can then be retrieved using
+ * `ddlog_last_error_code()` and `ddlog_last_error()`.
+ *
+ * The numeric values of error codes are stable across DDlog releases.
+ */
+typedef enum {
+  /**
+   * The last call succeeded.
+   */
+  DDLOG_OK = 0,
+  /**
+   * NULL pointer or otherwise malformed argument.
+   */
+  DDLOG_ERR_INVALID_ARGUMENT = 1,
+  /**
+   * Unknown relation, or a relation of the wrong kind, e.g., an input
+   * relation where an output relation is expected.
+   */
+  DDLOG_ERR_UNKNOWN_TABLE = 2,
+  DDLOG_ERR_UNKNOWN_INDEX = 3,
+  /**
+   * Insertion of a value whose primary key already exists.
+   */
+  DDLOG_ERR_DUPLICATE_KEY = 4,
+  /**
+   * Deletion or modification of a key that does not exist.
+   */
+  DDLOG_ERR_KEY_NOT_FOUND = 5,
+  /**
+   * The operation requires a transaction, but none was started.
+   */
+  DDLOG_ERR_NO_TRANSACTION = 6,
+  DDLOG_ERR_TRANSACTION_IN_PROGRESS = 7,
+  /**
+   * A record does not match the type of its relation.
+   */
+  DDLOG_ERR_INVALID_RECORD = 8,
+  /**
+   * A DDlog worker thread failed or stopped responding.
+   */
+  DDLOG_ERR_WORKER_FAILURE = 9,
+  DDLOG_ERR_IO = 10,
+  /**
+   * The program handle is in use by another thread.
+   */
+  DDLOG_ERR_BUSY = 11,
+  /**
+   * The operation is not supported by this build or configuration of the
+   * program, e.g., FlatBuffers functions in a library compiled without
+   * FlatBuffers support.
+   */
+  DDLOG_ERR_UNSUPPORTED = 12,
+  DDLOG_ERR_OTHER = 13,
+  /**
+   * Text passed to `ddlog_parse_record()` or `ddlog_parse_commands()` is
+   * not valid DDlog syntax.  The error message contains the line and
+   * column of the error.
+   */
+  DDLOG_ERR_PARSE = 14,
+} ddlog_error_code;
+
+/**
+ * Unique DDlog table identifier
+ */
+typedef size_t table_id;
+
+/**
+ * Unique DDlog index identifier
+ */
+typedef size_t index_id;
+
+/**
+ * Callback invoked for every record added to or removed from an output
+ * relation.  Takes the following arguments:
+ * - `arg`    - opaque user-defined value
+ * - `table`  - table being modified
+ * - `rec`    - record that has been inserted or deleted
+ * - `weight` - change in the multiplicity of the record
+ */
+typedef void (*ddlog_update_callback)(uintptr_t arg,
+                                      table_id table,
+                                      const ddlog_record *rec,
+                                      ssize_t weight);
+
 typedef struct {
-    table_id table;
-    ddlog_record *rec;
-    ssize_t weight;
+  table_id table;
+  ddlog_record *rec;
+  ssize_t weight;
 } ddlog_record_update;
 
-/*
+/**
+ * Callback invoked with all changes made to an output relation by a
+ * transaction.  Takes the following arguments:
+ * - `arg`         - opaque user-defined value
+ * - `table`       - table being modified
+ * - `updates`     - array of records inserted into or deleted from the table
+ * - `num_updates` - number of elements in `updates`
+ */
+typedef void (*ddlog_batch_callback)(uintptr_t arg,
+                                     table_id table,
+                                     const ddlog_record_update *updates,
+                                     size_t num_updates);
+
+/**
+ * Callback invoked with all changes made to an output relation by a
+ * transaction, serialized to a FlatBuffer.  Takes the following arguments:
+ * - `arg`   - opaque user-defined value
+ * - `table` - table being modified
+ * - `buf`   - FlatBuffer containing the changes
+ * - `size`  - size of the FlatBuffer in bytes
+ */
+typedef void (*ddlog_flatbuf_callback)(uintptr_t arg,
+                                       table_id table,
+                                       const uint8_t *buf,
+                                       size_t size);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns the code of the error reported by the last `ddlog_XXX()` call
+ * made by the calling thread, or `DDLOG_OK` if the call succeeded.
+ *
+ * Every function in this library (except functions that manipulate
+ * records, e.g., `ddlog_bool()`, `ddlog_get_int()`, etc.) resets the error
+ * state of the calling thread on entry.  Error state is thread-local, so
+ * it is safe to call this function while other threads use the same DDlog
+ * program.
+ */
+ddlog_error_code ddlog_last_error_code(void);
+
+/**
+ * Returns a null-terminated description of the error reported by the last
+ * `ddlog_XXX()` call made by the calling thread, or NULL if the call
+ * succeeded.  The message starts with the name of the failed function, e.g.,
+ * "ddlog_transaction_commit(): no transaction in progress".
+ *
+ * The string is owned by DDlog and must not be deallocated by the caller.
+ * It remains valid until the next `ddlog_XXX()` call in the same thread.
+ */
+const char *ddlog_last_error(void);
+
+/**
  * Get DDlog table id by name.  The table name is a null-terminated UTF8
  * string.
  *
@@ -123,16 +330,16 @@ typedef struct {
  *
  * On error, returns -1.
  */
-extern table_id ddlog_get_table_id(const char* tname);
+table_id ddlog_get_table_id(const char *tname);
 
-/*
+/**
  * Get DDlog table name from id.
  *
  * Returns a null-terminated UTF8 string on success or NULL on error.
  */
-extern const char* ddlog_get_table_name(table_id id);
+const char *ddlog_get_table_name(table_id tid);
 
-/*
+/**
  * Get DDlog index id by name.  The index name is a null-terminated UTF8
  * string.
  *
@@ -141,16 +348,16 @@ extern const char* ddlog_get_table_name(table_id id);
  *
  * On error, returns -1.
  */
-extern index_id ddlog_get_index_id(const char* iname);
+index_id ddlog_get_index_id(const char *iname);
 
-/*
+/**
  * Get DDlog index name from id.
  *
  * Returns a null-terminated UTF8 string on success or NULL on error.
  */
-extern const char* ddlog_get_index_name(index_id id);
+const char *ddlog_get_index_name(index_id iid);
 
-/*
+/**
  * Create an instance of DDlog program.
  *
  * `workers` is the number of DDlog worker threads that will be
@@ -161,8 +368,8 @@ extern const char* ddlog_get_index_name(index_id id);
  * `do_store` - set to true to store the copy of output tables inside DDlog.
  * When set, the client can use the following APIs to retrieve the contents of
  * tables:
- *	- `ddlog_dump_ovsdb_delta()`
- *	- `ddlog_dump_table()`
+ *      - `ddlog_dump_ovsdb_delta_tables()`
+ *      - `ddlog_dump_table()`
  * This has a cost in terms of memory and CPU.  In addition, the current implementation
  * serializes all writes to its internal copies of tables, introducing contention
  * when `workers > 1`.  Therefore, this flag should be set to `false` if the
@@ -176,17 +383,17 @@ extern const char* ddlog_get_index_name(index_id id);
  * `ddlog_transaction_commit()`, as DDlog applies updates performed by the
  * transaction.
  *   The `cb` function takes the following arguments:
- *	- `arg`	     - opaque used-defined value
- *	- `table`    - table being modified
- *	- `rec`	     - record that has been inserted or deleted
- *	- `weight`   - change in the multiplicity of the record.  The same record can
- *	               be inserted (callback invoked with positive weight) and deleted
- *	               (callback invoked with negative weight) multiple times during
- *                 transaction commit.  In order to determine how the membership of
- *                 the value in the relation was changed by the transaction, sum up
- *                 all its weights.  The result of +1 means that the record was
- *                 inserted; -1 - record was deleted; 0 - record's membership
- *                 did not change.
+ *     - `arg`    - opaque used-defined value
+ *     - `table`  - table being modified
+ *     - `rec`    - record that has been inserted or deleted
+ *     - `weight` - change in the multiplicity of the record.  The same record can
+ *                  be inserted (callback invoked with positive weight) and deleted
+ *                  (callback invoked with negative weight) multiple times during
+ *                  transaction commit.  In order to determine how the membership of
+ *                  the value in the relation was changed by the transaction, sum up
+ *                  all its weights.  The result of +1 means that the record was
+ *                  inserted; -1 - record was deleted; 0 - record's membership
+ *                  did not change.
  * `init_state` - when not NULL, DDlog will store a pointer to `ddlog_delta`
  * containing initial snapshot of output relations at this address.  The caller
  * is responsible for freeing this delta, e.g., using `ddlog_free_delta()`.
@@ -213,18 +420,49 @@ extern const char* ddlog_get_index_name(index_id id);
  * `ddlog_transaction_start()`,
  * `ddlog_transaction_commit()`, etc., or NULL in case of error.
  */
-extern ddlog_prog ddlog_run(
-        unsigned int workers,
-        bool do_store,
-        void (*cb)(void *arg,
-                   table_id table,
-                   const ddlog_record *rec,
-                   ssize_t weight),
-        uintptr_t cb_arg,
-        void (*print_err_msg)(const char *msg),
-        ddlog_delta **init_state);
+ddlog_prog ddlog_run(unsigned int workers,
+                     bool do_store,
+                     ddlog_update_callback cb,
+                     uintptr_t cb_arg,
+                     void (*print_err_msg)(const char *msg),
+                     ddlog_delta **init_state);
 
-/*
+/**
+ * Like `ddlog_run()`, but invokes `cb` from a pool of `update_threads`
+ * threads rather than from a single thread, so that a slow callback does
+ * not stall the program as much.  Tables are sharded among the threads:
+ * all changes to a table are delivered by the same thread, in order, but
+ * changes to different tables may be delivered concurrently, and `cb`
+ * must be prepared to handle that.  Changes are still delivered before
+ * `ddlog_transaction_commit()` returns.
+ *
+ * `update_threads` of 0 or 1 is equivalent to `ddlog_run()`.
+ */
+ddlog_prog ddlog_run_with_update_threads(unsigned int workers,
+                                         bool do_store,
+                                         unsigned int update_threads,
+                                         ddlog_update_callback cb,
+                                         uintptr_t cb_arg,
+                                         void (*print_err_msg)(const char *msg),
+                                         ddlog_delta **init_state);
+
+/**
+ * Like `ddlog_run()`, but invokes `cb` asynchronously from a separate
+ * thread: `ddlog_transaction_commit()` returns without waiting for `cb` to
+ * see the changes, as long as at most `queue_size` changes are pending,
+ * so that a slow callback does not stall the program.  Changes are
+ * delivered in order.  Use `ddlog_flush_updates()` to wait until `cb` has
+ * seen all committed changes; `ddlog_stop()` does so before it returns.
+ */
+ddlog_prog ddlog_run_async(unsigned int workers,
+                           bool do_store,
+                           size_t queue_size,
+                           ddlog_update_callback cb,
+                           uintptr_t cb_arg,
+                           void (*print_err_msg)(const char *msg),
+                           ddlog_delta **init_state);
+
+/**
  * Record commands issued to DDlog via this API in a file.
  *
  * This is a debugging feature used to record DDlog commands issued through
@@ -238,9 +476,23 @@ extern ddlog_prog ddlog_run(
  * IMPORTANT: this function is _not_ thread-safe and must not be invoked
  * concurrently with other functions in this API.
  */
-extern int ddlog_record_commands(ddlog_prog hprog, int fd);
+int ddlog_record_commands(ddlog_prog prog, int fd);
 
-/*
+/**
+ * Like `ddlog_record_commands()`, but records commands in a compact binary
+ * format that is faster to write and to replay than the text format.  The CLI
+ * detects the format of its input automatically, and its `--convert-replay`
+ * option converts between the two formats.
+ *
+ * Binary recordings cannot be mixed with text: do not use
+ * `ddlog_dump_input_snapshot()` or inject commands into the file.
+ *
+ * IMPORTANT: this function is _not_ thread-safe and must not be invoked
+ * concurrently with other functions in this API.
+ */
+int ddlog_record_commands_binary(ddlog_prog prog, int fd);
+
+/**
  * Dump current snapshot of input tables to a file in a format suitable
  * for replay debugging.
  *
@@ -260,7 +512,7 @@ extern int ddlog_record_commands(ddlog_prog hprog, int fd);
  *
  * This function generates input snapshot in the following format:
  *
- * ```
+ * ```text
  * insert Table1[val1],
  * insert Table1[val2],
  * insert Table2[val3],
@@ -274,9 +526,9 @@ extern int ddlog_record_commands(ddlog_prog hprog, int fd);
  * `fd` - valid writable file descriptor.  The caller is responsible for opening
  * and closing the file.
  */
-extern int ddlog_dump_input_snapshot(ddlog_prog hprog, int fd);
+int ddlog_dump_input_snapshot(ddlog_prog prog, int fd);
 
-/*
+/**
  * Stops the program; deallocates all resources, invalidates the handle.
  *
  * All concurrent calls using the handle must complete before calling this
@@ -288,9 +540,9 @@ extern int ddlog_dump_input_snapshot(ddlog_prog hprog, int fd);
  * IMPORTANT: this function is _not_ thread-safe and must not be invoked
  * concurrently with other functions in this API.
  */
-extern int ddlog_stop(ddlog_prog hprog);
+int ddlog_stop(ddlog_prog prog);
 
-/*
+/**
  * Start a transaction.
  *
  * On success, returns `0`; on error, returns `-1` and prints error message
@@ -301,20 +553,9 @@ extern int ddlog_stop(ddlog_prog hprog);
  * Within a transaction, updates to input relations are buffered until
  * `ddlog_transaction_commit()` is called.
  */
-extern int ddlog_transaction_start(ddlog_prog hprog);
-
-/*
- * Commit a transaction; propagate all buffered changes through all
- * rules in the program and update all output relations.
- *
- * On success, returns `0`; on error, returns `-1` and prints error message
- * (see `print_err_msg` parameter to `ddlog_run()`).
- *
- * This function will fail if there is no transaction in progress.
- */
-extern int ddlog_transaction_commit(ddlog_prog hprog);
+int ddlog_transaction_start(ddlog_prog prog);
 
-/*
+/**
  * Commit a transaction; propagate all buffered changes through all
  * rules in the program and update all output relations and returns
  * the set of changes.  On error, returns `NULL` and prints error message
@@ -322,11 +563,9 @@ extern int ddlog_transaction_commit(ddlog_prog hprog);
  *
  * This function will fail if there is no transaction in progress.
  */
-extern ddlog_delta *
-ddlog_transaction_commit_dump_changes(
-        ddlog_prog hprog);
+ddlog_delta *ddlog_transaction_commit_dump_changes(ddlog_prog prog);
 
-/*
+/**
  * Commit a transaction; propagate all buffered changes through all
  * rules in the program and update all output relations.  Once all
  * updates are finished, returns an array of changes to output relations.
@@ -341,13 +580,11 @@ ddlog_transaction_commit_dump_changes(
  *
  * This function will fail if there is no transaction in progress.
  */
-extern int
-ddlog_transaction_commit_dump_changes_as_array(
-        ddlog_prog hprog,
-        ddlog_record_update **changes,
-        size_t *num_changes);
+int ddlog_transaction_commit_dump_changes_as_array(ddlog_prog prog,
+                                                   ddlog_record_update **changes,
+                                                   size_t *num_changes);
 
-/*
+/**
  * Deallocate array of updates returned by
  * `ddlog_transaction_commit_dump_changes_as_array()`.  Both `changes` and
  * `num_changes` arguments must be equal to the values returned by a successful
@@ -356,82 +593,70 @@ ddlog_transaction_commit_dump_changes_as_array(
  * This function invalidates record handles stored in the `changes` array;
  * they must not be accessed after the call.
  */
-extern void
-ddlog_free_record_updates(
-        ddlog_record_update *changes,
-        size_t num_changes);
+void ddlog_free_record_updates(ddlog_record_update *changes, size_t num_changes);
 
-/*
+/**
  * Same as `ddlog_transaction_commit_dump_changes`, but serializes changes to a
  * FlatBuffer.  On success, returns pointer to FlatBuffer, size and capacity of
  * the buffer, and offset where valid data starts inside the buffer.
  */
-extern int
-ddlog_transaction_commit_dump_changes_to_flatbuf(
-        ddlog_prog hprog,
-        unsigned char** buf,
-        size_t* buf_size,
-        size_t* buf_capacity,
-        size_t* buf_offset);
+int ddlog_transaction_commit_dump_changes_to_flatbuf(ddlog_prog prog,
+                                                     uint8_t **buf,
+                                                     size_t *buf_size,
+                                                     size_t *buf_capacity,
+                                                     size_t *buf_offset);
 
-/*
- * Deallocate a FlatBuffer returned by
- * `ddlog_transaction_commit_dump_changes_to_flatbuf`.  Must be called once for
- * each buffer returned by a successful invocation of
- * `ddlog_transaction_commit_dump_changes_to_flatbuf`.
- */
-extern void
-ddlog_flatbuf_free(
-        unsigned char* buf,
-        size_t size,
-        size_t capacity);
-
-/*
- * Discard all buffered updates and abort the current transaction.
+/**
+ * Perform a query serialized in a flatbuf; return result in another flatbuf.
  *
- * On success, returns `0`; on error, returns `-1` and prints error message
- * (see `print_err_msg` parameter to `ddlog_run()`).
+ * The FlatBuffer schema is auto-generated from DDlog code and is stored in
+ * `flatbuf/flatbuf.rs`.
  *
- * This function will fail if there is no transaction in progress.
- */
-extern int ddlog_transaction_rollback(ddlog_prog hprog);
-
-/*
- * Apply updates to DDlog tables.  See the ddlog_cmd API below.
+ * `buf` - pointer to FlatBuffer of size `n` containing the query.
+ *
+ * The following values returned by the function describe the flatbuffer
+ * containing response to the query.
+ * `resbuf` - address where the function will write the pointer to the reply
+ * flatbuf.
+ * `resbuf_size` - address to store the size of the generated flatbuf.
+ * `resbuf_capacity` - address to store generated flatbuf capacity.
+ * `resbuf_offset` - address to store data offset inside the returned buffer.
  *
  * On success, returns `0`. On error, returns a negative value and
  * writes error message (see `print_err_msg` parameter to `ddlog_run()`).
  *
- * Whether the function succeeds or fails, it consumes all commands in
- * the `upds` array (but not the array itself), so they can no longer be
- * accessed by the caller after the function returns.
- *
- * This function fails non-atomically: upon a failure, it may have applied
- * a _subset_ of input commands (one exception is when `ddlog_apply_updates`
- * is called with a single command, in which case it leaves the database
- * unmodified in case of a failure).  Use `ddlog_transaction_rollback()` to
- * bring the database back to a known state, specifically the state where it
- * was before the start of the transaction.
+ * Like `ddlog_dump_index()`, this function is thread-safe.
  */
-extern int ddlog_apply_updates(ddlog_prog prog, ddlog_cmd **upds, size_t n);
+int ddlog_query_index_from_flatbuf(ddlog_prog prog,
+                                   const uint8_t *buf,
+                                   size_t n,
+                                   uint8_t **resbuf,
+                                   size_t *resbuf_size,
+                                   size_t *resbuf_capacity,
+                                   size_t *resbuf_offset);
 
-/*
- * Apply updates, serialized into a FlatBuffer, to DDlog tables.
- *
- * The FlatBuffer schema is auto-generated from DDlog code and is stored in
- * `flatbuf/flatbuf.rs`.
+/**
+ * Enumerates the entire contents of an index.
+ * Note that an index over a multiset will still enumerate records only once.
  *
- * `buf` - pointer to FlatBuffer of size `n`.
+ * `idxid` - id of the index to dump.
+ * `cb` - callback invoked for each record in the index.
+ * `cb_arg` - opaque handle passed to each `cb invocation`.
  *
  * On success, returns `0`. On error, returns a negative value and
  * writes error message (see `print_err_msg` parameter to `ddlog_run()`).
  *
+ * This function is thread-safe: it can be invoked concurrently with other
+ * queries and with functions that modify the program, e.g.,
+ * `ddlog_apply_updates()`.  Queries wait for a transaction being committed
+ * to propagate through the dataflow and observe its outcome.
  */
-extern int ddlog_apply_updates_from_flatbuf(ddlog_prog prog,
-                                            const unsigned char *buf,
-                                            size_t n);
+int ddlog_dump_index(ddlog_prog prog,
+                     index_id idxid,
+                     void (*cb)(uintptr_t arg, const ddlog_record *rec),
+                     uintptr_t cb_arg);
 
-/*
+/**
  * Query index by key.
  *
  * `idxid` - id of the index to dump.
@@ -443,21 +668,22 @@ extern int ddlog_apply_updates_from_flatbuf(ddlog_prog prog,
  *
  * On success, returns `0`. On error, returns a negative value and
  * writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+ *
+ * Like `ddlog_dump_index()`, this function is thread-safe.
  */
-extern int
-ddlog_query_index(ddlog_prog prog,
-                  index_id idxid,
-                  ddlog_record *key,
-                  void (*cb)(uintptr_t arg, const ddlog_record *rec),
-                  uintptr_t cb_arg);
+int ddlog_query_index(ddlog_prog prog,
+                      index_id idxid,
+                      const ddlog_record *key,
+                      void (*cb)(uintptr_t arg, const ddlog_record *rec),
+                      uintptr_t cb_arg);
 
-/*
- * Perform a query serialized in a flatbuf; return result in another flatbuf.
+/**
+ * Dump all values in an index to a flatbuf.
  *
  * The FlatBuffer schema is auto-generated from DDlog code and is stored in
  * `flatbuf/flatbuf.rs`.
  *
- * `buf` - pointer to FlatBuffer of size `n` containing the query.
+ * `idxid` - id of the index to dump.
  *
  * The following values returned by the function describe the flatbuffer
  * containing response to the query.
@@ -469,61 +695,144 @@ ddlog_query_index(ddlog_prog prog,
  *
  * On success, returns `0`. On error, returns a negative value and
  * writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+ *
+ * Like `ddlog_dump_index()`, this function is thread-safe.
  */
-extern int ddlog_query_index_from_flatbuf(ddlog_prog prog,
-                                          const unsigned char *buf,
-                                          size_t n,
-                                          unsigned char ** resbuf,
-                                          size_t* resbuf_size,
-                                          size_t* resbuf_capacity,
-                                          size_t* resbuf_offset);
+int ddlog_dump_index_to_flatbuf(ddlog_prog prog,
+                                index_id idxid,
+                                uint8_t **resbuf,
+                                size_t *resbuf_size,
+                                size_t *resbuf_capacity,
+                                size_t *resbuf_offset);
 
-/*
- * Enumerates the entire contents of an index.
- * Note that an index over a multiset will still enumerate records only once.
+/**
+ * Deallocate a FlatBuffer returned by
+ * `ddlog_transaction_commit_dump_changes_to_flatbuf`.  Must be called once for
+ * each buffer returned by a successful invocation of
+ * `ddlog_transaction_commit_dump_changes_to_flatbuf`.
+ */
+void ddlog_flatbuf_free(uint8_t *buf, size_t buf_size, size_t buf_capacity);
+
+/**
+ * Commit a transaction; propagate all buffered changes through all
+ * rules in the program and update all output relations.
  *
- * `idxid` - id of the index to dump.
- * `cb` - callback invoked for each record in the index.
- * `cb_arg` - opaque handle passed to each `cb invocation`.
+ * On success, returns `0`; on error, returns `-1` and prints error message
+ * (see `print_err_msg` parameter to `ddlog_run()`).
+ *
+ * This function will fail if there is no transaction in progress.
+ */
+int ddlog_transaction_commit(ddlog_prog prog);
+
+/**
+ * Wait until the update callback has seen all changes committed so far.
+ * Only a program started with `ddlog_run_async()` delivers changes after
+ * `ddlog_transaction_commit()` returns; for other programs, this function
+ * returns immediately.
+ *
+ * On success, returns `0`; on error, returns `-1` and prints error message
+ * (see `print_err_msg` parameter to `ddlog_run()`).
+ */
+int ddlog_flush_updates(ddlog_prog prog);
+
+/**
+ * Discard all buffered updates and abort the current transaction.
+ *
+ * On success, returns `0`; on error, returns `-1` and prints error message
+ * (see `print_err_msg` parameter to `ddlog_run()`).
+ *
+ * This function will fail if there is no transaction in progress.
+ */
+int ddlog_transaction_rollback(ddlog_prog prog);
+
+/**
+ * Apply updates to DDlog tables.  See the ddlog_cmd API below.
  *
  * On success, returns `0`. On error, returns a negative value and
  * writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+ *
+ * Whether the function succeeds or fails, it consumes all commands in
+ * the `upds` array (but not the array itself), so they can no longer be
+ * accessed by the caller after the function returns.
+ *
+ * This function fails non-atomically: upon a failure, it may have applied
+ * a _subset_ of input commands (one exception is when `ddlog_apply_updates`
+ * is called with a single command, in which case it leaves the database
+ * unmodified in case of a failure).  Use `ddlog_transaction_rollback()` to
+ * bring the database back to a known state, specifically the state where it
+ * was before the start of the transaction.
  */
-extern int
-ddlog_dump_index(ddlog_prog prog,
-                 index_id idxid,
-                 void (*cb)(uintptr_t arg, const ddlog_record *rec),
-                 uintptr_t cb_arg);
+int ddlog_apply_updates(ddlog_prog prog, ddlog_cmd *const *upds, size_t n);
 
-
-/*
- * Dump all values in an index to a flatbuf.
+/**
+ * Apply updates, serialized into a FlatBuffer, to DDlog tables.
  *
  * The FlatBuffer schema is auto-generated from DDlog code and is stored in
  * `flatbuf/flatbuf.rs`.
  *
- * `idxid` - id of the index to dump.
- *
- * The following values returned by the function describe the flatbuffer
- * containing response to the query.
- * `resbuf` - address where the function will write the pointer to the reply
- * flatbuf.
- * `resbuf_size` - address to store the size of the generated flatbuf.
- * `resbuf_capacity` - address to store generated flatbuf capacity.
- * `resbuf_offset` - address to store data offset inside the returned buffer.
+ * `buf` - pointer to FlatBuffer of size `n`.
  *
  * On success, returns `0`. On error, returns a negative value and
  * writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+ */
+int ddlog_apply_updates_from_flatbuf(ddlog_prog prog, const uint8_t *buf, size_t n);
+
+/**
+ * Parse a record written in DDlog syntax, e.g., `Foo{1, "bar"}` or
+ * `Foo{.x = 1, .y = "bar"}`.  `text` is a null-terminated UTF8 string.
  *
+ * This is a faster alternative to constructing the record one field at a
+ * time using the record API below.  The record is not type checked until
+ * it is used in a command, e.g., `ddlog_insert_cmd()`.
+ *
+ * On success, returns a new record, which must be either deallocated using
+ * `ddlog_free()` or passed to a function that takes its ownership.  On error,
+ * returns NULL; the error message returned by `ddlog_last_error()` contains
+ * the line and column at which parsing failed.
  */
-extern int ddlog_dump_index_to_flatbuf(ddlog_prog prog,
-                                       index_id idxid,
-                                       unsigned char ** resbuf,
-                                       size_t* resbuf_size,
-                                       size_t* resbuf_capacity,
-                                       size_t* resbuf_offset);
+ddlog_record *ddlog_parse_record(const char *text);
 
-/*
+/**
+ * Parse a sequence of update commands written in the syntax of the DDlog
+ * command-line interface, e.g.:
+ *
+ * ```text
+ * insert Foo(1, "bar"),
+ * delete Foo(2, "baz"),
+ * modify Bar 5 <- Bar{.x = 6};
+ * ```
+ *
+ * Only `insert`, `insert_or_update`, `delete`, `delete_key`, and `modify`
+ * commands, separated by `,` or `;`, and comments are allowed; other
+ * commands, e.g., `start` or `commit`, are reported as errors.  Relations
+ * are referred to by name, as in `ddlog_get_table_id()`.
+ *
+ * `text` - null-terminated UTF8 string containing the commands.
+ *
+ * `cmds` - on success, receives an array of commands, which can be passed
+ *     to `ddlog_apply_updates()`.  The array must be deallocated with
+ *     `ddlog_free_commands()`.
+ *
+ * `num_cmds` - on success, receives the number of commands in `cmds`.
+ *
+ * On success, returns `0`.  On error, returns `-1`; the error message
+ * returned by `ddlog_last_error()` contains the line and column at which
+ * parsing failed.
+ */
+int ddlog_parse_commands(const char *text, ddlog_cmd ***cmds, size_t *num_cmds);
+
+/**
+ * Deallocate an array of commands returned by `ddlog_parse_commands()`.
+ * `num_cmds` must be equal to the number of commands returned along with
+ * the array.
+ *
+ * Only the array is deallocated, not the commands in it, as they are
+ * normally consumed by `ddlog_apply_updates()`.  Commands that have not
+ * been applied must be deallocated using `ddlog_free_cmd()`.
+ */
+void ddlog_free_commands(ddlog_cmd **cmds, size_t num_cmds);
+
+/**
  * Remove all records from an input relation.
  *
  * Fails if there is no transaction in progress.
@@ -531,9 +840,9 @@ extern int ddlog_dump_index_to_flatbuf(ddlog_prog prog,
  * On success, returns `0`. On error, returns a negative value and
  * writes error message (see `print_err_msg` parameter to `ddlog_run()`).
  */
-extern int ddlog_clear_relation(ddlog_prog prog, table_id table);
+int ddlog_clear_relation(ddlog_prog prog, table_id table);
 
-/*
+/**
  * Dump the content of an output table by invoking `cb` for each value
  * in the table.
  *
@@ -550,65 +859,178 @@ extern int ddlog_clear_relation(ddlog_prog prog, table_id table);
  *
  * The content of the table returned by this function represents
  * database state after the last committed transaction.
+ *
+ * This function is thread-safe: it can be invoked concurrently with
+ * `ddlog_dump_table()` and with functions that modify the program, e.g.,
+ * `ddlog_apply_updates()`.
  */
-extern int ddlog_dump_table(ddlog_prog prog, table_id table,
-                            bool (*cb)(uintptr_t arg, const ddlog_record *rec, ssize_t weight),
-                            uintptr_t cb_arg);
+int ddlog_dump_table(ddlog_prog prog, table_id table, bool (*cb)(uintptr_t arg,
+                                                                 const ddlog_record *rec,
+                                                                 ssize_t w), uintptr_t cb_arg);
 
+/**
+ * Subscribe to changes to an output table.
+ *
+ * `cb` is invoked for every record inserted into (positive `weight`) or
+ * deleted from (negative `weight`) the table, with `cb_arg` as its first
+ * argument.  Unlike the callback passed to `ddlog_run()`, which receives
+ * changes to all output tables, `cb` only receives changes to `table`.
+ * Any number of subscribers can be registered for the same table.
+ *
+ * The subscription takes effect at the start of the next transaction.
+ * Callbacks are invoked from a DDlog thread during
+ * `ddlog_transaction_commit()`; the `rec` argument is a borrowed reference
+ * that is only valid for the duration of the callback.
+ *
+ * On success, returns a non-negative subscription id to be passed to
+ * `ddlog_unsubscribe()`.  On error, returns -1 and writes error message
+ * (see `print_err_msg` parameter to `ddlog_run()`).
+ */
+int64_t ddlog_subscribe(ddlog_prog prog,
+                        table_id table,
+                        ddlog_update_callback cb,
+                        uintptr_t cb_arg);
 
-/**********************************************************************
- * Delta API.
- **********************************************************************/
+/**
+ * Like `ddlog_subscribe()`, but subscribes to changes to the contents of an
+ * index, i.e., `cb` is only invoked for records that are added to or
+ * removed from the index.  `table` passed to `cb` is the id of the table
+ * that the index is built on.  Fails if the index is not built on an output
+ * table.
+ */
+int64_t ddlog_subscribe_index(ddlog_prog prog,
+                              index_id index,
+                              ddlog_update_callback cb,
+                              uintptr_t cb_arg);
 
-/*
- * NOTE: This API is _not_ thread-safe. It is the callers responsibility to ensure
- * that at most one thread can access `ddlog_delta` at the same time.
+/**
+ * Like `ddlog_subscribe()`, but delivers changes in batches: once a
+ * transaction commits, `cb` is invoked with an array of all records
+ * inserted into or deleted from `table` by the transaction, rather than
+ * once for every record.  Each record occurs in the array at most once.  `cb`
+ * is not invoked for transactions that do not modify `table` or that fail.
+ *
+ * Batching saves one callback invocation per record, but not the cost of
+ * copying: every change in the batch is converted to a freshly allocated
+ * record before `cb` is invoked.  Use `ddlog_subscribe_flatbuf()` to
+ * serialize the batch without the conversion.
+ *
+ * The `updates` array and the records in it are owned by DDlog and are only
+ * valid for the duration of the callback; the callback must not deallocate
+ * them.
+ *
+ * On success, returns a non-negative subscription id to be passed to
+ * `ddlog_unsubscribe()`.  On error, returns -1; the error can be retrieved
+ * using `ddlog_last_error()`.
+ */
+int64_t ddlog_subscribe_batch(ddlog_prog prog,
+                              table_id table,
+                              ddlog_batch_callback cb,
+                              uintptr_t cb_arg);
+
+/**
+ * Like `ddlog_subscribe_batch()`, but serializes each batch of changes to a
+ * FlatBuffer, in the same format as
+ * `ddlog_transaction_commit_dump_changes_to_flatbuf()`.  The buffer is
+ * serialized directly from DDlog values, without converting them to records.
+ *
+ * The buffer is owned by DDlog and is only valid for the duration of the
+ * callback.
+ *
+ * Fails with `DDLOG_ERR_UNSUPPORTED` if DDlog was compiled without
+ * FlatBuffers support.
  */
+int64_t ddlog_subscribe_flatbuf(ddlog_prog prog,
+                                table_id table,
+                                ddlog_flatbuf_callback cb,
+                                uintptr_t cb_arg);
 
-/*
+/**
+ * Cancel a subscription created by `ddlog_subscribe()`,
+ * `ddlog_subscribe_index()`, `ddlog_subscribe_batch()`, or
+ * `ddlog_subscribe_flatbuf()`.  The callback stops being invoked at the
+ * start of the next transaction.
+ *
+ * On success, returns `0`. On error, returns -1.
+ */
+int ddlog_unsubscribe(ddlog_prog prog, int64_t subscription);
+
+/**
+ * Controls recording of differential operator runtimes.  When enabled,
+ * DDlog records each activation of every operator and prints the
+ * per-operator CPU usage summary in the profile.  When disabled, the
+ * recording stops, but the previously accumulated profile is preserved.
+ *
+ * Recording CPU events can be expensive in large dataflows and is
+ * therefore disabled by default.
+ */
+int ddlog_enable_cpu_profiling(ddlog_prog prog, bool enable);
+
+/**
+ * Controls recording of timely operator runtimes. When enabled,
+ * DDlog receives timely dataflow events and writes them out to a CSV file
+ * where they can be queried later for useful information about program
+ * execution. This is particularly useful for DDlog programs running with
+ * multiple workers. When disabled, the recording stops, but the previously
+ * accumulated profile is preserved.
+ *
+ * Recording timely events can be expensive in large dataflows and is
+ * therefore disabled by default.
+ */
+int ddlog_enable_timely_profiling(ddlog_prog prog, bool enable);
+
+/**
+ * Returns DDlog program runtime profile as a C string.
+ *
+ * The returned string must be deallocated using `ddlog_string_free()`.
+ */
+char *ddlog_profile(ddlog_prog prog);
+
+/**
+ * Deallocate a C string returned by DDlog
+ * (currently only applicable to the string returned by `ddlog_profile()` and
+ * `ddlog_dump_record()`).
+ */
+void ddlog_string_free(char *s);
+
+/**
  * Creates an empty delta.
  */
-extern ddlog_delta* ddlog_new_delta(void);
+ddlog_delta *ddlog_new_delta(void);
 
-/*
+/**
  * Retrieve changes to a specific table.
  *
  * Returns a delta that only contains changes to one table.
  */
-extern ddlog_delta* ddlog_delta_get_table(const ddlog_delta *delta, table_id table);
+ddlog_delta *ddlog_delta_get_table(const ddlog_delta *delta, table_id table);
 
-/*
+/**
  * Enumerate changes in this delta.
  *
  * Invokes `cb` for each record in `delta`.
  *
  * `cb_arg` is an opaque handle passed to each `cb invocation`.
  */
-extern void ddlog_delta_enumerate(
-    const ddlog_delta *delta,
-    void (*cb)(uintptr_t arg,
-               table_id table,
-               const ddlog_record *rec,
-               ssize_t weight),
-    uintptr_t cb_arg);
+void ddlog_delta_enumerate(const ddlog_delta *delta, ddlog_update_callback cb, uintptr_t cb_arg);
 
-/*
+/**
  * Remove changes to the specified table from `delta`.
  */
-extern void ddlog_delta_clear_table(ddlog_delta *delta, table_id table);
+void ddlog_delta_clear_table(ddlog_delta *delta, table_id table);
 
-/*
+/**
  * Remove changes to the specified table from `delta` and return them as a separate
  * delta.  The caller is responsible for deallocating the new delta.
  */
-extern ddlog_delta* ddlog_delta_remove_table(ddlog_delta *delta, table_id table);
+ddlog_delta *ddlog_delta_remove_table(ddlog_delta *delta, table_id table);
 
-/*
+/**
  * Clear changes to all tables.  Leaves `delta` empty.
  */
-extern void ddlog_delta_clear(ddlog_delta *delta);
+void ddlog_delta_clear(ddlog_delta *delta);
 
-/*
+/**
  * Adds the contents of `new_delta` to `delta`.
  *
  * Example 1: `delta` does not contain record `r`; `new_delta` contains record
@@ -626,18 +1048,14 @@ extern void ddlog_delta_clear(ddlog_delta *delta);
  * the sum of the two weights, it should only occur when working with multisets
  * or streams.
  */
-extern void ddlog_delta_union(ddlog_delta *delta, const ddlog_delta *new_delta);
+void ddlog_delta_union(ddlog_delta *delta, const ddlog_delta *new_delta);
 
-/*
+/**
  * Deallocate delta.  Invalidates the pointer.
  */
-extern void ddlog_free_delta(ddlog_delta *delta);
+void ddlog_free_delta(ddlog_delta *delta);
 
-/**********************************************************************
- * OVSDB API.
- **********************************************************************/
-
-/*
+/**
  * Parse OVSDB JSON <table-updates> value into DDlog commands; apply
  * commands to a DDlog program.
  *
@@ -648,14 +1066,15 @@ extern void ddlog_free_delta(ddlog_delta *delta);
  * northbound database updates.
  *
  * `updates` is the JSON string, e.g.,
- * ```
+ * ```text
  * {"Logical_Switch":{"ffe8d84e-b4a0-419e-b865-19f151eed878":{"new":{"acls":["set",[]],"dns_records":["set",[]],"external_ids":["map",[]],"load_balancer":["set",[]],"name":"lsw0","other_config":["map",[]],"ports":["set",[]],"qos_rules":["set",[]]}}}}
  * ```
  */
-extern int ddlog_apply_ovsdb_updates(ddlog_prog hprog, const char *prefix,
-                                     const char *updates);
+int ddlog_apply_ovsdb_updates(ddlog_prog prog,
+                              const char *prefix,
+                              const char *updates);
 
-/*
+/**
  * Dump Delta-Plus, Delta-Minus, and Delta-Update tables for OVSDB table
  * `table` declared in DDlog module `module`, as a sequence of OVSDB insert,
  * delete, and update commands in JSON format.
@@ -670,34 +1089,13 @@ extern int ddlog_apply_ovsdb_updates(ddlog_prog hprog, const char *prefix,
  * On error, returns a negative number and writes error message
  * (see `print_err_msg` parameter to `ddlog_run()`).
  */
-extern int ddlog_dump_ovsdb_delta_tables(
-    ddlog_prog hprog,
-    const ddlog_delta* delta,
-    const char *module,
-    const char *table, char **json);
+int ddlog_dump_ovsdb_delta_tables(ddlog_prog prog,
+                                  const ddlog_delta *delta,
+                                  const char *module,
+                                  const char *table,
+                                  char **json);
 
-/*
- * Dump output table for OVSDB table `table` declared in DDlog module
- * `module`, as a sequence of OVSDB insert and delete commands in JSON format.
- *
- * `delta` - DDlog delta containing the latest snapshot of `table`.
- * `module` - a fully qualified name of a module.
- *
- * On success, returns `0` and stores a pointer to JSON string in
- * `json`.  This pointer must be later deallocated by calling
- * `ddlog_free_json()`
- *
- * On error, returns a negative number and writes error message
- * (see `print_err_msg` parameter to `ddlog_run()`).
- */
-extern int ddlog_dump_ovsdb_output_table(
-    ddlog_prog hprog,
-    const ddlog_delta* delta,
-    const char *module,
-    const char *table,
-    char **json);
-
-/*
+/**
  * Serializes table record 'rec' into a JSON object for an OVSDB insert,
  * update, or delete operation, respectively, to apply to OVSDB table 'table'.
  *
@@ -708,200 +1106,148 @@ extern int ddlog_dump_ovsdb_output_table(
  * On error, returns a negative number and writes error message
  * (see `print_err_msg` parameter to `ddlog_run()`).
  */
-extern int ddlog_into_ovsdb_insert_str(
-    ddlog_prog prog,
-    const char *table,
-    const ddlog_record *rec,
-    char **json);
-extern int ddlog_into_ovsdb_update_str(
-    ddlog_prog prog,
-    const char *table,
-    const ddlog_record *rec,
-    char **json);
-extern int ddlog_into_ovsdb_delete_str(
-    ddlog_prog prog,
-    const char *table,
-    const ddlog_record *rec,
-    char **json);
-
-/*
- * Deallocates strings returned by other functions in this API.
- */
-extern void ddlog_free_json(char *json);
-
-/***********************************************************************
- * Profiling
- ***********************************************************************/
-
-/*
- * Controls recording of differential operator runtimes.  When enabled,
- * DDlog records each activation of every operator and prints the
- * per-operator CPU usage summary in the profile.  When disabled, the
- * recording stops, but the previously accumulated profile is preserved.
- *
- * Recording CPU events can be expensive in large dataflows and is
- * therefore disabled by default.
- */
-extern int ddlog_enable_cpu_profiling(ddlog_prog prog, bool enable);
-
-/*
- * Returns DDlog program runtime profile as a C string.
- *
- * The returned string must be deallocated using `ddlog_string_free()`.
- */
-extern char* ddlog_profile(ddlog_prog prog);
+int ddlog_into_ovsdb_insert_str(ddlog_prog prog,
+                                const char *table,
+                                const ddlog_record *rec,
+                                char **json);
 
-/*
- * Controls recording of timely operator runtimes. When enabled,
- * DDlog receives timely dataflow events and writes them out to a CSV file
- * where they can be queried later for useful information about program
- * execution. This is particularly useful for DDlog programs running with
- * multiple workers. When disabled, the recording stops, but the previously
- * accumulated profile is preserved.
- *
- * Recording timely events can be expensive in large dataflows and is
- * therefore disabled by default.
- */
-extern int ddlog_enable_timely_profiling(ddlog_prog prog, bool enable);
-
-/***********************************************************************
- * Record API
- ***********************************************************************/
-
-/*
- * The record API is intended solely for passing values to and from
- * DDlog.  It allows constructing and reading values, but does not
- * provide methods to otherwise manipulate them, e.g., to lookup an
- * element in a set or map.
- *
- * This API supports the following ownership policy (see additional
- * details in individual function description):
- *
- * - The client obtains ownership of a record by creating it using
- *   `ddlog_bool()`, `ddlog_string()`, `ddlog_tuple()`, etc.  This is
- *   currently the only way to obtain ownership.
- *
- * - The client yields ownership by either passing the record to DDlog
- *   as part of an update command (see `ddlog_apply_updates()`) or by
- *   attaching it to another owned object (e.g., appending it to a
- *   vector using `ddlog_vector_push()`).
- *
- * - Each owned record encapsulates some dynamically allocated memory.
- *   To avoid memory leaks, the client must transfer the ownership of
- *   every record they own or deallocate the record using
- *   `ddlog_free()`.
- *
- * - There is a limited API for modifying _owned_ records, e.g., by
- *   appending elements to an array.
- *
- * - In addition to owned records, the client can also obtain pointers to
- *   *borrowed* records in one of two ways:
+/**
+ * Serializes table record 'rec' into a JSON object for an OVSDB insert,
+ * update, or delete operation, respectively, to apply to OVSDB table 'table'.
  *
- *    1. By invoking `ddlog_dump_table()` or `ddlog_dump_index()` to
- *       enumerate the content of an output table or index.  These functions
- *       take a user callback and invoke it once for each record in the table
- *       or index.  The record, passed as argument to the callback is owned
- *       by DDlog and is only valid for the duration of the callback.
- *       (TODO: the only reason for this is convenience, so the client
- *       does not need to worry about deallocating the record later.
- *       The API could be changed to return owned records.)
+ * On success, returns `0` and stores a pointer to JSON string in
+ * `json`.  This pointer must be later deallocated by calling
+ * `ddlog_free_json()`
  *
- *    2. By querying another record of type tuple, vector, set or map.
- *       For instance, `ddlog_get_vector_elem()` returns a borrowed
- *       reference to an element of the vector.
+ * On error, returns a negative number and writes error message
+ * (see `print_err_msg` parameter to `ddlog_run()`).
+ */
+int ddlog_into_ovsdb_delete_str(ddlog_prog prog,
+                                const char *table,
+                                const ddlog_record *rec,
+                                char **json);
+
+/**
+ * Serializes table record 'rec' into a JSON object for an OVSDB insert,
+ * update, or delete operation, respectively, to apply to OVSDB table 'table'.
  *
- * - The client may inspect a borrowed record, but not modify it, attach
- *   to another records or pass to DDlog as part of an update.
+ * On success, returns `0` and stores a pointer to JSON string in
+ * `json`.  This pointer must be later deallocated by calling
+ * `ddlog_free_json()`
  *
- * - Owned records are represented in the API by mutable pointers
- *   (`ddlog_record*`), whereas borrowed records are represented by
- *   immutable pointers (`const ddlog_record*`).
+ * On error, returns a negative number and writes error message
+ * (see `print_err_msg` parameter to `ddlog_run()`).
+ */
+int ddlog_into_ovsdb_update_str(ddlog_prog prog,
+                                const char *table,
+                                const ddlog_record *rec,
+                                char **json);
+
+/**
+ * Dump output table for OVSDB table `table` declared in DDlog module
+ * `module`, as a sequence of OVSDB insert and delete commands in JSON format.
  *
- * Type checking:
+ * `delta` - DDlog delta containing the latest snapshot of `table`.
+ * `module` - a fully qualified name of a module.
  *
- * The Record API does not perform any type checking, e.g., one can
- * create a vector with elements of different types or a struct whose
- * fields don't match declarations in the DDlog program. Type checking
- * is performed by DDlog before inserting records to the database, e.g.,
- * in the `ddlog_apply_updates()` function.  The function will fail if
- * it detects type mismatch between relation type and the record
- * supplied by the client.
+ * On success, returns `0` and stores a pointer to JSON string in
+ * `json`.  This pointer must be later deallocated by calling
+ * `ddlog_free_json()`
  *
- * The client can rely on records read from the database to have types
- * that matches the corresponding DDlog output table declaration.
+ * On error, returns a negative number and writes error message
+ * (see `print_err_msg` parameter to `ddlog_run()`).
  */
+int ddlog_dump_ovsdb_output_table(ddlog_prog prog,
+                                  const ddlog_delta *delta,
+                                  const char *module,
+                                  const char *table,
+                                  char **json);
 
-/*
+/**
+ * Deallocates strings returned by other functions in this API.
+ */
+void ddlog_free_json(char *str);
+
+/**
  * Dump record into a C string for debug printing.
  *
  * Returns `NULL` on error.
  *
  * The returned string must be deallocated using `ddlog_string_free()`.
  */
-extern char* ddlog_dump_record(const ddlog_record *rec);
+char *ddlog_dump_record(const ddlog_record *record);
 
-/*
+/**
  * Deallocate an owned record.
  */
-extern void ddlog_free(ddlog_record *rec);
-
-/*
- * Deallocate a C string returned by DDlog
- * (currently only applicable to the string returned by `ddlog_profile()` and
- * `ddlog_dump_record()`).
- */
-extern void ddlog_string_free(char *s);
+void ddlog_free(ddlog_record *rec);
 
-/*
+/**
  * Create a Boolean value
  */
-extern ddlog_record* ddlog_bool(bool b);
+ddlog_record *ddlog_bool(bool b);
 
-/*
+/**
  * Returns `true` if `rec` is a Boolean and `false` otherwise
  */
-extern bool ddlog_is_bool(const ddlog_record *rec);
+bool ddlog_is_bool(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves the value of a Boolean.
  *
  * Returns `false` if `rec` is not a Boolean.
  */
-extern bool ddlog_get_bool(const ddlog_record *rec);
+bool ddlog_get_bool(const ddlog_record *rec);
 
-/*
+/**
  * Returns `true` if `rec` is an integer and `false` otherwise.
  */
-extern bool ddlog_is_int(const ddlog_record *rec);
+bool ddlog_is_int(const ddlog_record *rec);
 
-/*
+/**
  * Returns `true` if `rec` is a float and `false` otherwise.
  */
-extern bool ddlog_is_float(const ddlog_record *rec);
+bool ddlog_is_float(const ddlog_record *rec);
 
-/*
+/**
+ * Create a float value.
+ */
+ddlog_record *ddlog_float(float f);
+
+/**
+ * Retrieves the value of a float.
+ *
+ * Returns `0` if `rec` is not a float.
+ */
+float ddlog_get_float(const ddlog_record *rec);
+
+/**
  * Returns `true` if `rec` is a double and `false` otherwise.
  */
-extern bool ddlog_is_double(const ddlog_record *rec);
+bool ddlog_is_double(const ddlog_record *rec);
 
-/*
- * Returns the fewest bits necessary to express the integer value,
- * not including the sign.
+/**
+ * Create a double value.
+ */
+ddlog_record *ddlog_double(double v);
+
+/**
+ * Retrieves the value of a double.
  *
- * Returns `0` if the `rec` is not an integer record.
+ * Returns `0` if `rec` is not a double.
  */
-extern size_t ddlog_int_bits(const ddlog_record *rec);
+double ddlog_get_double(const ddlog_record *rec);
 
-/*
+/**
  * Create an integer value with arbitrary width and signedness.  Can be used to
  * populate any ddlog field of type `bit<N>`, `signed<N>` or `bigint`.
  *
  * `v` - byte array containing big endian two's complement representation of the number
  * `size` - size of `v` in bytes
  */
-extern ddlog_record* ddlog_int(unsigned char * v, size_t size);
+ddlog_record *ddlog_int(const unsigned char *v, size_t size);
 
-/*
+/**
  * Extract big-endian byte representation of arbitrary integer value.
  *
  * `buf`        - buffer to store the big-endian two's complement byte representation
@@ -912,101 +1258,82 @@ extern ddlog_record* ddlog_int(unsigned char * v, size_t size);
  * represent the value; otherwise returns the number of bytes stored in `buf` or `-1` if `buf`
  * is not big enough.
  */
-extern ssize_t ddlog_get_int(
-        const ddlog_record *rec,
-        unsigned char *buf,
-        size_t capacity);
+ssize_t ddlog_get_int(const ddlog_record *rec, unsigned char *buf, size_t capacity);
 
-/*
+/**
+ * Returns the fewest bits necessary to express the integer value,
+ * not including the sign.
+ *
+ * Returns `0` if the `rec` is not an integer record.
+ */
+size_t ddlog_int_bits(const ddlog_record *rec);
+
+/**
  * Create an unsigned integer value.  Can be used to populate any ddlog field
  * of type `bit<N>`, `N<=64`
  */
-extern ddlog_record* ddlog_u64(uint64_t v);
+ddlog_record *ddlog_u64(uint64_t v);
 
-/*
+/**
  * Retrieves the value of an unsigned integer.
  *
  * Returns `0` if `rec` is not an integer or if its value does not
  * fit into 64 bits.
  */
-extern uint64_t ddlog_get_u64(const ddlog_record *rec);
+uint64_t ddlog_get_u64(const ddlog_record *rec);
 
-/*
+/**
  * Create a signed integer value.  Can be used to populate any ddlog field
  * of type `signed<N>`, `N<=64`
  */
-extern ddlog_record* ddlog_i64(int64_t v);
+ddlog_record *ddlog_i64(int64_t v);
 
-/*
+/**
  * Retrieves the value of a signed integer.
  *
  * Returns `0` if `rec` is not an integer or if its value does not
  * fit into 64 bits.
  */
-extern int64_t ddlog_get_i64(const ddlog_record *rec);
-
-/*
- * Create a float value.
- */
-extern ddlog_record* ddlog_float(float f);
-
-/*
- * Retrieves the value of a float.
- *
- * Returns `0` if `rec` is not a float.
- */
-extern float ddlog_get_float(const ddlog_record* rec);
-
-/*
- * Create a double value.
- */
-extern ddlog_record* ddlog_double(double d);
-
-/*
- * Retrieves the value of a double.
- *
- * Returns `0` if `rec` is not a double.
- */
-extern double ddlog_get_double(const ddlog_record* rec);
+int64_t ddlog_get_i64(const ddlog_record *rec);
 
-/*
+/**
  * Create an unsigned integer value.  Can be used to populate any ddlog field
  * of type `bit<N>`, `N<=128`
  */
-extern ddlog_record* ddlog_u128(__uint128_t v);
+ddlog_record *ddlog_u128(__uint128_t v);
 
-/*
+/**
  * Retrieves the value of an integer.
  *
  * Returns `0` if `rec` is not an integer or if its value does not
  * fit into 128 bits.
  */
-extern __uint128_t ddlog_get_u128(const ddlog_record *rec);
+__uint128_t ddlog_get_u128(const ddlog_record *rec);
 
-/*
+/**
  * Create a signed integer value.  Can be used to populate any ddlog field
  * of type `signed<N>`, `N<=128`
  */
-extern ddlog_record* ddlog_i128(__int128_t v);
+ddlog_record *ddlog_i128(__int128_t v);
 
-/*
+/**
  * Retrieves the value of a signed integer.
  *
  * Returns `0` if `rec` is not an integer or if its value does not
  * fit into 128 bits.
  */
-extern __int128_t ddlog_get_i128(const ddlog_record *rec);
+__int128_t ddlog_get_i128(const ddlog_record *rec);
 
-/*
+/**
  * Create a string value from a NULL-terminated string `s`.  This function
  * copies `s` to an internal buffer, so the caller is responsible for
  * deallocating `s` if it was dynamically allocated.
  *
  * Returns `NULL` if `s` is not a valid null-terminated UTF8 string.
  */
-extern ddlog_record* ddlog_string(const char *s);
+ddlog_record *ddlog_string(const char *string);
 
-/*
+/**
  * Create a string value.
  *
  * `s` - points to the start of a UTF8 string.  The string does not have to be
@@ -1020,21 +1347,21 @@ extern ddlog_record* ddlog_string(const char *s);
  *
  * Returns `NULL` if `s` is not a valid UTF8 string.
  */
-extern ddlog_record* ddlog_string_with_length(const char * s, size_t len);
+ddlog_record *ddlog_string_with_length(const char *s, size_t len);
 
-/*
+/**
  * Returns `true` if `rec` is a string and `false` otherwise
  */
-extern bool ddlog_is_string(const ddlog_record *rec);
+bool ddlog_is_string(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves the length of a string in bytes.
  *
  * Returns `0` if `rec` is not a string.
  */
-extern size_t ddlog_get_strlen(const ddlog_record *rec);
+size_t ddlog_get_strlen(const ddlog_record *rec);
 
-/*
+/**
  * Returns the contents of a DDlog string.
  *
  * On success, returns pointer to the string and stores the length of the
@@ -1050,9 +1377,9 @@ extern size_t ddlog_get_strlen(const ddlog_record *rec);
  * the lifetime of the record it was obtained from, e.g., the pointer is
  * invalidated when the value is written to the database.
  */
-extern const char * ddlog_get_str_with_length(const ddlog_record *rec, size_t *len);
+const char *ddlog_get_str_with_length(const ddlog_record *rec, size_t *len);
 
-/*
+/**
  * Create a serialized record from a NULL-terminated string `t` containing
  * the serialization scheme and a NULL-terminated string `s` containing
  * the serialized data.
@@ -1061,9 +1388,9 @@ extern const char * ddlog_get_str_with_length(const ddlog_record *rec, size_t *l
  *
  * Returns `NULL` if `t` or `s` are not a valid null-terminated UTF8 strings.
  */
-extern ddlog_record* ddlog_serialized(const char *t, const char *s);
+ddlog_record *ddlog_serialized(const char *t, const char *s);
 
-/*
+/**
  * Create a serialized record.
  *
  * `t` - points to the start of a UTF8 string containing the
@@ -1084,15 +1411,17 @@ extern ddlog_record* ddlog_serialized(const char *t, const char *s);
  *
  * Returns `NULL` if `t` or `s` are not a valid UTF8 strings.
  */
-extern ddlog_record* ddlog_serialized_with_length(const char *t, size_t t_len,
-                                                  const char *s, size_t s_len);
+ddlog_record *ddlog_serialized_with_length(const char *t,
+                                           size_t t_len,
+                                           const char *s,
+                                           size_t s_len);
 
-/*
+/**
  * Returns `true` if `rec` is a serialized record and `false` otherwise
  */
-extern bool ddlog_is_serialized(const ddlog_record *rec);
+bool ddlog_is_serialized(const ddlog_record *rec);
 
-/*
+/**
  * Create a tuple with specified fields.
  *
  * `len` is the length of the `fields` array.
@@ -1103,21 +1432,21 @@ extern bool ddlog_is_serialized(const ddlog_record *rec);
  * ownership of the `fields` array itself.  The caller is responsible
  * for deallocating the array if needed.
  */
-extern ddlog_record* ddlog_tuple(ddlog_record ** fields, size_t len);
+ddlog_record *ddlog_tuple(ddlog_record *const *fields, size_t len);
 
-/*
+/**
  * Returns `true` if `rec` is a tuple and false otherwise
  */
-extern bool ddlog_is_tuple(const ddlog_record *rec);
+bool ddlog_is_tuple(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves the number of fields in a tuple.
  *
  * Returns `0` if `rec` is not a tuple.
  */
-extern size_t ddlog_get_tuple_size(const ddlog_record *rec);
+size_t ddlog_get_tuple_size(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves `i`th field of the tuple.
  *
  * Returns NULL if `tup` is not a tuple or if the tuple has fewer than `i`
@@ -1130,23 +1459,22 @@ extern size_t ddlog_get_tuple_size(const ddlog_record *rec);
  * the record it was obtained from, e.g., the pointer is invalidated
  * when the value is written to the database.
  */
-extern const ddlog_record* ddlog_get_tuple_field(const ddlog_record* tup,
-                                                 size_t i);
+const ddlog_record *ddlog_get_tuple_field(const ddlog_record *rec, size_t idx);
 
-/*
+/**
  * Convenience method to create a 2-tuple.  Such tuples are useful,
  * e.g., in constructing maps out of key-value pairs.
  *
  * The function takes ownership of `v1` and `v2`.
  */
-extern ddlog_record* ddlog_pair(ddlog_record *v1, ddlog_record *v2);
+ddlog_record *ddlog_pair(ddlog_record *v1, ddlog_record *v2);
 
-/*
+/**
  * An alternative way to construct tuples by adding fields one-by-one.
  *
  * To use this function, start with creating a tuple using, e.g.,
  *
- * ```
+ * ```text
  * ddlog_tuple(NULL, 0);
  * ```
  *
@@ -1156,9 +1484,9 @@ extern ddlog_record* ddlog_pair(ddlog_record *v1, ddlog_record *v2);
  * This function takes ownership of `rec`, which should not be used
  * after the call.
  */
-extern void ddlog_tuple_push(ddlog_record *tup, ddlog_record *rec);
+void ddlog_tuple_push(ddlog_record *tup, ddlog_record *rec);
 
-/*
+/**
  * Create a vector with specified elements.
  *
  * `len` is the length of the `recs` array.
@@ -1169,21 +1497,21 @@ extern void ddlog_tuple_push(ddlog_record *tup, ddlog_record *rec);
  * the `recs` array itself.  The caller is responsible for deallocating the
  * array if needed.
  */
-extern ddlog_record* ddlog_vector(ddlog_record **recs, size_t len);
+ddlog_record *ddlog_vector(ddlog_record *const *fields, size_t len);
 
-/*
+/**
  * Returns `true` if `rec` is a vector and false otherwise
  */
-extern bool ddlog_is_vector(const ddlog_record *rec);
+bool ddlog_is_vector(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves the number of elements in a vector.
  *
  * Returns `0` if `rec` is not a vector.
  */
-extern size_t ddlog_get_vector_size(const ddlog_record *rec);
+size_t ddlog_get_vector_size(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves `i`th element of the vector.
  *
  * Returns NULL if `vec` is not a vector or if the vector is shorter than `i`.
@@ -1195,18 +1523,17 @@ extern size_t ddlog_get_vector_size(const ddlog_record *rec);
  * the record it was obtained from, e.g., the pointer is invalidated
  * when the value is written to the database.
  */
-extern const ddlog_record* ddlog_get_vector_elem(const ddlog_record *vec,
-                                                 size_t idx);
+const ddlog_record *ddlog_get_vector_elem(const ddlog_record *rec, size_t idx);
 
-/*
+/**
  * Append a value at the end of the vector.
  *
  * This function takes ownership of `rec`, which should not be used after the
  * call.
  */
-extern void ddlog_vector_push(ddlog_record *vec, ddlog_record *rec);
+void ddlog_vector_push(ddlog_record *vec, ddlog_record *rec);
 
-/*
+/**
  * Create a set with specified elements.
  *
  * `len` is the length of the `recs` array.  If `len` is greater than
@@ -1217,21 +1544,21 @@ extern void ddlog_vector_push(ddlog_record *vec, ddlog_record *rec);
  * ownership of the `recs` array itself.  The caller is responsible for
  * deallocating the array if needed.
  */
-extern ddlog_record* ddlog_set(ddlog_record **recs, size_t len);
+ddlog_record *ddlog_set(ddlog_record *const *fields, size_t len);
 
-/*
+/**
  * Returns `true` if `rec` is a set and false otherwise
  */
-extern bool ddlog_is_set(const ddlog_record *rec);
+bool ddlog_is_set(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves the number of values in a set.
  *
  * Returns `0` if `rec` is not a set.
  */
-extern size_t ddlog_get_set_size(const ddlog_record *rec);
+size_t ddlog_get_set_size(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves `i`th element of the set.  The `ddlog_record` type
  * internally represents sets as vectors of elements.  `idx` indexes
  * into this vector.
@@ -1246,10 +1573,9 @@ extern size_t ddlog_get_set_size(const ddlog_record *rec);
  * the record it was obtained from, e.g., the pointer is invalidated
  * when the value is written to the database.
  */
-extern const ddlog_record* ddlog_get_set_elem(const ddlog_record* set,
-                                              size_t i);
+const ddlog_record *ddlog_get_set_elem(const ddlog_record *rec, size_t idx);
 
-/*
+/**
  * Append a value to a set.  The `ddlog_record` type internally
  * represents sets as vectors of elements.  These vectors only get
  * converted to an actual set representation inside DDlog.  In
@@ -1258,9 +1584,9 @@ extern const ddlog_record* ddlog_get_set_elem(const ddlog_record* set,
  * This function takes ownership of `rec`, which should not be used
  * after the call.
  */
-extern void ddlog_set_push(ddlog_record *set, ddlog_record *rec);
+void ddlog_set_push(ddlog_record *set, ddlog_record *rec);
 
-/*
+/**
  * Create a map with specified elements.  Each element in `recs` must be
  * a 2-tuple representing a key-value pair.
  *
@@ -1272,21 +1598,21 @@ extern void ddlog_set_push(ddlog_record *set, ddlog_record *rec);
  * ownership of the `recs` array itself.  The caller is responsible for
  * deallocating the array if needed.
  */
-extern ddlog_record* ddlog_map(ddlog_record **recs, size_t len);
+ddlog_record *ddlog_map(ddlog_record *const *fields, size_t len);
 
-/*
+/**
  * Returns `true` if `rec` is a map and false otherwise
  */
-extern bool ddlog_is_map(const ddlog_record *rec);
+bool ddlog_is_map(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves the number of elements in a map.
  *
  * Returns `0` if `rec` is not a map.
  */
-extern size_t ddlog_get_map_size(const ddlog_record *rec);
+size_t ddlog_get_map_size(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves the key of the `i`th element of the map.  The `ddlog_record`
  * type internally represents maps as vectors of elements.  `i` indexes
  * into this vector.
@@ -1301,10 +1627,9 @@ extern size_t ddlog_get_map_size(const ddlog_record *rec);
  * the record it was obtained from, e.g., the pointer is invalidated
  * when the value is written to the database.
  */
-extern const ddlog_record* ddlog_get_map_key(const ddlog_record *map,
-                                             size_t i);
+const ddlog_record *ddlog_get_map_key(const ddlog_record *rec, size_t idx);
 
-/*
+/**
  * Retrieves the value of the `i`th element of the map.  The
  * `ddlog_record` type internally represents maps as vectors of
  * elements.  `i` indexes into this vector.
@@ -1319,10 +1644,9 @@ extern const ddlog_record* ddlog_get_map_key(const ddlog_record *map,
  * the record it was obtained from, e.g., the pointer is invalidated
  * when the value is written to the database.
  */
-extern const ddlog_record* ddlog_get_map_val(const ddlog_record *rec,
-                                             size_t i);
+const ddlog_record *ddlog_get_map_val(const ddlog_record *rec, size_t idx);
 
-/*
+/**
  * Append a key-value pair to a map.  The `ddlog_record` type internally
  * represents maps as vectors of elements.  These vectors only get
  * converted to an actual map representation inside DDlog.  In
@@ -1331,10 +1655,9 @@ extern const ddlog_record* ddlog_get_map_val(const ddlog_record *rec,
  * This function takes ownership of `key` and `val`, which should not be
  * used after the call.
  */
-extern void ddlog_map_push(ddlog_record *map,
-                           ddlog_record *key, ddlog_record *val);
+void ddlog_map_push(ddlog_record *map, ddlog_record *key, ddlog_record *val);
 
-/*
+/**
  * Create a struct with specified constructor and arguments.  This creates a
  * "positional record" where arguments are identified by their order.
  *
@@ -1353,10 +1676,9 @@ extern void ddlog_map_push(ddlog_record *map,
  * ownership of the `args` array itself.  The caller is responsible for
  * deallocating the array if needed.
  */
-extern ddlog_record* ddlog_struct(const char* constructor,
-                                  ddlog_record ** args, size_t len);
+ddlog_record *ddlog_struct(const char *constructor, ddlog_record *const *fields, size_t len);
 
-/*
+/**
  * Create a struct with named fields with specified constructor and
  * fields.  This creates a "named struct" where arguments are
  * identified by their names.
@@ -1377,54 +1699,51 @@ extern ddlog_record* ddlog_struct(const char* constructor,
  * ownership of either `args`, `field_names`, or each field name.  The
  * caller is responsible for deallocating these arrays if needed.
  */
-extern ddlog_record* ddlog_named_struct(const char* constructor,
-                                        const char** field_names,
-                                        ddlog_record** args,
-                                        size_t len);
+ddlog_record *ddlog_named_struct(const char *constructor,
+                                 const char *const *field_names,
+                                 ddlog_record *const *fields,
+                                 size_t len);
 
-/*
+/**
  * Same as `ddlog_struct()`, but passes constructor name as
  * non-null-terminated string represented by its start address and length in
  * bytes.
  */
-extern ddlog_record* ddlog_struct_with_length(const char* constructor,
-                                              size_t constructor_len,
-                                              ddlog_record ** args,
-                                              size_t len);
+ddlog_record *ddlog_struct_with_length(const char *constructor,
+                                       size_t constructor_len,
+                                       ddlog_record *const *fields,
+                                       size_t len);
 
-/*
+/**
  * Same as `ddlog_struct()`, but assumes that `constructor` is a statically
  * allocated string and stores the pointer internally instead of copying it to
  * another buffer.
  */
-extern ddlog_record* ddlog_struct_static_cons(const char *constructor,
-                                              ddlog_record **args, size_t len);
+ddlog_record *ddlog_struct_static_cons(const char *constructor,
+                                       ddlog_record *const *fields,
+                                       size_t len);
 
-/*
+/**
  * Same as ddlog_struct_static_cons(), but passes constructor name as
  * non-null-terminated string represented by its start address and length in
  * bytes.
  */
-extern ddlog_record* ddlog_struct_static_cons_with_length(
-        const char *constructor,
-        size_t constructor_len,
-        ddlog_record **args, size_t args_len);
+ddlog_record *ddlog_struct_static_cons_with_length(const char *constructor,
+                                                   size_t constructor_len,
+                                                   ddlog_record *const *fields,
+                                                   size_t len);
 
-/*
+/**
  * Returns `true` if `rec` is a struct.
  */
-extern bool ddlog_is_struct(const ddlog_record *rec);
+bool ddlog_is_struct(const ddlog_record *rec);
 
-/*
- * Retrieves constructor name as a non-null-terminated string.  Returns
- * string length in bytes in `len`.
- *
- * Returns NULL if `rec` is not a struct.
+/**
+ * Returns `true` if `rec` is a struct with named fields.
  */
-extern const char * ddlog_get_constructor_with_length(const ddlog_record *rec,
-                                                      size_t *len);
+bool ddlog_is_named_struct(const ddlog_record *rec);
 
-/*
+/**
  * Retrieves `i`th argument of a struct.
  *
  * Returns NULL if `rec` is not a struct or if the struct has fewer than
@@ -1437,10 +1756,9 @@ extern const char * ddlog_get_constructor_with_length(const ddlog_record *rec,
  * the record it was obtained from, e.g., the pointer is invalidated
  * when the value is written to the database.
  */
-extern const ddlog_record* ddlog_get_struct_field(const ddlog_record* rec,
-                                                  size_t i);
+const ddlog_record *ddlog_get_struct_field(const ddlog_record *rec, size_t idx);
 
-/*
+/**
  * Retrieves field 'name' of struct 'rec'.
  *
  * Returns NULL if `rec` is not a struct with named fields or if the struct
@@ -1453,15 +1771,9 @@ extern const ddlog_record* ddlog_get_struct_field(const ddlog_record* rec,
  * the record it was obtained from, e.g., the pointer is invalidated
  * when the value is written to the database.
  */
-extern const ddlog_record* ddlog_get_named_struct_field(const ddlog_record* rec,
-                                                        const char* name);
+const ddlog_record *ddlog_get_named_struct_field(const ddlog_record *rec, const char *name);
 
 /**
- * Returns `true` if `rec` is a struct with named fields.
- */
-extern bool ddlog_is_named_struct(const ddlog_record* rec);
-
-/*
  * Retrieves the name of the i-th field of the record.
  *
  * Returns NULL if `rec` is not a struct with named fields or if the struct
@@ -1475,15 +1787,17 @@ extern bool ddlog_is_named_struct(const ddlog_record* rec);
  * the record it was obtained from, e.g., the pointer is invalidated
  * when the value is written to the database.
  */
-extern const char* ddlog_get_named_struct_field_name(const ddlog_record* rec,
-                                                     size_t i,
-                                                     size_t *len);
+const char *ddlog_get_named_struct_field_name(const ddlog_record *rec, size_t idx, size_t *len);
 
-/***********************************************************************
- * Command API
- ***********************************************************************/
+/**
+ * Retrieves constructor name as a non-null-terminated string.  Returns
+ * string length in bytes in `len`.
+ *
+ * Returns NULL if `rec` is not a struct.
+ */
+const char *ddlog_get_constructor_with_length(const ddlog_record *rec, size_t *len);
 
-/*
+/**
  * Create an insert command.
  *
  * `table` - input table to insert to.
@@ -1499,9 +1813,9 @@ extern const char* ddlog_get_named_struct_field_name(const ddlog_record* rec,
  * - The table has a primary key and there exists a record with the same key
  *   as `rec` in the table.
  */
-extern ddlog_cmd* ddlog_insert_cmd(table_id table, ddlog_record *rec);
+ddlog_cmd *ddlog_insert_cmd(size_t table, ddlog_record *rec);
 
-/*
+/**
  * Create an insert-or-update command that inserts a new record, deleting
  * an existing record with the same primary key, if there is one.
  *
@@ -1517,9 +1831,9 @@ extern ddlog_cmd* ddlog_insert_cmd(table_id table, ddlog_record *rec);
  * - `table` does not have a primary key
  * - `rec` does not match the record type of `table`
  */
-extern ddlog_cmd* ddlog_insert_or_update_cmd(table_id table, ddlog_record *rec);
+ddlog_cmd *ddlog_insert_or_update_cmd(size_t table, ddlog_record *rec);
 
-/*
+/**
  * Create a delete-by-value command.
  *
  * `table` - input table to delete from.
@@ -1536,9 +1850,9 @@ extern ddlog_cmd* ddlog_insert_or_update_cmd(table_id table, ddlog_record *rec);
  *   (NOTE: for tables without a primary key, the command succeeds even if
  *   the record does not exist, in which case it is a no-op)
  */
-extern ddlog_cmd* ddlog_delete_val_cmd(table_id table, ddlog_record *rec);
+ddlog_cmd *ddlog_delete_val_cmd(size_t table, ddlog_record *rec);
 
-/*
+/**
  * Create a delete-by-key command.
  *
  * `table` - input table to delete from.
@@ -1554,9 +1868,9 @@ extern ddlog_cmd* ddlog_delete_val_cmd(table_id table, ddlog_record *rec);
  * - `rec` does not match the primary key type of `table`
  * - a record with the specified key does not exist in `table`
  */
-extern ddlog_cmd* ddlog_delete_key_cmd(table_id table, ddlog_record *rec);
+ddlog_cmd *ddlog_delete_key_cmd(size_t table, ddlog_record *rec);
 
-/*
+/**
  * Create a modify-by-key command.
  *
  * `table` - input table to delete from.
@@ -1574,6 +1888,16 @@ extern ddlog_cmd* ddlog_delete_key_cmd(table_id table, ddlog_record *rec);
  * - a record with the specified key does not exist in `table`
  * - `values` does not match the columns of `table`
  */
-extern ddlog_cmd* ddlog_modify_cmd(table_id table, ddlog_record* key, ddlog_record* values);
+ddlog_cmd *ddlog_modify_cmd(size_t table, ddlog_record *key, ddlog_record *values);
+
+/**
+ * Deallocate a command that has not been passed to `ddlog_apply_updates()`,
+ * along with the records it contains.  Does nothing if `cmd` is NULL.
+ */
+void ddlog_free_cmd(ddlog_cmd *cmd);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
 
-#endif
+#endif /* __DDLOG_H__ */
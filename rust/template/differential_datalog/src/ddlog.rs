@@ -1,11 +1,18 @@
+use std::any::TypeId;
 use std::collections::btree_set::BTreeSet;
+#[cfg(feature = "c_api")]
+use std::ffi::CStr;
 use std::fmt::Debug;
 use std::iter::Iterator;
 use std::ops::Deref;
 
 use crate::callback::Callback;
 use crate::ddval::DDValue;
+use crate::error::DDlogError;
+use crate::program::ArrId;
+use crate::program::CBFn;
 use crate::program::IdxId;
+use crate::program::Program;
 use crate::program::RelId;
 use crate::program::Update;
 use crate::record::Record;
@@ -23,10 +30,74 @@ pub trait DDlogConvert: Debug {
     /// Convert a `IdxId` into its symbolic name.
     fn indexid2name(idx_id: IdxId) -> Option<&'static str>;
 
+    /// Retrieve the names of all relations of the program along with
+    /// the (DDlog) types of the values stored in them.
+    fn relation_types() -> Vec<(&'static str, &'static str)>;
+
     /// Convert an `UpdCmd` into an `Update`.
     fn updcmd2upd(upd_cmd: &UpdCmd) -> Result<Update<DDValue>, String>;
 }
 
+/// Program-specific parts of a DDlog program: its dataflow graph and
+/// relation and index tables.  Implemented by the crate generated for each
+/// program; `HDDlog` is generic over this trait, so the same embedding code
+/// can run any DDlog program.
+pub trait DDlogProgram: DDlogConvert + 'static {
+    /// Wrapper type that implements Serialize/Deserialize functionality for Update<DDValue>.
+    type UpdateSerializer: Debug
+        + Send
+        + Serialize
+        + DeserializeOwned
+        + From<Update<DDValue>>
+        + Into<Update<DDValue>>;
+
+    /// Instantiate the dataflow graph of the program.  `update_cb` is
+    /// invoked for every change to an output relation.
+    fn prog(update_cb: Box<dyn CBFn>) -> Program;
+
+    /// Convert a relation name into its `RelId`.
+    fn relname2id(name: &str) -> Option<RelId>;
+
+    /// Convert an index name into its `IdxId`.
+    fn indexname2id(name: &str) -> Option<IdxId>;
+
+    /// Convert a `RelId` into its symbolic name as a C string.
+    #[cfg(feature = "c_api")]
+    fn relid2cname(rel_id: RelId) -> Option<&'static CStr>;
+
+    /// Convert an `IdxId` into its symbolic name as a C string.
+    #[cfg(feature = "c_api")]
+    fn indexid2cname(idx_id: IdxId) -> Option<&'static CStr>;
+
+    /// All input relations of the program.
+    fn input_relations() -> Vec<RelId>;
+
+    /// Returns `true` if `rel_id` is an output relation.
+    fn is_output_relation(rel_id: RelId) -> bool;
+
+    /// Type of values stored in relation `rel_id`, or `None` if the program
+    /// has no such relation.
+    fn relation_type_id(rel_id: RelId) -> Option<TypeId>;
+
+    /// All indexes of the program.
+    fn indexes() -> Vec<IdxId>;
+
+    /// Arrangement that stores the contents of index `idx_id`.
+    fn index_arrid(idx_id: IdxId) -> Option<ArrId>;
+
+    /// Convert a `Record` into a key of index `idx_id`.
+    fn idxkey_from_record(idx_id: IdxId, rec: &Record) -> Result<DDValue, String>;
+
+    /// Extract a set of updates from their flatbuffer representation.
+    #[cfg(feature = "flatbuf")]
+    fn updates_from_flatbuf(buf: &[u8]) -> Result<Vec<Update<DDValue>>, String>;
+
+    /// Extract an index query (index and key) from its flatbuffer
+    /// representation.
+    #[cfg(feature = "flatbuf")]
+    fn query_from_flatbuf(buf: &[u8]) -> Result<(IdxId, DDValue), String>;
+}
+
 /// A trait capturing program instantiation and handling of
 /// transactions.
 pub trait DDlog: Debug {
@@ -41,55 +112,59 @@ pub trait DDlog: Debug {
         + Into<Update<DDValue>>;
 
     /// Run the program.
-    fn run<F>(workers: usize, do_store: bool, cb: F) -> Result<(Self, DeltaMap<DDValue>), String>
+    fn run<F>(
+        workers: usize,
+        do_store: bool,
+        cb: F,
+    ) -> Result<(Self, DeltaMap<DDValue>), DDlogError>
     where
         Self: Sized,
         F: Callback;
 
     /// Start a transaction.
-    fn transaction_start(&self) -> Result<(), String>;
+    fn transaction_start(&self) -> Result<(), DDlogError>;
 
     /// Commit a transaction previously started using
     /// `transaction_start`, producing a map of deltas.
-    fn transaction_commit_dump_changes(&self) -> Result<DeltaMap<DDValue>, String>;
+    fn transaction_commit_dump_changes(&self) -> Result<DeltaMap<DDValue>, DDlogError>;
 
     /// Commit a transaction previously started using
     /// `transaction_start`.
-    fn transaction_commit(&self) -> Result<(), String>;
+    fn transaction_commit(&self) -> Result<(), DDlogError>;
 
     /// Roll back a transaction previously started using
     /// `transaction_start`.
-    fn transaction_rollback(&self) -> Result<(), String>;
+    fn transaction_rollback(&self) -> Result<(), DDlogError>;
 
     /// Apply a set of updates.
-    fn apply_updates<V, I>(&self, upds: I) -> Result<(), String>
+    fn apply_updates<V, I>(&self, upds: I) -> Result<(), DDlogError>
     where
         V: Deref<Target = UpdCmd>,
         I: Iterator<Item = V>;
 
     /// Apply a set of updates.
-    fn apply_valupdates<I>(&self, upds: I) -> Result<(), String>
+    fn apply_valupdates<I>(&self, upds: I) -> Result<(), DDlogError>
     where
         I: Iterator<Item = Update<DDValue>>;
 
     /// Apply a set of updates directly from the flatbuffer
     /// representation
     #[cfg(feature = "flatbuf")]
-    fn apply_updates_from_flatbuf(&self, buf: &[u8]) -> Result<(), String>;
+    fn apply_updates_from_flatbuf(&self, buf: &[u8]) -> Result<(), DDlogError>;
 
     /// Query index.  Returns all values associated with the given key in the index.
-    fn query_index(&self, index: IdxId, key: DDValue) -> Result<BTreeSet<DDValue>, String>;
+    fn query_index(&self, index: IdxId, key: DDValue) -> Result<BTreeSet<DDValue>, DDlogError>;
 
     /// Query index passing key as a record.  Returns all values associated with the given key in the index.
-    fn query_index_rec(&self, index: IdxId, key: &Record) -> Result<BTreeSet<DDValue>, String>;
+    fn query_index_rec(&self, index: IdxId, key: &Record) -> Result<BTreeSet<DDValue>, DDlogError>;
 
     /// Similar to `query_index`, but extracts query from a flatbuffer.
     #[cfg(feature = "flatbuf")]
-    fn query_index_from_flatbuf(&self, buf: &[u8]) -> Result<BTreeSet<DDValue>, String>;
+    fn query_index_from_flatbuf(&self, buf: &[u8]) -> Result<BTreeSet<DDValue>, DDlogError>;
 
     /// Dump all values in an index.
-    fn dump_index(&self, index: IdxId) -> Result<BTreeSet<DDValue>, String>;
+    fn dump_index(&self, index: IdxId) -> Result<BTreeSet<DDValue>, DDlogError>;
 
     /// Stop the program.
-    fn stop(&mut self) -> Result<(), String>;
+    fn stop(&mut self) -> Result<(), DDlogError>;
 }
@@ -0,0 +1,91 @@
+//! Errors returned by a running DDlog program.
+
+use std::error;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+/// Class of failure reported by a `DDlogError`.  Lets API layers, e.g., the
+/// C API, tell failures apart without inspecting error messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Malformed argument, e.g., an unknown subscription id.
+    InvalidArgument,
+    /// Unknown relation, or a relation of the wrong kind, e.g., an input
+    /// relation where an output relation is expected.
+    UnknownRelation,
+    /// Unknown index, e.g., an index name or id that the program does not
+    /// declare.
+    UnknownIndex,
+    /// Insertion of a value whose primary key already exists.
+    DuplicateKey,
+    /// Deletion or modification of a key that does not exist.
+    KeyNotFound,
+    /// The operation requires a transaction, but none was started.
+    NoTransaction,
+    /// The operation cannot be performed while a transaction is in
+    /// progress, e.g., starting another transaction.
+    TransactionInProgress,
+    /// A value or command does not match the type of its relation, or
+    /// cannot be converted to one.
+    InvalidRecord,
+    /// A timely dataflow worker thread failed or stopped responding.
+    WorkerFailure,
+    /// The operation is not supported by the relation or by the way the
+    /// program was started.
+    Unsupported,
+    /// Any other failure, e.g., one reported by a user-defined function
+    /// as a plain string.
+    Other,
+}
+
+/// Error returned by `RunningProgram` and `HDDlog`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DDlogError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl DDlogError {
+    pub fn new<S>(kind: ErrorKind, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for DDlogError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for DDlogError {}
+
+/// Errors that have not been classified, e.g., ones returned by
+/// user-defined functions, are of kind `Other`.
+impl From<String> for DDlogError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorKind::Other, message)
+    }
+}
+
+/// Lets callers that report errors as strings use `?` on results returned
+/// by `HDDlog`.
+impl From<DDlogError> for String {
+    fn from(e: DDlogError) -> Self {
+        e.message
+    }
+}
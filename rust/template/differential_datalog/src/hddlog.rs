@@ -0,0 +1,806 @@
+//! `HDDlog`: a handle to a running DDlog program.
+//!
+//! `HDDlog` is generic over the program it runs.  Program-specific
+//! functionality, i.e., the dataflow graph, relation and index tables, and
+//! conversions from `Record`s, is supplied by an implementation of the
+//! `DDlogProgram` trait in the crate generated for the program.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::ffi;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::iter;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Deref;
+use std::os::raw;
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::callback::Callback;
+use crate::ddlog::{DDlog, DDlogProgram};
+use crate::ddval::DDValue;
+use crate::error::{DDlogError, ErrorKind};
+use crate::program::{ArrangeFunc, IdxId, QueryHandle, RelId, RunningProgram, Update};
+use crate::record;
+use crate::record::IntoRecord;
+use crate::replay::{
+    record_val_upds, record_val_upds_binary, write_binary_header, write_frame, RecordReplay,
+    ReplayFormat, ReplayFrame,
+};
+use crate::update_handler::*;
+use crate::valmap::DeltaMap;
+
+/// Handle to a running DDlog program.
+///
+/// `HDDlog` is `Send` and `Sync`.  Transactions and updates are serialized
+/// by the `prog` mutex, while index queries (`query_index`, `dump_index`)
+/// and `dump_table` do not take the mutex and can run concurrently with
+/// each other and with updates buffered by a transaction in progress.
+#[derive(Debug)]
+pub struct HDDlog<P: DDlogProgram> {
+    pub prog: Mutex<RunningProgram>,
+    /// Handle used to query indexes without locking `prog`.
+    pub queries: QueryHandle,
+    pub update_handler: Box<dyn IMTUpdateHandler>,
+    /// Handler that delivers changes asynchronously with
+    /// `UpdateThreads::Async`.
+    pub async_updates: Option<ThreadUpdateHandler>,
+    pub db: Option<Arc<RwLock<DeltaMap<DDValue>>>>,
+    pub deltadb: Arc<Mutex<Option<DeltaMap<DDValue>>>>,
+    pub print_err: Option<extern "C" fn(msg: *const raw::c_char)>,
+    /// When set, all commands sent to the program are recorded in
+    /// the specified `.dat` file so that they can be replayed later.
+    pub replay_file: Option<Mutex<fs::File>>,
+    /// Format in which commands are written to `replay_file`.
+    pub replay_format: ReplayFormat,
+    /// Subscribers to individual output relations and indexes.
+    pub subscriptions: SubscriptionUpdateHandler,
+    /// Relation and arrangement function of every index that can be
+    /// subscribed to.
+    pub index_funcs: BTreeMap<IdxId, (RelId, ArrangeFunc)>,
+    /// `HDDlog` does not own a `P`; `fn() -> P` keeps it `Send` and `Sync`
+    /// regardless of `P`.
+    program: PhantomData<fn() -> P>,
+}
+
+/// Threads that deliver changes to output relations to the update handler
+/// passed to `HDDlog::do_run`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateThreads {
+    /// A single thread, which also maintains the stored copy of output
+    /// relations and delivers changes to subscribers.
+    Single,
+    /// A pool of `threads` threads that shard output relations among them
+    /// (see `PoolUpdateHandler`).  The handler is cloned for every thread
+    /// and must therefore tolerate being invoked concurrently for
+    /// different relations.  The stored copy of output relations and
+    /// subscribers are still served by a separate thread.
+    Pool { threads: usize },
+    /// A separate thread that delivers changes asynchronously (see
+    /// `ThreadUpdateHandler::new_async`): a commit returns before the
+    /// handler has seen its changes, as long as at most `queue_size`
+    /// changes are pending.  The stored copy of output relations and
+    /// subscribers are still served synchronously.  `HDDlog::flush_updates`
+    /// waits for pending changes to be delivered.
+    Async { queue_size: usize },
+}
+
+impl Default for UpdateThreads {
+    fn default() -> Self {
+        UpdateThreads::Single
+    }
+}
+
+/* Public API */
+impl<P: DDlogProgram> HDDlog<P> {
+    pub fn print_err(f: Option<extern "C" fn(msg: *const raw::c_char)>, msg: &str) {
+        match f {
+            None => eprintln!("{}", msg),
+            Some(f) => f(ffi::CString::new(msg).unwrap().into_raw()),
+        }
+    }
+
+    pub fn eprintln(&self, msg: &str) {
+        Self::print_err(self.print_err, msg)
+    }
+
+    /// Wait until the update handler has seen all changes committed so
+    /// far.  Only `UpdateThreads::Async` delivers changes after a commit
+    /// returns, so this is a no-op in the other modes.
+    pub fn flush_updates(&self) -> Result<(), DDlogError> {
+        match &self.async_updates {
+            Some(handler) => handler
+                .flush()
+                .map_err(|e| DDlogError::new(ErrorKind::WorkerFailure, e)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_table_id(tname: &str) -> Result<RelId, DDlogError> {
+        P::relname2id(tname).ok_or_else(|| {
+            DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("unknown relation {}", tname),
+            )
+        })
+    }
+
+    pub fn get_table_name(tid: RelId) -> Result<&'static str, DDlogError> {
+        P::relid2name(tid).ok_or_else(|| {
+            DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("unknown relation {}", tid),
+            )
+        })
+    }
+
+    #[cfg(feature = "c_api")]
+    pub fn get_table_cname(tid: RelId) -> Result<&'static ffi::CStr, DDlogError> {
+        P::relid2cname(tid).ok_or_else(|| {
+            DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("unknown relation {}", tid),
+            )
+        })
+    }
+
+    pub fn get_index_id(iname: &str) -> Result<IdxId, DDlogError> {
+        P::indexname2id(iname).ok_or_else(|| {
+            DDlogError::new(ErrorKind::UnknownIndex, format!("unknown index {}", iname))
+        })
+    }
+
+    pub fn get_index_name(iid: IdxId) -> Result<&'static str, DDlogError> {
+        P::indexid2name(iid).ok_or_else(|| {
+            DDlogError::new(ErrorKind::UnknownIndex, format!("unknown index {}", iid))
+        })
+    }
+
+    #[cfg(feature = "c_api")]
+    pub fn get_index_cname(iid: IdxId) -> Result<&'static ffi::CStr, DDlogError> {
+        P::indexid2cname(iid).ok_or_else(|| {
+            DDlogError::new(ErrorKind::UnknownIndex, format!("unknown index {}", iid))
+        })
+    }
+
+    pub fn record_commands(&mut self, file: &mut Option<Mutex<fs::File>>) {
+        mem::swap(&mut self.replay_file, file);
+        self.replay_format = ReplayFormat::Text;
+    }
+
+    /// Like `record_commands`, but records commands in the specified
+    /// format.  When `format` is `ReplayFormat::Binary`, the binary replay
+    /// header is written to the new file first.
+    pub fn record_commands_as(
+        &mut self,
+        file: &mut Option<Mutex<fs::File>>,
+        format: ReplayFormat,
+    ) -> io::Result<()> {
+        if let (ReplayFormat::Binary, Some(f)) = (format, file.as_ref()) {
+            write_binary_header(&mut *f.lock().unwrap())?;
+        }
+        mem::swap(&mut self.replay_file, file);
+        self.replay_format = format;
+        Ok(())
+    }
+
+    /// Register `handler` to receive changes to output relation `relid`.
+    /// The subscription takes effect at the start of the next transaction
+    /// and lasts until `unsubscribe` is called with the returned id.
+    pub fn subscribe(
+        &self,
+        relid: RelId,
+        handler: Box<dyn IMTUpdateHandler>,
+    ) -> Result<SubscriptionId, DDlogError> {
+        if P::is_output_relation(relid) {
+            Ok(self.subscriptions.subscribe(relid, handler))
+        } else {
+            Err(DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("unknown output relation {}", relid),
+            ))
+        }
+    }
+
+    /// Register `handler` to receive changes to the contents of index
+    /// `idxid`, i.e., the values that updates to the indexed relation add
+    /// to or remove from the index.
+    pub fn subscribe_index(
+        &self,
+        idxid: IdxId,
+        handler: Box<dyn IMTUpdateHandler>,
+    ) -> Result<SubscriptionId, DDlogError> {
+        let (relid, afun) = self.index_funcs.get(&idxid).ok_or_else(|| {
+            DDlogError::new(ErrorKind::UnknownIndex, format!("unknown index {}", idxid))
+        })?;
+        if P::is_output_relation(*relid) {
+            Ok(self
+                .subscriptions
+                .subscribe_arrangement(*relid, *afun, handler))
+        } else {
+            Err(DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("index {} is not built on an output relation", idxid),
+            ))
+        }
+    }
+
+    /// Remove a subscription created by `subscribe` or `subscribe_index`.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> Result<(), DDlogError> {
+        if self.subscriptions.unsubscribe(id) {
+            Ok(())
+        } else {
+            Err(DDlogError::new(
+                ErrorKind::InvalidArgument,
+                format!("unknown subscription {}", id),
+            ))
+        }
+    }
+
+    pub fn dump_input_snapshot<W>(&self, w: &mut W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        for rel in P::input_relations() {
+            let relname = P::relid2name(rel).unwrap_or("???");
+            let prog = self.prog.lock().unwrap();
+            match prog.get_input_relation_data(rel) {
+                Ok(valset) => {
+                    for v in valset.iter() {
+                        w.record_insert(relname, v)?;
+                        writeln!(w, ",")?;
+                    }
+                }
+                _ => match prog.get_input_relation_index(rel) {
+                    Ok(ivalset) => {
+                        for v in ivalset.values() {
+                            w.record_insert(relname, v)?;
+                            writeln!(w, ",")?;
+                        }
+                    }
+                    _ => match prog.get_input_multiset_data(rel) {
+                        Ok(ivalmset) => {
+                            for (v, weight) in ivalmset.iter() {
+                                if *weight >= 0 {
+                                    for _ in 0..*weight {
+                                        w.record_insert(relname, v)?;
+                                        writeln!(w, ",")?;
+                                    }
+                                } else {
+                                    for _ in 0..(-*weight) {
+                                        w.record_delete(relname, v)?;
+                                        writeln!(w, ",")?;
+                                    }
+                                }
+                            }
+                        }
+                        _ => {
+                            panic!("Unknown input relation {} in dump_input_snapshot", relname);
+                        }
+                    },
+                },
+            }
+        }
+        Ok(())
+    }
+
+    pub fn clear_relation(&self, table: usize) -> Result<(), DDlogError> {
+        self.record_clear_relation(table);
+        self.prog.lock().unwrap().clear_relation(table)
+    }
+
+    pub fn dump_table<F>(&self, table: usize, cb: Option<F>) -> Result<(), DDlogError>
+    where
+        F: Fn(&record::Record, isize) -> bool,
+    {
+        self.record_dump_table(table);
+        if let Some(ref db) = self.db {
+            Self::db_dump_table(&db.read().unwrap(), table, cb);
+            Ok(())
+        } else {
+            Err(DDlogError::new(
+                ErrorKind::Unsupported,
+                "cannot dump table: ddlog_run() was invoked with do_store flag set to false",
+            ))
+        }
+    }
+
+    /// Controls recording of differential operator runtimes.  When enabled,
+    /// DDlog records each activation of every operator and prints the
+    /// per-operator CPU usage summary in the profile.  When disabled, the
+    /// recording stops, but the previously accumulated profile is preserved.
+    ///
+    /// Recording CPU events can be expensive in large dataflows and is
+    /// therefore disabled by default.
+    pub fn enable_cpu_profiling(&self, enable: bool) {
+        self.record_enable_cpu_profiling(enable);
+        self.prog.lock().unwrap().enable_cpu_profiling(enable);
+    }
+
+    pub fn enable_timely_profiling(&self, enable: bool) {
+        self.record_enable_timely_profiling(enable);
+        self.prog.lock().unwrap().enable_timely_profiling(enable);
+    }
+
+    /// returns DDlog program runtime profile
+    pub fn profile(&self) -> String {
+        self.record_profile();
+        let rprog = self.prog.lock().unwrap();
+        let profile: String = rprog.profile.lock().unwrap().to_string();
+        profile
+    }
+
+    /// Start the program, delivering changes to output relations to `cb`.
+    /// Like `DDlog::run`, but accepts an arbitrary update handler, the
+    /// threads that invoke it, and a function to print error messages to
+    /// (see `print_err`).
+    pub fn do_run<UH>(
+        workers: usize,
+        do_store: bool,
+        update_threads: UpdateThreads,
+        cb: UH,
+        print_err: Option<extern "C" fn(msg: *const raw::c_char)>,
+    ) -> Result<(Self, DeltaMap<DDValue>), DDlogError>
+    where
+        UH: UpdateHandler + Clone + Send + Sync + 'static,
+    {
+        let workers = if workers == 0 { 1 } else { workers };
+
+        let db: Arc<RwLock<DeltaMap<DDValue>>> = Arc::new(RwLock::new(DeltaMap::new()));
+        let db2 = db.clone();
+
+        let deltadb: Arc<Mutex<Option<DeltaMap<_>>>> = Arc::new(Mutex::new(Some(DeltaMap::new())));
+        let deltadb2 = deltadb.clone();
+
+        let subscriptions = SubscriptionUpdateHandler::new();
+        let subscriptions2 = subscriptions.clone();
+
+        /* Handlers that run in a single thread: the delta and store
+         * handlers lock their maps for the duration of a commit, and
+         * subscribers expect one `before_commit` and `after_commit` call
+         * per transaction, so none of them can be sharded.  `cb` joins them
+         * unless it gets a pool of its own. */
+        let handler_generator = move |cb: Option<UH>| {
+            move || {
+                /* Always use delta handler, which costs nothing unless it is
+                 * actually used. */
+                let delta_handler = DeltaUpdateHandler::new(deltadb2);
+
+                let store_handler = if do_store {
+                    Some(ValMapUpdateHandler::new(db2))
+                } else {
+                    None
+                };
+
+                let mut handlers: Vec<Box<dyn UpdateHandler>> = Vec::new();
+                handlers.push(Box::new(delta_handler));
+                if let Some(h) = store_handler {
+                    handlers.push(Box::new(h))
+                };
+                if let Some(cb) = cb {
+                    handlers.push(Box::new(cb));
+                }
+                handlers.push(Box::new(subscriptions2));
+                Box::new(ChainedUpdateHandler::new(handlers)) as Box<dyn UpdateHandler>
+            }
+        };
+
+        let (handler, async_updates): (Box<dyn IMTUpdateHandler>, _) = match update_threads {
+            UpdateThreads::Single => (
+                Box::new(ThreadUpdateHandler::new(handler_generator(Some(cb)))),
+                None,
+            ),
+            UpdateThreads::Pool { threads } => {
+                let pool = PoolUpdateHandler::new(threads, move |_| {
+                    Box::new(cb.clone()) as Box<dyn UpdateHandler>
+                });
+                (
+                    Box::new(MTChainedUpdateHandler::new(vec![
+                        Box::new(ThreadUpdateHandler::new(handler_generator(None))),
+                        Box::new(pool),
+                    ])),
+                    None,
+                )
+            }
+            UpdateThreads::Async { queue_size } => {
+                let async_handler = ThreadUpdateHandler::new_async(queue_size, move || {
+                    Box::new(cb) as Box<dyn UpdateHandler>
+                });
+                (
+                    Box::new(MTChainedUpdateHandler::new(vec![
+                        Box::new(ThreadUpdateHandler::new(handler_generator(None))),
+                        Box::new(async_handler.clone()),
+                    ])),
+                    Some(async_handler),
+                )
+            }
+        };
+
+        let program = P::prog(handler.mt_update_cb());
+        let index_funcs = P::indexes()
+            .into_iter()
+            .filter_map(|idx| {
+                let arrid = P::index_arrid(idx)?;
+                program
+                    .arrangement_func(arrid)
+                    .map(|afun| (idx, (arrid.0, afun)))
+            })
+            .collect();
+
+        /* Notify handler about initial transaction */
+        handler.before_commit();
+        let prog = program
+            .run(workers)
+            .map_err(|e| DDlogError::new(ErrorKind::WorkerFailure, e))?;
+        handler.after_commit(true);
+
+        /* Extract state after initial transaction. */
+        let init_state = deltadb.lock().unwrap().take().unwrap();
+
+        Ok((
+            HDDlog {
+                queries: prog.query_handle(),
+                prog: Mutex::new(prog),
+                update_handler: handler,
+                async_updates,
+                db: Some(db),
+                deltadb,
+                print_err,
+                replay_file: None,
+                replay_format: ReplayFormat::Text,
+                subscriptions,
+                index_funcs,
+                program: PhantomData,
+            },
+            init_state,
+        ))
+    }
+}
+
+impl<P: DDlogProgram> DDlog for HDDlog<P> {
+    type Convert = P;
+    type UpdateSerializer = P::UpdateSerializer;
+
+    fn run<F>(
+        workers: usize,
+        do_store: bool,
+        cb: F,
+    ) -> Result<(Self, DeltaMap<DDValue>), DDlogError>
+    where
+        Self: Sized,
+        F: Callback,
+    {
+        Self::do_run(
+            workers,
+            do_store,
+            UpdateThreads::Single,
+            CallbackUpdateHandler::new(cb),
+            None,
+        )
+    }
+
+    fn transaction_start(&self) -> Result<(), DDlogError> {
+        self.record_transaction_start();
+        self.prog.lock().unwrap().transaction_start()
+    }
+
+    fn transaction_commit_dump_changes(&self) -> Result<DeltaMap<DDValue>, DDlogError> {
+        self.record_transaction_commit(true);
+        *self.deltadb.lock().unwrap() = Some(DeltaMap::new());
+
+        self.update_handler.before_commit();
+        match self.prog.lock().unwrap().transaction_commit() {
+            Ok(()) => {
+                self.update_handler.after_commit(true);
+                let mut delta = self.deltadb.lock().unwrap();
+                Ok(delta.take().unwrap())
+            }
+            Err(e) => {
+                self.update_handler.after_commit(false);
+                Err(e)
+            }
+        }
+    }
+
+    fn transaction_commit(&self) -> Result<(), DDlogError> {
+        self.record_transaction_commit(false);
+        self.update_handler.before_commit();
+
+        match self.prog.lock().unwrap().transaction_commit() {
+            Ok(()) => {
+                self.update_handler.after_commit(true);
+                Ok(())
+            }
+            Err(e) => {
+                self.update_handler.after_commit(false);
+                Err(e)
+            }
+        }
+    }
+
+    fn transaction_rollback(&self) -> Result<(), DDlogError> {
+        self.record_transaction_rollback();
+        self.prog.lock().unwrap().transaction_rollback()
+    }
+
+    /// Two implementations of `apply_updates`: one that takes `Record`s and one that takes `DDValue`s.
+    fn apply_updates<V, I>(&self, upds: I) -> Result<(), DDlogError>
+    where
+        V: Deref<Target = record::UpdCmd>,
+        I: iter::Iterator<Item = V>,
+    {
+        let mut conversion_err = false;
+        let mut error: Option<DDlogError> = None;
+
+        // Iterate through all updates, but only feed them to `apply_valupdates` until we reach
+        // the first invalid command.
+        // XXX: We must iterate till the end of `upds`, as `ddlog_apply_updates` relies on this to
+        // deallocate all commands.
+        let res = self.apply_valupdates(upds.flat_map(|u| {
+            if conversion_err {
+                None
+            } else {
+                match P::updcmd2upd(u.deref()) {
+                    Ok(u) => Some(u),
+                    Err(e) => {
+                        conversion_err = true;
+                        error = Some(Self::conversion_error(u.deref(), e));
+                        None
+                    }
+                }
+            }
+        }));
+
+        match error {
+            Some(e) => Err(e),
+            None => res,
+        }
+    }
+
+    #[cfg(feature = "flatbuf")]
+    fn apply_updates_from_flatbuf(&self, buf: &[u8]) -> Result<(), DDlogError> {
+        let upds = P::updates_from_flatbuf(buf)
+            .map_err(|e| DDlogError::new(ErrorKind::InvalidRecord, e))?;
+        self.apply_valupdates(upds.into_iter())
+    }
+
+    fn apply_valupdates<I>(&self, updates: I) -> Result<(), DDlogError>
+    where
+        I: Iterator<Item = Update<DDValue>>,
+    {
+        // Make sure that the updates being inserted have the correct value types for their
+        // relation
+        let inspect_update: fn(&Update<DDValue>) -> Result<(), DDlogError> = |update| {
+            let type_id = P::relation_type_id(update.relid()).ok_or_else(|| {
+                DDlogError::new(
+                    ErrorKind::UnknownRelation,
+                    format!("unknown relation id {}", update.relid()),
+                )
+            })?;
+
+            if let Some(value) = update.get_value() {
+                if type_id != value.type_id() {
+                    return Err(DDlogError::new(ErrorKind::InvalidRecord, format!("attempted to insert the incorrect type {:?} into relation {} whose value type is {:?}", value.type_id(), P::relid2name(update.relid()).unwrap_or("???"), type_id)));
+                }
+            }
+
+            Ok(())
+        };
+
+        if let Some(ref f) = self.replay_file {
+            let mut file = f.lock().unwrap();
+            let mut prog = self.prog.lock().unwrap();
+            match self.replay_format {
+                ReplayFormat::Text => {
+                    let updates =
+                        record_val_upds::<Self::Convert, _, _, _>(&mut *file, updates, |_| ());
+                    prog.apply_updates(updates, inspect_update)
+                }
+                ReplayFormat::Binary => {
+                    let updates =
+                        record_val_upds_binary::<Self::Convert, Self::UpdateSerializer, _, _, _>(
+                            &mut *file,
+                            updates,
+                            |_| (),
+                        );
+                    prog.apply_updates(updates, inspect_update)
+                }
+            }
+        } else {
+            self.prog
+                .lock()
+                .unwrap()
+                .apply_updates(updates, inspect_update)
+        }
+    }
+
+    fn dump_index(&self, index: IdxId) -> Result<BTreeSet<DDValue>, DDlogError> {
+        self.record_dump_index(index);
+        let arrid = P::index_arrid(index).ok_or_else(|| {
+            DDlogError::new(ErrorKind::UnknownIndex, format!("unknown index {}", index))
+        })?;
+        self.queries.dump_arrangement(arrid)
+    }
+
+    fn query_index(&self, index: IdxId, key: DDValue) -> Result<BTreeSet<DDValue>, DDlogError> {
+        self.record_query_index(index, &key);
+        let arrid = P::index_arrid(index).ok_or_else(|| {
+            DDlogError::new(ErrorKind::UnknownIndex, format!("unknown index {}", index))
+        })?;
+        self.queries.query_arrangement(arrid, key)
+    }
+
+    fn query_index_rec(
+        &self,
+        index: IdxId,
+        key: &record::Record,
+    ) -> Result<BTreeSet<DDValue>, DDlogError> {
+        let arrid = P::index_arrid(index).ok_or_else(|| {
+            DDlogError::new(ErrorKind::UnknownIndex, format!("unknown index {}", index))
+        })?;
+        let k = P::idxkey_from_record(index, key)
+            .map_err(|e| DDlogError::new(ErrorKind::InvalidRecord, e))?;
+        self.record_query_index(index, &k);
+        self.queries.query_arrangement(arrid, k)
+    }
+
+    #[cfg(feature = "flatbuf")]
+    fn query_index_from_flatbuf(&self, buf: &[u8]) -> Result<BTreeSet<DDValue>, DDlogError> {
+        let (idxid, key) =
+            P::query_from_flatbuf(buf).map_err(|e| DDlogError::new(ErrorKind::InvalidRecord, e))?;
+        self.query_index(idxid, key)
+    }
+
+    fn stop(&mut self) -> Result<(), DDlogError> {
+        self.prog.lock().unwrap().stop()?;
+        self.flush_updates()
+    }
+}
+
+/* Internals */
+impl<P: DDlogProgram> HDDlog<P> {
+    /// Error reported when `cmd` cannot be converted into an update: the
+    /// command refers to an unknown relation or to a record of the wrong
+    /// type.
+    fn conversion_error(cmd: &record::UpdCmd, e: String) -> DDlogError {
+        let rel = match cmd {
+            record::UpdCmd::Insert(rel, _)
+            | record::UpdCmd::InsertOrUpdate(rel, _)
+            | record::UpdCmd::Delete(rel, _)
+            | record::UpdCmd::DeleteKey(rel, _)
+            | record::UpdCmd::Modify(rel, _, _) => rel,
+        };
+        let known = match rel {
+            record::RelIdentifier::RelName(name) => P::relname2id(name).is_some(),
+            record::RelIdentifier::RelId(relid) => P::relid2name(*relid).is_some(),
+        };
+        let kind = if known {
+            ErrorKind::InvalidRecord
+        } else {
+            ErrorKind::UnknownRelation
+        };
+        DDlogError::new(kind, format!("invalid command {:?}: {}", cmd, e))
+    }
+
+    fn db_dump_table<F>(db: &DeltaMap<DDValue>, table: libc::size_t, cb: Option<F>)
+    where
+        F: Fn(&record::Record, isize) -> bool,
+    {
+        if let (Some(f), Some(rel)) = (cb, db.try_get_rel(table)) {
+            for (val, w) in rel {
+                //assert!(*w == 1);
+                if !f(&val.clone().into_record(), *w) {
+                    break;
+                }
+            }
+        };
+    }
+
+    /// Record a command in the replay file, if any.  `frame` is the
+    /// binary representation of the command, if it has one; `text` writes
+    /// the command in the text format.
+    fn record_command<F>(
+        &self,
+        frame: Option<ReplayFrame<P::UpdateSerializer>>,
+        text: F,
+        errmsg: &str,
+    ) where
+        F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+    {
+        if let Some(ref f) = self.replay_file {
+            let mut file = f.lock().unwrap();
+            let mut buf = Vec::new();
+            let result = match (self.replay_format, frame) {
+                (ReplayFormat::Binary, Some(frame)) => write_frame(&mut *file, &frame),
+                (ReplayFormat::Binary, None) => text(&mut buf).and_then(|_| {
+                    let text = String::from_utf8_lossy(&buf).into_owned();
+                    write_frame(&mut *file, &ReplayFrame::<P::UpdateSerializer>::Text(text))
+                }),
+                (ReplayFormat::Text, _) => text(&mut buf).and_then(|_| file.write_all(&buf)),
+            };
+            let _ = result.map_err(|_| self.eprintln(errmsg));
+        }
+    }
+
+    fn record_transaction_start(&self) {
+        self.record_command(
+            Some(ReplayFrame::Start),
+            |w| w.record_start(),
+            "failed to record invocation in replay file",
+        );
+    }
+
+    fn record_transaction_commit(&self, record_changes: bool) {
+        self.record_command(
+            Some(ReplayFrame::Commit(record_changes)),
+            |w| w.record_commit(record_changes),
+            "failed to record invocation in replay file",
+        );
+    }
+
+    fn record_transaction_rollback(&self) {
+        self.record_command(
+            Some(ReplayFrame::Rollback),
+            |w| w.record_rollback(),
+            "failed to record invocation in replay file",
+        );
+    }
+
+    fn record_clear_relation(&self, rid: RelId) {
+        self.record_command(
+            Some(ReplayFrame::Clear(rid)),
+            |w| w.record_clear::<P>(rid),
+            "failed to record invocation in replay file",
+        );
+    }
+
+    fn record_dump_table(&self, rid: RelId) {
+        self.record_command(
+            None,
+            |w| w.record_dump::<P>(rid),
+            "ddlog_dump_table(): failed to record invocation in replay file",
+        );
+    }
+
+    fn record_dump_index(&self, iid: IdxId) {
+        self.record_command(
+            None,
+            |w| w.record_dump_index::<P>(iid),
+            "ddlog_dump_index(): failed to record invocation in replay file",
+        );
+    }
+
+    fn record_query_index(&self, iid: IdxId, key: &DDValue) {
+        self.record_command(
+            None,
+            |w| w.record_query_index::<P>(iid, key),
+            "ddlog_dump_index(): failed to record invocation in replay file",
+        );
+    }
+
+    fn record_enable_cpu_profiling(&self, enable: bool) {
+        self.record_command(
+            None,
+            |w| w.record_cpu_profiling(enable),
+            "ddlog_cpu_profiling_enable(): failed to record invocation in replay file",
+        );
+    }
+
+    fn record_enable_timely_profiling(&self, enable: bool) {
+        self.record_command(
+            None,
+            |w| w.record_timely_profiling(enable),
+            "ddlog_timely_profiling_enable(): failed to record invocation in replay file",
+        );
+    }
+
+    fn record_profile(&self) {
+        self.record_command(
+            None,
+            |w| w.record_profile(),
+            "record_profile: failed to record invocation in replay file",
+        );
+    }
+}
@@ -7,6 +7,8 @@
 
 mod callback;
 mod ddlog;
+mod error;
+mod hddlog;
 mod profile;
 mod profile_statistics;
 mod replay;
@@ -16,6 +18,7 @@ mod variable;
 #[macro_use]
 pub mod ddval;
 pub mod program;
+pub mod update_handler;
 
 #[macro_use]
 pub mod record;
@@ -26,7 +29,20 @@ mod test_record;
 pub use callback::Callback;
 pub use ddlog::DDlog;
 pub use ddlog::DDlogConvert;
+pub use ddlog::DDlogProgram;
+pub use error::DDlogError;
+pub use error::ErrorKind;
+pub use hddlog::HDDlog;
+pub use hddlog::UpdateThreads;
+pub use replay::read_binary_header;
+pub use replay::read_frame;
 pub use replay::record_upd_cmds;
 pub use replay::record_val_upds;
+pub use replay::record_val_upds_binary;
+pub use replay::update_frame;
+pub use replay::write_binary_header;
+pub use replay::write_frame;
 pub use replay::RecordReplay;
+pub use replay::ReplayFormat;
+pub use replay::ReplayFrame;
 pub use valmap::DeltaMap;
@@ -21,7 +21,12 @@ pub use arrange::concatenate_collections;
 pub use timestamp::{TSNested, TupleTS, TS, TS16};
 pub use update::Update;
 
-use crate::{ddval::*, profile::*, record::Mutator};
+use crate::{
+    ddval::*,
+    error::{DDlogError, ErrorKind},
+    profile::*,
+    record::Mutator,
+};
 use arrange::{antijoin_arranged, ArrangedCollection, Arrangements, A};
 use fnv::{FnvHashMap, FnvHashSet};
 use std::{
@@ -32,7 +37,7 @@ use std::{
     sync::{
         atomic::{AtomicBool, Ordering},
         mpsc::{self, Receiver, Sender},
-        Arc, Barrier, Mutex,
+        Arc, Barrier, Mutex, RwLock,
     },
     thread::{self, JoinHandle},
 };
@@ -49,10 +54,7 @@ use differential_dataflow::trace::implementations::ord::OrdValSpine as DefaultVa
 use differential_dataflow::trace::wrappers::enter::TraceEnter;
 use differential_dataflow::trace::{BatchReader, Cursor, TraceReader};
 use differential_dataflow::Collection;
-use timely::communication::{
-    initialize::{Configuration, WorkerGuards},
-    Allocator,
-};
+use timely::communication::{initialize::Configuration, Allocator};
 use timely::dataflow::scopes::*;
 use timely::order::TotalOrder;
 use timely::progress::{timestamp::Refines, Timestamp};
@@ -635,17 +637,19 @@ pub type DeltaSet = FnvHashMap<DDValue, isize>;
 /// of scope. Error occurring as part of that operation are silently
 /// ignored. If you want to handle such errors, call `stop` manually.
 pub struct RunningProgram {
-    /// Producer sides of channels used to send commands to workers.
-    /// We use async channels to avoid deadlocks when workers are blocked
-    /// in `step_or_park`.
-    senders: Vec<Sender<Msg>>,
+    /// Channels used to send commands to workers, shared with `QueryHandle`s.
+    workers: Arc<Workers>,
     /// Channels to receive replies from worker threads. We could use a single
     /// channel with multiple senders, but use many channels instead to avoid
     /// deadlocks when one of the workers has died, but `recv` blocks instead
     /// of failing, since the channel is still considered alive.
     reply_recv: Vec<Receiver<Reply>>,
     relations: FnvHashMap<RelId, RelationInstance>,
-    worker_guards: Option<WorkerGuards<Result<(), String>>>,
+    /// Thread that owns timely `WorkerGuards` and waits for worker threads to
+    /// terminate.  `WorkerGuards` are not `Send`; keeping them in a separate
+    /// thread allows `RunningProgram` to be sent between threads.  The
+    /// thread returns the first error reported by a worker.
+    supervisor: Option<JoinHandle<Result<(), String>>>,
     transaction_in_progress: bool,
     need_to_flush: bool,
     /// CPU profiling enabled (can be expensive).
@@ -664,7 +668,7 @@ pub struct RunningProgram {
 impl Debug for RunningProgram {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("RunningProgram")
-            .field("senders", &self.senders)
+            .field("workers", &self.workers)
             .field("reply_recv", &self.reply_recv)
             .field(
                 "relations",
@@ -740,8 +744,10 @@ enum Msg {
     Flush,
     /// Query arrangement.  If the second argument is `None`, returns
     /// all values in the collection; otherwise returns values associated
-    /// with the specified key.
-    Query(ArrId, Option<DDValue>),
+    /// with the specified key.  The result is sent to the channel in the
+    /// third argument, so that concurrent queries do not receive each
+    /// other's replies.
+    Query(ArrId, Option<DDValue>, Sender<Reply>),
     /// Stop all workers (worker 0 only)
     Stop,
 }
@@ -755,6 +761,140 @@ enum Reply {
     QueryRes(Option<BTreeSet<DDValue>>),
 }
 
+/// Channels used to send commands to timely worker threads.
+#[derive(Debug)]
+struct Workers {
+    /// Producer sides of channels used to send commands to workers.
+    /// We use async channels to avoid deadlocks when workers are blocked
+    /// in `step_or_park`.
+    senders: Vec<Mutex<Sender<Msg>>>,
+    /// Worker threads, unparked after sending them a message.
+    threads: Vec<thread::Thread>,
+    /// Held for reading by queries and for writing while changes are
+    /// propagated through the dataflow, so that a query never observes a
+    /// transaction that has been committed by some workers but not others.
+    flush_lock: RwLock<()>,
+}
+
+impl Workers {
+    /// Send message to a worker thread.
+    fn send(&self, worker_index: usize, msg: Msg) -> Result<(), DDlogError> {
+        match self.senders[worker_index].lock().unwrap().send(msg) {
+            Ok(()) => {
+                // Worker 0 may be blocked in `step_or_park`. Unpark it to ensure
+                // the message is received.
+                self.threads[worker_index].unpark();
+
+                Ok(())
+            }
+
+            Err(_) => Err(DDlogError::new(
+                ErrorKind::WorkerFailure,
+                "failed to communicate with timely dataflow thread",
+            )),
+        }
+    }
+
+    /// Broadcast message to all worker threads.
+    fn broadcast(&self, msg: Msg) -> Result<(), DDlogError> {
+        for worker_index in 0..self.senders.len() {
+            self.send(worker_index, msg.clone())?;
+        }
+
+        Ok(())
+    }
+
+    fn query_arrangement(
+        &self,
+        arrid: ArrId,
+        k: Option<DDValue>,
+    ) -> Result<BTreeSet<DDValue>, DDlogError> {
+        let _flush_guard = self.flush_lock.read().unwrap();
+
+        // Send query and receive replies from all workers. If a key is specified, then at most
+        // one worker will send a non-empty reply.  `broadcast` consumes the sender, so
+        // `recv` fails instead of blocking if a worker drops the query without replying.
+        let (reply_send, reply_recv) = mpsc::channel();
+        self.broadcast(Msg::Query(arrid, k, reply_send))?;
+
+        let mut res: BTreeSet<DDValue> = BTreeSet::new();
+        let mut unknown = false;
+        for _ in 0..self.senders.len() {
+            let reply = reply_recv.recv().map_err(|e| {
+                DDlogError::new(
+                    ErrorKind::WorkerFailure,
+                    format!(
+                        "query_arrangement: failed to receive reply from worker: {:?}",
+                        e
+                    ),
+                )
+            })?;
+
+            match reply {
+                Reply::QueryRes(Some(mut vals)) => {
+                    if !vals.is_empty() {
+                        if res.is_empty() {
+                            std::mem::swap(&mut res, &mut vals);
+                        } else {
+                            res.append(&mut vals);
+                        }
+                    }
+                }
+                Reply::QueryRes(None) => {
+                    unknown = true;
+                }
+                repl => {
+                    return Err(DDlogError::new(
+                        ErrorKind::WorkerFailure,
+                        format!(
+                            "query_arrangement: unexpected reply from worker: {:?}",
+                            repl
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if unknown {
+            Err(DDlogError::new(
+                ErrorKind::UnknownIndex,
+                format!("query_arrangement: unknown index: {:?}", arrid),
+            ))
+        } else {
+            Ok(res)
+        }
+    }
+}
+
+/// Handle used to query arrangements of a `RunningProgram`.
+///
+/// Queries only communicate with worker threads and never modify the
+/// program, so, unlike `RunningProgram` methods, which require exclusive
+/// access to the program, `QueryHandle` methods can be invoked from
+/// multiple threads concurrently with each other and with updates to the
+/// program.  A query observes the state of the program after the last
+/// committed transaction.
+#[derive(Clone, Debug)]
+pub struct QueryHandle {
+    workers: Arc<Workers>,
+}
+
+impl QueryHandle {
+    /// Returns all values in the arrangement with the specified key.
+    pub fn query_arrangement(
+        &self,
+        arrid: ArrId,
+        k: DDValue,
+    ) -> Result<BTreeSet<DDValue>, DDlogError> {
+        self.workers.query_arrangement(arrid, Some(k))
+    }
+
+    /// Returns the entire content of an arrangement.
+    pub fn dump_arrangement(&self, arrid: ArrId) -> Result<BTreeSet<DDValue>, DDlogError> {
+        self.workers.query_arrangement(arrid, None)
+    }
+}
+
 impl Program {
     /// Instantiate the program with `nworkers` timely threads.
     pub fn run(&self, nworkers: usize) -> Result<RunningProgram, String> {
@@ -798,27 +938,56 @@ impl Program {
         let program = Arc::new(self.clone());
         let profiling = ProfilingData::new(profile_cpu.clone(), profile_timely.clone(), prof_send);
 
-        // Start up timely computation.
-        let worker_guards = timely::execute(
-            Configuration::Process(nworkers),
-            move |worker: &mut Worker<Allocator>| -> Result<_, String> {
-                let worker = DDlogWorker::new(
-                    worker,
-                    program.clone(),
-                    &frontier_ts,
-                    nworkers,
-                    progress_barrier.clone(),
-                    profiling.clone(),
-                    request_recv.clone(),
-                    reply_send.clone(),
-                    thandle_send.clone(),
-                    thandle_recv.clone(),
-                );
-
-                worker.run()
-            },
-        )
-        .map_err(|err| format!("Failed to start timely computation: {:?}", err))?;
+        // Start up timely computation.  `WorkerGuards` returned by `timely::execute`
+        // are not `Send`, so the computation is started by a supervisor thread, which
+        // keeps the guards and waits for the workers to terminate.
+        let (threads_send, threads_recv) = mpsc::sync_channel(1);
+        let supervisor = thread::spawn(move || -> Result<(), String> {
+            let worker_guards = match timely::execute(
+                Configuration::Process(nworkers),
+                move |worker: &mut Worker<Allocator>| -> Result<_, String> {
+                    let worker = DDlogWorker::new(
+                        worker,
+                        program.clone(),
+                        &frontier_ts,
+                        nworkers,
+                        progress_barrier.clone(),
+                        profiling.clone(),
+                        request_recv.clone(),
+                        reply_send.clone(),
+                        thandle_send.clone(),
+                        thandle_recv.clone(),
+                    );
+
+                    worker.run()
+                },
+            ) {
+                Ok(worker_guards) => worker_guards,
+                Err(err) => {
+                    let err = format!("Failed to start timely computation: {:?}", err);
+                    let _ = threads_send.send(Err(err.clone()));
+                    return Err(err);
+                }
+            };
+
+            let threads: Vec<thread::Thread> = worker_guards
+                .guards()
+                .iter()
+                .map(|guard| guard.thread().clone())
+                .collect();
+            let _ = threads_send.send(Ok(threads));
+
+            worker_guards
+                .join()
+                .into_iter()
+                .filter_map(Result::err)
+                .next()
+                .map_or(Ok(()), Err)
+        });
+
+        let threads = threads_recv
+            .recv()
+            .map_err(|e| format!("Failed to start timely computation: {}", e))??;
 
         let mut rels = FnvHashMap::default();
         for relid in self.input_relations() {
@@ -873,10 +1042,14 @@ impl Program {
             .map_err(|e| format!("failed to receive ACK: {}", e))?;
 
         Ok(RunningProgram {
-            senders: request_send,
+            workers: Arc::new(Workers {
+                senders: request_send.into_iter().map(Mutex::new).collect(),
+                threads,
+                flush_lock: RwLock::new(()),
+            }),
             reply_recv,
             relations: rels,
-            worker_guards: Some(worker_guards),
+            supervisor: Some(supervisor),
             transaction_in_progress: false,
             need_to_flush: false,
             profile_cpu,
@@ -922,6 +1095,16 @@ impl Program {
         panic!("get_relation({}): relation not found", relid)
     }
 
+    /// Returns the function used to build arrangement `arrid`, or `None`
+    /// if `arrid` is not a key-value arrangement.  Indexes are built from
+    /// such arrangements.
+    pub fn arrangement_func(&self, arrid: ArrId) -> Option<ArrangeFunc> {
+        match self.get_relation(arrid.0).arrangements.get(arrid.1) {
+            Some(Arrangement::Map { afun, .. }) => Some(*afun),
+            _ => None,
+        }
+    }
+
     /* indices of program nodes that use arrangement */
     fn arrangement_used_by_nodes<'a>(&'a self, arrid: ArrId) -> impl Iterator<Item = usize> + 'a {
         self.nodes.iter().enumerate().filter_map(move |(i, n)| {
@@ -1301,33 +1484,42 @@ impl RunningProgram {
     }
 
     /// Terminate program, killing all worker threads.
-    pub fn stop(&mut self) -> Response<()> {
-        if self.worker_guards.is_none() {
+    pub fn stop(&mut self) -> Result<(), DDlogError> {
+        if self.supervisor.is_none() {
             // Already stopped.
             return Ok(());
         };
         self.flush()
             .and_then(|_| self.send(0, Msg::Stop))
             .and_then(|_| {
-                self.worker_guards.take().map_or(Ok(()), |worker_guards| {
-                    worker_guards
+                self.supervisor.take().map_or(Ok(()), |supervisor| {
+                    supervisor
                         .join()
-                        .into_iter()
-                        .filter_map(Result::err)
-                        .next()
-                        .map_or(Ok(()), Err)
+                        .unwrap_or_else(|_| Err("timely supervisor thread panicked".to_string()))
+                        .map_err(|e| DDlogError::new(ErrorKind::WorkerFailure, e))
                 })
             })?;
 
         Ok(())
     }
 
+    /// Returns a handle that can be used to query arrangements of the
+    /// program concurrently with other operations on it.
+    pub fn query_handle(&self) -> QueryHandle {
+        QueryHandle {
+            workers: self.workers.clone(),
+        }
+    }
+
     /// Start a transaction. Does not return a transaction handle, as there
     /// can be at most one transaction in progress at any given time. Fails
     /// if there is already a transaction in progress.
-    pub fn transaction_start(&mut self) -> Response<()> {
+    pub fn transaction_start(&mut self) -> Result<(), DDlogError> {
         if self.transaction_in_progress {
-            return Err("transaction already in progress".to_string());
+            return Err(DDlogError::new(
+                ErrorKind::TransactionInProgress,
+                "transaction already in progress",
+            ));
         }
 
         self.transaction_in_progress = true;
@@ -1335,9 +1527,12 @@ impl RunningProgram {
     }
 
     /// Commit a transaction.
-    pub fn transaction_commit(&mut self) -> Response<()> {
+    pub fn transaction_commit(&mut self) -> Result<(), DDlogError> {
         if !self.transaction_in_progress {
-            return Err("transaction_commit: no transaction in progress".to_string());
+            return Err(DDlogError::new(
+                ErrorKind::NoTransaction,
+                "transaction_commit: no transaction in progress",
+            ));
         }
 
         self.flush().and_then(|_| self.delta_cleanup()).map(|_| {
@@ -1346,9 +1541,12 @@ impl RunningProgram {
     }
 
     /// Rollback the transaction, undoing all changes.
-    pub fn transaction_rollback(&mut self) -> Response<()> {
+    pub fn transaction_rollback(&mut self) -> Result<(), DDlogError> {
         if !self.transaction_in_progress {
-            return Err("transaction_rollback: no transaction in progress".to_string());
+            return Err(DDlogError::new(
+                ErrorKind::NoTransaction,
+                "transaction_rollback: no transaction in progress",
+            ));
         }
 
         self.flush().and_then(|_| self.delta_undo()).map(|_| {
@@ -1358,22 +1556,22 @@ impl RunningProgram {
 
     /// Insert one record into input relation. Relations have set semantics, i.e.,
     /// adding an existing record is a no-op.
-    pub fn insert(&mut self, relid: RelId, v: DDValue) -> Response<()> {
+    pub fn insert(&mut self, relid: RelId, v: DDValue) -> Result<(), DDlogError> {
         self.apply_updates(iter::once(Update::Insert { relid, v }), |_| Ok(()))
     }
 
     /// Insert one record into input relation or replace existing record with the same key.
-    pub fn insert_or_update(&mut self, relid: RelId, v: DDValue) -> Response<()> {
+    pub fn insert_or_update(&mut self, relid: RelId, v: DDValue) -> Result<(), DDlogError> {
         self.apply_updates(iter::once(Update::InsertOrUpdate { relid, v }), |_| Ok(()))
     }
 
     /// Remove a record if it exists in the relation.
-    pub fn delete_value(&mut self, relid: RelId, v: DDValue) -> Response<()> {
+    pub fn delete_value(&mut self, relid: RelId, v: DDValue) -> Result<(), DDlogError> {
         self.apply_updates(iter::once(Update::DeleteValue { relid, v }), |_| Ok(()))
     }
 
     /// Remove a key if it exists in the relation.
-    pub fn delete_key(&mut self, relid: RelId, k: DDValue) -> Response<()> {
+    pub fn delete_key(&mut self, relid: RelId, k: DDValue) -> Result<(), DDlogError> {
         self.apply_updates(iter::once(Update::DeleteKey { relid, k }), |_| Ok(()))
     }
 
@@ -1383,7 +1581,7 @@ impl RunningProgram {
         relid: RelId,
         k: DDValue,
         m: Arc<dyn Mutator<DDValue> + Send + Sync>,
-    ) -> Response<()> {
+    ) -> Result<(), DDlogError> {
         self.apply_updates(iter::once(Update::Modify { relid, k, m }), |_| Ok(()))
     }
 
@@ -1392,11 +1590,13 @@ impl RunningProgram {
         &mut self,
         update: Update<DDValue>,
         filtered_updates: &mut Vec<Update<DDValue>>,
-    ) -> Response<()> {
-        let rel = self
-            .relations
-            .get_mut(&update.relid())
-            .ok_or_else(|| format!("apply_update: unknown input relation {}", update.relid()))?;
+    ) -> Result<(), DDlogError> {
+        let rel = self.relations.get_mut(&update.relid()).ok_or_else(|| {
+            DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("apply_update: unknown input relation {}", update.relid()),
+            )
+        })?;
 
         match rel {
             RelationInstance::Stream { delta } => {
@@ -1418,13 +1618,16 @@ impl RunningProgram {
 
     /// Apply multiple insert and delete operations in one batch.
     /// Updates can only be applied to input relations (see `struct Relation`).
-    pub fn apply_updates<I, F>(&mut self, updates: I, inspect: F) -> Response<()>
+    pub fn apply_updates<I, F>(&mut self, updates: I, inspect: F) -> Result<(), DDlogError>
     where
         I: Iterator<Item = Update<DDValue>>,
-        F: Fn(&Update<DDValue>) -> Response<()>,
+        F: Fn(&Update<DDValue>) -> Result<(), DDlogError>,
     {
         if !self.transaction_in_progress {
-            return Err("apply_updates: no transaction in progress".to_string());
+            return Err(DDlogError::new(
+                ErrorKind::NoTransaction,
+                "apply_updates: no transaction in progress",
+            ));
         }
 
         // Remove no-op updates to maintain set semantics
@@ -1440,20 +1643,28 @@ impl RunningProgram {
     }
 
     /// Deletes all values in an input table
-    pub fn clear_relation(&mut self, relid: RelId) -> Response<()> {
+    pub fn clear_relation(&mut self, relid: RelId) -> Result<(), DDlogError> {
         if !self.transaction_in_progress {
-            return Err("clear_relation: no transaction in progress".to_string());
+            return Err(DDlogError::new(
+                ErrorKind::NoTransaction,
+                "clear_relation: no transaction in progress",
+            ));
         }
 
         let updates = {
-            let rel = self
-                .relations
-                .get_mut(&relid)
-                .ok_or_else(|| format!("clear_relation: unknown input relation {}", relid))?;
+            let rel = self.relations.get_mut(&relid).ok_or_else(|| {
+                DDlogError::new(
+                    ErrorKind::UnknownRelation,
+                    format!("clear_relation: unknown input relation {}", relid),
+                )
+            })?;
 
             match rel {
                 RelationInstance::Stream { .. } => {
-                    return Err("clear_relation: operation not supported for streams".to_string())
+                    return Err(DDlogError::new(
+                        ErrorKind::Unsupported,
+                        "clear_relation: operation not supported for streams",
+                    ))
                 }
                 RelationInstance::Multiset { elements, .. } => {
                     let mut updates: Vec<Update<DDValue>> = Vec::with_capacity(elements.len());
@@ -1490,61 +1701,17 @@ impl RunningProgram {
     }
 
     /// Returns all values in the arrangement with the specified key.
-    pub fn query_arrangement(&mut self, arrid: ArrId, k: DDValue) -> Response<BTreeSet<DDValue>> {
-        self._query_arrangement(arrid, Some(k))
+    pub fn query_arrangement(
+        &self,
+        arrid: ArrId,
+        k: DDValue,
+    ) -> Result<BTreeSet<DDValue>, DDlogError> {
+        self.workers.query_arrangement(arrid, Some(k))
     }
 
     /// Returns the entire content of an arrangement.
-    pub fn dump_arrangement(&mut self, arrid: ArrId) -> Response<BTreeSet<DDValue>> {
-        self._query_arrangement(arrid, None)
-    }
-
-    fn _query_arrangement(
-        &mut self,
-        arrid: ArrId,
-        k: Option<DDValue>,
-    ) -> Response<BTreeSet<DDValue>> {
-        // Send query and receive replies from all workers. If a key is specified, then at most
-        // one worker will send a non-empty reply.
-        self.broadcast(Msg::Query(arrid, k))?;
-
-        let mut res: BTreeSet<DDValue> = BTreeSet::new();
-        let mut unknown = false;
-        for (worker_index, chan) in self.reply_recv.iter().enumerate() {
-            let reply = chan.recv().map_err(|e| {
-                format!(
-                    "query_arrangement: failed to receive reply from worker {}: {:?}",
-                    worker_index, e
-                )
-            })?;
-
-            match reply {
-                Reply::QueryRes(Some(mut vals)) => {
-                    if !vals.is_empty() {
-                        if res.is_empty() {
-                            std::mem::swap(&mut res, &mut vals);
-                        } else {
-                            res.append(&mut vals);
-                        }
-                    }
-                }
-                Reply::QueryRes(None) => {
-                    unknown = true;
-                }
-                repl => {
-                    return Err(format!(
-                        "query_arrangement: unexpected reply from worker {}: {:?}",
-                        worker_index, repl
-                    ));
-                }
-            }
-        }
-
-        if unknown {
-            Err(format!("query_arrangement: unknown index: {:?}", arrid))
-        } else {
-            Ok(res)
-        }
+    pub fn dump_arrangement(&self, arrid: ArrId) -> Result<BTreeSet<DDValue>, DDlogError> {
+        self.workers.query_arrangement(arrid, None)
     }
 
     /// increment the counter associated with value `x` in the delta-set
@@ -1596,7 +1763,7 @@ impl RunningProgram {
         ds: &mut DeltaSet,
         update: Update<DDValue>,
         updates: &mut Vec<Update<DDValue>>,
-    ) -> Response<()> {
+    ) -> Result<(), DDlogError> {
         match &update {
             Update::Insert { v, .. } => {
                 Self::delta_inc(ds, v);
@@ -1605,21 +1772,30 @@ impl RunningProgram {
                 Self::delta_dec(ds, v);
             }
             Update::InsertOrUpdate { relid, .. } => {
-                return Err(format!(
-                    "Cannot perform insert_or_update operation on relation {} that does not have a primary key",
-                    relid,
+                return Err(DDlogError::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Cannot perform insert_or_update operation on relation {} that does not have a primary key",
+                        relid
+                    ),
                 ));
             }
             Update::DeleteKey { relid, .. } => {
-                return Err(format!(
-                    "Cannot delete by key from relation {} that does not have a primary key",
-                    relid,
+                return Err(DDlogError::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Cannot delete by key from relation {} that does not have a primary key",
+                        relid
+                    ),
                 ));
             }
             Update::Modify { relid, .. } => {
-                return Err(format!(
-                    "Cannot modify record in relation {} that does not have a primary key",
-                    relid,
+                return Err(DDlogError::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Cannot modify record in relation {} that does not have a primary key",
+                        relid
+                    ),
                 ));
             }
         };
@@ -1639,7 +1815,7 @@ impl RunningProgram {
         ds: &mut DeltaSet,
         upd: Update<DDValue>,
         updates: &mut Vec<Update<DDValue>>,
-    ) -> Response<()> {
+    ) -> Result<(), DDlogError> {
         match &upd {
             Update::Insert { v, .. } => {
                 Self::delta_inc(s, v);
@@ -1650,21 +1826,30 @@ impl RunningProgram {
                 Self::delta_dec(ds, v);
             }
             Update::InsertOrUpdate { relid, .. } => {
-                return Err(format!(
-                    "Cannot perform insert_or_update operation on relation {} that does not have a primary key",
-                    relid
+                return Err(DDlogError::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Cannot perform insert_or_update operation on relation {} that does not have a primary key",
+                        relid
+                    ),
                 ));
             }
             Update::DeleteKey { relid, .. } => {
-                return Err(format!(
-                    "Cannot delete by key from relation {} that does not have a primary key",
-                    relid
+                return Err(DDlogError::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Cannot delete by key from relation {} that does not have a primary key",
+                        relid
+                    ),
                 ));
             }
             Update::Modify { relid, .. } => {
-                return Err(format!(
-                    "Cannot modify record in relation {} that does not have a primary key",
-                    relid
+                return Err(DDlogError::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Cannot modify record in relation {} that does not have a primary key",
+                        relid
+                    ),
                 ));
             }
         };
@@ -1684,7 +1869,7 @@ impl RunningProgram {
         ds: &mut DeltaSet,
         upd: Update<DDValue>,
         updates: &mut Vec<Update<DDValue>>,
-    ) -> Response<()> {
+    ) -> Result<(), DDlogError> {
         let ok = match &upd {
             Update::Insert { v, .. } => {
                 let new = s.insert(v.clone());
@@ -1703,21 +1888,30 @@ impl RunningProgram {
                 present
             }
             Update::InsertOrUpdate { relid, .. } => {
-                return Err(format!(
-                    "Cannot perform insert_or_update operation on relation {} that does not have a primary key",
-                    relid,
+                return Err(DDlogError::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Cannot perform insert_or_update operation on relation {} that does not have a primary key",
+                        relid
+                    ),
                 ));
             }
             Update::DeleteKey { relid, .. } => {
-                return Err(format!(
-                    "Cannot delete by key from relation {} that does not have a primary key",
-                    relid,
+                return Err(DDlogError::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Cannot delete by key from relation {} that does not have a primary key",
+                        relid
+                    ),
                 ));
             }
             Update::Modify { relid, .. } => {
-                return Err(format!(
-                    "Cannot modify record in relation {} that does not have a primary key",
-                    relid,
+                return Err(DDlogError::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "Cannot modify record in relation {} that does not have a primary key",
+                        relid
+                    ),
                 ));
             }
         };
@@ -1747,13 +1941,12 @@ impl RunningProgram {
         ds: &mut DeltaSet,
         upd: Update<DDValue>,
         updates: &mut Vec<Update<DDValue>>,
-    ) -> Response<()> {
+    ) -> Result<(), DDlogError> {
         match upd {
             Update::Insert { relid, v } => match s.entry(key_func(&v)) {
-                hash_map::Entry::Occupied(_) => Err(format!(
-                    "Insert: duplicate key {:?} in value {:?}",
-                    key_func(&v),
-                    v
+                hash_map::Entry::Occupied(_) => Err(DDlogError::new(
+                    ErrorKind::DuplicateKey,
+                    format!("Insert: duplicate key {:?} in value {:?}", key_func(&v), v),
                 )),
                 hash_map::Entry::Vacant(ve) => {
                     ve.insert(v.clone());
@@ -1795,7 +1988,7 @@ impl RunningProgram {
             Update::DeleteValue { relid, v } => match s.entry(key_func(&v)) {
                 hash_map::Entry::Occupied(oe) => {
                     if *oe.get() != v {
-                        Err(format!("DeleteValue: key exists with a different value. Value specified: {:?}; existing value: {:?}", v, oe.get()))
+                        Err(DDlogError::new(ErrorKind::KeyNotFound, format!("DeleteValue: key exists with a different value. Value specified: {:?}; existing value: {:?}", v, oe.get())))
                     } else {
                         Self::delta_dec(ds, oe.get());
                         oe.remove_entry();
@@ -1803,9 +1996,10 @@ impl RunningProgram {
                         Ok(())
                     }
                 }
-                hash_map::Entry::Vacant(_) => {
-                    Err(format!("DeleteValue: key not found {:?}", key_func(&v)))
-                }
+                hash_map::Entry::Vacant(_) => Err(DDlogError::new(
+                    ErrorKind::KeyNotFound,
+                    format!("DeleteValue: key not found {:?}", key_func(&v)),
+                )),
             },
 
             Update::DeleteKey { relid, k } => match s.entry(k.clone()) {
@@ -1816,14 +2010,18 @@ impl RunningProgram {
                     updates.push(Update::DeleteValue { relid, v: old });
                     Ok(())
                 }
-                hash_map::Entry::Vacant(_) => Err(format!("DeleteKey: key not found {:?}", k)),
+                hash_map::Entry::Vacant(_) => Err(DDlogError::new(
+                    ErrorKind::KeyNotFound,
+                    format!("DeleteKey: key not found {:?}", k),
+                )),
             },
 
             Update::Modify { relid, k, m } => match s.entry(k.clone()) {
                 hash_map::Entry::Occupied(mut oe) => {
                     let new = oe.get_mut();
                     let old: DDValue = (*new).clone();
-                    m.mutate(new)?;
+                    m.mutate(new)
+                        .map_err(|e| DDlogError::new(ErrorKind::InvalidRecord, e))?;
                     Self::delta_dec(ds, &old);
                     updates.push(Update::DeleteValue { relid, v: old });
                     Self::delta_inc(ds, &new);
@@ -1834,7 +2032,10 @@ impl RunningProgram {
 
                     Ok(())
                 }
-                hash_map::Entry::Vacant(_) => Err(format!("Modify: key not found {:?}", k)),
+                hash_map::Entry::Vacant(_) => Err(DDlogError::new(
+                    ErrorKind::KeyNotFound,
+                    format!("Modify: key not found {:?}", k),
+                )),
             },
         }
     }
@@ -1842,33 +2043,51 @@ impl RunningProgram {
     /// Returns a reference to indexed input relation content.
     /// If called in the middle of a transaction, returns state snapshot including changes
     /// made by the current transaction.
-    pub fn get_input_relation_index(&self, relid: RelId) -> Response<&IndexedValSet> {
+    pub fn get_input_relation_index(&self, relid: RelId) -> Result<&IndexedValSet, DDlogError> {
         match self.relations.get(&relid) {
-            None => Err(format!("unknown relation {}", relid)),
+            None => Err(DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("unknown relation {}", relid),
+            )),
             Some(RelationInstance::Indexed { elements, .. }) => Ok(elements),
-            Some(_) => Err(format!("not an indexed relation {}", relid)),
+            Some(_) => Err(DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("not an indexed relation {}", relid),
+            )),
         }
     }
 
     /// Returns a reference to a flat input relation content.
     /// If called in the middle of a transaction, returns state snapshot including changes
     /// made by the current transaction.
-    pub fn get_input_relation_data(&self, relid: RelId) -> Response<&ValSet> {
+    pub fn get_input_relation_data(&self, relid: RelId) -> Result<&ValSet, DDlogError> {
         match self.relations.get(&relid) {
-            None => Err(format!("unknown relation {}", relid)),
+            None => Err(DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("unknown relation {}", relid),
+            )),
             Some(RelationInstance::Flat { elements, .. }) => Ok(elements),
-            Some(_) => Err(format!("not a flat relation {}", relid)),
+            Some(_) => Err(DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("not a flat relation {}", relid),
+            )),
         }
     }
 
     /// Returns a reference to an input multiset content.
     /// If called in the middle of a transaction, returns state snapshot including changes
     /// made by the current transaction.
-    pub fn get_input_multiset_data(&self, relid: RelId) -> Response<&ValMSet> {
+    pub fn get_input_multiset_data(&self, relid: RelId) -> Result<&ValMSet, DDlogError> {
         match self.relations.get(&relid) {
-            None => Err(format!("unknown relation {}", relid)),
+            None => Err(DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("unknown relation {}", relid),
+            )),
             Some(RelationInstance::Multiset { elements, .. }) => Ok(elements),
-            Some(_) => Err(format!("not a flat relation {}", relid)),
+            Some(_) => Err(DDlogError::new(
+                ErrorKind::UnknownRelation,
+                format!("not a flat relation {}", relid),
+            )),
         }
     }
 
@@ -1889,33 +2108,12 @@ impl RunningProgram {
     */
 
     /// Send message to a worker thread.
-    fn send(&self, worker_index: usize, msg: Msg) -> Response<()> {
-        match self.senders[worker_index].send(msg) {
-            Ok(()) => {
-                // Worker 0 may be blocked in `step_or_park`. Unpark it to ensure
-                // the message is received.
-                self.worker_guards.as_ref().unwrap().guards()[worker_index]
-                    .thread()
-                    .unpark();
-
-                Ok(())
-            }
-
-            Err(_) => Err("failed to communicate with timely dataflow thread".to_string()),
-        }
-    }
-
-    /// Broadcast message to all worker threads.
-    fn broadcast(&self, msg: Msg) -> Response<()> {
-        for worker_index in 0..self.senders.len() {
-            self.send(worker_index, msg.clone())?;
-        }
-
-        Ok(())
+    fn send(&self, worker_index: usize, msg: Msg) -> Result<(), DDlogError> {
+        self.workers.send(worker_index, msg)
     }
 
     /// Clear delta sets of all input relations on transaction commit.
-    fn delta_cleanup(&mut self) -> Response<()> {
+    fn delta_cleanup(&mut self) -> Result<(), DDlogError> {
         for rel in self.relations.values_mut() {
             rel.delta_mut().clear();
         }
@@ -1950,7 +2148,7 @@ impl RunningProgram {
     }
 
     /// Reverse all changes recorded in delta sets to rollback the transaction.
-    fn delta_undo(&mut self) -> Response<()> {
+    fn delta_undo(&mut self) -> Result<(), DDlogError> {
         let mut updates = Vec::with_capacity(self.relations.len());
         for (relid, rel) in &self.relations {
             Self::delta_undo_updates(*relid, rel.delta(), &mut updates);
@@ -1969,21 +2167,24 @@ impl RunningProgram {
     }
 
     /// Propagates all changes through the dataflow pipeline.
-    fn flush(&mut self) -> Response<()> {
+    fn flush(&mut self) -> Result<(), DDlogError> {
         if !self.need_to_flush {
             return Ok(());
         }
 
+        let workers = self.workers.clone();
+        let _flush_guard = workers.flush_lock.write().unwrap();
         self.send(0, Msg::Flush).and_then(|()| {
             self.need_to_flush = false;
             match self.reply_recv[0].recv() {
-                Err(_) => Err(
-                    "failed to receive flush ack message from timely dataflow thread".to_string(),
-                ),
+                Err(_) => Err(DDlogError::new(
+                    ErrorKind::WorkerFailure,
+                    "failed to receive flush ack message from timely dataflow thread",
+                )),
                 Ok(Reply::FlushAck) => Ok(()),
-                Ok(msg) => Err(format!(
-                    "received unexpected reply to flush request: {:?}",
-                    msg,
+                Ok(msg) => Err(DDlogError::new(
+                    ErrorKind::WorkerFailure,
+                    format!("received unexpected reply to flush request: {:?}", msg),
                 )),
             }
         })
@@ -257,8 +257,8 @@ impl<'a> DDlogWorker<'a> {
                             .map_err(|e| format!("failed to send ACK: {}", e))?;
                     }
 
-                    Ok(Msg::Query(arrid, key)) => {
-                        self.handle_query(&mut traces, arrid, key)?;
+                    Ok(Msg::Query(arrid, key, reply_sender)) => {
+                        self.handle_query(&mut traces, arrid, key, reply_sender);
                     }
 
                     Ok(Msg::Stop) => {
@@ -323,8 +323,8 @@ impl<'a> DDlogWorker<'a> {
                     // Non-blocking receive, so that we can do some garbage collecting
                     // when there is no real work to do.
                     match self.request_receiver.try_recv() {
-                        Ok(Msg::Query(arrid, key)) => {
-                            self.handle_query(&mut traces, arrid, key)?;
+                        Ok(Msg::Query(arrid, key, reply_sender)) => {
+                            self.handle_query(&mut traces, arrid, key, reply_sender);
                         }
 
                         Ok(msg) => {
@@ -406,14 +406,16 @@ impl<'a> DDlogWorker<'a> {
         self.progress_barrier.wait();
     }
 
-    /// Handle a query
+    /// Handle a query, sending the result to `reply_sender`.  The reply is
+    /// dropped if the querying thread has gone away: that is no reason to
+    /// stop the worker.
     fn handle_query<Trace>(
         &self,
         traces: &mut BTreeMap<ArrId, Trace>,
         arrid: ArrId,
         key: Option<DDValue>,
-    ) -> Result<(), String>
-    where
+        reply_sender: Sender<Reply>,
+    ) where
         Trace: TraceReader<Key = DDValue, Val = DDValue, Time = TS, R = Weight>,
         <Trace as TraceReader>::Batch: BatchReader<DDValue, DDValue, TS, Weight>,
         <Trace as TraceReader>::Cursor: Cursor<DDValue, DDValue, TS, Weight>,
@@ -421,11 +423,8 @@ impl<'a> DDlogWorker<'a> {
         let trace = match traces.get_mut(&arrid) {
             Some(trace) => trace,
             None => {
-                self.reply_sender
-                    .send(Reply::QueryRes(None))
-                    .map_err(|e| format!("handle_query: failed to send error response: {}", e))?;
-
-                return Ok(());
+                let _ = reply_sender.send(Reply::QueryRes(None));
+                return;
             }
         };
 
@@ -487,11 +486,7 @@ impl<'a> DDlogWorker<'a> {
             }
         };
 
-        self.reply_sender
-            .send(Reply::QueryRes(Some(values)))
-            .map_err(|e| format!("handle_query: failed to send query response: {}", e))?;
-
-        Ok(())
+        let _ = reply_sender.send(Reply::QueryRes(Some(values)));
     }
 
     /// Initialize timely and differential profiling logging hooks
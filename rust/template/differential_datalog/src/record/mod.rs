@@ -1055,6 +1055,11 @@ macro_rules! decl_val_enum_into_record {
 // generated .so), presumably this compiler bug:
 // https://github.com/rust-lang/rust/issues/50007
 
+/// Dump record into a C string for debug printing.
+///
+/// Returns `NULL` on error.
+///
+/// The returned string must be deallocated using `ddlog_string_free()`.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_dump_record(record: *const Record) -> *mut libc::c_char {
@@ -1065,48 +1070,60 @@ pub unsafe extern "C" fn ddlog_dump_record(record: *const Record) -> *mut libc::
         .unwrap_or(ptr::null_mut())
 }
 
+/// Deallocate an owned record.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_free(rec: *mut Record) {
     Box::from_raw(rec);
 }
 
+/// Create a Boolean value
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub extern "C" fn ddlog_bool(b: bool) -> *mut Record {
     Box::into_raw(Box::new(Record::Bool(b)))
 }
 
+/// Returns `true` if `rec` is a Boolean and `false` otherwise
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_bool(rec: *const Record) -> bool {
     rec.as_ref().map(Record::is_bool).unwrap_or_default()
 }
 
+/// Retrieves the value of a Boolean.
+///
+/// Returns `false` if `rec` is not a Boolean.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_bool(rec: *const Record) -> bool {
     rec.as_ref().and_then(Record::as_bool).unwrap_or_default()
 }
 
+/// Returns `true` if `rec` is an integer and `false` otherwise.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_int(rec: *const Record) -> bool {
     rec.as_ref().map(Record::is_int).unwrap_or_default()
 }
 
+/// Returns `true` if `rec` is a float and `false` otherwise.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_float(rec: *const Record) -> bool {
     rec.as_ref().map(Record::is_float).unwrap_or_default()
 }
 
+/// Create a float value.
 #[cfg(feature = "c_api")]
 #[no_mangle]
-pub extern "C" fn ddlog_float(float: f32) -> *mut Record {
-    Box::into_raw(Box::new(Record::Float(OrderedFloat(float))))
+pub extern "C" fn ddlog_float(f: f32) -> *mut Record {
+    Box::into_raw(Box::new(Record::Float(OrderedFloat(f))))
 }
 
+/// Retrieves the value of a float.
+///
+/// Returns `0` if `rec` is not a float.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_float(rec: *const Record) -> f32 {
@@ -1116,18 +1133,23 @@ pub unsafe extern "C" fn ddlog_get_float(rec: *const Record) -> f32 {
         .unwrap_or(0.0)
 }
 
+/// Returns `true` if `rec` is a double and `false` otherwise.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_double(rec: *const Record) -> bool {
     rec.as_ref().map(Record::is_double).unwrap_or_default()
 }
 
+/// Create a double value.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub extern "C" fn ddlog_double(v: f64) -> *mut Record {
     Box::into_raw(Box::new(Record::Double(OrderedFloat(v))))
 }
 
+/// Retrieves the value of a double.
+///
+/// Returns `0` if `rec` is not a double.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_double(rec: *const Record) -> f64 {
@@ -1137,19 +1159,27 @@ pub unsafe extern "C" fn ddlog_get_double(rec: *const Record) -> f64 {
         .unwrap_or(0.0)
 }
 
+/// Create an integer value with arbitrary width and signedness.  Can be used to
+/// populate any ddlog field of type `bit<N>`, `signed<N>` or `bigint`.
+///
+/// `v` - byte array containing big endian two's complement representation of the number
+/// `size` - size of `v` in bytes
 #[cfg(feature = "c_api")]
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_int(int: *const libc::c_uchar, size: libc::size_t) -> *mut Record {
+pub unsafe extern "C" fn ddlog_int(v: *const libc::c_uchar, size: libc::size_t) -> *mut Record {
     Box::into_raw(Box::new(Record::Int(BigInt::from_signed_bytes_be(
-        slice::from_raw_parts(int as *const u8, size as usize),
+        slice::from_raw_parts(v as *const u8, size as usize),
     ))))
 }
 
-/// `buf`        - buffer to store the big-endian byte representation of the integer value
+/// Extract big-endian byte representation of arbitrary integer value.
+///
+/// `buf`        - buffer to store the big-endian two's complement byte representation
+///                of the integer value
 /// `capacity`   - buffer capacity
 ///
 /// Return value: if `capacity` is 0, returns the minimal buffer capacity necessary to
-/// represent the value otherwise returns the number of bytes stored in `buf` or `-1` if `buf`
+/// represent the value; otherwise returns the number of bytes stored in `buf` or `-1` if `buf`
 /// is not big enough.
 #[cfg(feature = "c_api")]
 #[no_mangle]
@@ -1180,7 +1210,10 @@ pub unsafe extern "C" fn ddlog_get_int(
     }
 }
 
-/// Determines the fewest bits necessary to express the integer value, not including the sign.
+/// Returns the fewest bits necessary to express the integer value,
+/// not including the sign.
+///
+/// Returns `0` if the `rec` is not an integer record.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_int_bits(rec: *const Record) -> libc::size_t {
@@ -1190,12 +1223,18 @@ pub unsafe extern "C" fn ddlog_int_bits(rec: *const Record) -> libc::size_t {
     }
 }
 
+/// Create an unsigned integer value.  Can be used to populate any ddlog field
+/// of type `bit<N>`, `N<=64`
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub extern "C" fn ddlog_u64(v: u64) -> *mut Record {
     Box::into_raw(Box::new(Record::Int(BigInt::from(v))))
 }
 
+/// Retrieves the value of an unsigned integer.
+///
+/// Returns `0` if `rec` is not an integer or if its value does not
+/// fit into 64 bits.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_u64(rec: *const Record) -> u64 {
@@ -1205,12 +1244,18 @@ pub unsafe extern "C" fn ddlog_get_u64(rec: *const Record) -> u64 {
         .unwrap_or(0)
 }
 
+/// Create a signed integer value.  Can be used to populate any ddlog field
+/// of type `signed<N>`, `N<=64`
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub extern "C" fn ddlog_i64(v: i64) -> *mut Record {
     Box::into_raw(Box::new(Record::Int(BigInt::from(v))))
 }
 
+/// Retrieves the value of a signed integer.
+///
+/// Returns `0` if `rec` is not an integer or if its value does not
+/// fit into 64 bits.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_i64(rec: *const Record) -> i64 {
@@ -1222,6 +1267,8 @@ pub unsafe extern "C" fn ddlog_get_i64(rec: *const Record) -> i64 {
 
 // FIXME: 128 bit integers are not FFI-safe, so we need to find an alternate
 //        method to make this defined behavior
+/// Create an unsigned integer value.  Can be used to populate any ddlog field
+/// of type `bit<N>`, `N<=128`
 #[cfg(feature = "c_api")]
 #[no_mangle]
 #[allow(improper_ctypes_definitions)]
@@ -1231,6 +1278,10 @@ pub extern "C" fn ddlog_u128(v: u128) -> *mut Record {
 
 // FIXME: 128 bit integers are not FFI-safe, so we need to find an alternate
 //        method to make this defined behavior
+/// Retrieves the value of an integer.
+///
+/// Returns `0` if `rec` is not an integer or if its value does not
+/// fit into 128 bits.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 #[allow(improper_ctypes_definitions)]
@@ -1243,6 +1294,8 @@ pub unsafe extern "C" fn ddlog_get_u128(rec: *const Record) -> u128 {
 
 // FIXME: 128 bit integers are not FFI-safe, so we need to find an alternate
 //        method to make this defined behavior
+/// Create a signed integer value.  Can be used to populate any ddlog field
+/// of type `signed<N>`, `N<=128`
 #[cfg(feature = "c_api")]
 #[no_mangle]
 #[allow(improper_ctypes_definitions)]
@@ -1252,6 +1305,10 @@ pub extern "C" fn ddlog_i128(v: i128) -> *mut Record {
 
 // FIXME: 128 bit integers are not FFI-safe, so we need to find an alternate
 //        method to make this defined behavior
+/// Retrieves the value of a signed integer.
+///
+/// Returns `0` if `rec` is not an integer or if its value does not
+/// fit into 128 bits.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 #[allow(improper_ctypes_definitions)]
@@ -1262,7 +1319,11 @@ pub unsafe extern "C" fn ddlog_get_i128(rec: *const Record) -> i128 {
         .unwrap_or(0)
 }
 
-/// Returns NULL if the given string is not valid UTF8
+/// Create a string value from a NULL-terminated string `s`.  This function
+/// copies `s` to an internal buffer, so the caller is responsible for
+/// deallocating `s` if it was dynamically allocated.
+///
+/// Returns `NULL` if `s` is not a valid null-terminated UTF8 string.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_string(string: *const libc::c_char) -> *mut Record {
@@ -1273,7 +1334,18 @@ pub unsafe extern "C" fn ddlog_string(string: *const libc::c_char) -> *mut Recor
     }
 }
 
-/// Returns NULL if s is not a valid UTF8 string.
+/// Create a string value.
+///
+/// `s` - points to the start of a UTF8 string.  The string does not have to be
+///       NULL-terminated.  The pointer must not be `NULL`, unless `len==0`, in
+///       which case the function ignores the value of the pointer and returns
+///       a record containing an empty string.
+/// `len` - length of string in bytes.
+///
+/// This function copies `s` to an internal buffer, so the caller is responsible for
+/// deallocating `s` if it was dynamically allocated.
+///
+/// Returns `NULL` if `s` is not a valid UTF8 string.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_string_with_length(
@@ -1296,6 +1368,7 @@ pub unsafe extern "C" fn ddlog_string_with_length(
     }
 }
 
+/// Returns `true` if `rec` is a string and `false` otherwise
 #[no_mangle]
 #[cfg(feature = "c_api")]
 pub unsafe extern "C" fn ddlog_is_string(rec: *const Record) -> bool {
@@ -1305,6 +1378,9 @@ pub unsafe extern "C" fn ddlog_is_string(rec: *const Record) -> bool {
     }
 }
 
+/// Retrieves the length of a string in bytes.
+///
+/// Returns `0` if `rec` is not a string.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_strlen(rec: *const Record) -> libc::size_t {
@@ -1314,6 +1390,20 @@ pub unsafe extern "C" fn ddlog_get_strlen(rec: *const Record) -> libc::size_t {
     }
 }
 
+/// Returns the contents of a DDlog string.
+///
+/// On success, returns pointer to the string and stores the length of the
+/// string in bytes in `len`.
+///
+/// If `rec` is not a record of type string, returns `NULL`.
+///
+/// IMPORTANT: The returned string is _not_ null-terminated.
+///
+/// The pointer returned by this function points to an internal DDlog
+/// buffer. The caller must not modify the contents of the string or
+/// deallocate this pointer.  The lifetime of the pointer coincides with
+/// the lifetime of the record it was obtained from, e.g., the pointer is
+/// invalidated when the value is written to the database.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_str_with_length(
@@ -1329,7 +1419,13 @@ pub unsafe extern "C" fn ddlog_get_str_with_length(
     }
 }
 
-/// Returns NULL if s is not a valid UTF8 string.
+/// Create a serialized record from a NULL-terminated string `t` containing
+/// the serialization scheme and a NULL-terminated string `s` containing
+/// the serialized data.
+/// This function copies `s` to an internal buffer, so the caller is
+/// responsible for deallocating `s` if it was dynamically allocated.
+///
+/// Returns `NULL` if `t` or `s` are not a valid null-terminated UTF8 strings.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_serialized(
@@ -1351,7 +1447,25 @@ pub unsafe extern "C" fn ddlog_serialized(
     }
 }
 
-/// Returns NULL if s is not a valid UTF8 string.
+/// Create a serialized record.
+///
+/// `t` - points to the start of a UTF8 string containing the
+///       serialization scheme. The string does not have to be
+///       NULL-terminated. The pointer must not be `NULL`
+/// `t_len` - length of string in bytes.
+/// `s` - points to the start of a UTF8 string containing the
+///       serialization scheme.  The string does not have to be
+///       NULL-terminated. The pointer must not be `NULL`, unless
+///       `s_len==0`, in which case the function ignores the value of the
+///       pointer and returns a record containing an empty string.
+/// `s_len` - length of string in bytes.
+///
+/// This function copies `s` to an internal buffer, so the caller is
+/// responsible for deallocating `s` if it was dynamically
+/// allocated. It assumes that `t` is a statically allocated string and
+/// stores the pointer internally instead of copying it to another buffer.
+///
+/// Returns `NULL` if `t` or `s` are not a valid UTF8 strings.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_serialized_with_length(
@@ -1384,12 +1498,22 @@ pub unsafe extern "C" fn ddlog_serialized_with_length(
     }
 }
 
+/// Returns `true` if `rec` is a serialized record and `false` otherwise
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_serialized(rec: *const Record) -> bool {
     matches!(rec.as_ref(), Some(Record::Serialized(_, _)))
 }
 
+/// Create a tuple with specified fields.
+///
+/// `len` is the length of the `fields` array.
+/// If `len` is greater than `0`, then `fields` must not be NULL.
+///
+/// The function takes ownership of all records, invalidating all
+/// `ddlog_record` pointers in `fields`.  However it does not take
+/// ownership of the `fields` array itself.  The caller is responsible
+/// for deallocating the array if needed.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_tuple(fields: *const *mut Record, len: libc::size_t) -> *mut Record {
@@ -1397,12 +1521,16 @@ pub unsafe extern "C" fn ddlog_tuple(fields: *const *mut Record, len: libc::size
     Box::into_raw(Box::new(Record::Tuple(fields)))
 }
 
+/// Returns `true` if `rec` is a tuple and false otherwise
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_tuple(rec: *const Record) -> bool {
     matches!(rec.as_ref(), Some(Record::Tuple(_)))
 }
 
+/// Retrieves the number of fields in a tuple.
+///
+/// Returns `0` if `rec` is not a tuple.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_tuple_size(rec: *const Record) -> libc::size_t {
@@ -1412,6 +1540,17 @@ pub unsafe extern "C" fn ddlog_get_tuple_size(rec: *const Record) -> libc::size_
     }
 }
 
+/// Retrieves `i`th field of the tuple.
+///
+/// Returns NULL if `tup` is not a tuple or if the tuple has fewer than `i`
+/// fields.
+///
+/// The pointer returned by this function is owned by DDlog. The caller
+/// may inspect the returned record, but must not modify it, attach to
+/// other records (e.g., using `ddlog_tuple_push()`) or write to the
+/// database.  The lifetime of the pointer coincides with the lifetime of
+/// the record it was obtained from, e.g., the pointer is invalidated
+/// when the value is written to the database.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_tuple_field(
@@ -1425,7 +1564,10 @@ pub unsafe extern "C" fn ddlog_get_tuple_field(
         .unwrap_or(ptr::null_mut())
 }
 
-/// Convenience method to create a 2-tuple.
+/// Convenience method to create a 2-tuple.  Such tuples are useful,
+/// e.g., in constructing maps out of key-value pairs.
+///
+/// The function takes ownership of `v1` and `v2`.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_pair(v1: *mut Record, v2: *mut Record) -> *mut Record {
@@ -1434,6 +1576,19 @@ pub unsafe extern "C" fn ddlog_pair(v1: *mut Record, v2: *mut Record) -> *mut Re
     Box::into_raw(Box::new(Record::Tuple(vec![*v1, *v2])))
 }
 
+/// An alternative way to construct tuples by adding fields one-by-one.
+///
+/// To use this function, start with creating a tuple using, e.g.,
+///
+/// ```text
+/// ddlog_tuple(NULL, 0);
+/// ```
+///
+/// and then call `ddlog_tuple_push()` once for each field, in the order
+/// fields appear in the tuple.
+///
+/// This function takes ownership of `rec`, which should not be used
+/// after the call.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_tuple_push(tup: *mut Record, rec: *mut Record) {
@@ -1447,6 +1602,15 @@ pub unsafe extern "C" fn ddlog_tuple_push(tup: *mut Record, rec: *mut Record) {
     Box::into_raw(tup);
 }
 
+/// Create a vector with specified elements.
+///
+/// `len` is the length of the `recs` array.
+/// If `len` is greater than `0`, then `recs` must not be NULL.
+///
+/// The function takes ownership of all records, invalidating all
+/// `ddlog_record` pointers in `recs`.  However it does not take ownership of
+/// the `recs` array itself.  The caller is responsible for deallocating the
+/// array if needed.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_vector(
@@ -1457,6 +1621,7 @@ pub unsafe extern "C" fn ddlog_vector(
     Box::into_raw(Box::new(Record::Array(CollectionKind::Vector, fields)))
 }
 
+/// Returns `true` if `rec` is a vector and false otherwise
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_vector(rec: *const Record) -> bool {
@@ -1466,6 +1631,9 @@ pub unsafe extern "C" fn ddlog_is_vector(rec: *const Record) -> bool {
     }
 }
 
+/// Retrieves the number of elements in a vector.
+///
+/// Returns `0` if `rec` is not a vector.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_vector_size(rec: *const Record) -> libc::size_t {
@@ -1475,6 +1643,16 @@ pub unsafe extern "C" fn ddlog_get_vector_size(rec: *const Record) -> libc::size
     }
 }
 
+/// Retrieves `i`th element of the vector.
+///
+/// Returns NULL if `vec` is not a vector or if the vector is shorter than `i`.
+///
+/// The pointer returned by this function is owned by DDlog. The caller
+/// may inspect the returned record, but must not modify it, attach to
+/// other records (e.g., using `ddlog_vector_push()`) or write to the
+/// database.  The lifetime of the pointer coincides with the lifetime of
+/// the record it was obtained from, e.g., the pointer is invalidated
+/// when the value is written to the database.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_vector_elem(
@@ -1488,6 +1666,10 @@ pub unsafe extern "C" fn ddlog_get_vector_elem(
         .unwrap_or(ptr::null_mut())
 }
 
+/// Append a value at the end of the vector.
+///
+/// This function takes ownership of `rec`, which should not be used after the
+/// call.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_vector_push(vec: *mut Record, rec: *mut Record) {
@@ -1501,6 +1683,15 @@ pub unsafe extern "C" fn ddlog_vector_push(vec: *mut Record, rec: *mut Record) {
     Box::into_raw(vec);
 }
 
+/// Create a set with specified elements.
+///
+/// `len` is the length of the `recs` array.  If `len` is greater than
+/// `0`, then `recs` must not be NULL.
+///
+/// The function takes ownership of all records, invalidating all
+/// `ddlog_record` pointers in `recs`.  However it does not take
+/// ownership of the `recs` array itself.  The caller is responsible for
+/// deallocating the array if needed.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_set(fields: *const *mut Record, len: libc::size_t) -> *mut Record {
@@ -1508,12 +1699,16 @@ pub unsafe extern "C" fn ddlog_set(fields: *const *mut Record, len: libc::size_t
     Box::into_raw(Box::new(Record::Array(CollectionKind::Set, fields)))
 }
 
+/// Returns `true` if `rec` is a set and false otherwise
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_set(rec: *const Record) -> bool {
     matches!(rec.as_ref(), Some(Record::Array(CollectionKind::Set, _)))
 }
 
+/// Retrieves the number of values in a set.
+///
+/// Returns `0` if `rec` is not a set.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_set_size(rec: *const Record) -> libc::size_t {
@@ -1523,6 +1718,19 @@ pub unsafe extern "C" fn ddlog_get_set_size(rec: *const Record) -> libc::size_t
         .unwrap_or_default()
 }
 
+/// Retrieves `i`th element of the set.  The `ddlog_record` type
+/// internally represents sets as vectors of elements.  `idx` indexes
+/// into this vector.
+///
+/// Returns NULL if `set` is not a set or if the set has fewer than `i`
+/// elements.
+///
+/// The pointer returned by this function is owned by DDlog. The caller
+/// may inspect the returned record, but must not modify it, attach to
+/// other records (e.g., using `ddlog_vector_push()`) or write to the
+/// database.  The lifetime of the pointer coincides with the lifetime of
+/// the record it was obtained from, e.g., the pointer is invalidated
+/// when the value is written to the database.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_set_elem(
@@ -1536,6 +1744,13 @@ pub unsafe extern "C" fn ddlog_get_set_elem(
         .unwrap_or(ptr::null_mut())
 }
 
+/// Append a value to a set.  The `ddlog_record` type internally
+/// represents sets as vectors of elements.  These vectors only get
+/// converted to an actual set representation inside DDlog.  In
+/// particular, duplicate values are not merged inside a record.
+///
+/// This function takes ownership of `rec`, which should not be used
+/// after the call.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_set_push(set: *mut Record, rec: *mut Record) {
@@ -1549,6 +1764,16 @@ pub unsafe extern "C" fn ddlog_set_push(set: *mut Record, rec: *mut Record) {
     Box::into_raw(set);
 }
 
+/// Create a map with specified elements.  Each element in `recs` must be
+/// a 2-tuple representing a key-value pair.
+///
+/// `len` is the length of the `recs` array.  If `len` is greater than
+/// `0`, then `recs` must not be NULL.
+///
+/// The function takes ownership of all records, invalidating all
+/// `ddlog_record` pointers in `recs`.  However it does not take
+/// ownership of the `recs` array itself.  The caller is responsible for
+/// deallocating the array if needed.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_map(fields: *const *mut Record, len: libc::size_t) -> *mut Record {
@@ -1556,6 +1781,7 @@ pub unsafe extern "C" fn ddlog_map(fields: *const *mut Record, len: libc::size_t
     Box::into_raw(Box::new(Record::Array(CollectionKind::Map, fields)))
 }
 
+/// Returns `true` if `rec` is a map and false otherwise
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_map(rec: *const Record) -> bool {
@@ -1565,6 +1791,9 @@ pub unsafe extern "C" fn ddlog_is_map(rec: *const Record) -> bool {
     }
 }
 
+/// Retrieves the number of elements in a map.
+///
+/// Returns `0` if `rec` is not a map.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_map_size(rec: *const Record) -> libc::size_t {
@@ -1574,6 +1803,19 @@ pub unsafe extern "C" fn ddlog_get_map_size(rec: *const Record) -> libc::size_t
     }
 }
 
+/// Retrieves the key of the `i`th element of the map.  The `ddlog_record`
+/// type internally represents maps as vectors of elements.  `i` indexes
+/// into this vector.
+///
+/// Returns NULL if `map` is not a map or if the map has fewer than `i`
+/// elements.
+///
+/// The pointer returned by this function is owned by DDlog. The caller
+/// may inspect the returned record, but must not modify it, attach to
+/// other records (e.g., using `ddlog_vector_push()`) or write to the
+/// database.  The lifetime of the pointer coincides with the lifetime of
+/// the record it was obtained from, e.g., the pointer is invalidated
+/// when the value is written to the database.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_map_key(rec: *const Record, idx: libc::size_t) -> *const Record {
@@ -1586,6 +1828,19 @@ pub unsafe extern "C" fn ddlog_get_map_key(rec: *const Record, idx: libc::size_t
         .unwrap_or(ptr::null_mut())
 }
 
+/// Retrieves the value of the `i`th element of the map.  The
+/// `ddlog_record` type internally represents maps as vectors of
+/// elements.  `i` indexes into this vector.
+///
+/// Returns NULL if `map` is not a map or if the map has fewer than `i`
+/// elements.
+///
+/// The pointer returned by this function is owned by DDlog. The caller
+/// may inspect the returned record, but must not modify it, attach to
+/// other records (e.g., using `ddlog_vector_push()`) or write to the
+/// database.  The lifetime of the pointer coincides with the lifetime of
+/// the record it was obtained from, e.g., the pointer is invalidated
+/// when the value is written to the database.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_map_val(rec: *const Record, idx: libc::size_t) -> *const Record {
@@ -1598,6 +1853,13 @@ pub unsafe extern "C" fn ddlog_get_map_val(rec: *const Record, idx: libc::size_t
         .unwrap_or(ptr::null_mut())
 }
 
+/// Append a key-value pair to a map.  The `ddlog_record` type internally
+/// represents maps as vectors of elements.  These vectors only get
+/// converted to an actual map representation inside DDlog.  In
+/// particular, duplicate keys are not merged inside a record.
+///
+/// This function takes ownership of `key` and `val`, which should not be
+/// used after the call.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_map_push(map: *mut Record, key: *mut Record, val: *mut Record) {
@@ -1611,6 +1873,23 @@ pub unsafe extern "C" fn ddlog_map_push(map: *mut Record, key: *mut Record, val:
     Box::into_raw(map);
 }
 
+/// Create a struct with specified constructor and arguments.  This creates a
+/// "positional record" where arguments are identified by their order.
+///
+/// The number and types of field should match the corresponding DDlog
+/// constructor declaration.
+///
+/// `constructor` can point to statically, dynamically or stack-allocated
+/// strings.  The function copies constructor name to an internal buffer,
+/// so the caller is responsible for deallocating it if necessary.
+///
+/// `len` is the length of the `args` array.  If `len` is greater than
+/// `0`, then `args` must not be NULL.
+///
+/// The function takes ownership of all records, invalidating all
+/// `ddlog_record` pointers in `args`.  However it does not take
+/// ownership of the `args` array itself.  The caller is responsible for
+/// deallocating the array if needed.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_struct(
@@ -1630,6 +1909,25 @@ pub unsafe extern "C" fn ddlog_struct(
     }
 }
 
+/// Create a struct with named fields with specified constructor and
+/// fields.  This creates a "named struct" where arguments are
+/// identified by their names.
+///
+/// The number and types of field should match the corresponding DDlog
+/// constructor declaration.
+///
+/// `constructor` can point to statically, dynamically or stack-allocated
+/// strings.  The function copies constructor name to an internal buffer,
+/// so the caller is responsible for deallocating it if necessary.
+///
+/// `len` is the length of the `args` array and the field_names array.
+/// If `len` is greater than `0`, then `args` and `field_names` must
+/// not be NULL.
+///
+/// The function takes ownership of all records, invalidating all
+/// `ddlog_record` pointers in `args`.  However it does not take
+/// ownership of either `args`, `field_names`, or each field name.  The
+/// caller is responsible for deallocating these arrays if needed.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_named_struct(
@@ -1664,6 +1962,9 @@ pub unsafe extern "C" fn ddlog_named_struct(
     )))
 }
 
+/// Same as `ddlog_struct()`, but passes constructor name as
+/// non-null-terminated string represented by its start address and length in
+/// bytes.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_struct_with_length(
@@ -1692,8 +1993,9 @@ pub unsafe extern "C" fn ddlog_struct_with_length(
     }
 }
 
-/// Similar to `ddlog_struct()`, but expects `constructor` to be static string.
-/// Doesn't allocate memory for a local copy of the string.
+/// Same as `ddlog_struct()`, but assumes that `constructor` is a statically
+/// allocated string and stores the pointer internally instead of copying it to
+/// another buffer.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_struct_static_cons(
@@ -1710,6 +2012,9 @@ pub unsafe extern "C" fn ddlog_struct_static_cons(
     }
 }
 
+/// Same as ddlog_struct_static_cons(), but passes constructor name as
+/// non-null-terminated string represented by its start address and length in
+/// bytes.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_struct_static_cons_with_length(
@@ -1735,12 +2040,14 @@ pub unsafe extern "C" fn ddlog_struct_static_cons_with_length(
     }
 }
 
+/// Returns `true` if `rec` is a struct.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_struct(rec: *const Record) -> bool {
     rec.as_ref().map(Record::is_struct).unwrap_or_default()
 }
 
+/// Returns `true` if `rec` is a struct with named fields.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_is_named_struct(rec: *const Record) -> bool {
@@ -1749,6 +2056,17 @@ pub unsafe extern "C" fn ddlog_is_named_struct(rec: *const Record) -> bool {
         .unwrap_or_default()
 }
 
+/// Retrieves `i`th argument of a struct.
+///
+/// Returns NULL if `rec` is not a struct or if the struct has fewer than
+/// `i` arguments.
+///
+/// The pointer returned by this function is owned by DDlog. The caller
+/// may inspect the returned record, but must not modify it, attach to
+/// other records (e.g., using `ddlog_vector_push()`) or write to the
+/// database.  The lifetime of the pointer coincides with the lifetime of
+/// the record it was obtained from, e.g., the pointer is invalidated
+/// when the value is written to the database.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_struct_field(
@@ -1761,6 +2079,17 @@ pub unsafe extern "C" fn ddlog_get_struct_field(
         .unwrap_or(ptr::null_mut())
 }
 
+/// Retrieves field 'name' of struct 'rec'.
+///
+/// Returns NULL if `rec` is not a struct with named fields or if the struct
+/// does not have a field with the given name.
+///
+/// The pointer returned by this function is owned by DDlog. The caller
+/// may inspect the returned record, but must not modify it, attach to
+/// other records (e.g., using `ddlog_vector_push()`) or write to the
+/// database.  The lifetime of the pointer coincides with the lifetime of
+/// the record it was obtained from, e.g., the pointer is invalidated
+/// when the value is written to the database.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_named_struct_field(
@@ -1779,6 +2108,18 @@ pub unsafe extern "C" fn ddlog_get_named_struct_field(
         .unwrap_or(ptr::null_mut())
 }
 
+/// Retrieves the name of the i-th field of the record.
+///
+/// Returns NULL if `rec` is not a struct with named fields or if the struct
+/// does not have a field with the given name, or the field index is
+/// out of bounds.
+///
+/// The pointer returned by this function is owned by DDlog. The caller
+/// may inspect the returned record, but must not modify it, attach to
+/// other records (e.g., using `ddlog_vector_push()`) or write to the
+/// database.  The lifetime of the pointer coincides with the lifetime of
+/// the record it was obtained from, e.g., the pointer is invalidated
+/// when the value is written to the database.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_named_struct_field_name(
@@ -1802,6 +2143,10 @@ pub unsafe extern "C" fn ddlog_get_named_struct_field_name(
         })
 }
 
+/// Retrieves constructor name as a non-null-terminated string.  Returns
+/// string length in bytes in `len`.
+///
+/// Returns NULL if `rec` is not a struct.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_get_constructor_with_length(
@@ -1832,6 +2177,20 @@ unsafe fn ffi_record_vec(fields: *const *mut Record, len: libc::size_t) -> Vec<R
     boxed_fields
 }
 
+/// Create an insert command.
+///
+/// `table` - input table to insert to.
+/// `rec` - record to insert.  The function takes ownership of this record.
+///
+/// Returns pointer to a new command, which can be sent to DDlog by calling
+/// `ddlog_apply_updates()`.
+///
+/// This function never fails; however the command it creates may fail to
+/// execute, causing `ddlog_apply_updates()` to return an error if:
+/// - `table` is not a valid input table id
+/// - `rec` does not match the record type of `table`
+/// - The table has a primary key and there exists a record with the same key
+///   as `rec` in the table.
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_insert_cmd(table: libc::size_t, rec: *mut Record) -> *mut UpdCmd {
@@ -1839,6 +2198,20 @@ pub unsafe extern "C" fn ddlog_insert_cmd(table: libc::size_t, rec: *mut Record)
     Box::into_raw(Box::new(UpdCmd::Insert(RelIdentifier::RelId(table), *rec)))
 }
 
+/// Create an insert-or-update command that inserts a new record, deleting
+/// an existing record with the same primary key, if there is one.
+///
+/// `table` - input table to insert to.
+/// `rec` - record to insert.  The function takes ownership of this record.
+///
+/// Returns pointer to a new command, which can be sent to DDlog by calling
+/// `ddlog_apply_updates()`.
+///
+/// This function never fails; however the command it creates may fail to
+/// execute, causing `ddlog_apply_updates()` to return an error if:
+/// - `table` is not a valid input table id
+/// - `table` does not have a primary key
+/// - `rec` does not match the record type of `table`
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_insert_or_update_cmd(
@@ -1851,6 +2224,21 @@ pub unsafe extern "C" fn ddlog_insert_or_update_cmd(
     )))
 }
 
+/// Create a delete-by-value command.
+///
+/// `table` - input table to delete from.
+/// `rec` - record to delete.  The function takes ownership of this record.
+///
+/// Returns pointer to a new command, which can be sent to DDlog by calling
+/// `ddlog_apply_updates()`.
+///
+/// This function never fails; however the command it creates may fail to
+/// execute, causing `ddlog_apply_updates()` to return an error if:
+/// - `table` is not a valid input table id
+/// - `rec` does not match the record type of `table`
+/// - `table` has a primary key and record `rec` does not exist in `table`
+///   (NOTE: for tables without a primary key, the command succeeds even if
+///   the record does not exist, in which case it is a no-op)
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_delete_val_cmd(
@@ -1861,6 +2249,20 @@ pub unsafe extern "C" fn ddlog_delete_val_cmd(
     Box::into_raw(Box::new(UpdCmd::Delete(RelIdentifier::RelId(table), *rec)))
 }
 
+/// Create a delete-by-key command.
+///
+/// `table` - input table to delete from.
+/// `rec` - key to delete.  The function takes ownership of this record.
+///
+/// Returns pointer to a new command, which can be sent to DDlog by calling
+/// `ddlog_apply_updates()`.
+///
+/// This function never fails; however the command it creates may fail to
+/// execute, causing `ddlog_apply_updates()` to return an error if:
+/// - `table` is not a valid input table id
+/// - `table` does not have a primary key
+/// - `rec` does not match the primary key type of `table`
+/// - a record with the specified key does not exist in `table`
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_delete_key_cmd(
@@ -1874,6 +2276,22 @@ pub unsafe extern "C" fn ddlog_delete_key_cmd(
     )))
 }
 
+/// Create a modify-by-key command.
+///
+/// `table` - input table to delete from.
+/// `key` - key to modify.  The function takes ownership of this record.
+/// `values` - values to modify.  The function takes ownership of this record.
+///
+/// Returns pointer to a new command, which can be sent to DDlog by calling
+/// `ddlog_apply_updates()`.
+///
+/// This function never fails; however the command it creates may fail to
+/// execute, causing `ddlog_apply_updates()` to return an error if:
+/// - `table` is not a valid input table id
+/// - `table` does not have a primary key
+/// - `key` does not match the primary key type of `table`
+/// - a record with the specified key does not exist in `table`
+/// - `values` does not match the columns of `table`
 #[cfg(feature = "c_api")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_modify_cmd(
@@ -1890,6 +2308,17 @@ pub unsafe extern "C" fn ddlog_modify_cmd(
     )))
 }
 
+/// Deallocate a command that has not been passed to `ddlog_apply_updates()`,
+/// along with the records it contains.  Does nothing if `cmd` is NULL.
+#[cfg(feature = "c_api")]
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_free_cmd(cmd: *mut UpdCmd) {
+    if cmd.is_null() {
+        return;
+    }
+    drop(Box::from_raw(cmd));
+}
+
 #[cfg(feature = "c_api")]
 #[cfg(test)]
 mod tests {
@@ -1,8 +1,18 @@
 use std::fmt::Display;
+use std::io::BufRead;
+use std::io::Chain;
+use std::io::Cursor;
 use std::io::Error;
+use std::io::ErrorKind;
+use std::io::Read;
 use std::io::Result;
 use std::io::Write;
 use std::iter::Peekable;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::ddlog::DDlogConvert;
 use crate::ddval::DDValue;
@@ -124,6 +134,196 @@ where
     record_updates(writer, upds, |w, u| w.record_val_upd::<C>(&u), error)
 }
 
+/// Magic bytes at the start of a binary replay file.
+pub const BINARY_REPLAY_MAGIC: &[u8] = b"DDlogReplay";
+
+/// Version of the binary replay format, stored after `BINARY_REPLAY_MAGIC`.
+const BINARY_REPLAY_VERSION: u8 = 1;
+
+/// Format of a file produced by recording commands sent to a DDlog program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayFormat {
+    /// Commands in the text format accepted by the CLI.
+    Text,
+    /// A header followed by a sequence of bincode-encoded `ReplayFrame`s.
+    Binary,
+}
+
+impl ReplayFormat {
+    /// Determine the format of a replay file from its first bytes.
+    ///
+    /// Reading continues until enough bytes arrived to tell the formats
+    /// apart or the end of the input is reached, so that short reads
+    /// (e.g., from a pipe) do not cause a binary file to be mistaken
+    /// for a text one. The returned reader yields the entire input,
+    /// including the bytes consumed for the detection.
+    pub fn detect<R>(mut reader: R) -> Result<(Self, Chain<Cursor<Vec<u8>>, R>)>
+    where
+        R: Read,
+    {
+        let mut prefix = Vec::with_capacity(BINARY_REPLAY_MAGIC.len());
+        let _ = reader
+            .by_ref()
+            .take(BINARY_REPLAY_MAGIC.len() as u64)
+            .read_to_end(&mut prefix)?;
+
+        let format = if prefix.as_slice() == BINARY_REPLAY_MAGIC {
+            ReplayFormat::Binary
+        } else {
+            ReplayFormat::Text
+        };
+        Ok((format, Cursor::new(prefix).chain(reader)))
+    }
+}
+
+impl FromStr for ReplayFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s {
+            "text" => Ok(ReplayFormat::Text),
+            "binary" => Ok(ReplayFormat::Binary),
+            _ => Err(format!(
+                "unknown replay format '{}'; expected 'text' or 'binary'",
+                s
+            )),
+        }
+    }
+}
+
+/// A command stored in a binary replay file.
+///
+/// Insertions and deletions, which make up the bulk of most recordings,
+/// are stored in binary form using the program's `UpdateSerializer`.
+/// Everything else is rare enough to be stored as text.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ReplayFrame<U> {
+    /// `start;`
+    Start,
+    /// `commit;` or, if the flag is set, `commit dump_changes;`.
+    Commit(bool),
+    /// `rollback;`
+    Rollback,
+    /// `clear <relation>;`
+    Clear(RelId),
+    /// An insertion or deletion of a value.  The flag is set for the last
+    /// update in a batch, i.e., where the text format has a `;`.
+    Update(U, bool),
+    /// Any other command in the text format.
+    Text(String),
+}
+
+/// Write the header of a binary replay file.
+pub fn write_binary_header<W>(writer: &mut W) -> Result<()>
+where
+    W: Write,
+{
+    writer.write_all(BINARY_REPLAY_MAGIC)?;
+    writer.write_all(&[BINARY_REPLAY_VERSION])
+}
+
+/// Read and validate the header of a binary replay file.
+pub fn read_binary_header<R>(reader: &mut R) -> Result<()>
+where
+    R: Read,
+{
+    let mut header = vec![0; BINARY_REPLAY_MAGIC.len() + 1];
+    reader.read_exact(&mut header)?;
+    if !header.starts_with(BINARY_REPLAY_MAGIC) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "not a binary replay file",
+        ));
+    }
+    match header[BINARY_REPLAY_MAGIC.len()] {
+        BINARY_REPLAY_VERSION => Ok(()),
+        version => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("unsupported binary replay format version {}", version),
+        )),
+    }
+}
+
+/// Append a frame to a binary replay file.
+pub fn write_frame<W, U>(writer: &mut W, frame: &ReplayFrame<U>) -> Result<()>
+where
+    W: Write,
+    U: Serialize,
+{
+    bincode::serialize_into(writer, frame).map_err(|e| bincode_error(*e))
+}
+
+/// Read the next frame of a binary replay file.  Returns `Ok(None)` at the
+/// end of the file.
+pub fn read_frame<R, U>(reader: &mut R) -> Result<Option<ReplayFrame<U>>>
+where
+    R: BufRead,
+    U: DeserializeOwned,
+{
+    if reader.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    bincode::deserialize_from(reader)
+        .map(Some)
+        .map_err(|e| bincode_error(*e))
+}
+
+fn bincode_error(e: bincode::ErrorKind) -> Error {
+    match e {
+        bincode::ErrorKind::Io(e) => e,
+        e => Error::new(ErrorKind::InvalidData, e),
+    }
+}
+
+/// Convert an `Update` into a frame.  Updates that `U` cannot represent,
+/// i.e., anything but insertions and deletions of values, are stored as
+/// text.
+pub fn update_frame<C, U>(upd: &Update<DDValue>, last: bool) -> ReplayFrame<U>
+where
+    C: DDlogConvert,
+    U: From<Update<DDValue>>,
+{
+    match upd {
+        Update::Insert { .. } | Update::DeleteValue { .. } => {
+            ReplayFrame::Update(U::from(upd.clone()), last)
+        }
+        _ => {
+            let mut text = Vec::new();
+            // Writing to a `Vec` does not fail.
+            let _ = text
+                .record_val_upd::<C>(upd)
+                .and_then(|_| writeln!(text, "{}", if last { ";" } else { "," }));
+            ReplayFrame::Text(String::from_utf8_lossy(&text).into_owned())
+        }
+    }
+}
+
+/// Record a list of `Update` objects into the given writable object in the
+/// binary replay format.
+///
+/// `error` is a function that is invoked whenever writing out a record
+/// failed. Note that such errors do not cause the overall operation to
+/// fail.
+pub fn record_val_upds_binary<'w, C, U, W, I, F>(
+    writer: &'w mut W,
+    upds: I,
+    mut error: F,
+) -> impl Iterator<Item = Update<DDValue>> + 'w
+where
+    C: DDlogConvert,
+    U: Serialize + From<Update<DDValue>>,
+    W: Write,
+    I: Iterator<Item = Update<DDValue>> + 'w,
+    F: FnMut(Error) + 'w,
+{
+    Peeking::new(upds).map(move |(upd, last)| {
+        if let Err(e) = write_frame(writer, &update_frame::<C, U>(&upd, last)) {
+            error(e);
+        }
+        upd
+    })
+}
+
 /// A trait for recording various operations into something that can be written
 /// to, in order to be able to replay them at a later point in time.
 pub trait RecordReplay: Write {
@@ -287,6 +487,25 @@ pub trait RecordReplay: Write {
     fn record_profile(&mut self) -> Result<()> {
         writeln!(self, "profile;")
     }
+
+    /// Record a frame read from a binary replay file in the text format.
+    fn record_frame<C, U>(&mut self, frame: ReplayFrame<U>) -> Result<()>
+    where
+        C: DDlogConvert,
+        U: Into<Update<DDValue>>,
+    {
+        match frame {
+            ReplayFrame::Start => self.record_start(),
+            ReplayFrame::Commit(record_changes) => self.record_commit(record_changes),
+            ReplayFrame::Rollback => self.record_rollback(),
+            ReplayFrame::Clear(rid) => self.record_clear::<C>(rid),
+            ReplayFrame::Update(upd, last) => {
+                self.record_val_upd::<C>(&upd.into())?;
+                writeln!(self, "{}", if last { ";" } else { "," })
+            }
+            ReplayFrame::Text(text) => self.write_all(text.as_bytes()),
+        }
+    }
 }
 
 impl<W> RecordReplay for W
@@ -300,6 +519,8 @@ where
 mod tests {
     use super::*;
 
+    use crate::ddval::DDValConvert;
+
     /// Test recording of "updates" using `record_updates`.
     #[test]
     fn multi_update_recording() {
@@ -327,4 +548,158 @@ update 10;
 "#;
         test(updates, expected);
     }
+
+    #[derive(Debug)]
+    struct Converter;
+
+    impl DDlogConvert for Converter {
+        fn relid2name(rel_id: RelId) -> Option<&'static str> {
+            if rel_id == 1 {
+                Some("Foo")
+            } else {
+                None
+            }
+        }
+
+        fn indexid2name(_idx_id: IdxId) -> Option<&'static str> {
+            None
+        }
+
+        fn relation_types() -> Vec<(&'static str, &'static str)> {
+            vec![("Foo", "bigint")]
+        }
+
+        fn updcmd2upd(_upd_cmd: &UpdCmd) -> std::result::Result<Update<DDValue>, String> {
+            Err("not supported".to_string())
+        }
+    }
+
+    /// Stand-in for an `UpdateSerializer` of a program whose only
+    /// relation, `Foo`, holds `bigint`s.
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum TestUpdate {
+        Insert(RelId, u64),
+        Delete(RelId, u64),
+    }
+
+    impl From<Update<DDValue>> for TestUpdate {
+        fn from(upd: Update<DDValue>) -> Self {
+            match upd {
+                Update::Insert { relid, v } => {
+                    TestUpdate::Insert(relid, *u64::from_ddvalue_ref(&v))
+                }
+                Update::DeleteValue { relid, v } => {
+                    TestUpdate::Delete(relid, *u64::from_ddvalue_ref(&v))
+                }
+                _ => panic!("unsupported update {:?}", upd),
+            }
+        }
+    }
+
+    impl From<TestUpdate> for Update<DDValue> {
+        fn from(upd: TestUpdate) -> Self {
+            match upd {
+                TestUpdate::Insert(relid, v) => Update::Insert {
+                    relid,
+                    v: v.into_ddvalue(),
+                },
+                TestUpdate::Delete(relid, v) => Update::DeleteValue {
+                    relid,
+                    v: v.into_ddvalue(),
+                },
+            }
+        }
+    }
+
+    /// A reader returning at most one byte per read, like a slow pipe.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let count = self.0.len().min(buf.len()).min(1);
+            buf[..count].copy_from_slice(&self.0[..count]);
+            self.0 = &self.0[count..];
+            Ok(count)
+        }
+    }
+
+    /// Test that frames written to a binary replay file are read back and
+    /// converted to the text format.
+    #[test]
+    fn binary_replay_roundtrip() {
+        let updates = vec![
+            Update::Insert {
+                relid: 1,
+                v: 42u64.into_ddvalue(),
+            },
+            Update::DeleteValue {
+                relid: 1,
+                v: 7u64.into_ddvalue(),
+            },
+            Update::Insert {
+                relid: 1,
+                v: 13u64.into_ddvalue(),
+            },
+        ];
+        let frames = vec![
+            ReplayFrame::Start,
+            ReplayFrame::Clear(1),
+            update_frame::<Converter, TestUpdate>(&updates[0], false),
+            update_frame::<Converter, TestUpdate>(&updates[1], true),
+            update_frame::<Converter, TestUpdate>(&updates[2], true),
+            ReplayFrame::Text("dump Foo;\n".to_string()),
+            ReplayFrame::Commit(true),
+        ];
+
+        let mut buf = Vec::new();
+        write_binary_header(&mut buf).unwrap();
+        for frame in frames.iter() {
+            write_frame(&mut buf, frame).unwrap();
+        }
+
+        let (format, mut reader) = ReplayFormat::detect(Trickle(&buf)).unwrap();
+        assert_eq!(format, ReplayFormat::Binary);
+        let mut data = Vec::new();
+        let _ = reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, buf);
+
+        let (format, _) = ReplayFormat::detect(&b"start;\n"[..]).unwrap();
+        assert_eq!(format, ReplayFormat::Text);
+        let (format, _) = ReplayFormat::detect(&b""[..]).unwrap();
+        assert_eq!(format, ReplayFormat::Text);
+
+        let (_, mut reader) = ReplayFormat::detect(buf.as_slice()).unwrap();
+        read_binary_header(&mut reader).unwrap();
+        let mut read = Vec::new();
+        while let Some(frame) = read_frame::<_, TestUpdate>(&mut reader).unwrap() {
+            read.push(frame);
+        }
+        assert_eq!(read.len(), frames.len());
+        match (&read[2], &read[3], &read[4]) {
+            (
+                ReplayFrame::Update(TestUpdate::Insert(1, 42), false),
+                ReplayFrame::Update(TestUpdate::Delete(1, 7), true),
+                ReplayFrame::Update(TestUpdate::Insert(1, 13), true),
+            ) => (),
+            frames => panic!("unexpected update frames {:?}", frames),
+        }
+
+        let mut text = Vec::new();
+        for frame in read {
+            text.record_frame::<Converter, _>(frame).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "start;\nclear Foo;\ninsert Foo[42],\ndelete Foo[7];\ninsert Foo[13];\ndump Foo;\ncommit dump_changes;\n"
+        );
+    }
+
+    #[test]
+    fn binary_replay_bad_header() {
+        let mut buf = BINARY_REPLAY_MAGIC.to_vec();
+        buf.push(BINARY_REPLAY_VERSION + 1);
+        let err = read_binary_header(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(read_binary_header(&mut &b"start;\n"[..]).is_err());
+    }
 }
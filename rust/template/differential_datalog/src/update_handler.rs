@@ -0,0 +1,1430 @@
+//! `UpdateHandler` trait and its implementations.
+//!
+//! `UpdateHandler` abstracts away different methods of handling
+//! relation update notifications from differential dataflow.
+//! Possible implementations include:
+//! - handling notifications by invoking a user-defined callback
+//! - storing output tables in an in-memory database
+//! - accumulating changes from one or multiple transactions in
+//!   an in-memory database
+//! - delivering all changes made by a transaction to a relation in
+//!   a single batch
+//! - chaining multiple update handlers
+//! - dispatching updates to subscribers registered for individual
+//!   relations at runtime
+//! - all of the above, but processed by a separate thread
+//!   rather than the differential worker threads that computes
+//!   the update
+//! - all of the above, but processed by a pool of worker threads
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::*;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
+use std::thread::spawn;
+
+use crate::callback::Callback;
+use crate::ddval::DDValue;
+use crate::program::ArrangeFunc;
+use crate::program::CBFn;
+use crate::program::RelId;
+use crate::program::Weight;
+use crate::record;
+use crate::record::IntoRecord;
+use crate::valmap::DeltaMap;
+
+/// Single-threaded (non-thread-safe callback)
+#[allow(non_camel_case_types)]
+pub trait ST_CBFn: FnMut(RelId, &DDValue, isize) {
+    fn clone_boxed(&self) -> Box<dyn ST_CBFn>;
+}
+
+impl<T> ST_CBFn for T
+where
+    T: 'static + Clone + FnMut(RelId, &DDValue, isize),
+{
+    fn clone_boxed(&self) -> Box<dyn ST_CBFn> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn ST_CBFn> {
+    fn clone(&self) -> Self {
+        self.as_ref().clone_boxed()
+    }
+}
+
+pub trait UpdateHandler: Debug {
+    /// Returns a handler to be invoked on each output relation update.
+    fn update_cb(&self) -> Box<dyn ST_CBFn>;
+
+    /// Notifies the handler that a transaction_commit method is about to be
+    /// called. The handler has an opportunity to prepare to handle
+    /// update notifications.
+    fn before_commit(&self);
+
+    /// Notifies the handler that transaction_commit has finished. The
+    /// `success` flag indicates whether the commit succeeded or failed.
+    fn after_commit(&self, success: bool);
+}
+
+/// Multi-threaded update handler that can be invoked from multiple DDlog
+/// worker threads.
+pub trait MTUpdateHandler: UpdateHandler + Sync + Send {
+    /// Returns a thread-safe handler to be invoked on each output
+    /// relation update.
+    fn mt_update_cb(&self) -> Box<dyn CBFn>;
+}
+
+/// Rust magic to make `MTUpdateHandler` clonable.
+pub trait IMTUpdateHandler: MTUpdateHandler {
+    fn clone_boxed(&self) -> Box<dyn IMTUpdateHandler>;
+}
+
+impl<T> IMTUpdateHandler for T
+where
+    T: MTUpdateHandler + Clone + 'static,
+{
+    fn clone_boxed(&self) -> Box<dyn IMTUpdateHandler> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn IMTUpdateHandler> {
+    fn clone(&self) -> Self {
+        self.as_ref().clone_boxed()
+    }
+}
+
+/// A no-op `UpdateHandler` implementation
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullUpdateHandler {}
+
+impl NullUpdateHandler {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl UpdateHandler for NullUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        Box::new(|_, _, _| {})
+    }
+    fn before_commit(&self) {}
+    fn after_commit(&self, _success: bool) {}
+}
+
+impl MTUpdateHandler for NullUpdateHandler {
+    fn mt_update_cb(&self) -> Box<dyn CBFn> {
+        Box::new(|_, _, _| {})
+    }
+}
+
+/// `UpdateHandler` implementation that invokes user-provided closure.
+#[derive(Clone)]
+pub struct CallbackUpdateHandler<F: Callback> {
+    cb: F,
+}
+
+impl<F: Callback> CallbackUpdateHandler<F> {
+    pub fn new(cb: F) -> Self {
+        Self { cb }
+    }
+}
+
+impl<F: Callback> Debug for CallbackUpdateHandler<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut builder = f.debug_struct("CallbackUpdateHandler");
+        let _ = builder.field("cb", &(&self.cb as *const F));
+        builder.finish()
+    }
+}
+
+impl<F: Callback> UpdateHandler for CallbackUpdateHandler<F> {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let mut cb = self.cb.clone();
+        Box::new(move |relid, v, w| cb(relid, &v.clone().into_record(), w))
+    }
+    fn before_commit(&self) {}
+    fn after_commit(&self, _success: bool) {}
+}
+
+impl<F: Callback> MTUpdateHandler for CallbackUpdateHandler<F> {
+    fn mt_update_cb(&self) -> Box<dyn CBFn> {
+        let mut cb = self.cb.clone();
+        Box::new(move |relid, v, w| cb(relid, &v.clone().into_record(), w as isize))
+    }
+}
+
+#[cfg(feature = "c_api")]
+pub type ExternCCallback = extern "C" fn(
+    arg: libc::uintptr_t,
+    table: libc::size_t,
+    rec: *const record::Record,
+    weight: libc::ssize_t,
+);
+
+/// `UpdateHandler` implementation that invokes user-provided C function.
+#[cfg(feature = "c_api")]
+#[derive(Clone, Copy, Debug)]
+pub struct ExternCUpdateHandler {
+    cb: ExternCCallback,
+    cb_arg: libc::uintptr_t,
+}
+
+#[cfg(feature = "c_api")]
+impl ExternCUpdateHandler {
+    pub fn new(cb: ExternCCallback, cb_arg: libc::uintptr_t) -> Self {
+        Self { cb, cb_arg }
+    }
+}
+
+#[cfg(feature = "c_api")]
+impl UpdateHandler for ExternCUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let cb = self.cb;
+        let cb_arg = self.cb_arg;
+        Box::new(move |relid, v, w| {
+            cb(
+                cb_arg,
+                relid,
+                &v.clone().into_record() as *const record::Record,
+                w,
+            )
+        })
+    }
+    fn before_commit(&self) {}
+    fn after_commit(&self, _success: bool) {}
+}
+
+#[cfg(feature = "c_api")]
+impl MTUpdateHandler for ExternCUpdateHandler {
+    fn mt_update_cb(&self) -> Box<dyn CBFn> {
+        let cb = self.cb;
+        let cb_arg = self.cb_arg;
+        Box::new(move |relid, v, w| {
+            cb(
+                cb_arg,
+                relid,
+                &v.clone().into_record() as *const record::Record,
+                w as isize,
+            )
+        })
+    }
+}
+
+/// Callback invoked by `BatchUpdateHandler` with the changes to one relation.
+pub trait BatchCBFn: Fn(RelId, BTreeMap<DDValue, isize>) + Send + Sync {}
+
+impl<T> BatchCBFn for T where T: Fn(RelId, BTreeMap<DDValue, isize>) + Send + Sync {}
+
+/// `UpdateHandler` implementation that accumulates the changes made by a
+/// transaction and, once the transaction commits, invokes the callback
+/// once for every modified relation with all changes to the relation.
+/// Like in `DeltaUpdateHandler`, each value occurs in a batch at most once.
+///
+/// Delivering changes in batches amortizes the cost of invoking the
+/// callback, which dominates when the callback crosses an FFI boundary.
+/// Changes made by failed transactions are discarded.
+///
+/// Values are only borrowed by the update callback, so they are cloned into
+/// the batch as the transaction produces them; the batch is then moved to
+/// `cb` without further copies.
+#[derive(Clone)]
+pub struct BatchUpdateHandler {
+    batch: Arc<Mutex<DeltaMap<DDValue>>>,
+    cb: Arc<dyn BatchCBFn>,
+}
+
+impl BatchUpdateHandler {
+    pub fn new<F>(cb: F) -> Self
+    where
+        F: BatchCBFn + 'static,
+    {
+        Self {
+            batch: Arc::new(Mutex::new(DeltaMap::new())),
+            cb: Arc::new(cb),
+        }
+    }
+}
+
+impl Debug for BatchUpdateHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut builder = f.debug_struct("BatchUpdateHandler");
+        let _ = builder.field("batch", &self.batch);
+        let _ = builder.field("cb", &(&*self.cb as *const dyn BatchCBFn));
+        builder.finish()
+    }
+}
+
+impl UpdateHandler for BatchUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let batch = self.batch.clone();
+        Box::new(move |relid, v, w| batch.lock().unwrap().update(relid, v, w))
+    }
+    fn before_commit(&self) {}
+    fn after_commit(&self, success: bool) {
+        // Release the lock before invoking the callback, which may take a
+        // while.
+        let batch = mem::replace(&mut *self.batch.lock().unwrap(), DeltaMap::new());
+        if success {
+            for (relid, delta) in batch.into_iter() {
+                if !delta.is_empty() {
+                    (self.cb)(relid, delta);
+                }
+            }
+        }
+    }
+}
+
+impl MTUpdateHandler for BatchUpdateHandler {
+    fn mt_update_cb(&self) -> Box<dyn CBFn> {
+        let batch = self.batch.clone();
+        Box::new(move |relid, v, w| batch.lock().unwrap().update(relid, v, w as isize))
+    }
+}
+
+/// Multi-threaded `UpdateHandler` implementation that stores updates
+/// in a `DeltaMap` and locks the map on every update.
+#[derive(Clone, Debug)]
+pub struct MTValMapUpdateHandler {
+    db: Arc<Mutex<DeltaMap<DDValue>>>,
+}
+
+impl MTValMapUpdateHandler {
+    pub fn new(db: Arc<Mutex<DeltaMap<DDValue>>>) -> Self {
+        Self { db }
+    }
+}
+
+impl UpdateHandler for MTValMapUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let db = self.db.clone();
+        Box::new(move |relid, v, w| db.lock().unwrap().update(relid, v, w))
+    }
+    fn before_commit(&self) {}
+    fn after_commit(&self, _success: bool) {}
+}
+
+impl MTUpdateHandler for MTValMapUpdateHandler {
+    fn mt_update_cb(&self) -> Box<dyn CBFn> {
+        let db = self.db.clone();
+        Box::new(move |relid, v, w| db.lock().unwrap().update(relid, v, w as isize))
+    }
+}
+
+/// Single-threaded `UpdateHandler` implementation that stores updates
+/// in a `DeltaMap`, locking the map for the entire duration of a commit.
+/// After the commit is done, the map can be read by other threads
+/// concurrently.
+#[derive(Clone, Debug)]
+pub struct ValMapUpdateHandler {
+    db: Arc<RwLock<DeltaMap<DDValue>>>,
+    /// Stores pointer to `RwLockWriteGuard` between `before_commit()` and
+    /// `after_commit()`.  This has to be unsafe, because Rust does
+    /// not let us express a borrow from a field of the same struct in a
+    /// safe way.
+    locked: Arc<Cell<*mut libc::c_void>>,
+}
+
+impl Drop for ValMapUpdateHandler {
+    /// Release the mutex if still held.
+    fn drop<'a>(&'a mut self) {
+        let guard_ptr =
+            self.locked.replace(ptr::null_mut()) as *mut RwLockWriteGuard<'a, DeltaMap<DDValue>>;
+        if !guard_ptr.is_null() {
+            let _guard: Box<RwLockWriteGuard<'_, DeltaMap<DDValue>>> =
+                unsafe { Box::from_raw(guard_ptr) };
+        }
+    }
+}
+
+impl ValMapUpdateHandler {
+    pub fn new(db: Arc<RwLock<DeltaMap<DDValue>>>) -> Self {
+        Self {
+            db,
+            locked: Arc::new(Cell::new(ptr::null_mut())),
+        }
+    }
+}
+
+impl UpdateHandler for ValMapUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let handler = self.clone();
+        Box::new(move |relid, v, w| {
+            let guard_ptr = handler.locked.get();
+            // `update_cb` can also be called during rollback and stop operations.
+            // Ignore those.
+            if !guard_ptr.is_null() {
+                let mut guard: Box<RwLockWriteGuard<'_, DeltaMap<DDValue>>> = unsafe {
+                    Box::from_raw(guard_ptr as *mut RwLockWriteGuard<'_, DeltaMap<DDValue>>)
+                };
+                guard.update(relid, v, w);
+                Box::into_raw(guard);
+            }
+        })
+    }
+    fn before_commit(&self) {
+        let guard = Box::into_raw(Box::new(self.db.write().unwrap())) as *mut libc::c_void;
+        let old = self.locked.replace(guard);
+        assert_eq!(old, ptr::null_mut());
+    }
+    fn after_commit(&self, _success: bool) {
+        let guard_ptr = self.locked.replace(ptr::null_mut());
+        assert_ne!(guard_ptr, ptr::null_mut());
+        let _guard =
+            unsafe { Box::from_raw(guard_ptr as *mut RwLockWriteGuard<'_, DeltaMap<DDValue>>) };
+        // Lock will be released when `_guard` goes out of scope.
+    }
+}
+
+/// `UpdateHandler` implementation that records _changes_ to output relations
+/// rather than complete state.
+#[derive(Clone, Debug)]
+pub struct DeltaUpdateHandler {
+    /// Setting the `DeltaMap` to `None` disables recording.
+    db: Arc<Mutex<Option<DeltaMap<DDValue>>>>,
+    locked: Arc<Cell<*mut libc::c_void>>,
+}
+
+impl Drop for DeltaUpdateHandler {
+    /// Release the mutex if still held.
+    fn drop<'a>(&'a mut self) {
+        let guard_ptr =
+            self.locked.replace(ptr::null_mut()) as *mut MutexGuard<'a, DeltaMap<DDValue>>;
+        if !guard_ptr.is_null() {
+            let _guard: Box<MutexGuard<'_, DeltaMap<DDValue>>> =
+                unsafe { Box::from_raw(guard_ptr) };
+        }
+    }
+}
+
+impl DeltaUpdateHandler {
+    pub fn new(db: Arc<Mutex<Option<DeltaMap<DDValue>>>>) -> Self {
+        Self {
+            db,
+            locked: Arc::new(Cell::new(ptr::null_mut())),
+        }
+    }
+}
+
+impl UpdateHandler for DeltaUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let handler = self.clone();
+        Box::new(move |relid, v, w| {
+            let guard_ptr = handler.locked.get();
+            if !guard_ptr.is_null() {
+                let mut guard: Box<MutexGuard<'_, Option<DeltaMap<DDValue>>>> = unsafe {
+                    Box::from_raw(guard_ptr as *mut MutexGuard<'_, Option<DeltaMap<DDValue>>>)
+                };
+                if let Some(db) = (*guard).as_mut() {
+                    db.update(relid, v, w)
+                };
+                // make sure that guard does not get dropped
+                Box::into_raw(guard);
+            }
+        })
+    }
+    fn before_commit(&self) {
+        let guard = Box::into_raw(Box::new(self.db.lock().unwrap())) as *mut libc::c_void;
+        let old = self.locked.replace(guard);
+        assert_eq!(old, ptr::null_mut());
+    }
+    fn after_commit(&self, _success: bool) {
+        let guard_ptr = self.locked.replace(ptr::null_mut());
+        assert_ne!(guard_ptr, ptr::null_mut());
+        let _guard = unsafe { Box::from_raw(guard_ptr as *mut MutexGuard<'_, DeltaMap<DDValue>>) };
+        // Lock will be released when `_guard` goes out of scope.
+    }
+}
+
+/// `UpdateHandler` implementation that chains multiple single-threaded
+/// handlers.
+#[derive(Debug)]
+pub struct ChainedUpdateHandler {
+    handlers: Vec<Box<dyn UpdateHandler>>,
+}
+
+impl ChainedUpdateHandler {
+    pub fn new(handlers: Vec<Box<dyn UpdateHandler>>) -> Self {
+        Self { handlers }
+    }
+}
+
+impl UpdateHandler for ChainedUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let mut cbs: Vec<Box<dyn ST_CBFn>> = self.handlers.iter().map(|h| h.update_cb()).collect();
+        Box::new(move |relid, v, w| {
+            for cb in cbs.iter_mut() {
+                cb(relid, v, w);
+            }
+        })
+    }
+    fn before_commit(&self) {
+        for h in self.handlers.iter() {
+            h.before_commit();
+        }
+    }
+
+    fn after_commit(&self, success: bool) {
+        for h in self.handlers.iter() {
+            h.after_commit(success);
+        }
+    }
+}
+
+/// `UpdateHandler` implementation that chains multiple multi-threaded
+/// handlers.
+#[derive(Clone, Debug)]
+pub struct MTChainedUpdateHandler {
+    handlers: Vec<Box<dyn IMTUpdateHandler>>,
+}
+
+impl MTChainedUpdateHandler {
+    pub fn new(handlers: Vec<Box<dyn IMTUpdateHandler>>) -> Self {
+        Self { handlers }
+    }
+}
+
+impl UpdateHandler for MTChainedUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let mut cbs: Vec<Box<dyn ST_CBFn>> = self.handlers.iter().map(|h| h.update_cb()).collect();
+        Box::new(move |relid, v, w| {
+            for cb in cbs.iter_mut() {
+                cb(relid, v, w);
+            }
+        })
+    }
+    fn before_commit(&self) {
+        for h in self.handlers.iter() {
+            h.before_commit();
+        }
+    }
+
+    fn after_commit(&self, success: bool) {
+        for h in self.handlers.iter() {
+            h.after_commit(success);
+        }
+    }
+}
+
+impl MTUpdateHandler for MTChainedUpdateHandler {
+    fn mt_update_cb(&self) -> Box<dyn CBFn> {
+        let mut cbs: Vec<Box<dyn CBFn>> = self.handlers.iter().map(|h| h.mt_update_cb()).collect();
+        Box::new(move |relid, v, w| {
+            for cb in cbs.iter_mut() {
+                cb(relid, v, w);
+            }
+        })
+    }
+}
+
+/// Identifies a subscriber registered with `SubscriptionUpdateHandler`.
+pub type SubscriptionId = u64;
+
+/// A handler registered with `SubscriptionUpdateHandler`.
+#[derive(Clone, Debug)]
+struct Subscriber {
+    relid: RelId,
+    /// Only updates for which `filter` returns a value are delivered to
+    /// the subscriber, with the value replaced by the one returned.  Used
+    /// to subscribe to indexes.
+    filter: Option<ArrangeFunc>,
+    handler: Box<dyn IMTUpdateHandler>,
+}
+
+#[derive(Debug, Default)]
+struct Subscriptions {
+    next_id: AtomicU64,
+    /// All registered subscribers.
+    registered: Mutex<BTreeMap<SubscriptionId, Subscriber>>,
+    /// Set whenever `registered` changes and cleared once `active` has
+    /// caught up with it.  Only modified while holding the lock on
+    /// `registered`.
+    changed: AtomicBool,
+    /// Subscribers that receive updates of the current transaction: a
+    /// snapshot of `registered` taken at the start of the transaction.
+    active: Mutex<Vec<Subscriber>>,
+    /// Incremented whenever `active` changes, so that callbacks know to
+    /// rebuild their list of subscriber callbacks.
+    generation: AtomicU64,
+}
+
+/// `UpdateHandler` implementation that dispatches updates to handlers
+/// subscribed to individual relations, so that each subscriber only sees
+/// changes to the relations it is interested in.
+///
+/// Subscribers can be added and removed at any time; changes take effect
+/// at the start of the next transaction.  Subscribers receive
+/// `before_commit` and `after_commit` notifications for every transaction
+/// committed while they are active.
+#[derive(Clone, Debug, Default)]
+pub struct SubscriptionUpdateHandler {
+    subscriptions: Arc<Subscriptions>,
+}
+
+impl SubscriptionUpdateHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `handler` to receive updates to relation `relid`.
+    pub fn subscribe(&self, relid: RelId, handler: Box<dyn IMTUpdateHandler>) -> SubscriptionId {
+        self.add_subscriber(Subscriber {
+            relid,
+            filter: None,
+            handler,
+        })
+    }
+
+    /// Register `handler` to receive the values that updates to relation
+    /// `relid` add to or remove from the arrangement built by `afun`.
+    pub fn subscribe_arrangement(
+        &self,
+        relid: RelId,
+        afun: ArrangeFunc,
+        handler: Box<dyn IMTUpdateHandler>,
+    ) -> SubscriptionId {
+        self.add_subscriber(Subscriber {
+            relid,
+            filter: Some(afun),
+            handler,
+        })
+    }
+
+    /// Remove a subscriber.  Returns `false` if there is no subscriber with
+    /// the given id.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut registered = self.subscriptions.registered.lock().unwrap();
+        let removed = registered.remove(&id).is_some();
+        if removed {
+            self.subscriptions.changed.store(true, Ordering::SeqCst);
+        }
+        removed
+    }
+
+    fn add_subscriber(&self, subscriber: Subscriber) -> SubscriptionId {
+        let id = self.subscriptions.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut registered = self.subscriptions.registered.lock().unwrap();
+        let _ = registered.insert(id, subscriber);
+        self.subscriptions.changed.store(true, Ordering::SeqCst);
+        id
+    }
+
+    fn dispatcher(&self) -> Dispatcher {
+        Dispatcher {
+            subscriptions: self.subscriptions.clone(),
+            generation: None,
+            callbacks: BTreeMap::new(),
+        }
+    }
+}
+
+impl UpdateHandler for SubscriptionUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let mut dispatcher = self.dispatcher();
+        Box::new(move |relid, v, w| dispatcher.update(relid, v, w as Weight))
+    }
+
+    fn before_commit(&self) {
+        let registered = self.subscriptions.registered.lock().unwrap();
+        let mut active = self.subscriptions.active.lock().unwrap();
+        // Callbacks only need to be rebuilt if the set of subscribers
+        // changed since the last transaction.
+        if self.subscriptions.changed.swap(false, Ordering::SeqCst) {
+            *active = registered.values().cloned().collect();
+            let _ = self.subscriptions.generation.fetch_add(1, Ordering::SeqCst);
+        }
+        for subscriber in active.iter() {
+            subscriber.handler.before_commit();
+        }
+    }
+
+    fn after_commit(&self, success: bool) {
+        for subscriber in self.subscriptions.active.lock().unwrap().iter() {
+            subscriber.handler.after_commit(success);
+        }
+    }
+}
+
+impl MTUpdateHandler for SubscriptionUpdateHandler {
+    fn mt_update_cb(&self) -> Box<dyn CBFn> {
+        let mut dispatcher = self.dispatcher();
+        Box::new(move |relid, v, w| dispatcher.update(relid, v, w))
+    }
+}
+
+/// Update callback of `SubscriptionUpdateHandler`.  Caches callbacks of
+/// active subscribers by relation.
+struct Dispatcher {
+    subscriptions: Arc<Subscriptions>,
+    /// Generation of `subscriptions.active` that `callbacks` were built
+    /// from.
+    generation: Option<u64>,
+    callbacks: BTreeMap<RelId, Vec<(Option<ArrangeFunc>, Box<dyn CBFn>)>>,
+}
+
+impl Clone for Dispatcher {
+    /// Each clone builds its own callbacks, as callbacks may not be invoked
+    /// concurrently.
+    fn clone(&self) -> Self {
+        Self {
+            subscriptions: self.subscriptions.clone(),
+            generation: None,
+            callbacks: BTreeMap::new(),
+        }
+    }
+}
+
+impl Dispatcher {
+    fn update(&mut self, relid: RelId, v: &DDValue, w: Weight) {
+        let generation = self.subscriptions.generation.load(Ordering::SeqCst);
+        if self.generation != Some(generation) {
+            self.refresh(generation);
+        }
+        if let Some(callbacks) = self.callbacks.get_mut(&relid) {
+            for (filter, cb) in callbacks.iter_mut() {
+                match filter {
+                    None => cb(relid, v, w),
+                    Some(afun) => {
+                        if let Some((_, v)) = afun(v.clone()) {
+                            cb(relid, &v, w)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn refresh(&mut self, generation: u64) {
+        self.callbacks.clear();
+        for subscriber in self.subscriptions.active.lock().unwrap().iter() {
+            self.callbacks
+                .entry(subscriber.relid)
+                .or_insert_with(Vec::new)
+                .push((subscriber.filter, subscriber.handler.mt_update_cb()));
+        }
+        self.generation = Some(generation);
+    }
+}
+
+/// We use a single mpsc channel to notify worker about
+/// update, start, and commit events.
+enum Msg {
+    BeforeCommit,
+    Update { relid: RelId, v: DDValue, w: isize },
+    AfterCommit { success: bool },
+    Stop,
+}
+
+/// Sending end of the channel to a handler thread: unbounded, or bounded
+/// in the asynchronous mode of `ThreadUpdateHandler`.
+#[derive(Clone, Debug)]
+enum MsgSender {
+    Unbounded(Sender<Msg>),
+    Bounded(SyncSender<Msg>),
+}
+
+impl MsgSender {
+    /// Send `msg`, blocking while a bounded channel is full.
+    fn send(&self, msg: Msg) -> Result<(), SendError<Msg>> {
+        match self {
+            MsgSender::Unbounded(sender) => sender.send(msg),
+            MsgSender::Bounded(sender) => sender.send(msg),
+        }
+    }
+}
+
+/// Sequence number of a commit processed by `ThreadUpdateHandler`, i.e., of
+/// a call to its `after_commit` method.  The first commit is 1.
+pub type CommitId = u64;
+
+/// Number of commits a handler thread has finished processing.
+#[derive(Debug, Default)]
+struct HandledCommits {
+    /// The number of commits handled and whether the thread has exited.
+    state: Mutex<(CommitId, bool)>,
+    changed: Condvar,
+}
+
+impl HandledCommits {
+    fn commit_handled(&self) {
+        self.state.lock().unwrap().0 += 1;
+        self.changed.notify_all();
+    }
+
+    fn thread_exited(&self) {
+        self.state.lock().unwrap().1 = true;
+        self.changed.notify_all();
+    }
+
+    fn wait_for(&self, commit: CommitId) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            match *state {
+                (handled, _) if handled >= commit => return Ok(()),
+                (_, true) => return Err("update handler thread has terminated".to_string()),
+                _ => state = self.changed.wait(state).unwrap(),
+            }
+        }
+    }
+}
+
+/// `UpdateHandler` implementation that handles updates in a separate
+/// worker thread.
+///
+/// By default, `after_commit` waits for the worker to process all updates
+/// of the transaction.  In the asynchronous mode, created by `new_async`,
+/// `after_commit` returns immediately and the worker drains its queue in
+/// the background, so that a slow handler does not stall the dataflow
+/// until the queue fills up.  Use `wait_for_commit` to wait until the
+/// worker has processed a commit.
+///
+/// Clones share the worker, which stops once the last clone is dropped.
+/// If the handler panics, the worker terminates and later notifications
+/// are dropped.
+#[derive(Clone, Debug)]
+pub struct ThreadUpdateHandler {
+    worker: Arc<Worker>,
+}
+
+/// Worker thread of a `ThreadUpdateHandler`, shared by its clones.
+#[derive(Debug)]
+struct Worker {
+    /// Channel to worker thread.
+    msg_channel: Mutex<MsgSender>,
+
+    /// Whether `after_commit` waits for the worker to process the commit.
+    synchronous: bool,
+
+    /// Number of commits sent to the worker.
+    commits: AtomicU64,
+
+    /// Number of commits processed by the worker.
+    handled: Arc<HandledCommits>,
+}
+
+impl ThreadUpdateHandler {
+    pub fn new<F>(handler_generator: F) -> Self
+    where
+        F: FnOnce() -> Box<dyn UpdateHandler> + Send + 'static,
+    {
+        let (tx_msg_channel, rx_message_channel) = channel();
+        Self::spawn(
+            handler_generator,
+            MsgSender::Unbounded(tx_msg_channel),
+            rx_message_channel,
+            true,
+        )
+    }
+
+    /// Create a handler in the asynchronous mode.  At most `queue_size`
+    /// messages (updates and commit notifications) are queued for the
+    /// worker; callbacks block while the queue is full.
+    pub fn new_async<F>(queue_size: usize, handler_generator: F) -> Self
+    where
+        F: FnOnce() -> Box<dyn UpdateHandler> + Send + 'static,
+    {
+        let (tx_msg_channel, rx_message_channel) = sync_channel(queue_size);
+        Self::spawn(
+            handler_generator,
+            MsgSender::Bounded(tx_msg_channel),
+            rx_message_channel,
+            false,
+        )
+    }
+
+    fn spawn<F>(
+        handler_generator: F,
+        tx_msg_channel: MsgSender,
+        rx_message_channel: Receiver<Msg>,
+        synchronous: bool,
+    ) -> Self
+    where
+        F: FnOnce() -> Box<dyn UpdateHandler> + Send + 'static,
+    {
+        let handled = Arc::new(HandledCommits::default());
+        spawn_handler_thread(handler_generator, rx_message_channel, handled.clone());
+
+        Self {
+            worker: Arc::new(Worker {
+                msg_channel: Mutex::new(tx_msg_channel),
+                synchronous,
+                commits: AtomicU64::new(0),
+                handled,
+            }),
+        }
+    }
+
+    /// The id of the last commit passed to the worker, or 0 if there were
+    /// no commits yet.
+    pub fn last_commit(&self) -> CommitId {
+        self.worker.commits.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the worker has processed all updates of `commit` and
+    /// the preceding commits, including the `after_commit` notification.
+    /// Fails if the worker terminates before that.
+    pub fn wait_for_commit(&self, commit: CommitId) -> Result<(), String> {
+        self.worker.handled.wait_for(commit)
+    }
+
+    /// Wait until the worker has processed all commits so far.
+    pub fn flush(&self) -> Result<(), String> {
+        self.wait_for_commit(self.last_commit())
+    }
+
+    fn channel(&self) -> MsgSender {
+        self.worker.msg_channel.lock().unwrap().clone()
+    }
+}
+
+/// Start a thread that feeds messages received from `rx_message_channel` to
+/// the handler created by `handler_generator`.  Once it has processed all
+/// updates of a transaction, the thread records the commit in `handled`.
+fn spawn_handler_thread<F>(
+    handler_generator: F,
+    rx_message_channel: Receiver<Msg>,
+    handled: Arc<HandledCommits>,
+) where
+    F: FnOnce() -> Box<dyn UpdateHandler> + Send + 'static,
+{
+    /// Wakes up threads waiting for commits when the handler thread exits,
+    /// including by panicking.
+    struct ExitGuard(Arc<HandledCommits>);
+
+    impl Drop for ExitGuard {
+        fn drop(&mut self) {
+            self.0.thread_exited();
+        }
+    }
+
+    spawn(move || {
+        let _guard = ExitGuard(handled.clone());
+        let handler = handler_generator();
+        let mut update_cb = handler.update_cb();
+        loop {
+            match rx_message_channel.recv() {
+                Ok(Msg::Update { relid, v, w }) => {
+                    update_cb(relid, &v, w);
+                }
+                Ok(Msg::BeforeCommit) => handler.before_commit(),
+                Ok(Msg::AfterCommit { success }) => {
+                    // All updates of the transaction were sent before
+                    // `after_commit`, so they have been processed by now.
+                    handler.after_commit(success);
+                    handled.commit_handled();
+                }
+                // `Stop`, or every sender is gone.  Exiting drops the
+                // receiver, so later sends fail and are ignored by the
+                // senders.
+                _ => return,
+            }
+        }
+    });
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // The worker may have terminated already.
+        let _ = self.msg_channel.lock().unwrap().send(Msg::Stop);
+    }
+}
+
+impl UpdateHandler for ThreadUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let channel = self.channel();
+        Box::new(move |relid, v, w| {
+            // The worker may have terminated, e.g., if the handler panicked.
+            let _ = channel.send(Msg::Update {
+                relid,
+                v: v.clone(),
+                w,
+            });
+        })
+    }
+
+    fn before_commit(&self) {
+        let _ = self.channel().send(Msg::BeforeCommit);
+    }
+
+    fn after_commit(&self, success: bool) {
+        let commit = self.worker.commits.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.channel().send(Msg::AfterCommit { success }).is_ok() && self.worker.synchronous {
+            // Wait for all queued updates to get processed by worker.  The
+            // worker may terminate instead, e.g., if the handler panics.
+            let _ = self.wait_for_commit(commit);
+        }
+    }
+}
+
+impl MTUpdateHandler for ThreadUpdateHandler {
+    fn mt_update_cb(&self) -> Box<dyn CBFn> {
+        let channel = self.channel();
+        Box::new(move |relid, v, w| {
+            // The worker may have terminated, e.g., if the handler panicked.
+            let _ = channel.send(Msg::Update {
+                relid,
+                v: v.clone(),
+                w: w as isize,
+            });
+        })
+    }
+}
+
+/// `UpdateHandler` implementation that handles updates in a pool of worker
+/// threads.
+///
+/// Updates are sharded by relation: all updates to a relation are processed
+/// by the same worker, in the order in which they were produced, and each
+/// worker runs its own handler, created by calling `handler_generator` with
+/// the index of the worker.  Handlers must therefore not share state that
+/// spans relations, e.g., a `DeltaUpdateHandler` that locks a shared map for
+/// the duration of a commit.
+///
+/// Every worker's handler receives `before_commit` and `after_commit`
+/// notifications for every transaction, and `after_commit` returns once all
+/// workers have processed all updates of the transaction.  A worker whose
+/// handler panics terminates, and `after_commit` stops waiting for it.
+#[derive(Clone, Debug)]
+pub struct PoolUpdateHandler {
+    workers: Arc<Workers>,
+}
+
+/// Channels to the workers of a `PoolUpdateHandler`, shared by its clones.
+#[derive(Debug)]
+struct Workers {
+    msg_channels: Mutex<Vec<Sender<Msg>>>,
+
+    /// Number of commits sent to the workers.
+    commits: AtomicU64,
+
+    /// Number of commits processed by each worker.
+    handled: Vec<Arc<HandledCommits>>,
+}
+
+impl PoolUpdateHandler {
+    /// Create a pool of `threads` workers (at least one).
+    pub fn new<F>(threads: usize, handler_generator: F) -> Self
+    where
+        F: Fn(usize) -> Box<dyn UpdateHandler> + Send + Sync + 'static,
+    {
+        let threads = threads.max(1);
+        let handler_generator = Arc::new(handler_generator);
+        let handled: Vec<_> = (0..threads)
+            .map(|_| Arc::new(HandledCommits::default()))
+            .collect();
+
+        let msg_channels = handled
+            .iter()
+            .enumerate()
+            .map(|(i, handled)| {
+                let (tx_msg_channel, rx_message_channel) = channel();
+                let handler_generator = handler_generator.clone();
+                spawn_handler_thread(
+                    move || handler_generator(i),
+                    rx_message_channel,
+                    handled.clone(),
+                );
+                tx_msg_channel
+            })
+            .collect();
+
+        Self {
+            workers: Arc::new(Workers {
+                msg_channels: Mutex::new(msg_channels),
+                commits: AtomicU64::new(0),
+                handled,
+            }),
+        }
+    }
+
+    /// Number of worker threads in the pool.
+    pub fn threads(&self) -> usize {
+        self.workers.msg_channels.lock().unwrap().len()
+    }
+
+    /// Send a message to every worker, including after failing to send it
+    /// to a worker that has terminated.  Returns whether each send
+    /// succeeded.
+    fn broadcast<F>(&self, msg: F) -> Vec<bool>
+    where
+        F: Fn() -> Msg,
+    {
+        self.workers
+            .msg_channels
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|channel| channel.send(msg()).is_ok())
+            .collect()
+    }
+
+    fn channels(&self) -> Vec<Sender<Msg>> {
+        self.workers.msg_channels.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        for channel in self.msg_channels.lock().unwrap().iter() {
+            let _ = channel.send(Msg::Stop);
+        }
+    }
+}
+
+impl UpdateHandler for PoolUpdateHandler {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let channels = self.channels();
+        Box::new(move |relid, v, w| {
+            // Updates to relations of a worker that has terminated are
+            // dropped, as `after_commit` does not wait for it.
+            let _ = channels[relid % channels.len()].send(Msg::Update {
+                relid,
+                v: v.clone(),
+                w,
+            });
+        })
+    }
+
+    fn before_commit(&self) {
+        let _ = self.broadcast(|| Msg::BeforeCommit);
+    }
+
+    fn after_commit(&self, success: bool) {
+        let commit = self.workers.commits.fetch_add(1, Ordering::SeqCst) + 1;
+        let sent = self.broadcast(|| Msg::AfterCommit { success });
+        // Wait for all queued updates to get processed by workers.  Workers
+        // that have terminated are not waited for, so that they cannot
+        // stall the others.
+        for (handled, sent) in self.workers.handled.iter().zip(sent) {
+            if sent {
+                let _ = handled.wait_for(commit);
+            }
+        }
+    }
+}
+
+impl MTUpdateHandler for PoolUpdateHandler {
+    fn mt_update_cb(&self) -> Box<dyn CBFn> {
+        let channels = self.channels();
+        Box::new(move |relid, v, w| {
+            // Updates to relations of a worker that has terminated are
+            // dropped, as `after_commit` does not wait for it.
+            let _ = channels[relid % channels.len()].send(Msg::Update {
+                relid,
+                v: v.clone(),
+                w: w as isize,
+            });
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ddval::DDValConvert;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Event {
+        BeforeCommit,
+        Update(RelId, DDValue, isize),
+        AfterCommit(bool),
+    }
+
+    /// Handler that records every notification it receives.
+    #[derive(Clone, Debug, Default)]
+    struct Recorder {
+        events: Arc<Mutex<Vec<Event>>>,
+    }
+
+    impl Recorder {
+        fn take(&self) -> Vec<Event> {
+            mem::take(&mut *self.events.lock().unwrap())
+        }
+    }
+
+    impl UpdateHandler for Recorder {
+        fn update_cb(&self) -> Box<dyn ST_CBFn> {
+            let events = self.events.clone();
+            Box::new(move |relid, v, w| {
+                events
+                    .lock()
+                    .unwrap()
+                    .push(Event::Update(relid, v.clone(), w))
+            })
+        }
+
+        fn before_commit(&self) {
+            self.events.lock().unwrap().push(Event::BeforeCommit);
+        }
+
+        fn after_commit(&self, success: bool) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(Event::AfterCommit(success));
+        }
+    }
+
+    impl MTUpdateHandler for Recorder {
+        fn mt_update_cb(&self) -> Box<dyn CBFn> {
+            let events = self.events.clone();
+            Box::new(move |relid, v: &DDValue, w| {
+                events
+                    .lock()
+                    .unwrap()
+                    .push(Event::Update(relid, v.clone(), w as isize))
+            })
+        }
+    }
+
+    fn update(relid: RelId, v: u64, w: isize) -> Event {
+        Event::Update(relid, v.into_ddvalue(), w)
+    }
+
+    /// Run a transaction that applies `updates` through `cb`.
+    fn commit(
+        handler: &SubscriptionUpdateHandler,
+        cb: &mut Box<dyn CBFn>,
+        updates: &[(RelId, u64)],
+    ) {
+        handler.before_commit();
+        for (relid, v) in updates {
+            cb(*relid, &v.into_ddvalue(), 1);
+        }
+        handler.after_commit(true);
+    }
+
+    /// Subscribers only see updates to the relation they subscribed to, and
+    /// only while they are subscribed.
+    #[test]
+    fn subscribe_unsubscribe() {
+        let handler = SubscriptionUpdateHandler::new();
+        let mut cb = handler.mt_update_cb();
+        let (foo, bar) = (Recorder::default(), Recorder::default());
+
+        commit(&handler, &mut cb, &[(0, 1)]);
+
+        let foo_id = handler.subscribe(0, Box::new(foo.clone()));
+        let bar_id = handler.subscribe(1, Box::new(bar.clone()));
+        assert_ne!(foo_id, bar_id);
+
+        commit(&handler, &mut cb, &[(0, 2), (1, 3), (2, 4)]);
+        assert_eq!(
+            foo.take(),
+            vec![
+                Event::BeforeCommit,
+                update(0, 2, 1),
+                Event::AfterCommit(true)
+            ]
+        );
+        assert_eq!(
+            bar.take(),
+            vec![
+                Event::BeforeCommit,
+                update(1, 3, 1),
+                Event::AfterCommit(true)
+            ]
+        );
+
+        assert!(handler.unsubscribe(foo_id));
+        assert!(!handler.unsubscribe(foo_id));
+
+        commit(&handler, &mut cb, &[(0, 5), (1, 6)]);
+        assert_eq!(foo.take(), vec![]);
+        assert_eq!(
+            bar.take(),
+            vec![
+                Event::BeforeCommit,
+                update(1, 6, 1),
+                Event::AfterCommit(true)
+            ]
+        );
+    }
+
+    /// Arrangement subscribers only see the values selected by the
+    /// arrangement function, as transformed by it.
+    #[test]
+    fn subscribe_arrangement() {
+        fn only_one(v: DDValue) -> Option<(DDValue, DDValue)> {
+            if v == 1u64.into_ddvalue() {
+                Some((v, 10u64.into_ddvalue()))
+            } else {
+                None
+            }
+        }
+
+        let handler = SubscriptionUpdateHandler::new();
+        let mut cb = handler.mt_update_cb();
+        let (rel, idx) = (Recorder::default(), Recorder::default());
+        let _ = handler.subscribe(0, Box::new(rel.clone()));
+        let _ = handler.subscribe_arrangement(0, only_one, Box::new(idx.clone()));
+
+        commit(&handler, &mut cb, &[(0, 1), (0, 2), (1, 1)]);
+        assert_eq!(
+            rel.take(),
+            vec![
+                Event::BeforeCommit,
+                update(0, 1, 1),
+                update(0, 2, 1),
+                Event::AfterCommit(true)
+            ]
+        );
+        assert_eq!(
+            idx.take(),
+            vec![
+                Event::BeforeCommit,
+                update(0, 10, 1),
+                Event::AfterCommit(true)
+            ]
+        );
+    }
+
+    /// Callbacks of subscribers are only created when the set of
+    /// subscribers changes, not for every transaction.
+    #[test]
+    fn callbacks_built_once() {
+        /// Handler counting the callbacks created for it.
+        #[derive(Clone, Debug, Default)]
+        struct Counter {
+            callbacks: Arc<AtomicU64>,
+        }
+
+        impl UpdateHandler for Counter {
+            fn update_cb(&self) -> Box<dyn ST_CBFn> {
+                Box::new(|_, _, _| ())
+            }
+
+            fn before_commit(&self) {}
+
+            fn after_commit(&self, _success: bool) {}
+        }
+
+        impl MTUpdateHandler for Counter {
+            fn mt_update_cb(&self) -> Box<dyn CBFn> {
+                let _ = self.callbacks.fetch_add(1, Ordering::SeqCst);
+                Box::new(|_, _, _| ())
+            }
+        }
+
+        let handler = SubscriptionUpdateHandler::new();
+        let mut cb = handler.mt_update_cb();
+        let counter = Counter::default();
+        let _ = handler.subscribe(0, Box::new(counter.clone()));
+
+        for i in 0..3 {
+            commit(&handler, &mut cb, &[(0, i)]);
+        }
+        assert_eq!(counter.callbacks.load(Ordering::SeqCst), 1);
+
+        let id = handler.subscribe(1, Box::new(Recorder::default()));
+        commit(&handler, &mut cb, &[(0, 3)]);
+        commit(&handler, &mut cb, &[(0, 4)]);
+        assert_eq!(counter.callbacks.load(Ordering::SeqCst), 2);
+
+        assert!(handler.unsubscribe(id));
+        commit(&handler, &mut cb, &[(0, 5)]);
+        assert_eq!(counter.callbacks.load(Ordering::SeqCst), 3);
+    }
+
+    /// Subscription changes made during a transaction take effect at the
+    /// start of the next one, including for callbacks that were created
+    /// before the change.
+    #[test]
+    fn subscription_changes_during_commit() {
+        let handler = SubscriptionUpdateHandler::new();
+        let mut cb = handler.mt_update_cb();
+        let mut st_cb = handler.update_cb();
+        let (old, new) = (Recorder::default(), Recorder::default());
+        let old_id = handler.subscribe(0, Box::new(old.clone()));
+
+        handler.before_commit();
+        cb(0, &1u64.into_ddvalue(), 1);
+        assert!(handler.unsubscribe(old_id));
+        let _ = handler.subscribe(0, Box::new(new.clone()));
+        cb(0, &2u64.into_ddvalue(), -1);
+        st_cb(0, &3u64.into_ddvalue(), 1);
+        handler.after_commit(false);
+
+        assert_eq!(
+            old.take(),
+            vec![
+                Event::BeforeCommit,
+                update(0, 1, 1),
+                update(0, 2, -1),
+                update(0, 3, 1),
+                Event::AfterCommit(false)
+            ]
+        );
+        assert_eq!(new.take(), vec![]);
+
+        handler.before_commit();
+        cb(0, &4u64.into_ddvalue(), 1);
+        st_cb(0, &5u64.into_ddvalue(), 1);
+        handler.after_commit(true);
+
+        assert_eq!(old.take(), vec![]);
+        assert_eq!(
+            new.take(),
+            vec![
+                Event::BeforeCommit,
+                update(0, 4, 1),
+                update(0, 5, 1),
+                Event::AfterCommit(true)
+            ]
+        );
+    }
+
+    /// Batch handlers deliver the net changes made to each relation by a
+    /// successful transaction in a single call once the transaction
+    /// commits, and discard the changes made by failed transactions.
+    #[test]
+    fn batch() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let handler = {
+            let batches = batches.clone();
+            BatchUpdateHandler::new(move |relid, delta: BTreeMap<DDValue, isize>| {
+                batches
+                    .lock()
+                    .unwrap()
+                    .push((relid, delta.into_iter().collect::<Vec<_>>()))
+            })
+        };
+        let mut cb = handler.mt_update_cb();
+        let mut st_cb = handler.update_cb();
+        let take = || mem::take(&mut *batches.lock().unwrap());
+
+        handler.before_commit();
+        cb(1, &2u64.into_ddvalue(), 1);
+        cb(0, &1u64.into_ddvalue(), 1);
+        st_cb(0, &3u64.into_ddvalue(), -1);
+        /* Relation 2 does not change. */
+        cb(2, &4u64.into_ddvalue(), 1);
+        st_cb(2, &4u64.into_ddvalue(), -1);
+        assert_eq!(take(), vec![]);
+        handler.after_commit(true);
+        assert_eq!(
+            take(),
+            vec![
+                (0, vec![(1u64.into_ddvalue(), 1), (3u64.into_ddvalue(), -1)]),
+                (1, vec![(2u64.into_ddvalue(), 1)])
+            ]
+        );
+
+        handler.before_commit();
+        cb(0, &5u64.into_ddvalue(), 1);
+        handler.after_commit(false);
+        assert_eq!(take(), vec![]);
+
+        handler.before_commit();
+        cb(0, &6u64.into_ddvalue(), 1);
+        handler.after_commit(true);
+        assert_eq!(take(), vec![(0, vec![(6u64.into_ddvalue(), 1)])]);
+    }
+}
@@ -102,9 +102,8 @@ impl<V: Display + Ord + Clone> DeltaMap<V> {
         Ok(())
     }
 
-    pub fn format_rel_as_set(&mut self, relid: RelId, w: &mut dyn io::Write) -> io::Result<()> {
-        let map = self.get_rel(relid);
-        for (val, weight) in map {
+    pub fn format_rel_as_set(&self, relid: RelId, w: &mut dyn io::Write) -> io::Result<()> {
+        for (val, weight) in self.try_get_rel(relid).into_iter().flatten() {
             if *weight == 1 {
                 w.write_fmt(format_args!("{}\n", *val))?;
             } else {
@@ -36,6 +36,7 @@ use differential_dataflow::Collection;
 
 use differential_datalog::ddval::*;
 use differential_datalog::program::*;
+use differential_datalog::ErrorKind;
 
 pub mod test_value;
 use test_value::*;
@@ -125,11 +126,9 @@ fn test_insert_non_existent_relation() {
 
     let mut running = prog.run(3).unwrap();
     running.transaction_start().unwrap();
-    let result = running.insert(1, U64(42).into_ddvalue());
-    assert_eq!(
-        &result.unwrap_err(),
-        "apply_update: unknown input relation 1"
-    );
+    let err = running.insert(1, U64(42).into_ddvalue()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnknownRelation);
+    assert_eq!(err.message(), "apply_update: unknown input relation 1");
     running.transaction_commit().unwrap();
 }
 
@@ -680,7 +679,38 @@ fn test_join(nthreads: usize) {
         assert_eq!(vals, expect);
     }
 
-    running.stop().unwrap();
+    // Query arrangement from multiple threads while the main thread runs
+    // a transaction that does not affect the arrangement.
+    let queriers: Vec<_> = (0..4)
+        .map(|_| {
+            let queries = running.query_handle();
+            let vals = vals.clone();
+            std::thread::spawn(move || {
+                for key in vals.iter() {
+                    let res = queries
+                        .query_arrangement((2, 0), U64(*key).into_ddvalue())
+                        .unwrap();
+                    assert_eq!(res, BTreeSet::from_iter(vec![U64(*key).into_ddvalue()]));
+                }
+            })
+        })
+        .collect();
+
+    running.transaction_start().unwrap();
+    for x in set.keys() {
+        running.delete_value(1, x.clone().into_ddvalue()).unwrap();
+    }
+    running.transaction_commit().unwrap();
+
+    for querier in queriers.into_iter() {
+        querier.join().unwrap();
+    }
+    assert_eq!(*relset4.lock().unwrap(), BTreeMap::default());
+
+    // `RunningProgram` can be stopped from a different thread.
+    std::thread::spawn(move || running.stop().unwrap())
+        .join()
+        .unwrap();
 }
 
 #[test]
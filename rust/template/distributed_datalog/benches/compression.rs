@@ -0,0 +1,88 @@
+//! A benchmark comparing the throughput of a TCP channel using the
+//! different kinds of compression.
+
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use criterion::Throughput;
+
+use distributed_datalog::Compression;
+use distributed_datalog::Observable;
+use distributed_datalog::Observer;
+use distributed_datalog::SchemaHash;
+use distributed_datalog::TcpReceiver;
+use distributed_datalog::TcpSender;
+
+/// The number of updates per transaction.
+const UPDATES: usize = 100_000;
+
+/// An observer signaling each commit it sees.
+#[derive(Debug)]
+struct Commits(Sender<()>);
+
+impl Observer<String, String> for Commits {
+    fn on_start(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn on_commit(&mut self) -> Result<(), String> {
+        self.0.send(()).map_err(|e| e.to_string())
+    }
+
+    fn on_updates<'a>(
+        &mut self,
+        updates: Box<dyn Iterator<Item = String> + 'a>,
+    ) -> Result<(), String> {
+        updates.for_each(drop);
+        Ok(())
+    }
+
+    fn on_completed(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Send a transaction comprising the given updates and wait until the
+/// receiving end committed it.
+fn transmit(send: &mut TcpSender<String>, updates: &[String], commits: &Receiver<()>) {
+    let observer = send as &mut dyn Observer<String, _>;
+    observer.on_start().unwrap();
+    observer
+        .on_updates(Box::new(updates.iter().cloned()))
+        .unwrap();
+    observer.on_commit().unwrap();
+    commits.recv().unwrap();
+}
+
+fn compression(c: &mut Criterion) {
+    let updates = (0..UPDATES)
+        .map(|i| format!("update #{} for relation {}", i, i % 16))
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("transmit");
+    group.throughput(Throughput::Elements(UPDATES as u64));
+    group.sample_size(10);
+
+    for compression in &[Compression::None, Compression::Lz4, Compression::Zstd] {
+        let schema = SchemaHash::default();
+        let mut recv = TcpReceiver::<String, String>::new("127.0.0.1:0", schema).unwrap();
+        recv.set_compression(*compression);
+        let (sender, commits) = channel();
+        recv.subscribe(Box::new(Commits(sender))).unwrap();
+
+        let mut send = TcpSender::<String>::new(*recv.addr(), schema).unwrap();
+        send.wait_connected().unwrap();
+
+        group.bench_function(format!("{:?}", compression), |b| {
+            b.iter(|| transmit(&mut send, &updates, &commits))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, compression);
+criterion_main!(benches);
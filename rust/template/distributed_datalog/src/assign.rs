@@ -1,18 +1,92 @@
-use std::collections::BTreeMap;
+//! Strategies for assigning abstract nodes to actual members in the
+//! system.
 
+use std::collections::BTreeSet;
+
+use crate::hash::fnv1a;
 use crate::instantiate::Assignment;
 use crate::schema::Member;
 use crate::schema::Node;
 
+/// Compute the rendezvous score of a node on a member.
+///
+/// The score has to be the same on every member of the system, so we
+/// use FNV-1a over the node's UUID and the member's address, followed
+/// by a final avalanche step (taken from SplitMix64) to spread FNV's
+/// weak low bits.
+fn score(node: &Node, member: &Member) -> u64 {
+    let addr = member.addr().to_string();
+    let hash = fnv1a(node.as_bytes().iter().chain(addr.as_bytes()).copied());
+
+    let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Assign nodes to members using rendezvous (or "highest random
+/// weight") hashing.
+///
+/// The score of every node on every member is a deterministic hash of
+/// the two. A member only hosts a single node (see
+/// `instantiate::check_assignment`), so node and member pairs are
+/// matched greedily, highest score first, with each node taking the
+/// best scoring member that is still free. That has a couple of
+/// desirable properties:
+/// - the assignment only depends on the set of nodes and members and
+///   not on the order in which they are provided, so all members in
+///   the system arrive at the same result independently
+/// - when a member is removed only its node and the nodes it displaces
+///   in turn move; when a member is added only the node that now
+///   scores highest on it and the nodes moving into the place vacated
+///   by it move
+///
+/// `None` is returned if there are more nodes than members to assign
+/// them to.
+pub fn rendezvous_assign<'n, 'm, N, M>(nodes: N, members: M) -> Option<Assignment>
+where
+    N: Iterator<Item = &'n Node>,
+    M: Iterator<Item = &'m Member>,
+{
+    let nodes = nodes.collect::<BTreeSet<_>>();
+    let members = members.collect::<BTreeSet<_>>();
+    if nodes.len() > members.len() {
+        return None;
+    }
+
+    let mut pairs = nodes
+        .iter()
+        .flat_map(|node| {
+            members
+                .iter()
+                .map(move |member| (score(node, member), *member, *node))
+        })
+        .collect::<Vec<_>>();
+    // Ties are broken by the member and node themselves, to keep the
+    // result independent of the order of `nodes` and `members`.
+    pairs.sort_unstable_by(|lhs, rhs| rhs.cmp(lhs));
+
+    let mut taken = BTreeSet::new();
+    let assignment =
+        pairs
+            .into_iter()
+            .fold(Assignment::new(), |mut assignment, (_, member, node)| {
+                if !assignment.contains_key(node) && taken.insert(member) {
+                    let _ = assignment.insert(*node, member.addr().clone());
+                }
+                assignment
+            });
+    debug_assert_eq!(assignment.len(), nodes.len());
+    Some(assignment)
+}
+
 /// Assign nodes to actual members in the system.
 ///
-/// Right now the assignment is the simplest possible where we just take
-/// members in "some" order and assign them to nodes until we have
-/// covered all.
-// TODO: We very likely want to have some more brains in here (such as
-//       a consistent hashing algorithm) or we will incur potentially
-//       excessive reconfigurations in the system whenever a node is
-//       added or removed.
+/// The assignment is the simplest possible where we just take members
+/// in "some" order and assign them to nodes until we have covered all.
+/// Because any membership change can reshuffle the entire assignment
+/// this function is only meant for tests; `rendezvous_assign` should
+/// be used otherwise.
+#[cfg(any(test, feature = "test"))]
 pub fn simple_assign<'n, 'm, N, M>(nodes: N, mut members: M) -> Option<Assignment>
 where
     N: Iterator<Item = &'n Node> + ExactSizeIterator,
@@ -20,18 +94,16 @@ where
 {
     // If the configuration prescribes more nodes than we have members
     // in the system we can't find an assignment.
-    // TODO: In theory we could map two or more nodes to a single
-    //       member.
     if members.len() < nodes.len() {
         return None;
     }
 
-    let assignment = BTreeMap::new();
+    let assignment = Assignment::new();
 
     Some(nodes.fold(assignment, |mut assignment, uuid| {
         match members.next() {
             Some(member) => {
-                let node = *member.addr();
+                let node = member.addr().clone();
                 let _result = assignment.insert(*uuid, node);
                 debug_assert_eq!(_result, None);
             }
@@ -110,4 +182,92 @@ mod tests {
         };
         assert_eq!(assignment, expected);
     }
+
+    fn members(count: u16) -> Vec<Member> {
+        (1..=count)
+            .map(|port| Member::new(Addr::Ip(([127, 0, 0, 1], port).into())))
+            .collect()
+    }
+
+    #[test]
+    fn rendezvous_assign_no_members() {
+        let nodes = [Uuid::new_v4()];
+        assert_eq!(rendezvous_assign(nodes.iter(), [].iter()), None);
+        assert_eq!(
+            rendezvous_assign([].iter(), members(1).iter()),
+            Some(btreemap! {})
+        );
+    }
+
+    #[test]
+    fn rendezvous_assign_one_node_per_member() {
+        let nodes = (0..16).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+        let members = members(16);
+
+        let assignment = rendezvous_assign(nodes.iter(), members.iter()).unwrap();
+        assert_eq!(assignment.len(), nodes.len());
+        assert_eq!(assignment.values().collect::<BTreeSet<_>>().len(), 16);
+        assert!(assignment
+            .values()
+            .all(|addr| members.iter().any(|member| member.addr() == addr)));
+
+        assert_eq!(rendezvous_assign(nodes.iter(), members[1..].iter()), None);
+    }
+
+    #[test]
+    fn rendezvous_assign_is_order_independent() {
+        let nodes = (0..16).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+        let members = members(5);
+
+        let assignment = rendezvous_assign(nodes.iter(), members.iter());
+        let reversed = rendezvous_assign(nodes.iter().rev(), members.iter().rev());
+        assert_eq!(assignment, reversed);
+    }
+
+    /// Retrieve the nodes assigned differently in `before` and `after`.
+    fn moved(before: &Assignment, after: &Assignment) -> Vec<(Addr, Addr)> {
+        before
+            .iter()
+            .filter(|(node, addr)| after[node] != **addr)
+            .map(|(node, addr)| (addr.clone(), after[node].clone()))
+            .collect()
+    }
+
+    #[test]
+    fn rendezvous_assign_minimizes_movement() {
+        let nodes = (0..16).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+        let members = members(25);
+        let before = rendezvous_assign(nodes.iter(), members[..24].iter()).unwrap();
+
+        // Adding a member must only move nodes onto that member or into
+        // the place another moved node left.
+        let added = members[24].addr();
+        let after = rendezvous_assign(nodes.iter(), members.iter()).unwrap();
+        let moves = moved(&before, &after);
+        moves.iter().for_each(|(_, to)| {
+            assert!(to == added || moves.iter().any(|(from, _)| from == to));
+        });
+
+        // Removing a member must only move nodes off that member or out
+        // of the place another moved node took.
+        let removed = before.values().next().unwrap();
+        let remaining = members[..24]
+            .iter()
+            .filter(|member| member.addr() != removed);
+        let after = rendezvous_assign(nodes.iter(), remaining).unwrap();
+        let moves = moved(&before, &after);
+        assert!(!moves.is_empty());
+        moves.iter().for_each(|(from, _)| {
+            assert!(from == removed || moves.iter().any(|(_, to)| to == from));
+        });
+
+        // Removing a member without a node must not move anything.
+        let unused = members[..24]
+            .iter()
+            .find(|member| !before.values().any(|addr| addr == member.addr()))
+            .unwrap();
+        let remaining = members[..24].iter().filter(|member| *member != unused);
+        let after = rendezvous_assign(nodes.iter(), remaining).unwrap();
+        assert_eq!(after, before);
+    }
 }
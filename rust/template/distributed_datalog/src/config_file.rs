@@ -0,0 +1,547 @@
+//! A module providing a file based backend for membership and
+//! configuration, meant for running a d3log topology locally.
+//!
+//! The file describes the members as well as the system configuration
+//! in either JSON or TOML format (determined by the file's extension),
+//! using the same serialized representation as is stored in ZooKeeper.
+//! A configuration file in TOML format could look as follows:
+//! ```toml
+//! members = [
+//!   { addr = { Ip = "127.0.0.1:5000" } },
+//!   { addr = { Ip = "127.0.0.1:5001" } },
+//! ]
+//!
+//! [config.7aba4dd8-80fc-43f0-bbf6-0dc56abaa001]
+//! 1 = [{ Source = { File = "input.dat" } }]
+//! 2 = [{ Output = ["bcda5ea4-b27d-4baf-b405-dfed7cd739ae", 3] }]
+//!
+//! [config.bcda5ea4-b27d-4baf-b405-dfed7cd739ae]
+//! 3 = [{ Input = 2 }]
+//! 4 = [{ Sink = { File = "output.dump" } }]
+//! ```
+
+use std::ffi::OsStr;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use log::trace;
+use serde::Deserialize;
+use serde_json::from_str as from_json;
+use serde_json::from_value;
+use serde_json::to_value;
+use toml::from_str as from_toml;
+use toml::Value as TomlValue;
+
+use crate::schema::Member;
+use crate::schema::Members;
+use crate::schema::SysCfg;
+use crate::ReadConfig;
+use crate::ReadMembers;
+use crate::WriteMembers;
+
+#[cfg(target_os = "linux")]
+use inotify::watch;
+#[cfg(not(target_os = "linux"))]
+use polling::watch;
+
+/// The interval (in ms) in which the watcher thread checks whether it
+/// should terminate (and, without inotify, whether the file changed).
+const POLL_INTERVAL_MS: libc::c_int = 100;
+
+/// The contents of a configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Contents {
+    /// The members participating in the computation.
+    members: Members,
+    /// The system configuration.
+    config: SysCfg,
+}
+
+/// A membership and configuration backend reading everything from a
+/// file.
+///
+/// Membership is static as far as the backend is concerned: members
+/// are only ever added or removed by changing the file.
+#[derive(Clone, Debug)]
+pub struct ConfigFile {
+    /// The path to the configuration file.
+    path: PathBuf,
+}
+
+impl ConfigFile {
+    /// Create a new backend reading from the file at the given path.
+    ///
+    /// Files with a `.toml` extension are interpreted as TOML, all
+    /// others as JSON.
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self { path: path.into() }
+    }
+
+    /// Read and parse the configuration file.
+    fn read(&self) -> Result<Contents, String> {
+        let data = read_to_string(&self.path)
+            .map_err(|e| format!("failed to read {}: {}", self.path.display(), e))?;
+
+        let is_toml = self.path.extension() == Some(OsStr::new("toml"));
+        let contents = if is_toml {
+            // TOML only allows for strings as keys, which can't be
+            // deserialized into the integers we use for relation IDs
+            // directly. So we take a detour through JSON, which has
+            // support for interpreting keys as integers.
+            from_toml::<TomlValue>(&data)
+                .map_err(|e| e.to_string())
+                .and_then(|value| to_value(value).map_err(|e| e.to_string()))
+                .and_then(|value| from_value(value).map_err(|e| e.to_string()))
+        } else {
+            from_json(&data).map_err(|e| e.to_string())
+        };
+
+        contents.map_err(|e| format!("failed to parse {}: {}", self.path.display(), e))
+    }
+
+    /// Watch the configuration file for changes.
+    ///
+    /// See `FileWatcher::new` for details.
+    pub fn watch(&self) -> Result<(FileWatcher, Receiver<()>), String> {
+        FileWatcher::new(&self.path)
+    }
+}
+
+impl ReadMembers for ConfigFile {
+    fn members(&self) -> Result<Members, String> {
+        self.read().map(|contents| contents.members)
+    }
+}
+
+impl ReadConfig for ConfigFile {
+    fn config(&self) -> Result<SysCfg, String> {
+        self.read().map(|contents| contents.config)
+    }
+}
+
+/// Because membership is described by the file, registering a member
+/// merely checks that it is listed in there.
+impl WriteMembers for ConfigFile {
+    fn register(&self, member: &Member) -> Result<(), String> {
+        if self.members()?.contains(member) {
+            Ok(())
+        } else {
+            Err(format!(
+                "member {} not found in {}",
+                member.addr(),
+                self.path.display()
+            ))
+        }
+    }
+
+    fn unregister(&self, _member: &Member) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// A watch on a file, signaling changes to it through a channel.
+///
+/// On Linux, the directory containing the file is watched with inotify.
+/// Elsewhere, the file is checked for changes every `POLL_INTERVAL_MS`.
+#[derive(Debug)]
+pub struct FileWatcher {
+    /// A flag indicating to the watcher thread that it should stop.
+    stop: Arc<AtomicBool>,
+    /// The thread waiting for changes to the file.
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FileWatcher {
+    /// Start watching the file at the given path, returning the watcher
+    /// along with the receiving end of the channel it signals changes
+    /// through.
+    ///
+    /// Changes are signaled for as long as the watcher is alive.
+    pub fn new(path: &Path) -> Result<(Self, Receiver<()>), String> {
+        trace!("FileWatcher::new({})", path.display());
+
+        let (sender, receiver) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = watch(path, stop.clone(), sender)?;
+
+        let watcher = Self {
+            stop,
+            thread: Some(thread),
+        };
+        Ok((watcher, receiver))
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Change detection based on inotify.
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::convert::TryFrom;
+    use std::ffi::CString;
+    use std::io::Error;
+    use std::mem::size_of;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+    use std::ptr::read_unaligned;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+    use std::sync::mpsc::Sender;
+    use std::sync::Arc;
+    use std::thread::spawn;
+    use std::thread::JoinHandle;
+
+    use libc::c_uint;
+    use log::error;
+
+    use crate::tcp_channel::Fd;
+
+    use super::POLL_INTERVAL_MS;
+
+    /// Wait for inotify events on `fd` and signal the ones concerning
+    /// `name` through `sender`.
+    fn process(fd: Fd, name: Vec<u8>, stop: Arc<AtomicBool>, sender: Sender<()>) {
+        const HEADER: usize = size_of::<libc::inotify_event>();
+        // The buffer needs to be able to hold at least one event with a
+        // name of maximum length.
+        let mut buffer = [0u8; 4096 + HEADER];
+
+        while !stop.load(Ordering::SeqCst) {
+            let mut pollfd = libc::pollfd {
+                fd: fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            match unsafe { libc::poll(&mut pollfd, 1, POLL_INTERVAL_MS) } {
+                -1 => {
+                    let e = Error::last_os_error();
+                    if e.raw_os_error() == Some(libc::EINTR) {
+                        continue;
+                    }
+                    error!("failed to poll inotify file descriptor: {}", e);
+                    return;
+                }
+                0 => continue,
+                _ => (),
+            }
+
+            let count = unsafe {
+                libc::read(
+                    fd.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                )
+            };
+            let count = match usize::try_from(count) {
+                Ok(count) => count,
+                Err(_) => {
+                    let e = Error::last_os_error();
+                    if e.raw_os_error() == Some(libc::EINTR)
+                        || e.raw_os_error() == Some(libc::EAGAIN)
+                    {
+                        continue;
+                    }
+                    error!("failed to read inotify events: {}", e);
+                    return;
+                }
+            };
+
+            let mut changed = false;
+            let mut offset = 0;
+            while offset + HEADER <= count {
+                let event = unsafe {
+                    read_unaligned(buffer[offset..].as_ptr() as *const libc::inotify_event)
+                };
+                let len = event.len as usize;
+                let start = offset + HEADER;
+                let end = (start + len).min(count);
+                // The name is padded with NUL bytes.
+                let event_name = buffer[start..end].split(|b| *b == 0).next().unwrap_or(&[]);
+                changed |= event_name == name.as_slice();
+                offset = start + len;
+            }
+
+            if changed && sender.send(()).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Watch the directory containing the file at `path`, so that
+    /// changes are picked up even if the file gets replaced (which is
+    /// what many editors do when saving).
+    pub fn watch(
+        path: &Path,
+        stop: Arc<AtomicBool>,
+        sender: Sender<()>,
+    ) -> Result<JoinHandle<()>, String> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| format!("{} does not refer to a file", path.display()))?
+            .as_bytes()
+            .to_vec();
+        let dir = match path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+        let dir = CString::new(dir.as_os_str().as_bytes())
+            .map_err(|e| format!("invalid path {}: {}", dir.display(), e))?;
+
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        let fd = c_uint::try_from(fd).map_err(|_| {
+            format!(
+                "failed to create inotify file descriptor: {}",
+                Error::last_os_error()
+            )
+        })?;
+        let fd = Fd::new(fd);
+
+        let mask = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE | libc::IN_DELETE;
+        let result = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), dir.as_ptr(), mask) };
+        if result < 0 {
+            return Err(format!(
+                "failed to watch {}: {}",
+                path.display(),
+                Error::last_os_error()
+            ));
+        }
+
+        Ok(spawn(move || process(fd, name, stop, sender)))
+    }
+}
+
+/// Change detection based on periodically checking the file's
+/// modification time and size, for systems without inotify.
+#[cfg(any(not(target_os = "linux"), test))]
+mod polling {
+    use std::fs::metadata;
+    use std::path::Path;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+    use std::sync::mpsc::Sender;
+    use std::sync::Arc;
+    use std::thread::sleep;
+    use std::thread::spawn;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    use super::POLL_INTERVAL_MS;
+
+    /// The modification time and size of the file at `path`, if it
+    /// exists.
+    fn stamp(path: &Path) -> Option<(Option<SystemTime>, u64)> {
+        metadata(path)
+            .ok()
+            .map(|meta| (meta.modified().ok(), meta.len()))
+    }
+
+    /// Check the file at `path` for changes every `POLL_INTERVAL_MS`.
+    pub fn watch(
+        path: &Path,
+        stop: Arc<AtomicBool>,
+        sender: Sender<()>,
+    ) -> Result<JoinHandle<()>, String> {
+        let path = path.to_path_buf();
+        let mut last = stamp(&path);
+        Ok(spawn(move || {
+            while !stop.load(Ordering::SeqCst) {
+                sleep(Duration::from_millis(POLL_INTERVAL_MS as u64));
+                let current = stamp(&path);
+                if current != last {
+                    last = current;
+                    if sender.send(()).is_err() {
+                        return;
+                    }
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::remove_file;
+    use std::fs::rename;
+    use std::fs::write;
+    use std::sync::mpsc::RecvError;
+    use std::time::Duration;
+
+    use maplit::btreemap;
+    use maplit::btreeset;
+
+    use tempfile::tempdir;
+
+    use uuid::Uuid;
+
+    use crate::schema::Addr;
+    use crate::schema::RelCfg;
+    use crate::schema::Sink;
+    use crate::schema::Source;
+
+    fn expected() -> (Members, SysCfg) {
+        let members = btreeset! {
+            Member::new(Addr::Ip("127.0.0.1:5000".parse().unwrap())),
+            Member::new(Addr::Ip("127.0.0.1:5001".parse().unwrap())),
+        };
+        let uuid0 = Uuid::parse_str("7aba4dd8-80fc-43f0-bbf6-0dc56abaa001").unwrap();
+        let uuid1 = Uuid::parse_str("bcda5ea4-b27d-4baf-b405-dfed7cd739ae").unwrap();
+        let config = btreemap! {
+            uuid0 => btreemap! {
+                1 => btreeset! { RelCfg::Source(Source::File(PathBuf::from("input.dat"))) },
+                2 => btreeset! { RelCfg::Output(uuid1, 3) },
+            },
+            uuid1 => btreemap! {
+                3 => btreeset! { RelCfg::Input(2) },
+                4 => btreeset! { RelCfg::Sink(Sink::File(PathBuf::from("output.dump"))) },
+            },
+        };
+        (members, config)
+    }
+
+    #[test]
+    fn read_json() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("d3log.json");
+        let (members, config) = expected();
+        let contents = format!(
+            r#"{{"members": {}, "config": {}}}"#,
+            serde_json::to_string(&members).unwrap(),
+            serde_json::to_string(&config).unwrap(),
+        );
+        write(&path, contents).unwrap();
+
+        let file = ConfigFile::new(&path);
+        assert_eq!(file.members().unwrap(), members);
+        assert_eq!(file.config().unwrap(), config);
+    }
+
+    #[test]
+    fn read_toml() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("d3log.toml");
+        let contents = r#"
+members = [
+  { addr = { Ip = "127.0.0.1:5000" } },
+  { addr = { Ip = "127.0.0.1:5001" } },
+]
+
+[config.7aba4dd8-80fc-43f0-bbf6-0dc56abaa001]
+1 = [{ Source = { File = "input.dat" } }]
+2 = [{ Output = ["bcda5ea4-b27d-4baf-b405-dfed7cd739ae", 3] }]
+
+[config.bcda5ea4-b27d-4baf-b405-dfed7cd739ae]
+3 = [{ Input = 2 }]
+4 = [{ Sink = { File = "output.dump" } }]
+"#;
+        write(&path, contents).unwrap();
+
+        let file = ConfigFile::new(&path);
+        let (members, config) = expected();
+        assert_eq!(file.members().unwrap(), members);
+        assert_eq!(file.config().unwrap(), config);
+    }
+
+    #[test]
+    fn read_invalid() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("d3log.json");
+
+        let file = ConfigFile::new(&path);
+        assert!(file.config().is_err());
+
+        write(&path, "{").unwrap();
+        assert!(file.config().is_err());
+
+        // Everything is optional.
+        write(&path, "{}").unwrap();
+        assert_eq!(file.members().unwrap(), Members::new());
+        assert_eq!(file.config().unwrap(), SysCfg::new());
+    }
+
+    #[test]
+    fn register_listed_members_only() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("d3log.toml");
+        write(&path, r#"members = [{ addr = { Ip = "127.0.0.1:5000" } }]"#).unwrap();
+
+        let file = ConfigFile::new(&path);
+        let listed = Member::new(Addr::Ip("127.0.0.1:5000".parse().unwrap()));
+        let unlisted = Member::new(Addr::Ip("127.0.0.1:5001".parse().unwrap()));
+        assert_eq!(file.register(&listed), Ok(()));
+        assert!(file.register(&unlisted).is_err());
+    }
+
+    #[test]
+    fn watch_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("d3log.json");
+        write(&path, "{}").unwrap();
+
+        let file = ConfigFile::new(&path);
+        let (watcher, changes) = file.watch().unwrap();
+        let timeout = Duration::from_secs(5);
+
+        // Unrelated files in the same directory should be ignored.
+        write(dir.path().join("other.json"), "{}").unwrap();
+
+        write(&path, r#"{"members": []}"#).unwrap();
+        assert_eq!(changes.recv_timeout(timeout), Ok(()));
+
+        // Replacing the file should be detected as well.
+        while changes.try_recv().is_ok() {}
+        let tmp = dir.path().join("d3log.json.tmp");
+        write(&tmp, "{}").unwrap();
+        rename(&tmp, &path).unwrap();
+        assert_eq!(changes.recv_timeout(timeout), Ok(()));
+
+        // Once the watcher is gone, the channel is closed.
+        drop(watcher);
+        while changes.try_recv().is_ok() {}
+        assert_eq!(changes.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn poll_changes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("d3log.json");
+        write(&path, "{}").unwrap();
+
+        let (sender, changes) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = polling::watch(&path, stop.clone(), sender).unwrap();
+        let timeout = Duration::from_secs(5);
+
+        write(&path, r#"{"members": []}"#).unwrap();
+        assert_eq!(changes.recv_timeout(timeout), Ok(()));
+
+        // Removing the file counts as a change, too.
+        remove_file(&path).unwrap();
+        assert_eq!(changes.recv_timeout(timeout), Ok(()));
+
+        stop.store(true, Ordering::SeqCst);
+        thread.join().unwrap();
+        assert_eq!(changes.recv(), Err(RecvError));
+    }
+}
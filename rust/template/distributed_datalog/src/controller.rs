@@ -0,0 +1,422 @@
+//! A module providing the control loop of a member participating in a
+//! d3log computation.
+//!
+//! The controller registers the member with the membership service and
+//! then waits for changes to the membership or the system
+//! configuration. On every change it computes a new assignment of nodes
+//! to members and hands it off to be realized (typically by a
+//! `Reconciler`).
+
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::sync::mpsc::Receiver;
+
+use log::error;
+use log::info;
+use log::trace;
+
+use crate::assign::rendezvous_assign;
+use crate::instantiate::Assignment;
+use crate::schema::Member;
+use crate::schema::Members;
+use crate::schema::SysCfg;
+use crate::ReadConfig;
+use crate::ReadMembers;
+use crate::WriteConfig;
+use crate::WriteMembers;
+
+/// Assign the nodes of the given configuration to the given members.
+fn assign(sys_cfg: &SysCfg, members: &Members) -> Result<Assignment, String> {
+    rendezvous_assign(sys_cfg.keys(), members.iter()).ok_or_else(|| {
+        format!(
+            "failed to assign {} nodes to {} members: every member can host only one node",
+            sys_cfg.len(),
+            members.len()
+        )
+    })
+}
+
+/// The control loop of a single member.
+///
+/// `C` is the client used for accessing membership and configuration,
+/// `F` the function invoked with every new configuration and the
+/// assignment computed for it.
+pub struct Controller<C, F>
+where
+    C: ReadMembers + ReadConfig + WriteMembers,
+    F: FnMut(&SysCfg, &Assignment) -> Result<(), String>,
+{
+    /// The client providing membership and configuration.
+    client: C,
+    /// The member we control.
+    member: Member,
+    /// The function to invoke for realizing a configuration.
+    reconfigure: F,
+    /// The configuration and assignment we realized last.
+    current: Option<(SysCfg, Assignment)>,
+}
+
+impl<C, F> Controller<C, F>
+where
+    C: ReadMembers + ReadConfig + WriteMembers,
+    F: FnMut(&SysCfg, &Assignment) -> Result<(), String>,
+{
+    /// Create a new controller for the given member, registering it
+    /// with the membership service in the process.
+    pub fn new(client: C, member: Member, reconfigure: F) -> Result<Self, String> {
+        client.register(&member)?;
+
+        Ok(Self {
+            client,
+            member,
+            reconfigure,
+            current: None,
+        })
+    }
+
+    /// Read the current membership and configuration and realize the
+    /// result, if anything changed since the last invocation.
+    pub fn reconfigure(&mut self) -> Result<(), String> {
+        trace!("Controller({})::reconfigure", self.member.addr());
+
+        let members = self.client.members()?;
+        let sys_cfg = self.client.config()?;
+        let assignment = assign(&sys_cfg, &members)?;
+
+        let current = (sys_cfg, assignment);
+        if self.current.as_ref() != Some(&current) {
+            info!("Reconfiguring {}...", self.member.addr());
+            // Only remember the configuration once it is realized, so
+            // that the next invocation retries a failed one.
+            let (sys_cfg, assignment) = &current;
+            (self.reconfigure)(sys_cfg, assignment)?;
+            self.current = Some(current);
+            Ok(())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run the control loop.
+    ///
+    /// The configuration is realized once initially and then again
+    /// every time a change is signaled through `changes`. The loop
+    /// terminates once the sending side of the channel is closed.
+    /// Errors while reconfiguring are logged but do not terminate the
+    /// loop.
+    pub fn run(&mut self, changes: Receiver<()>) {
+        loop {
+            if let Err(e) = self.reconfigure() {
+                error!("failed to reconfigure {}: {}", self.member.addr(), e);
+            }
+
+            if changes.recv().is_err() {
+                break;
+            }
+            // Changes tend to come in bursts; there is no point in
+            // reconfiguring for each of them.
+            while changes.try_recv().is_ok() {}
+        }
+    }
+
+    /// Retrieve the client used by the controller.
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+}
+
+impl<C, F> Controller<C, F>
+where
+    C: ReadMembers + ReadConfig + WriteMembers + WriteConfig,
+    F: FnMut(&SysCfg, &Assignment) -> Result<(), String>,
+{
+    /// Publish a new system configuration.
+    ///
+    /// The configuration is only published if it can be realized by
+    /// the current members, i.e., if there is a member for every node.
+    pub fn publish(&self, sys_cfg: &SysCfg) -> Result<(), String> {
+        let members = self.client.members()?;
+        let _ = assign(sys_cfg, &members)?;
+        self.client.set_config(sys_cfg)
+    }
+}
+
+impl<C, F> Debug for Controller<C, F>
+where
+    C: ReadMembers + ReadConfig + WriteMembers,
+    F: FnMut(&SysCfg, &Assignment) -> Result<(), String>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Controller")
+            .field("member", &self.member)
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+impl<C, F> Drop for Controller<C, F>
+where
+    C: ReadMembers + ReadConfig + WriteMembers,
+    F: FnMut(&SysCfg, &Assignment) -> Result<(), String>,
+{
+    fn drop(&mut self) {
+        if let Err(e) = self.client.unregister(&self.member) {
+            error!("failed to unregister {}: {}", self.member.addr(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::channel;
+    use std::sync::mpsc::Sender;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::thread::spawn;
+    use std::time::Duration;
+
+    use maplit::btreemap;
+    use maplit::btreeset;
+
+    use uuid::Uuid;
+
+    use crate::schema::Addr;
+
+    /// The state shared by all handles to a `Fake`.
+    #[derive(Debug, Default)]
+    struct State {
+        members: Members,
+        config: SysCfg,
+        watchers: Vec<Sender<()>>,
+    }
+
+    /// An in-process stand-in for a membership service and
+    /// configuration manager such as ZooKeeper.
+    #[derive(Clone, Debug, Default)]
+    struct Fake(Arc<Mutex<State>>);
+
+    impl Fake {
+        /// Retrieve a channel signaling changes.
+        fn watch(&self) -> Receiver<()> {
+            let (sender, receiver) = channel();
+            self.0.lock().unwrap().watchers.push(sender);
+            receiver
+        }
+
+        /// Close all channels retrieved through `watch`.
+        fn hang_up(&self) {
+            self.0.lock().unwrap().watchers.clear();
+        }
+
+        fn modify<M>(&self, modify: M)
+        where
+            M: FnOnce(&mut State),
+        {
+            let mut state = self.0.lock().unwrap();
+            modify(&mut state);
+            state.watchers.iter().for_each(|watcher| {
+                let _ = watcher.send(());
+            });
+        }
+    }
+
+    impl ReadMembers for Fake {
+        fn members(&self) -> Result<Members, String> {
+            Ok(self.0.lock().unwrap().members.clone())
+        }
+    }
+
+    impl ReadConfig for Fake {
+        fn config(&self) -> Result<SysCfg, String> {
+            Ok(self.0.lock().unwrap().config.clone())
+        }
+    }
+
+    impl WriteMembers for Fake {
+        fn register(&self, member: &Member) -> Result<(), String> {
+            self.modify(|state| {
+                let _ = state.members.insert(member.clone());
+            });
+            Ok(())
+        }
+
+        fn unregister(&self, member: &Member) -> Result<(), String> {
+            self.modify(|state| {
+                let _ = state.members.remove(member);
+            });
+            Ok(())
+        }
+    }
+
+    impl WriteConfig for Fake {
+        fn set_config(&self, config: &SysCfg) -> Result<(), String> {
+            self.modify(|state| state.config = config.clone());
+            Ok(())
+        }
+    }
+
+    fn member(port: u16) -> Member {
+        Member::new(Addr::Ip(([127, 0, 0, 1], port).into()))
+    }
+
+    /// Run a controller for the given member in a separate thread,
+    /// returning a channel of all the assignments it realizes.
+    fn control(fake: &Fake, member: Member) -> Receiver<Assignment> {
+        let (sender, receiver) = channel();
+        let changes = fake.watch();
+        let mut controller = Controller::new(fake.clone(), member, move |_, assignment| {
+            sender.send(assignment.clone()).map_err(|e| e.to_string())
+        })
+        .unwrap();
+
+        let _handle = spawn(move || controller.run(changes));
+        receiver
+    }
+
+    fn next(assignments: &Receiver<Assignment>) -> Assignment {
+        assignments.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn registration() {
+        let fake = Fake::default();
+        let member = member(1);
+
+        {
+            let _controller = Controller::new(fake.clone(), member.clone(), |_, _| Ok(())).unwrap();
+            assert_eq!(fake.members().unwrap(), btreeset! { member });
+        }
+
+        assert_eq!(fake.members().unwrap(), Members::new());
+    }
+
+    #[test]
+    fn reconfigure_unchanged() {
+        let fake = Fake::default();
+        let count = Arc::new(Mutex::new(0));
+        let count_ = count.clone();
+        let mut controller = Controller::new(fake, member(1), move |_, _| {
+            *count_.lock().unwrap() += 1;
+            Ok(())
+        })
+        .unwrap();
+
+        controller.reconfigure().unwrap();
+        controller.reconfigure().unwrap();
+        assert_eq!(*count.lock().unwrap(), 1);
+
+        let uuid = Uuid::new_v4();
+        let config = btreemap! { uuid => btreemap! {} };
+        controller.client().set_config(&config).unwrap();
+        controller.reconfigure().unwrap();
+        assert_eq!(*count.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn reconfigure_retries_failure() {
+        let fake = Fake::default();
+        let count = Arc::new(Mutex::new(0));
+        let count_ = count.clone();
+        let mut controller = Controller::new(fake, member(1), move |_, _| {
+            let mut count = count_.lock().unwrap();
+            *count += 1;
+            if *count == 1 {
+                Err("failed to realize configuration".to_string())
+            } else {
+                Ok(())
+            }
+        })
+        .unwrap();
+
+        assert!(controller.reconfigure().is_err());
+        controller.reconfigure().unwrap();
+        controller.reconfigure().unwrap();
+        assert_eq!(*count.lock().unwrap(), 2);
+    }
+
+    /// Configurations with more nodes than there are members can
+    /// neither be published nor realized.
+    #[test]
+    fn reject_unrealizable_config() {
+        let fake = Fake::default();
+        let realized = Arc::new(Mutex::new(Vec::new()));
+        let realized_ = realized.clone();
+        let mut controller = Controller::new(fake.clone(), member(1), move |_, assignment| {
+            realized_.lock().unwrap().push(assignment.clone());
+            Ok(())
+        })
+        .unwrap();
+
+        let uuids = [Uuid::new_v4(), Uuid::new_v4()];
+        let one = btreemap! { uuids[0] => btreemap! {} };
+        let two = btreemap! {
+            uuids[0] => btreemap! {},
+            uuids[1] => btreemap! {},
+        };
+
+        assert!(controller.publish(&two).is_err());
+        assert_eq!(fake.config().unwrap(), SysCfg::new());
+
+        controller.publish(&one).unwrap();
+        assert_eq!(fake.config().unwrap(), one);
+        controller.reconfigure().unwrap();
+
+        // A configuration published behind the controller's back is not
+        // realized, and the previous one stays in place.
+        fake.set_config(&two).unwrap();
+        assert!(controller.reconfigure().is_err());
+        assert_eq!(
+            *realized.lock().unwrap(),
+            vec![btreemap! { uuids[0] => member(1).addr().clone() }]
+        );
+
+        fake.register(&member(2)).unwrap();
+        controller.reconfigure().unwrap();
+        controller.publish(&two).unwrap();
+        assert_eq!(realized.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn control_loop() {
+        let fake = Fake::default();
+        let member1 = member(1);
+        let member2 = member(2);
+        let assignments = control(&fake, member1.clone());
+
+        // Without a configuration there is nothing to assign.
+        assert_eq!(next(&assignments), Assignment::new());
+
+        // Make sure that the node moves to the second member once it
+        // joins, or we would not notice it joining.
+        let members = btreeset! { member1.clone(), member2.clone() };
+        let (config, joined) = loop {
+            let config = btreemap! { Uuid::new_v4() => btreemap! {} };
+            let assignment = rendezvous_assign(config.keys(), members.iter()).unwrap();
+            if assignment.values().any(|addr| addr == member2.addr()) {
+                break (config, assignment);
+            }
+        };
+
+        fake.set_config(&config).unwrap();
+
+        let expected = config
+            .keys()
+            .map(|uuid| (*uuid, member1.addr().clone()))
+            .collect::<Assignment>();
+        assert_eq!(next(&assignments), expected);
+
+        fake.register(&member2).unwrap();
+        assert_eq!(next(&assignments), joined);
+
+        fake.unregister(&member2).unwrap();
+        fake.hang_up();
+
+        // The loop terminates once we hang up, at which point the
+        // controller unregisters its member.
+        while assignments.recv_timeout(Duration::from_secs(5)).is_ok() {}
+        assert_eq!(fake.members().unwrap(), Members::new());
+    }
+}
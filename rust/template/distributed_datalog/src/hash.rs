@@ -0,0 +1,35 @@
+//! A module providing a hash function that is stable across processes,
+//! machines, and Rust releases.
+
+/// The 64 bit FNV-1a offset basis.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+/// The 64 bit FNV-1a prime.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Compute the 64 bit FNV-1a hash of the given bytes.
+///
+/// Unlike the hashers provided by the standard library (which are
+/// neither guaranteed to be stable across Rust releases nor, in the
+/// case of `RandomState`, across processes), FNV-1a produces the same
+/// result on every member of the system.
+pub fn fnv1a<I>(bytes: I) -> u64
+where
+    I: IntoIterator<Item = u8>,
+{
+    bytes.into_iter().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check our hash against known FNV-1a test vectors.
+    #[test]
+    fn test_vectors() {
+        assert_eq!(fnv1a(b"".iter().copied()), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a".iter().copied()), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar".iter().copied()), 0x8594_4171_f739_67e8);
+    }
+}
@@ -2,8 +2,13 @@
 //! computation. This functionality is meant to be executed by all nodes
 //! participating in the distributed computation and will take care of
 //! configuring the "local" compute node accordingly, by creating a
-//! `DDlogServer` instance, a `TcpReceiver`, a `TxnMux`, and file sinks
-//! and sources if desired.
+//! `DDlogServer` instance, a `TcpReceiver` (or `UdsReceiver`), a
+//! `TxnMux`, and file sinks and sources as well as additional receivers
+//! if desired.
+//!
+//! Once instantiated, a configuration can be changed by means of a
+//! `Reconciler`, which only touches the parts of the computation that
+//! are actually affected by a change.
 
 use std::collections::HashMap;
 use std::collections::{BTreeMap, BTreeSet};
@@ -23,6 +28,7 @@ use differential_datalog::{DDlog, DDlogConvert};
 use crate::accumulate::Accumulator;
 use crate::accumulate::DistributingAccumulator;
 use crate::observe::Observable;
+use crate::observe::ObserverBox;
 use crate::observe::SharedObserver;
 use crate::observe::UpdatesObservable;
 use crate::schema::Addr;
@@ -32,10 +38,14 @@ use crate::schema::RelCfg;
 use crate::schema::Sink;
 use crate::schema::Source;
 use crate::schema::SysCfg;
+use crate::schema::TlsCfg;
 use crate::sinks::File as FileSink;
 use crate::sources::File as FileSource;
+use crate::tcp_channel::SchemaHash;
 use crate::tcp_channel::TcpReceiver;
 use crate::tcp_channel::TcpSender;
+use crate::tcp_channel::UdsReceiver;
+use crate::tcp_channel::UdsSender;
 use crate::txnmux::TxnMux;
 use crate::DDlogServer;
 
@@ -48,6 +58,30 @@ pub type Outputs = BTreeMap<Addr, BTreeSet<RelId>>;
 /// A mapping from abstract nodes to actual members in the system.
 pub type Assignment = BTreeMap<Node, Addr>;
 
+/// Check that the given assignment can be realized.
+///
+/// Every member accepts input on a single receiver listening on its
+/// address, which feeds the one node assigned to it. Assigning more
+/// than one node to a member would make all of them bind the same
+/// address, so such assignments are rejected.
+pub(crate) fn check_assignment(assignment: &Assignment) -> Result<(), String> {
+    assignment
+        .iter()
+        .try_fold(
+            BTreeMap::new(),
+            |mut assigned, (node, addr)| match assigned.insert(addr, node) {
+                Some(other) => Err(format!(
+                    "nodes {} and {} are both assigned to member {}",
+                    other.to_hyphenated_ref(),
+                    node.to_hyphenated_ref(),
+                    addr
+                )),
+                None => Ok(assigned),
+            },
+        )
+        .map(|_| ())
+}
+
 /// Deduce the output streaming relations we require.
 fn deduce_outputs(node_cfg: &NodeCfg, assignment: &Assignment) -> Result<Outputs, String> {
     node_cfg
@@ -62,7 +96,7 @@ fn deduce_outputs(node_cfg: &NodeCfg, assignment: &Assignment) -> Result<Outputs
                                 node.to_hyphenated_ref()
                             )
                         })?;
-                        let rels = outputs.entry(*addr).or_default();
+                        let rels = outputs.entry(addr.clone()).or_default();
                         let _ = rels.insert(*rel_id);
                     }
                     RelCfg::Input(..) | RelCfg::Source(..) | RelCfg::Sink(..) => (),
@@ -72,6 +106,59 @@ fn deduce_outputs(node_cfg: &NodeCfg, assignment: &Assignment) -> Result<Outputs
         })
 }
 
+/// Determine the sink feeding the member with the given address.
+///
+/// Members reachable through TLS can only be fed if we have the
+/// credentials to authenticate ourselves.
+fn output_sink(addr: Addr, tls: Option<&TlsCfg>) -> Result<Sink, String> {
+    match addr {
+        Addr::Ip(..) => Ok(Sink::Sender(addr)),
+        Addr::Tls(..) if tls.is_some() => Ok(Sink::Sender(addr)),
+        Addr::Tls(..) => Err(format!("no TLS credentials for connecting to {}", addr)),
+        Addr::Unix(..) => Ok(Sink::Sender(addr)),
+    }
+}
+
+/// Determine the source accepting input on the given address.
+///
+/// Accepting TLS secured connections requires credentials to
+/// authenticate ourselves to senders.
+fn input_source(addr: &Addr, tls: Option<&TlsCfg>) -> Result<Source, String> {
+    match addr {
+        Addr::Ip(..) => Ok(Source::Receiver(addr.clone())),
+        Addr::Tls(..) if tls.is_some() => Ok(Source::Receiver(addr.clone())),
+        Addr::Tls(..) => Err(format!("no TLS credentials for accepting on {}", addr)),
+        Addr::Unix(..) => Ok(Source::Receiver(addr.clone())),
+    }
+}
+
+/// Deduce the receivers a node configuration asks for, in addition to
+/// the one listening on the address of the member `addr` the node is
+/// assigned to.
+fn deduce_receivers(
+    node_cfg: &NodeCfg,
+    addr: &Addr,
+    tls: Option<&TlsCfg>,
+) -> Result<BTreeSet<Source>, String> {
+    let own = input_source(addr, tls)?;
+    node_cfg
+        .values()
+        .flatten()
+        .try_fold(BTreeSet::new(), |mut receivers, rel_cfg| {
+            let source = match rel_cfg {
+                RelCfg::Source(Source::Receiver(addr)) => input_source(addr, tls)?,
+                RelCfg::Source(Source::File(..))
+                | RelCfg::Input(..)
+                | RelCfg::Output(..)
+                | RelCfg::Sink(..) => return Ok(receivers),
+            };
+            if source != own {
+                let _ = receivers.insert(source);
+            }
+            Ok(receivers)
+        })
+}
+
 /// Deduce the required redirections for a given node configuration.
 ///
 /// Redirections determine what input relations the inputs to a certain
@@ -129,26 +216,86 @@ fn deduce_sinks_or_sources(node_cfg: &NodeCfg, sinks: bool) -> BTreeMap<&Path, B
         })
 }
 
+/// The parts of a node configuration that a `Realization` is made of,
+/// resolved under a certain assignment.
+///
+/// Comparing the plans of the old and new configuration of a node tells
+/// us what we have to change about its realization.
+#[derive(Debug, Default, PartialEq)]
+struct Plan {
+    /// The redirections the node's `DDlogServer` has to apply.
+    redirects: HashMap<RelId, RelId>,
+    /// The files feeding the node.
+    sources: BTreeSet<PathBuf>,
+    /// The receivers feeding the node, besides the one listening on the
+    /// address of the member it is assigned to.
+    receivers: BTreeSet<Source>,
+    /// All file sinks and senders, along with the
+    /// relations they are fed from.
+    sinks: BTreeMap<Sink, BTreeSet<RelId>>,
+}
+
+impl Plan {
+    /// Deduce the plan for the given node configuration, assigned to
+    /// the member with the given address.
+    fn new(
+        node_cfg: &NodeCfg,
+        addr: &Addr,
+        assignment: &Assignment,
+        tls: Option<&TlsCfg>,
+    ) -> Result<Self, String> {
+        let sources = deduce_sinks_or_sources(node_cfg, false)
+            .keys()
+            .map(PathBuf::from)
+            .collect();
+        let file_sinks = deduce_sinks_or_sources(node_cfg, true)
+            .into_iter()
+            .map(|(path, rel_ids)| (Sink::File(PathBuf::from(path)), rel_ids));
+        let sinks = deduce_outputs(node_cfg, assignment)?
+            .into_iter()
+            .map(|(addr, rel_ids)| Ok((output_sink(addr, tls)?, rel_ids)))
+            .collect::<Result<BTreeMap<_, _>, String>>()?
+            .into_iter()
+            .chain(file_sinks)
+            .collect();
+
+        Ok(Self {
+            redirects: deduce_redirects(node_cfg),
+            sources,
+            receivers: deduce_receivers(node_cfg, addr, tls)?,
+            sinks,
+        })
+    }
+}
+
 /// Realize the given configuration locally.
+///
+/// `tls` contains the credentials used for securing connections to and
+/// from members reachable through TLS only.
 fn realize<P>(
     addr: &Addr,
     node_cfg: &NodeCfg,
     assignment: &Assignment,
+    tls: Option<&TlsCfg>,
 ) -> Result<Realization<P>, String>
 where
     P: Send + DDlog + 'static,
     P::Convert: Send + DDlogConvert,
 {
     let now = Instant::now();
-    let mut realization = Realization::new();
+    let mut realization = match tls {
+        Some(tls) => Realization::with_tls(tls.clone()),
+        None => Realization::new(),
+    };
     let mut server = create_server::<P>(&node_cfg)?;
 
-    realization.add_tcp_senders(node_cfg, &mut server, assignment)?;
+    realization.add_tcp_senders(node_cfg, &mut server, assignment, tls)?;
     realization.add_file_sinks(node_cfg, &mut server)?;
     realization.subscribe_txnmux(server)?;
-    match addr {
-        Addr::Ip(addr) => realization.add_tcp_receiver(addr)?,
-    }
+    realization.add_source(&input_source(addr, tls)?)?;
+    deduce_receivers(node_cfg, addr, tls)?
+        .iter()
+        .try_for_each(|source| realization.add_source(source))?;
     realization.add_file_sources(node_cfg)?;
 
     println!(
@@ -159,6 +306,40 @@ where
     Ok(realization)
 }
 
+/// Create a `TcpSender` connecting to the member reachable through TLS
+/// on the given address, using the given credentials.
+#[cfg(feature = "tls")]
+fn tls_sender<S>(
+    addr: SocketAddr,
+    schema: SchemaHash,
+    tls: Option<&TlsCfg>,
+) -> Result<TcpSender<S>, String>
+where
+    S: Serialize + Debug + Send + 'static,
+{
+    let tls =
+        tls.ok_or_else(|| format!("no TLS credentials for connecting to {}", Addr::Tls(addr)))?;
+    TcpSender::with_tls(addr, schema, tls)
+        .map_err(|e| format!("failed to create TcpSender socket: {}", e))
+}
+
+/// Fail to create a `TcpSender` connecting to a member reachable
+/// through TLS, as support for TLS is not compiled in.
+#[cfg(not(feature = "tls"))]
+fn tls_sender<S>(
+    addr: SocketAddr,
+    _schema: SchemaHash,
+    _tls: Option<&TlsCfg>,
+) -> Result<TcpSender<S>, String>
+where
+    S: Serialize + Debug + Send + 'static,
+{
+    Err(format!(
+        "cannot connect to {}: built without TLS support",
+        Addr::Tls(addr)
+    ))
+}
+
 /// All possible sources of a Realization
 #[derive(Debug)]
 enum SourceRealization<C, D>
@@ -168,6 +349,7 @@ where
 {
     File(Arc<Mutex<FileSource<C>>>),
     Node(Arc<Mutex<TcpReceiver<Update<DDValue>, D>>>),
+    LocalNode(Arc<Mutex<UdsReceiver<Update<DDValue>, D>>>),
 }
 
 /// All possible sinks of a Realization
@@ -179,6 +361,7 @@ where
 {
     File(SharedObserver<FileSink<C>>),
     Node(SharedObserver<TcpSender<S>>),
+    LocalNode(SharedObserver<UdsSender<S>>),
 }
 
 /// An object representing a realized configuration.
@@ -202,6 +385,9 @@ where
     >,
     /// The transaction multiplexer as input to the DDLogServer
     _txnmux: TxnMux<Update<DDValue>, String>,
+    /// The DDlogServer fed by the transaction multiplexer, if it has
+    /// been subscribed already
+    _server: Option<SharedObserver<DDlogServer<P>>>,
     /// All sinks of this realization with their subscription
     _sinks: HashMap<
         BTreeSet<RelId>,
@@ -211,6 +397,8 @@ where
             HashMap<Sink, (SinkRealization<P::Convert, P::UpdateSerializer>, usize)>,
         ),
     >,
+    /// The credentials used for TLS secured connections, if any.
+    _tls: Option<TlsCfg>,
 }
 
 impl<P> Default for Realization<P>
@@ -223,6 +411,8 @@ where
             _sinks: HashMap::new(),
             _sources: HashMap::new(),
             _txnmux: TxnMux::new(),
+            _server: None,
+            _tls: None,
         }
     }
 }
@@ -237,36 +427,121 @@ where
         Default::default()
     }
 
+    /// Instantiates a new Realization using the given credentials for
+    /// receivers and senders on `Addr::Tls` addresses.
+    pub fn with_tls(tls: TlsCfg) -> Realization<P> {
+        Realization {
+            _tls: Some(tls),
+            ..Default::default()
+        }
+    }
+
     /// Subscribe the `TxnMux` of the existing realization to the given server.
     pub fn subscribe_txnmux(&mut self, server: DDlogServer<P>) -> Result<(), &str> {
+        let server = Arc::new(Mutex::new(server));
         self._txnmux
-            .subscribe(Box::new(server))
-            .map_err(|_| "failed to subscribe DDlogServer to TxnMux")
+            .subscribe(Box::new(server.clone()))
+            .map_err(|_| "failed to subscribe DDlogServer to TxnMux")?;
+        self._server = Some(server);
+        Ok(())
     }
 
     /// Remove a source from the existing realization.
     /// Also clear the accumulator and disconnect
     /// from the TxnMux.
     pub fn remove_source(&mut self, src: &Source) -> Result<(), &str> {
-        // Remove entry.
-        let (_, accumulator, id) = self._sources.remove(src).unwrap();
-        let mut accum_observ = accumulator.lock().unwrap();
+        let (_, accumulator, id) = self
+            ._sources
+            .get(src)
+            .ok_or("Cannot remove source, not found in realization")?;
+        let id = *id;
 
         // Clear accumulator.
-        if accum_observ.clear().is_ok() {
-            // Disconnect from TxnMux.
-            self._txnmux.remove_observable(id);
-            Ok(())
-        } else {
-            Err("Cannot remove source, transaction in progress")
+        if accumulator.lock().unwrap().clear().is_err() {
+            return Err("Cannot remove source, transaction in progress");
+        }
+
+        // Remove entry and disconnect from TxnMux.
+        let _ = self._sources.remove(src);
+        self._txnmux.remove_observable(id);
+        Ok(())
+    }
+
+    /// Add the given source to the existing realization.
+    pub fn add_source(&mut self, source: &Source) -> Result<(), String> {
+        match source {
+            Source::File(path) => self.add_file_source(path),
+            Source::Receiver(Addr::Ip(addr)) => self.add_tcp_receiver(addr),
+            Source::Receiver(Addr::Tls(addr)) => self.add_tls_receiver(addr),
+            Source::Receiver(Addr::Unix(path)) => self.add_uds_receiver(path),
         }
     }
 
-    /// Add a `TcpReceiver` to the existing realization feeding the given server
-    /// if one is needed given the provided node configuration.
+    /// Add a `TcpReceiver` listening on the given address to the
+    /// existing realization feeding the given server.
     pub fn add_tcp_receiver(&mut self, addr: &SocketAddr) -> Result<(), String> {
-        let receiver =
-            TcpReceiver::new(addr).map_err(|e| format!("failed to create TcpReceiver: {}", e))?;
+        let receiver = TcpReceiver::new(addr, SchemaHash::of::<P::Convert>())
+            .map_err(|e| format!("failed to create TcpReceiver on {}: {}", addr, e))?;
+        self.add_receiver(Source::Receiver(Addr::Ip(*addr)), receiver)
+    }
+
+    /// Add a `TcpReceiver` only accepting TLS secured connections from
+    /// authenticated senders to the existing realization, using the
+    /// realization's credentials.
+    #[cfg(feature = "tls")]
+    pub fn add_tls_receiver(&mut self, addr: &SocketAddr) -> Result<(), String> {
+        let tls = self
+            ._tls
+            .as_ref()
+            .ok_or_else(|| format!("no TLS credentials for accepting on {}", Addr::Tls(*addr)))?;
+        let receiver = TcpReceiver::with_tls(addr, SchemaHash::of::<P::Convert>(), tls)
+            .map_err(|e| format!("failed to create TcpReceiver on {}: {}", addr, e))?;
+        self.add_receiver(Source::Receiver(Addr::Tls(*addr)), receiver)
+    }
+
+    /// Fail to add a `TcpReceiver` only accepting TLS secured
+    /// connections, as support for TLS is not compiled in.
+    #[cfg(not(feature = "tls"))]
+    pub fn add_tls_receiver(&mut self, addr: &SocketAddr) -> Result<(), String> {
+        Err(format!(
+            "cannot accept on {}: built without TLS support",
+            Addr::Tls(*addr)
+        ))
+    }
+
+    /// Add a `UdsReceiver` listening on the given path to the existing
+    /// realization.
+    pub fn add_uds_receiver(&mut self, path: &Path) -> Result<(), String> {
+        let receiver = Arc::new(Mutex::new(
+            UdsReceiver::new(path, SchemaHash::of::<P::Convert>())
+                .map_err(|e| format!("failed to create UdsReceiver: {}", e))?,
+        ));
+        // FIXME: Eventually need to find a way to connect the accumulator to
+        // the receiver.
+        let accumulator = Arc::new(Mutex::new(DistributingAccumulator::new()));
+
+        match self._txnmux.add_observable(Box::new(receiver.clone())) {
+            Ok(id) => {
+                let _ = self._sources.insert(
+                    Source::Receiver(Addr::Unix(PathBuf::from(path))),
+                    (
+                        Some(SourceRealization::LocalNode(receiver)),
+                        accumulator,
+                        id,
+                    ),
+                );
+                Ok(())
+            }
+            Err(_) => Err("failed to register UdsReceiver with TxnMux".to_string()),
+        }
+    }
+
+    /// Register the given `TcpReceiver` with the `TxnMux`.
+    fn add_receiver(
+        &mut self,
+        source: Source,
+        receiver: TcpReceiver<Update<DDValue>, P::UpdateSerializer>,
+    ) -> Result<(), String> {
         let receiver = Arc::new(Mutex::new(receiver));
         // FIXME: Eventually need to find a way to connect the accumulator to
         // the receiver.
@@ -275,7 +550,7 @@ where
         match self._txnmux.add_observable(Box::new(receiver.clone())) {
             Ok(id) => {
                 let _ = self._sources.insert(
-                    Source::TcpReceiver,
+                    source,
                     (Some(SourceRealization::Node(receiver)), accumulator, id),
                 );
                 Ok(())
@@ -365,27 +640,47 @@ where
                     (SinkRealization::File(file_sink), subscription),
                 );
             }
-            Sink::TcpSender(addr) => {
-                match addr {
+            Sink::Sender(addr) => {
+                let schema = SchemaHash::of::<P::Convert>();
+                let (observer, realization): (ObserverBox<Update<DDValue>, String>, _) = match addr
+                {
                     Addr::Ip(address) => {
-                        let tcp_sender = TcpSender::new(*address)
+                        let tcp_sender = TcpSender::new(*address, schema)
                             .map_err(|e| format!("failed to create TcpSender socket: {}", e))?;
-                        let sink = Arc::new(Mutex::new(tcp_sender));
-
-                        // Subscribe the accumulator to this sink.
-                        let subscription = accumulator
-                            .lock()
-                            .unwrap()
-                            .subscribe(Box::new(sink.clone()))
-                            .map_err(|_| "failed to subscribe TcpSender sink to accumulator")?;
-
-                        // Add sink to sink map for this accumulator.
-                        let _ = sink_map.insert(
-                            Sink::TcpSender(*addr),
-                            (SinkRealization::Node(sink), subscription),
-                        );
+                        let tcp_sender = Arc::new(Mutex::new(tcp_sender));
+                        (
+                            Box::new(tcp_sender.clone()),
+                            SinkRealization::Node(tcp_sender),
+                        )
                     }
-                }
+                    Addr::Tls(address) => {
+                        let tcp_sender = tls_sender(*address, schema, self._tls.as_ref())?;
+                        let tcp_sender = Arc::new(Mutex::new(tcp_sender));
+                        (
+                            Box::new(tcp_sender.clone()),
+                            SinkRealization::Node(tcp_sender),
+                        )
+                    }
+                    Addr::Unix(path) => {
+                        let uds_sender = UdsSender::new(path, schema)
+                            .map_err(|e| format!("failed to create UdsSender socket: {}", e))?;
+                        let uds_sender = Arc::new(Mutex::new(uds_sender));
+                        (
+                            Box::new(uds_sender.clone()),
+                            SinkRealization::LocalNode(uds_sender),
+                        )
+                    }
+                };
+
+                // Subscribe the accumulator to this sink.
+                let subscription = accumulator
+                    .lock()
+                    .unwrap()
+                    .subscribe(observer)
+                    .map_err(|_| "failed to subscribe TcpSender sink to accumulator")?;
+
+                // Add sink to sink map for this accumulator.
+                let _ = sink_map.insert(sink.clone(), (realization, subscription));
             }
         }
         Ok(())
@@ -447,10 +742,12 @@ where
         self._sources.contains_key(&Source::File(path))
     }
 
-    /// Checks that a tcp receiver exists in the Realization.
+    /// Checks that a tcp receiver listening on the given address exists
+    /// in the Realization.
     /// Used for testing.
-    pub fn contains_tcp_receiver(&self) -> bool {
-        self._sources.contains_key(&Source::TcpReceiver)
+    pub fn contains_tcp_receiver(&self, addr: &SocketAddr) -> bool {
+        self._sources
+            .contains_key(&Source::Receiver(Addr::Ip(*addr)))
     }
 
     /// Retrieves the id for the source (generated by the TxnMux).
@@ -460,10 +757,14 @@ where
         *id
     }
 
-    /// Retrieves the id for the TcpReceiver in the Realization.
+    /// Retrieves the id for the TcpReceiver listening on the given
+    /// address in the Realization.
     /// Used for testing.
-    pub fn get_tcp_receiver_id(&self) -> usize {
-        let (_, _, id) = self._sources.get(&Source::TcpReceiver).unwrap();
+    pub fn get_tcp_receiver_id(&self, addr: &SocketAddr) -> usize {
+        let (_, _, id) = self
+            ._sources
+            .get(&Source::Receiver(Addr::Ip(*addr)))
+            .unwrap();
         *id
     }
 
@@ -482,6 +783,68 @@ where
         sink_map.contains_key(sink)
     }
 
+    /// Bring the realization from the `old` plan to the `new` one.
+    ///
+    /// Both plans have to share the same redirects, as those are baked
+    /// into the `DDlogServer`.
+    fn reconcile(&mut self, old: &Plan, new: &Plan) -> Result<(), String> {
+        if old.redirects != new.redirects {
+            return Err("cannot reconcile plans with different redirects".to_string());
+        }
+
+        // Removing a source retracts everything it fed into the
+        // computation, which makes its way through the server. So we
+        // must not hold the server's lock while adding or removing
+        // sources.
+        old.sources.difference(&new.sources).try_for_each(|path| {
+            self.remove_source(&Source::File(path.clone()))
+                .map_err(String::from)
+        })?;
+        old.receivers
+            .difference(&new.receivers)
+            .try_for_each(|source| self.remove_source(source).map_err(String::from))?;
+
+        {
+            let server = self
+                ._server
+                .clone()
+                .ok_or_else(|| "no DDlogServer subscribed to TxnMux".to_string())?;
+            let mut server = server.lock().unwrap();
+
+            old.sinks.iter().try_for_each(|(sink, rel_ids)| {
+                if new.sinks.get(sink) == Some(rel_ids) {
+                    return Ok(());
+                }
+                self.remove_sink(sink)?;
+
+                let unused = self
+                    ._sinks
+                    .get(rel_ids)
+                    .map_or(false, |(_, _, sink_map)| sink_map.is_empty());
+                if unused {
+                    self.remove_sink_accumulator(rel_ids.clone(), &mut server)
+                } else {
+                    Ok(())
+                }
+            })?;
+
+            new.sinks.iter().try_for_each(|(sink, rel_ids)| {
+                if old.sinks.get(sink) == Some(rel_ids) {
+                    Ok(())
+                } else {
+                    self.add_sink(sink, rel_ids.clone(), &mut server)
+                }
+            })?;
+        }
+
+        new.receivers
+            .difference(&old.receivers)
+            .try_for_each(|source| self.add_source(source))?;
+        new.sources
+            .difference(&old.sources)
+            .try_for_each(|path| self.add_file_source(path))
+    }
+
     /// Add file sources as per the node configuration to the TxnMux for this
     /// Realization.
     fn add_file_sources(&mut self, node_cfg: &NodeCfg) -> Result<(), String> {
@@ -513,11 +876,12 @@ where
         node_cfg: &NodeCfg,
         server: &mut DDlogServer<P>,
         assignment: &Assignment,
+        tls: Option<&TlsCfg>,
     ) -> Result<(), String> {
         deduce_outputs(node_cfg, assignment)?
             .into_iter()
             .try_for_each(|(addr, rel_ids)| {
-                let addr_sink = Sink::TcpSender(addr);
+                let addr_sink = output_sink(addr, tls)?;
                 self.add_sink(&addr_sink, rel_ids, server)
             })
     }
@@ -525,6 +889,8 @@ where
 
 /// Instantiate a configuration on a particular node under the given
 /// assignment.
+///
+/// The assignment must not assign more than one node to a member.
 pub fn instantiate<P>(
     sys_cfg: SysCfg,
     addr: &Addr,
@@ -534,6 +900,7 @@ where
     P: Send + DDlog + 'static,
     P::Convert: Send + DDlogConvert,
 {
+    check_assignment(assignment)?;
     assignment
         .iter()
         .filter_map(|(uuid, assigned_addr)| {
@@ -544,13 +911,132 @@ where
             }
         })
         .try_fold(Vec::new(), |mut accumulator, node_cfg| {
-            realize::<P>(addr, node_cfg, assignment).map(|realization| {
+            realize::<P>(addr, node_cfg, assignment, None).map(|realization| {
                 accumulator.push(realization);
                 accumulator
             })
         })
 }
 
+/// The configuration realized on a particular member, which can be
+/// changed incrementally.
+///
+/// Whenever a new configuration and/or assignment is provided, the
+/// reconciler compares it against the one currently in place:
+/// - realizations of nodes no longer assigned to the member are torn
+///   down, and nodes newly assigned to it are realized from scratch
+/// - for all other nodes file sources, file sinks, senders and
+///   additional receivers are added and removed as necessary, without
+///   restarting the node's `DDlogServer` (or the `TcpReceiver` of the
+///   member feeding it)
+///
+/// The only exception to the above are changes to the input relations
+/// of a node, which require the node to be realized anew.
+#[derive(Debug)]
+pub struct Reconciler<P>
+where
+    P: Send + DDlog + 'static,
+    P::Convert: Send + DDlogConvert,
+{
+    /// The address of the member we reconcile the configuration of.
+    addr: Addr,
+    /// The credentials used for TLS secured connections, if any.
+    tls: Option<TlsCfg>,
+    /// The realizations of all nodes assigned to the member, along
+    /// with the plans they were created from.
+    realizations: BTreeMap<Node, (Plan, Realization<P>)>,
+}
+
+impl<P> Reconciler<P>
+where
+    P: Send + DDlog + 'static,
+    P::Convert: Send + DDlogConvert,
+{
+    /// Create a new reconciler for the member with the given address,
+    /// with nothing realized yet.
+    pub fn new(addr: Addr) -> Self {
+        Self {
+            addr,
+            tls: None,
+            realizations: BTreeMap::new(),
+        }
+    }
+
+    /// Create a new reconciler for the member with the given address,
+    /// using the given credentials for accepting TLS secured
+    /// connections (if the address is an `Addr::Tls`) and for
+    /// connecting to members reachable through TLS.
+    pub fn with_tls(addr: Addr, tls: TlsCfg) -> Self {
+        Self {
+            addr,
+            tls: Some(tls),
+            realizations: BTreeMap::new(),
+        }
+    }
+
+    /// Bring the member in line with the given configuration under the
+    /// given assignment.
+    ///
+    /// Assignments of more than one node to a member are rejected
+    /// without touching anything.
+    ///
+    /// If reconciling a node fails, the node's realization is torn
+    /// down (and will be created from scratch by the next successful
+    /// reconciliation), while nodes that have not been reached yet keep
+    /// running as per the previous configuration.
+    pub fn reconcile(&mut self, sys_cfg: &SysCfg, assignment: &Assignment) -> Result<(), String> {
+        check_assignment(assignment)?;
+
+        let addr = self.addr.clone();
+        let tls = self.tls.clone();
+        let plans = assignment
+            .iter()
+            .filter(|(_, assigned_addr)| **assigned_addr == addr)
+            .filter_map(|(node, _)| sys_cfg.get(node).map(|node_cfg| (node, node_cfg)))
+            .try_fold(BTreeMap::new(), |mut plans, (node, node_cfg)| {
+                let plan = Plan::new(node_cfg, &addr, assignment, tls.as_ref())?;
+                let _ = plans.insert(*node, (node_cfg, plan));
+                Ok::<_, String>(plans)
+            })?;
+
+        // Tear down everything that is gone first, so that it does not
+        // get in the way of what we create.
+        let gone = self
+            .realizations
+            .keys()
+            .filter(|node| !plans.contains_key(node))
+            .cloned()
+            .collect::<Vec<_>>();
+        gone.iter().for_each(|node| {
+            let _ = self.realizations.remove(node);
+        });
+
+        plans.into_iter().try_for_each(|(node, (node_cfg, plan))| {
+            let realization = match self.realizations.remove(&node) {
+                Some((old, mut realization)) if old.redirects == plan.redirects => {
+                    realization.reconcile(&old, &plan)?;
+                    realization
+                }
+                old => {
+                    drop(old);
+                    realize::<P>(&addr, node_cfg, assignment, tls.as_ref())?
+                }
+            };
+            let _ = self.realizations.insert(node, (plan, realization));
+            Ok(())
+        })
+    }
+
+    /// Retrieve the realization of the given node, if it is assigned
+    /// to the member.
+    /// Used for testing.
+    pub fn realization(&self, node: &Node) -> Option<&Realization<P>> {
+        self.realizations
+            .get(node)
+            .map(|(_, realization)| realization)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -559,11 +1045,34 @@ mod tests {
 
     use maplit::btreemap;
     use maplit::btreeset;
+    use maplit::hashmap;
 
     use uuid::Uuid;
 
     use crate::schema::Source;
 
+    #[test]
+    fn assignment_check() {
+        let uuid0 = Uuid::new_v4();
+        let uuid1 = Uuid::new_v4();
+        let node0 = Addr::Ip("127.0.0.1:1".parse().unwrap());
+        let node1 = Addr::Ip("127.0.0.1:2".parse().unwrap());
+
+        let assignment = btreemap! {
+            uuid0 => node0.clone(),
+            uuid1 => node1,
+        };
+        assert_eq!(check_assignment(&assignment), Ok(()));
+        assert_eq!(check_assignment(&Assignment::new()), Ok(()));
+
+        let packed = btreemap! {
+            uuid0 => node0.clone(),
+            uuid1 => node0,
+        };
+        let err = check_assignment(&packed).unwrap_err();
+        assert!(err.contains("127.0.0.1:1"), "{}", err);
+    }
+
     #[test]
     fn file_sink_deduction() {
         let node_cfg = btreemap! {
@@ -709,4 +1218,153 @@ mod tests {
         let expected = btreemap! {};
         assert_eq!(outputs, expected);
     }
+
+    #[test]
+    fn plan_deduction() {
+        let uuid0 = Uuid::new_v4();
+        let uuid1 = Uuid::new_v4();
+        let node0 = Addr::Ip("127.0.0.1:1".parse().unwrap());
+        let node1 = Addr::Ip("127.0.0.1:2".parse().unwrap());
+
+        let node0_cfg = btreemap! {
+            0 => btreeset! {
+                RelCfg::Source(Source::File(PathBuf::from("input.cmd"))),
+                RelCfg::Sink(Sink::File(PathBuf::from("output.dump"))),
+            },
+            1 => btreeset! {
+                RelCfg::Input(3),
+                RelCfg::Output(uuid1, 2),
+            },
+        };
+        let assignment = btreemap! {
+            uuid0 => node0.clone(),
+            uuid1 => node1.clone(),
+        };
+
+        let plan = Plan::new(&node0_cfg, &node0, &assignment, None).unwrap();
+        let expected = Plan {
+            redirects: hashmap! { 3 => 1 },
+            sources: btreeset! { PathBuf::from("input.cmd") },
+            receivers: btreeset! {},
+            sinks: btreemap! {
+                Sink::File(PathBuf::from("output.dump")) => btreeset! { 0 },
+                Sink::Sender(node1) => btreeset! { 1 },
+            },
+        };
+        assert_eq!(plan, expected);
+
+        // Moving the receiving node to a different member should only
+        // affect the sender.
+        let node2 = Addr::Ip("127.0.0.1:3".parse().unwrap());
+        let assignment = btreemap! {
+            uuid0 => node0.clone(),
+            uuid1 => node2.clone(),
+        };
+        let moved = Plan::new(&node0_cfg, &node0, &assignment, None).unwrap();
+        assert_eq!(moved.redirects, plan.redirects);
+        assert_eq!(moved.sources, plan.sources);
+        assert_eq!(
+            moved.sinks,
+            btreemap! {
+                Sink::File(PathBuf::from("output.dump")) => btreeset! { 0 },
+                Sink::Sender(node2) => btreeset! { 1 },
+            }
+        );
+    }
+    #[test]
+    fn tls_plan_deduction() {
+        let uuid0 = Uuid::new_v4();
+        let uuid1 = Uuid::new_v4();
+        let node0 = Addr::Tls("127.0.0.1:1".parse().unwrap());
+        let node1 = Addr::Tls("127.0.0.1:2".parse().unwrap());
+
+        let node0_cfg = btreemap! {
+            0 => btreeset! { RelCfg::Output(uuid1, 1) },
+        };
+        let assignment = btreemap! {
+            uuid0 => node0.clone(),
+            uuid1 => node1.clone(),
+        };
+
+        // Without credentials there is no way for us to talk to a
+        // member that only accepts TLS.
+        assert!(Plan::new(&node0_cfg, &node0, &assignment, None).is_err());
+
+        let tls = TlsCfg {
+            cert: PathBuf::from("cert.pem"),
+            key: PathBuf::from("key.pem"),
+            ca: PathBuf::from("ca.pem"),
+            name: "d3log".to_string(),
+        };
+        let plan = Plan::new(&node0_cfg, &node0, &assignment, Some(&tls)).unwrap();
+        assert_eq!(
+            plan.sinks,
+            btreemap! {
+                Sink::Sender(node1) => btreeset! { 0 },
+            }
+        );
+    }
+
+    #[test]
+    fn uds_plan_deduction() {
+        let uuid0 = Uuid::new_v4();
+        let uuid1 = Uuid::new_v4();
+        let node0 = Addr::Unix(PathBuf::from("/run/d3log/node0.sock"));
+        let node1 = Addr::Unix(PathBuf::from("/run/d3log/node1.sock"));
+
+        let node0_cfg = btreemap! {
+            0 => btreeset! { RelCfg::Output(uuid1, 1) },
+        };
+        let assignment = btreemap! {
+            uuid0 => node0.clone(),
+            uuid1 => node1.clone(),
+        };
+
+        let plan = Plan::new(&node0_cfg, &node0, &assignment, None).unwrap();
+        assert_eq!(
+            plan.sinks,
+            btreemap! {
+                Sink::Sender(node1) => btreeset! { 0 },
+            }
+        );
+    }
+
+    #[test]
+    fn receiver_deduction() {
+        let node0 = Addr::Ip("127.0.0.1:1".parse().unwrap());
+        let upstream0 = Addr::Ip("127.0.0.1:2".parse().unwrap());
+        let upstream1 = Addr::Tls("127.0.0.1:3".parse().unwrap());
+        let local = Addr::Unix(PathBuf::from("/run/d3log/upstream.sock"));
+
+        let node0_cfg = btreemap! {
+            0 => btreeset! {
+                RelCfg::Source(Source::Receiver(upstream0.clone())),
+                RelCfg::Source(Source::File(PathBuf::from("input.cmd"))),
+            },
+            1 => btreeset! {
+                RelCfg::Source(Source::Receiver(upstream1.clone())),
+                RelCfg::Source(Source::Receiver(local.clone())),
+            },
+            // The member's own receiver is always present and must not
+            // be reported again.
+            2 => btreeset! { RelCfg::Source(Source::Receiver(node0.clone())) },
+        };
+
+        // Receivers only accepting TLS require credentials.
+        assert!(deduce_receivers(&node0_cfg, &node0, None).is_err());
+
+        let tls = TlsCfg {
+            cert: PathBuf::from("cert.pem"),
+            key: PathBuf::from("key.pem"),
+            ca: PathBuf::from("ca.pem"),
+            name: "d3log".to_string(),
+        };
+        let receivers = deduce_receivers(&node0_cfg, &node0, Some(&tls)).unwrap();
+        let expected = btreeset! {
+            Source::Receiver(upstream0),
+            Source::Receiver(upstream1),
+            Source::Receiver(local),
+        };
+        assert_eq!(receivers, expected);
+    }
 }
@@ -44,8 +44,9 @@
 //! Distributed computing for differential-datalog.
 
 mod accumulate;
-#[cfg(any(test, feature = "test"))]
 mod assign;
+mod controller;
+mod hash;
 mod instantiate;
 mod observe;
 mod read_config;
@@ -55,6 +56,10 @@ mod tcp_channel;
 #[cfg(any(test, feature = "test"))]
 mod test;
 mod txnmux;
+mod write_config;
+
+/// A module providing a file based backend for d3log configuration.
+pub mod config_file;
 
 /// A module comprising sinks to forward data from a computation.
 pub mod sinks;
@@ -65,8 +70,12 @@ pub mod sources;
 /// A module providing functionality for using d3log with ZooKeeper.
 pub mod zookeeper;
 
+pub use assign::rendezvous_assign;
+pub use controller::Controller;
 pub use instantiate::instantiate;
+pub use instantiate::Assignment;
 pub use instantiate::Realization;
+pub use instantiate::Reconciler;
 pub use observe::Observable;
 pub use observe::ObservableBox;
 pub use observe::Observer;
@@ -85,10 +94,17 @@ pub use schema::RelCfg;
 pub use schema::Sink;
 pub use schema::Source;
 pub use schema::SysCfg;
+pub use schema::TlsCfg;
 pub use server::DDlogServer;
+pub use tcp_channel::Compression;
+pub use tcp_channel::SchemaHash;
 pub use tcp_channel::TcpReceiver;
 pub use tcp_channel::TcpSender;
+pub use tcp_channel::UdsReceiver;
+pub use tcp_channel::UdsSender;
 pub use txnmux::TxnMux;
+pub use write_config::WriteConfig;
+pub use write_config::WriteMembers;
 
 #[cfg(any(test, feature = "test"))]
 pub use {assign::simple_assign, observe::MockObserver, test::await_expected};
@@ -6,7 +6,7 @@ use crate::schema::SysCfg;
 
 /// A client to some form of membership service.
 ///
-/// Right now only read-only access is supported.
+/// Write access is provided by the `WriteMembers` trait.
 pub trait ReadMembers {
     /// Retrieve the current set of members.
     fn members(&self) -> Result<Members, String>;
@@ -14,7 +14,7 @@ pub trait ReadMembers {
 
 /// A client to some form of configuration manager.
 ///
-/// Right now only read-only access is supported.
+/// Write access is provided by the `WriteConfig` trait.
 pub trait ReadConfig {
     /// Retrieve the current system configuration.
     fn config(&self) -> Result<SysCfg, String>;
@@ -59,17 +59,32 @@ use differential_datalog::program::RelId;
 /// computation to happen.
 pub type Node = Uuid;
 
+/// The prefix identifying the textual representation of an
+/// `Addr::Tls`.
+const TLS_PREFIX: &str = "tls:";
+/// The prefix identifying the textual representation of an
+/// `Addr::Unix`.
+const UNIX_PREFIX: &str = "unix:";
+
 /// An address of an actual member in the system.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize, Hash)]
 pub enum Addr {
     /// A (ip-addr:port) pair describing how a member can be reached.
     Ip(SocketAddr),
+    /// A (ip-addr:port) pair of a member that only accepts TLS secured
+    /// and authenticated connections.
+    Tls(SocketAddr),
+    /// The path of a Unix domain socket through which a member can be
+    /// reached by members on the same machine.
+    Unix(PathBuf),
 }
 
 impl Display for Addr {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
             Addr::Ip(addr) => addr.fmt(f),
+            Addr::Tls(addr) => write!(f, "{}{}", TLS_PREFIX, addr),
+            Addr::Unix(path) => write!(f, "{}{}", UNIX_PREFIX, path.display()),
         }
     }
 }
@@ -78,17 +93,19 @@ impl FromStr for Addr {
     type Err = AddrParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        SocketAddr::from_str(s).map(Addr::Ip)
+        if let Some(path) = s.strip_prefix(UNIX_PREFIX) {
+            return Ok(Addr::Unix(PathBuf::from(path)));
+        }
+        match s.strip_prefix(TLS_PREFIX) {
+            Some(addr) => SocketAddr::from_str(addr).map(Addr::Tls),
+            None => SocketAddr::from_str(s).map(Addr::Ip),
+        }
     }
 }
 
 impl Ord for Addr {
     fn cmp(&self, other: &Addr) -> Ordering {
-        match self {
-            Addr::Ip(addr) => match other {
-                Addr::Ip(other_addr) => addr.to_string().cmp(&other_addr.to_string()),
-            },
-        }
+        self.to_string().cmp(&other.to_string())
     }
 }
 
@@ -100,7 +117,7 @@ impl PartialOrd for Addr {
 
 /// A struct representing an individual member participating in the
 /// distributed system.
-#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Deserialize, Serialize)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Deserialize, Serialize)]
 pub struct Member {
     /// The address of the member.
     addr: Addr,
@@ -124,15 +141,41 @@ impl Member {
 /// A type for representing a set of members.
 pub type Members = BTreeSet<Member>;
 
+/// The credentials used for securing a TCP channel with TLS.
+///
+/// Both ends of a channel authenticate each other: each presents its
+/// certificate to the other, which in turn has to be signed by the
+/// certificate authority the peer trusts.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Deserialize, Serialize, Hash)]
+pub struct TlsCfg {
+    /// The PEM file containing the certificate chain to present to
+    /// peers.
+    pub cert: PathBuf,
+    /// The PEM file containing the private key belonging to `cert`.
+    pub key: PathBuf,
+    /// The PEM file containing the certificates of the authorities
+    /// that peer certificates have to be signed by.
+    pub ca: PathBuf,
+    /// The DNS name receivers present certificates for.
+    pub name: String,
+}
+
 /// All the input sources we support.
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Deserialize, Serialize, Hash)]
 pub enum Source {
     /// Input is coming from a file.
     File(PathBuf),
-    /// Input is coming from a TcpReceiver.
-    /// Right now there is at most one TcpReceiver at a given time, so it does
-    /// not require an identifier.
-    TcpReceiver,
+    /// Input is coming from a receiver listening on the given address.
+    /// A node can have any number of them, e.g., to accept inputs from
+    /// multiple upstream clusters on different ports. Receivers on an
+    /// `Addr::Ip` are `TcpReceiver`s. Receivers on an `Addr::Tls` only
+    /// accept TLS secured connections from authenticated senders, using
+    /// the member's credentials. Receivers on an `Addr::Unix` are
+    /// `UdsReceiver`s listening on a Unix domain socket.
+    // Configurations published before the variant got renamed still
+    // refer to it by its old name.
+    #[serde(alias = "TcpReceiver")]
+    Receiver(Addr),
 }
 
 /// All the output sinks we support.
@@ -140,8 +183,13 @@ pub enum Source {
 pub enum Sink {
     /// Output is emitted into a file.
     File(PathBuf),
-    /// Output is emitted to a TcpSender.
-    TcpSender(Addr),
+    /// Output is emitted to a sender connecting to the given address.
+    /// Senders to an `Addr::Ip` are `TcpSender`s. Senders to an
+    /// `Addr::Tls` use a TLS secured and authenticated connection,
+    /// using the member's credentials. Senders to an `Addr::Unix` are
+    /// `UdsSender`s connecting to a Unix domain socket.
+    #[serde(alias = "TcpSender")]
+    Sender(Addr),
 }
 
 /// A description of inputs and outputs of a relation.
@@ -173,6 +221,7 @@ mod tests {
 
     use maplit::btreemap;
     use maplit::btreeset;
+    use serde_json::from_str as from_json;
     use serde_json::to_string as to_json;
 
     #[test]
@@ -183,6 +232,23 @@ mod tests {
         assert!(addr0 < addr1);
     }
 
+    #[test]
+    fn parse_addrs() {
+        let addr = "127.0.0.1:1".parse::<Addr>().unwrap();
+        assert_eq!(addr, Addr::Ip("127.0.0.1:1".parse().unwrap()));
+        assert_eq!(addr.to_string(), "127.0.0.1:1");
+
+        let addr = "tls:127.0.0.1:1".parse::<Addr>().unwrap();
+        assert_eq!(addr, Addr::Tls("127.0.0.1:1".parse().unwrap()));
+        assert_eq!(addr.to_string(), "tls:127.0.0.1:1");
+
+        assert!("tls:".parse::<Addr>().is_err());
+
+        let addr = "unix:/run/d3log/node0.sock".parse::<Addr>().unwrap();
+        assert_eq!(addr, Addr::Unix(PathBuf::from("/run/d3log/node0.sock")));
+        assert_eq!(addr.to_string(), "unix:/run/d3log/node0.sock");
+    }
+
     #[test]
     fn serialize_relations() {
         let relations = btreeset! {1, 2, 3};
@@ -220,4 +286,24 @@ mod tests {
 
         assert_eq!(serialized, expected);
     }
+
+    #[test]
+    fn deserialize_renamed_sources_and_sinks() {
+        let addr = Addr::Ip("127.0.0.1:1".parse().unwrap());
+        let source = Source::Receiver(addr.clone());
+        let sink = Sink::Sender(addr);
+        assert_eq!(
+            to_json(&source).unwrap(),
+            r#"{"Receiver":{"Ip":"127.0.0.1:1"}}"#
+        );
+        assert_eq!(
+            to_json(&sink).unwrap(),
+            r#"{"Sender":{"Ip":"127.0.0.1:1"}}"#
+        );
+
+        let old = from_json::<Source>(r#"{"TcpReceiver":{"Ip":"127.0.0.1:1"}}"#).unwrap();
+        assert_eq!(old, source);
+        let old = from_json::<Sink>(r#"{"TcpSender":{"Ip":"127.0.0.1:1"}}"#).unwrap();
+        assert_eq!(old, sink);
+    }
 }
@@ -106,7 +106,7 @@ where
         trace!("DDlogServer({})::on_start", self.id);
 
         if let Some(ref mut prog) = self.prog {
-            prog.transaction_start()
+            prog.transaction_start().map_err(String::from)
         } else {
             Ok(())
         }
@@ -179,6 +179,7 @@ where
                 },
                 update => panic!("Operation {:?} not allowed", update),
             }))
+            .map_err(String::from)
         } else {
             trace!("No DDlog program associated with this DDlogServer");
             Ok(())
@@ -110,6 +110,10 @@ mod tests {
             panic!("unexpected IdxId {}", idx_id)
         }
 
+        fn relation_types() -> Vec<(&'static str, &'static str)> {
+            vec![("test_rel", "string")]
+        }
+
         fn updcmd2upd(upd_cmd: &UpdCmd) -> Result<Update<DDValue>, std::string::String> {
             match upd_cmd {
                 UpdCmd::Insert(relident, record) => {
@@ -1,6 +1,5 @@
 use std::convert::TryFrom;
 use std::fs::File as FsFile;
-use std::io::BufRead;
 use std::io::BufReader;
 use std::iter::once;
 use std::marker::PhantomData;
@@ -16,12 +15,12 @@ use libc::c_uint;
 use log::error;
 use log::info;
 use log::trace;
-use nom::Err;
 use uid::Id;
 
-use cmd_parser::err_str;
-use cmd_parser::parse_command;
+use cmd_parser::include;
 use cmd_parser::Command;
+use cmd_parser::CommandReader;
+use cmd_parser::ReadError;
 use differential_datalog::ddval::DDValue;
 use differential_datalog::program::Update;
 use differential_datalog::DDlogConvert;
@@ -82,6 +81,7 @@ fn handle<C>(
 
 fn process<C>(
     id: usize,
+    path: PathBuf,
     file: FsFile,
     fd: Arc<Fd>,
     mut observer: ObserverBox<Update<DDValue>, String>,
@@ -89,46 +89,46 @@ fn process<C>(
 where
     C: DDlogConvert,
 {
-    // TODO: The logic here somewhat resembles that in
-    //       `cmd_parser/lib.rs`. We may want to deduplicate at some
-    //       point.
-    let mut buffer = Vec::new();
     let mut updates = Vec::new();
-    let mut reader = BufReader::new(file);
+    let mut reader = CommandReader::new(path.display().to_string(), BufReader::new(file));
     loop {
-        let mut line = String::new();
-        match reader.read_line(&mut line) {
-            // TODO: We need to handle the Ok(0) case which occurs if
-            //       the underlying file has reached EOF. We basically
-            //       would need to register a poll(2) for the fd or
-            //       something along those lines.
-            Ok(_) => {
-                buffer.extend_from_slice(line.as_bytes());
-                match parse_command(buffer.as_slice()) {
-                    Ok((rest, command)) => {
-                        // TODO: Remove unnecessary allocation. (Replace
-                        //       with Vec::splice perhaps?)
-                        buffer = rest.to_owned();
-                        handle::<C>(id, command, &mut updates, &mut observer);
-                    }
-                    Err(Err::Incomplete(_)) => (),
-                    Err(e) => {
-                        error!("encountered invalid input: {}", err_str(&e));
-                        buffer.clear();
-                    }
+        let result = match reader.next_command() {
+            Ok(Some(Command::Include(included))) => {
+                let result = include(&included, Some(&path), &mut |command| {
+                    handle::<C>(id, command, &mut updates, &mut observer);
+                    Ok(true)
+                });
+                if let Err(e) = result {
+                    error!("failed to process included file: {}", e);
                 }
+                Ok(())
             }
-            Err(e) => {
-                if fd.is_closed() {
-                    // Because `fd` has been closed, we should not
-                    // close the file object wrapped by our reader
-                    // again, or we may close an unrelated file
-                    // descriptor that happens to reuse the same number.
-                    let _ = reader.into_inner().into_raw_fd();
-                    return observer;
-                }
-                error!("failed to read from source file: {}", e);
+            Ok(Some(command)) => {
+                handle::<C>(id, command, &mut updates, &mut observer);
+                Ok(())
             }
+            // TODO: We need to handle the end of file case properly.
+            //       Currently we just keep polling for more data. We
+            //       basically would need to register a poll(2) for the
+            //       fd or something along those lines.
+            Ok(None) => Ok(()),
+            Err(ReadError::Parse(e)) => {
+                error!("{}", e);
+                reader.skip_command()
+            }
+            Err(ReadError::Io(e)) => Err(e),
+        };
+
+        if let Err(e) = result {
+            if fd.is_closed() {
+                // Because `fd` has been closed, we should not
+                // close the file object wrapped by our reader
+                // again, or we may close an unrelated file
+                // descriptor that happens to reuse the same number.
+                let _ = reader.into_inner().into_inner().into_raw_fd();
+                return observer;
+            }
+            error!("failed to read from source file: {}", e);
         }
     }
 }
@@ -189,9 +189,10 @@ where
         };
         let fd = c_uint::try_from(file.as_raw_fd()).unwrap();
         let fd = Arc::new(Fd::new(fd));
+        let path = path.to_path_buf();
         let state = State {
             fd: fd.clone(),
-            thread: spawn(move || process::<C>(id, file, fd, observer)),
+            thread: spawn(move || process::<C>(id, path, file, fd, observer)),
         };
 
         Ok(state)
@@ -300,6 +301,10 @@ mod tests {
             unimplemented!()
         }
 
+        fn relation_types() -> Vec<(&'static str, &'static str)> {
+            unimplemented!()
+        }
+
         fn updcmd2upd(_upd_cmd: &UpdCmd) -> Result<Update<DDValue>, std::string::String> {
             // Exact details do not matter in this context, so just fake
             // some data.
@@ -372,4 +377,35 @@ mod tests {
 
         assert_eq!(mock.lock().unwrap().called_on_start, 4);
     }
+
+    /// Check that `include` commands in a source file are executed.
+    #[test]
+    fn source_file_include() {
+        let mut included = NamedTempFile::new().unwrap();
+        included.write_all(TRANSACTION_DUMP).unwrap();
+        included.flush().unwrap();
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "include \"{}\";", included.path().display()).unwrap();
+        writeln!(file, "include \"{}\";", included.path().display()).unwrap();
+        file.flush().unwrap();
+
+        let mock = SharedObserver::new(Mutex::new(MockObserver::new()));
+        let mut adapter = File::<DummyConverter>::new(file.path());
+        let _ = adapter.subscribe(Box::new(mock.clone())).unwrap();
+
+        await_expected(|| {
+            let (on_start, on_updates, on_commit) = {
+                let guard = mock.lock().unwrap();
+                (
+                    guard.called_on_start,
+                    guard.called_on_updates,
+                    guard.called_on_commit,
+                )
+            };
+            assert_eq!(on_start, 4);
+            assert_eq!(on_updates, 10);
+            assert_eq!(on_commit, 4);
+        });
+    }
 }
@@ -0,0 +1,488 @@
+//! Optional compression of the data sent over a channel.
+//!
+//! Data is compressed in frames: the writing end collects data until it
+//! is flushed (or a certain amount accumulated), compresses it as a
+//! whole and sends it prefixed with its compressed and its original
+//! length. Without compression data is passed through unchanged.
+//!
+//! Support for LZ4 and zstd is only available with the `compression`
+//! feature; without it `Compression::None` is the only option. During
+//! the handshake senders advertise the codecs they support by their
+//! `CodecId`s and the receiver picks one of them.
+
+use std::cmp::min;
+use std::convert::TryFrom;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Result as IoResult;
+use std::io::Write;
+
+/// The amount of data we collect before emitting a frame, even if we
+/// were not flushed. No frame decompresses to more than that.
+const MAX_FRAME_SIZE: usize = 1 << 20;
+/// The size of the header preceding every frame.
+const FRAME_HEADER_SIZE: usize = 8;
+/// The zstd compression level to use; zero selects the library's
+/// default.
+#[cfg(feature = "compression")]
+const ZSTD_LEVEL: i32 = 0;
+
+/// The identifier of a codec as exchanged during the handshake. Unlike
+/// the variants of `Compression`, which depend on the enabled features,
+/// identifiers are the same in every build.
+pub type CodecId = u8;
+
+const NONE_ID: CodecId = 0;
+const LZ4_ID: CodecId = 1;
+const ZSTD_ID: CodecId = 2;
+
+/// The compressions supported by this build.
+#[cfg(feature = "compression")]
+const SUPPORTED: &[Compression] = &[Compression::None, Compression::Lz4, Compression::Zstd];
+#[cfg(not(feature = "compression"))]
+const SUPPORTED: &[Compression] = &[Compression::None];
+
+/// The compression to apply to the data sent over a channel.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Compression {
+    /// Do not compress data.
+    None,
+    /// Compress data using LZ4, favoring speed over compression ratio.
+    #[cfg(feature = "compression")]
+    Lz4,
+    /// Compress data using zstd.
+    #[cfg(feature = "compression")]
+    Zstd,
+}
+
+impl Compression {
+    /// Retrieve the identifiers of all codecs supported by this build.
+    pub fn supported() -> Vec<CodecId> {
+        SUPPORTED
+            .iter()
+            .map(|compression| compression.id())
+            .collect()
+    }
+
+    /// Retrieve the identifier of the codec.
+    pub fn id(self) -> CodecId {
+        match self {
+            Compression::None => NONE_ID,
+            #[cfg(feature = "compression")]
+            Compression::Lz4 => LZ4_ID,
+            #[cfg(feature = "compression")]
+            Compression::Zstd => ZSTD_ID,
+        }
+    }
+
+    /// Look up the codec with the given identifier, failing if this
+    /// build does not support it.
+    pub fn from_id(id: CodecId) -> Result<Self, String> {
+        SUPPORTED
+            .iter()
+            .copied()
+            .find(|compression| compression.id() == id)
+            .ok_or_else(|| match id {
+                LZ4_ID => "LZ4 compression requires the `compression` feature".to_string(),
+                ZSTD_ID => "zstd compression requires the `compression` feature".to_string(),
+                _ => format!("unknown compression codec {}", id),
+            })
+    }
+
+    /// Compress the given data.
+    fn compress(self, data: &[u8]) -> IoResult<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            #[cfg(feature = "compression")]
+            Compression::Lz4 => lz4::block::compress(data, None, false),
+            #[cfg(feature = "compression")]
+            Compression::Zstd => zstd::block::compress(data, ZSTD_LEVEL),
+        }
+    }
+
+    /// Determine the size data of the given length compresses to in
+    /// the worst case.
+    fn compress_bound(self, len: usize) -> usize {
+        match self {
+            Compression::None => len,
+            // See `LZ4_COMPRESSBOUND`.
+            #[cfg(feature = "compression")]
+            Compression::Lz4 => len + len / 255 + 16,
+            // See `ZSTD_COMPRESSBOUND`.
+            #[cfg(feature = "compression")]
+            Compression::Zstd => {
+                let small = 128 << 10;
+                len + (len >> 8) + if len < small { (small - len) >> 11 } else { 0 }
+            }
+        }
+    }
+
+    /// Decompress the given data, which is known to decompress to `len`
+    /// bytes.
+    fn decompress(self, data: &[u8], len: usize) -> IoResult<Vec<u8>> {
+        let data = match self {
+            Compression::None => data.to_vec(),
+            #[cfg(feature = "compression")]
+            Compression::Lz4 => {
+                let len = i32::try_from(len).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                lz4::block::decompress(data, Some(len))?
+            }
+            #[cfg(feature = "compression")]
+            Compression::Zstd => zstd::block::decompress(data, len)?,
+        };
+
+        if data.len() != len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "frame decompressed to {} bytes instead of {}",
+                    data.len(),
+                    len
+                ),
+            ));
+        }
+        Ok(data)
+    }
+}
+
+/// A writer compressing all data written to it before handing it to
+/// the wrapped writer.
+#[derive(Debug)]
+pub struct Compressor<W> {
+    /// The writer receiving the compressed data.
+    inner: W,
+    /// The compression to apply.
+    compression: Compression,
+    /// Data collected for the next frame.
+    buffer: Vec<u8>,
+}
+
+impl<W> Compressor<W>
+where
+    W: Write,
+{
+    /// Create a new `Compressor` applying the given compression.
+    pub fn new(inner: W, compression: Compression) -> Self {
+        Self {
+            inner,
+            compression,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Retrieve the wrapped writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Compress all collected data and emit it as a frame.
+    fn write_frame(&mut self) -> IoResult<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let data = self.compression.compress(&self.buffer)?;
+        let invalid = |e| Error::new(ErrorKind::InvalidInput, e);
+        let compressed = u32::try_from(data.len()).map_err(invalid)?;
+        let original = u32::try_from(self.buffer.len()).map_err(invalid)?;
+
+        self.inner.write_all(&compressed.to_le_bytes())?;
+        self.inner.write_all(&original.to_le_bytes())?;
+        self.inner.write_all(&data)?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<W> Write for Compressor<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        if self.compression == Compression::None {
+            return self.inner.write(buf);
+        }
+
+        // We never let a frame grow beyond the maximum size, as the
+        // reading end would reject it.
+        let count = min(buf.len(), MAX_FRAME_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..count]);
+        if self.buffer.len() == MAX_FRAME_SIZE {
+            self.write_frame()?;
+        }
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.write_frame()?;
+        self.inner.flush()
+    }
+}
+
+/// A reader decompressing the data read from the wrapped reader.
+#[derive(Debug)]
+pub struct Decompressor<R> {
+    /// The reader providing the compressed data.
+    inner: R,
+    /// The compression that was applied.
+    compression: Compression,
+    /// The decompressed data of the current frame.
+    buffer: Vec<u8>,
+    /// The position in `buffer` up to which data was consumed.
+    position: usize,
+}
+
+impl<R> Decompressor<R>
+where
+    R: Read,
+{
+    /// Create a new `Decompressor`, reversing the given compression.
+    pub fn new(inner: R, compression: Compression) -> Self {
+        Self {
+            inner,
+            compression,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Retrieve the wrapped reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Read and decompress the next frame.
+    ///
+    /// `false` is returned if the wrapped reader reached its end
+    /// instead.
+    fn read_frame(&mut self) -> IoResult<bool> {
+        let mut header = [0; FRAME_HEADER_SIZE];
+        let count = loop {
+            match self.inner.read(&mut header) {
+                Ok(count) => break count,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        if count == 0 {
+            return Ok(false);
+        }
+        self.inner.read_exact(&mut header[count..])?;
+
+        let mut compressed = [0; 4];
+        let mut original = [0; 4];
+        compressed.copy_from_slice(&header[..4]);
+        original.copy_from_slice(&header[4..]);
+        let compressed = u32::from_le_bytes(compressed) as usize;
+        let original = u32::from_le_bytes(original) as usize;
+
+        // We never emit empty frames, and passing one on would make our
+        // caller believe it reached the end of the data.
+        if original == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "received empty frame"));
+        }
+
+        // Do not trust the lengths we received to be sane before
+        // allocating memory for the frame.
+        if original > MAX_FRAME_SIZE || compressed > self.compression.compress_bound(original) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "frame of {} bytes decompressing to {} bytes exceeds size limit",
+                    compressed, original
+                ),
+            ));
+        }
+
+        let mut data = vec![0; compressed];
+        self.inner.read_exact(&mut data)?;
+        self.buffer = self.compression.decompress(&data, original)?;
+        self.position = 0;
+        Ok(true)
+    }
+}
+
+impl<R> Read for Decompressor<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if self.compression == Compression::None {
+            return self.inner.read(buf);
+        }
+
+        // Frames are never empty, so we only need to read a single one.
+        if self.position == self.buffer.len() && !self.read_frame()? {
+            return Ok(0);
+        }
+
+        let mut available = &self.buffer[self.position..];
+        let count = available.read(buf)?;
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::LinkedList;
+
+    use bincode::deserialize_from;
+    #[cfg(feature = "compression")]
+    use bincode::serialize;
+    use bincode::serialize_into;
+
+    use crate::tcp_channel::message::Message;
+
+    /// Create a transaction comprising the given number of updates.
+    fn transaction(count: u64) -> Message<String> {
+        let mut updates = LinkedList::new();
+        for i in 0..count / 100 {
+            let batch = (0..100)
+                .map(|j| format!("update #{}", i * 100 + j))
+                .collect();
+            updates.push_back(batch);
+        }
+        Message::UpdateList(updates)
+    }
+
+    /// Check that messages survive compression and decompression.
+    fn round_trip(compression: Compression) {
+        let messages = vec![
+            Message::Start,
+            transaction(100),
+            transaction(200_000),
+            Message::Commit(1),
+            Message::Start,
+            Message::Commit(2),
+        ];
+
+        let mut compressor = Compressor::new(Vec::new(), compression);
+        for message in &messages {
+            serialize_into(&mut compressor, message).unwrap();
+            compressor.flush().unwrap();
+        }
+        let data = compressor.get_mut().clone();
+
+        let mut decompressor = Decompressor::new(data.as_slice(), compression);
+        for message in messages {
+            let received = deserialize_from::<_, Message<String>>(&mut decompressor).unwrap();
+            assert_eq!(received, message);
+        }
+        assert!(decompressor.get_mut().is_empty());
+
+        let mut buffer = [0; 1];
+        assert_eq!(decompressor.read(&mut buffer).unwrap(), 0);
+    }
+
+    /// Round trip messages without compression.
+    #[test]
+    fn round_trip_none() {
+        round_trip(Compression::None)
+    }
+
+    /// Round trip messages using LZ4 compression.
+    #[cfg(feature = "compression")]
+    #[test]
+    fn round_trip_lz4() {
+        round_trip(Compression::Lz4)
+    }
+
+    /// Round trip messages using zstd compression.
+    #[cfg(feature = "compression")]
+    #[test]
+    fn round_trip_zstd() {
+        round_trip(Compression::Zstd)
+    }
+
+    /// Check that data is actually compressed and split into frames.
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compress_frames() {
+        let mut compressor = Compressor::new(Vec::new(), Compression::Zstd);
+        serialize_into(&mut compressor, &transaction(200_000)).unwrap();
+        // The transaction exceeds the maximum frame size, so at least
+        // one frame got emitted before the flush.
+        assert!(!compressor.get_mut().is_empty());
+        compressor.flush().unwrap();
+
+        let uncompressed = serialize(&transaction(200_000)).unwrap();
+        assert!(compressor.get_mut().len() < uncompressed.len() / 2);
+    }
+
+    /// Check that we detect frames that do not decompress properly.
+    #[cfg(feature = "compression")]
+    #[test]
+    fn corrupted_frame() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&4u32.to_le_bytes());
+        data.extend_from_slice(&64u32.to_le_bytes());
+        data.extend_from_slice(b"junk");
+
+        let mut decompressor = Decompressor::new(data.as_slice(), Compression::Lz4);
+        let mut buffer = [0; 64];
+        assert!(decompressor.read(&mut buffer).is_err());
+    }
+
+    /// Check that we reject empty frames instead of reporting them as
+    /// the end of the data.
+    #[cfg(feature = "compression")]
+    #[test]
+    fn empty_frame() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut decompressor = Decompressor::new(data.as_slice(), Compression::Lz4);
+        let mut buffer = [0; 64];
+        let error = decompressor.read(&mut buffer).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    /// Check that codec identifiers map back to the codecs they
+    /// identify and that unsupported ones are rejected.
+    #[test]
+    fn codec_ids() {
+        for id in Compression::supported() {
+            assert_eq!(Compression::from_id(id).unwrap().id(), id);
+        }
+        assert_eq!(Compression::from_id(NONE_ID), Ok(Compression::None));
+
+        let error = Compression::from_id(42).unwrap_err();
+        assert!(error.contains("unknown compression codec 42"), "{}", error);
+        #[cfg(not(feature = "compression"))]
+        {
+            let error = Compression::from_id(ZSTD_ID).unwrap_err();
+            assert!(
+                error.contains("requires the `compression` feature"),
+                "{}",
+                error
+            );
+        }
+    }
+
+    /// Check that we reject frames exceeding the size limit before
+    /// attempting to read them.
+    #[cfg(feature = "compression")]
+    #[test]
+    fn oversized_frame() {
+        let frames = [
+            (1u32 << 30, 64u32),
+            (64, MAX_FRAME_SIZE as u32 + 1),
+            (MAX_FRAME_SIZE as u32 * 2, MAX_FRAME_SIZE as u32),
+        ];
+        for (compressed, original) in &frames {
+            let mut data = Vec::new();
+            data.extend_from_slice(&compressed.to_le_bytes());
+            data.extend_from_slice(&original.to_le_bytes());
+
+            let mut decompressor = Decompressor::new(data.as_slice(), Compression::Zstd);
+            let mut buffer = [0; 64];
+            let error = decompressor.read(&mut buffer).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
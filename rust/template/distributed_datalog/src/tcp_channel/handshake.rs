@@ -0,0 +1,173 @@
+//! The handshake performed by both ends of a channel before any
+//! messages are exchanged.
+//!
+//! Each end sends a `Header` identifying the protocol version it
+//! speaks along with a hash of the schema of the program it runs, and
+//! checks the header it received against its own. Without it a node
+//! running a different version of a program would silently
+//! misinterpret the serialized updates it receives.
+//!
+//! The compression the sender applies to all data it sends afterwards
+//! is negotiated separately: the sender's `Message::Hello` lists the
+//! codecs it supports and the receiver's `Message::Welcome` names the
+//! one it picked.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::iter::once;
+
+use differential_datalog::DDlogConvert;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::hash::fnv1a;
+
+/// The bytes every header starts with.
+const MAGIC: [u8; 4] = *b"D3LG";
+/// The version of the wire protocol. It needs to be bumped with every
+/// incompatible change to the `Message` format or the handshake.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// A hash of the schema of a program, i.e., the names of its relations
+/// along with the types of the values stored in them.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct SchemaHash(u64);
+
+impl SchemaHash {
+    /// Compute the hash of the schema comprised of the given relation
+    /// names and types.
+    ///
+    /// The order in which relations are provided does not matter.
+    pub fn new<'a, I>(relations: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut relations = relations.into_iter().collect::<Vec<_>>();
+        relations.sort_unstable();
+
+        let bytes = relations.into_iter().flat_map(|(name, type_)| {
+            let name = name.bytes().chain(once(0));
+            let type_ = type_.bytes().chain(once(0));
+            name.chain(type_)
+        });
+        Self(fnv1a(bytes))
+    }
+
+    /// Compute the schema hash of the program with the given converter.
+    pub fn of<C>() -> Self
+    where
+        C: DDlogConvert,
+    {
+        Self::new(C::relation_types())
+    }
+}
+
+impl Display for SchemaHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// The header sent by both ends of a channel.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Header {
+    magic: [u8; 4],
+    version: u32,
+    schema: SchemaHash,
+}
+
+impl Header {
+    /// Create the header for the given schema.
+    pub fn new(schema: SchemaHash) -> Self {
+        Self {
+            magic: MAGIC,
+            version: PROTOCOL_VERSION,
+            schema,
+        }
+    }
+
+    /// Check that the header received from the remote end is compatible
+    /// with ours.
+    pub fn check(&self, remote: &Header) -> Result<(), String> {
+        if remote.magic != self.magic {
+            Err("remote end does not speak the d3log protocol".to_string())
+        } else if remote.version != self.version {
+            Err(format!(
+                "protocol version mismatch (local: {}, remote: {})",
+                self.version, remote.version
+            ))
+        } else if remote.schema != self.schema {
+            Err(format!(
+                "program schema mismatch (local: {}, remote: {}); both ends need to run the same program",
+                self.schema, remote.schema
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bincode::deserialize;
+    use bincode::serialize;
+
+    /// Check that the schema hash is independent of the order of
+    /// relations but depends on their names and types.
+    #[test]
+    fn schema_hash() {
+        let schema = SchemaHash::new(vec![("Foo", "bigint"), ("Bar", "string")]);
+        let reordered = SchemaHash::new(vec![("Bar", "string"), ("Foo", "bigint")]);
+        assert_eq!(schema, reordered);
+
+        let renamed = SchemaHash::new(vec![("Foo", "bigint"), ("Baz", "string")]);
+        assert_ne!(schema, renamed);
+
+        let retyped = SchemaHash::new(vec![("Foo", "bigint"), ("Bar", "bit<32>")]);
+        assert_ne!(schema, retyped);
+
+        // Names and types must not bleed into each other.
+        let shifted = SchemaHash::new(vec![("Fo", "obigint"), ("Bar", "string")]);
+        assert_ne!(schema, shifted);
+
+        let empty = SchemaHash::new(vec![]);
+        assert_ne!(schema, empty);
+    }
+
+    /// Check that a change to a type nested in a relation's type alters
+    /// the schema hash, given types as the compiler reports them, i.e.,
+    /// with all typedefs expanded.
+    #[test]
+    fn schema_hash_nested_type() {
+        let schema = SchemaHash::new(vec![("Foo", "Outer{id: bigint, inner: Inner{x: bit<32>}}")]);
+        let changed = SchemaHash::new(vec![("Foo", "Outer{id: bigint, inner: Inner{x: bit<64>}}")]);
+        assert_ne!(schema, changed);
+    }
+
+    /// Check that we detect incompatible headers.
+    #[test]
+    fn check_header() {
+        let schema = SchemaHash::new(vec![("Foo", "bigint")]);
+        let header = Header::new(schema);
+        assert_eq!(header.check(&Header::new(schema)), Ok(()));
+
+        let other = Header::new(SchemaHash::default());
+        let error = header.check(&other).unwrap_err();
+        assert!(error.contains("schema mismatch"), "{}", error);
+
+        let mut other = Header::new(schema);
+        other.version += 1;
+        let error = header.check(&other).unwrap_err();
+        assert!(error.contains("version mismatch"), "{}", error);
+
+        let mut bytes = serialize(&Header::new(schema)).unwrap();
+        bytes[0] = b'X';
+        let other = deserialize::<Header>(&bytes).unwrap();
+        let error = header.check(&other).unwrap_err();
+        assert!(error.contains("does not speak"), "{}", error);
+    }
+}
@@ -6,25 +6,45 @@ use std::fmt::Result;
 use serde::Deserialize;
 use serde::Serialize;
 
+use uuid::Uuid;
+
+use crate::tcp_channel::compress::CodecId;
+
 /// An enum used for representing (and serializing/deserializing)
 /// messages sent through the channel.
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub enum Message<T> {
+    /// The first message sent over a connection by a sender,
+    /// identifying it across reconnects and listing the codecs it
+    /// supports for compressing the data it sends.
+    Hello(Uuid, Vec<CodecId>),
+    /// The receiver's reply to a `Hello`, naming the codec the sender is
+    /// to compress data with and acknowledging all transactions with a
+    /// sequence number up to and including the given one.
+    Welcome(CodecId, u64),
+    /// A message sent by the receiver, acknowledging all transactions
+    /// with a sequence number up to and including the given one.
+    Ack(u64),
     Start,
     Updates(Vec<T>),
     UpdateList(LinkedList<Vec<T>>),
-    Commit,
-    Complete,
+    /// Commit the transaction with the given sequence number.
+    Commit(u64),
+    /// Signal completion; carries a sequence number just like a commit.
+    Complete(u64),
 }
 
 impl<T> Display for Message<T> {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
         let s = match self {
+            Message::Hello(..) => "hello",
+            Message::Welcome(..) => "welcome",
+            Message::Ack(_) => "ack",
             Message::Start => "on_start",
             Message::Updates(_) => "on_updates",
             Message::UpdateList(_) => "on_updates",
-            Message::Commit => "on_commit",
-            Message::Complete => "on_completed",
+            Message::Commit(_) => "on_commit",
+            Message::Complete(_) => "on_completed",
         };
         formatter.write_str(s)
     }
@@ -1,11 +1,20 @@
 //! TCP implementation of an Observer/Observable channel.
 
+mod compress;
+mod handshake;
 mod message;
 mod receiver;
 mod sender;
 mod socket;
+mod stream;
+#[cfg(feature = "tls")]
+mod tls;
 mod txnbuf;
 
+pub use compress::Compression;
+pub use handshake::SchemaHash;
 pub use receiver::TcpReceiver;
+pub use receiver::UdsReceiver;
 pub use sender::TcpSender;
+pub use sender::UdsSender;
 pub use socket::Fd;
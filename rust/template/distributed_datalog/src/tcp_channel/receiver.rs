@@ -1,18 +1,30 @@
+use std::collections::HashMap;
+use std::collections::LinkedList;
 use std::convert::TryFrom;
 use std::fmt::Debug;
+use std::fs::remove_file;
 use std::io::BufReader;
+use std::io::Error;
 use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
 use std::net::SocketAddr;
 use std::net::TcpListener;
-use std::net::TcpStream;
 use std::net::ToSocketAddrs;
 use std::os::unix::io::AsRawFd;
+use std::os::unix::io::RawFd;
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread::spawn;
 use std::thread::JoinHandle;
 
 use bincode::deserialize_from;
+use bincode::serialize;
 use bincode::ErrorKind as BincodeError;
 
 use libc::c_uint;
@@ -22,6 +34,7 @@ use log::error;
 use log::trace;
 
 use uid::Id;
+use uuid::Uuid;
 
 use serde::de::DeserializeOwned;
 
@@ -29,9 +42,18 @@ use crate::observe::Observable;
 use crate::observe::Observer;
 use crate::observe::ObserverBox;
 use crate::observe::SharedObserver;
+#[cfg(feature = "tls")]
+use crate::schema::TlsCfg;
+use crate::tcp_channel::compress::Compression;
+use crate::tcp_channel::compress::Decompressor;
+use crate::tcp_channel::handshake::Header;
+use crate::tcp_channel::handshake::SchemaHash;
 use crate::tcp_channel::message::Message;
 use crate::tcp_channel::socket::Fd;
 use crate::tcp_channel::socket::ShutdownExt;
+use crate::tcp_channel::stream::Stream;
+#[cfg(feature = "tls")]
+use crate::tcp_channel::tls::Acceptor;
 use crate::txnmux::TxnMux;
 
 /// A struct representing both an `Observer` and an `Observable` that
@@ -91,18 +113,68 @@ where
     }
 }
 
-/// The receiving end of a TCP channel has an address
-/// and streams data to an observer.
+/// A map from the ID of each sender that connected to us to the
+/// sequence number of the last transaction we delivered from it.
+///
+/// Entries are kept for as long as the receiver exists, because a
+/// sender may connect again at any point to replay transactions whose
+/// acknowledgement it did not receive.
+type Senders = Arc<Mutex<HashMap<Uuid, Arc<Mutex<u64>>>>>;
+
+/// A socket we accept connections on.
 #[derive(Debug)]
-pub struct TcpReceiver<T, D>
+enum Listener {
+    /// A TCP socket.
+    Tcp(TcpListener),
+    /// A TCP socket, securing accepted connections with TLS.
+    #[cfg(feature = "tls")]
+    Tls(TcpListener, Arc<Acceptor>),
+    /// A Unix domain socket.
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Accept a connection.
+    fn accept(&self) -> Result<Stream, Error> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (socket, _) = listener.accept()?;
+                Ok(Stream::Plain(socket))
+            }
+            #[cfg(feature = "tls")]
+            Listener::Tls(listener, acceptor) => {
+                let (socket, _) = listener.accept()?;
+                Ok(acceptor.accept(socket))
+            }
+            Listener::Unix(listener) => {
+                let (socket, _) = listener.accept()?;
+                Ok(Stream::Unix(socket))
+            }
+        }
+    }
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Listener::Tcp(listener) => listener.as_raw_fd(),
+            #[cfg(feature = "tls")]
+            Listener::Tls(listener, _) => listener.as_raw_fd(),
+            Listener::Unix(listener) => listener.as_raw_fd(),
+        }
+    }
+}
+
+/// The part of a receiver that is independent of the kind of socket
+/// it listens on.
+#[derive(Debug)]
+struct Receiver<T, D>
 where
     T: Debug + Send,
     D: Debug + Send,
 {
-    /// The TCP receiver's unique ID.
+    /// The receiver's unique ID.
     id: usize,
-    /// The address we are listening on.
-    addr: SocketAddr,
     /// Our listener file descriptor state; shared with the thread
     /// accepting connections.
     fd: Arc<Fd>,
@@ -111,75 +183,69 @@ where
     /// The transaction multiplexer we use to ensure serialization of
     /// transactions from all accepted connections.
     txnmux: Arc<Mutex<TxnMux<T, String>>>,
+    /// The compression we request from senders, if they support it;
+    /// shared with the thread accepting connections.
+    compression: Arc<Mutex<Compression>>,
     _phantom: std::marker::PhantomData<D>,
 }
 
-/// `T` - type received from the network.  This type is not required to implement `Deserialize`.
-/// `D` - a "wrapper" type that implements `Deserialize` and that can be converted into `T`.
-///
-/// `T` and `D` can be the same type.
-///
-/// Using two separate type arguments supports the use case when `Deserialize` implementation
-/// resides outside the crate that declares `T` and is defined over a wrapper type, without
-/// introducing a separate filter to perform the conversion.
-impl<T, D> TcpReceiver<T, D>
+impl<T, D> Receiver<T, D>
 where
     T: Send + Debug + 'static,
     D: DeserializeOwned + Into<T> + Send + Debug,
 {
-    /// Create a new TCP receiver with no observer.
-    ///
-    /// `addr` may have a port set (by setting it to 0). In such a case
-    /// the system will assign a port that is free. To retrieve this
-    /// assigned port (in the form of the full `SocketAddr`), use the
-    /// `addr` method.
-    pub fn new<A>(addr: A) -> Result<Self, String>
-    where
-        A: ToSocketAddrs,
-    {
-        let id = Id::<()>::new().get();
-        trace!("TcpReceiver({})::new", id);
-
-        let listener =
-            TcpListener::bind(addr).map_err(|e| format!("failed to bind TCP socket: {}", e))?;
-        // We want to allow for auto-assigned ports, by letting the user
-        // specify a `SocketAddr` with port 0. In this case, after
-        // actually binding to an address, we need to update the port we
-        // got assigned in `addr`, but for simplicity we just copy the
-        // entire thing.
-        let addr = listener
-            .local_addr()
-            .map_err(|e| format!("failed to inquire local address: {}", e))?;
+    /// Start accepting connections on the given listener, from senders
+    /// running a program with the given schema.
+    fn new(id: usize, listener: Listener, schema: SchemaHash) -> Self {
         let fd = c_uint::try_from(listener.as_raw_fd()).unwrap();
         let fd = Arc::new(Fd::new_unowned(fd));
         let txnmux = Arc::new(Mutex::new(TxnMux::new()));
-        let thread = Some(Self::accept(id, listener, fd.clone(), txnmux.clone()));
+        let senders = Arc::new(Mutex::new(HashMap::new()));
+        let compression = Arc::new(Mutex::new(Compression::None));
+        let thread = Some(Self::accept(
+            id,
+            listener,
+            schema,
+            compression.clone(),
+            fd.clone(),
+            senders,
+            txnmux.clone(),
+        ));
 
-        Ok(Self {
+        Self {
             id,
-            addr,
             fd,
             thread,
             txnmux,
+            compression,
             _phantom: std::marker::PhantomData,
-        })
+        }
+    }
+
+    /// Set the compression senders are to use for connections accepted
+    /// from now on.
+    fn set_compression(&self, compression: Compression) {
+        *self.compression.lock().unwrap() = compression;
     }
 
     /// Accept a connection (in a non-blocking manner), read data from
     /// it, and dispatch that to the transaction multiplexer.
     fn accept(
         id: usize,
-        listener: TcpListener,
+        listener: Listener,
+        schema: SchemaHash,
+        compression: Arc<Mutex<Compression>>,
         fd: Arc<Fd>,
+        senders: Senders,
         txnmux: Arc<Mutex<TxnMux<T, String>>>,
     ) -> JoinHandle<Result<(), String>> {
         spawn(move || {
-            let mut handles = Vec::new();
+            let mut handles: Vec<(JoinHandle<_>, Arc<Fd>, Arc<AtomicBool>)> = Vec::new();
             loop {
-                let socket = match listener.accept() {
-                    Ok((socket, _)) => {
+                let stream = match listener.accept() {
+                    Ok(stream) => {
                         debug!("TcpReceiver({}): accepted connection", id);
-                        socket
+                        stream
                     }
                     Err(e) => {
                         // The user may have dropped the receiver shortly after
@@ -193,28 +259,56 @@ where
                     }
                 };
 
+                // Join the threads of connections that are gone by now,
+                // so that we do not accumulate them.
+                let (done, alive) = handles
+                    .into_iter()
+                    .partition(|(_, _, done)| done.load(Ordering::SeqCst));
+                handles = alive;
+                for (thread, _, _) in done {
+                    let _result = thread.join();
+                    debug_assert!(_result.is_ok(), "processing thread panicked: {:?}", _result);
+                }
+
                 let passthrough = Arc::new(Mutex::new(Passthrough::new()));
                 let observable = Box::new(passthrough.clone());
-                if txnmux.lock().unwrap().add_observable(observable).is_err() {
-                    error!(
-                        "TcpReceiver({}): failed to register connection {} with TxnMux",
-                        id,
-                        socket.as_raw_fd()
-                    );
-                    continue;
-                }
+                let observable = match txnmux.lock().unwrap().add_observable(observable) {
+                    Ok(observable) => observable,
+                    Err(_) => {
+                        error!(
+                            "TcpReceiver({}): failed to register connection {} with TxnMux",
+                            id,
+                            stream.as_raw_fd()
+                        );
+                        continue;
+                    }
+                };
 
-                let fd = c_uint::try_from(socket.as_raw_fd()).unwrap();
+                let header = Header::new(schema);
+                let requested = *compression.lock().unwrap();
+                let fd = c_uint::try_from(stream.as_raw_fd()).unwrap();
                 let fd = Arc::new(Fd::new_unowned(fd));
                 let copy = fd.clone();
-                let thread = spawn(move || Self::process(id, socket, copy, passthrough));
-                handles.push((thread, fd));
+                let senders = senders.clone();
+                let txnmux = txnmux.clone();
+                let done = Arc::new(AtomicBool::new(false));
+                let exited = done.clone();
+                let thread = spawn(move || {
+                    let result =
+                        Self::process(id, stream, header, requested, copy, senders, passthrough);
+                    // The connection is gone, so there is no point in
+                    // the multiplexer tracking it any longer.
+                    txnmux.lock().unwrap().remove_observable(observable);
+                    exited.store(true, Ordering::SeqCst);
+                    result
+                });
+                handles.push((thread, fd, done));
             }
 
             // We only exit above loop when the receiver is dropped and
             // in this case we intend to stop and join all the
             // processing threads we started.
-            for (thread, fd) in handles.into_iter().rev() {
+            for (thread, fd, _) in handles.into_iter().rev() {
                 if let Err(e) = fd.shutdown() {
                     error!(
                         "TcpReceiver({}): failed to shut down TcpReceiver file descriptor: {}",
@@ -235,65 +329,252 @@ where
 
     /// Process data from a `TcpSender`, relaying messages to a
     /// connected `Observer`, if any, or dropping them.
+    ///
+    /// The sender is asked to compress data as `requested`, falling
+    /// back to no compression if it does not support that.
     fn process(
         id: usize,
-        socket: TcpStream,
+        mut stream: Stream,
+        header: Header,
+        requested: Compression,
         fd: Arc<Fd>,
+        senders: Senders,
         mut observer: SharedObserver<Passthrough<T, String>>,
     ) -> Result<(), String> {
-        let mut reader = BufReader::new(socket);
+        if let Err(e) = stream.handshake() {
+            if !fd.is_shutdown() {
+                error!(
+                    "TcpReceiver({}): failed to establish TLS session: {}",
+                    id, e
+                );
+                if let Err(e) = fd.shutdown() {
+                    error!("TcpReceiver({}): failed to shut down socket: {}", id, e);
+                }
+            }
+            return Ok(());
+        }
+
+        let mut reader = BufReader::new(stream);
+        // Both ends start out by exchanging headers, to make sure they
+        // run the same program and speak the same protocol.
+        let remote = match deserialize_from::<_, Header>(&mut reader) {
+            Ok(remote) => remote,
+            Err(e) => {
+                if !fd.is_shutdown() {
+                    debug!("TcpReceiver({}): failed to receive header: {}", id, e);
+                    if let Err(e) = fd.shutdown() {
+                        error!("TcpReceiver({}): failed to shut down socket: {}", id, e);
+                    }
+                }
+                return Ok(());
+            }
+        };
+        // We send our header even if we are about to reject the sender,
+        // so that it can tell why.
+        Self::send_header(id, reader.get_mut(), &header);
+        if let Err(e) = header.check(&remote) {
+            error!("TcpReceiver({}): rejecting connection: {}", id, e);
+            if let Err(e) = fd.shutdown() {
+                error!("TcpReceiver({}): failed to shut down socket: {}", id, e);
+            }
+            return Ok(());
+        }
+
+        // The sender introduces itself first, so that we can tell it
+        // which transactions we have seen already and which of the
+        // codecs it supports to use.
+        let (last, compression) = match Self::receive(id, &mut reader, &fd) {
+            Some(Message::Hello(uuid, codecs)) => {
+                let last = senders.lock().unwrap().entry(uuid).or_default().clone();
+                let compression = if codecs.contains(&requested.id()) {
+                    requested
+                } else {
+                    debug!(
+                        "TcpReceiver({}): sender does not support {:?} compression",
+                        id, requested
+                    );
+                    Compression::None
+                };
+                (last, compression)
+            }
+            Some(message) => {
+                error!(
+                    "TcpReceiver({}): expected hello message but received {}",
+                    id, message
+                );
+                if let Err(e) = fd.shutdown() {
+                    error!("TcpReceiver({}): failed to shut down socket: {}", id, e);
+                }
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+
+        let seq = *last.lock().unwrap();
+        Self::welcome(id, reader.get_mut(), compression, seq);
+
+        // Everything the sender sends from here on is compressed as
+        // requested in our welcome message.
+        let mut reader = Decompressor::new(reader, compression);
+
+        let mut txn = LinkedList::new();
+        while let Some(message) = Self::receive(id, &mut reader, &fd) {
+            let (seq, result) = match message {
+                Message::Start => {
+                    txn.clear();
+                    continue;
+                }
+                Message::Updates(updates) => {
+                    txn.push_back(updates);
+                    continue;
+                }
+                Message::UpdateList(mut updates) => {
+                    txn.append(&mut updates);
+                    continue;
+                }
+                Message::Commit(seq) => {
+                    let updates = txn.split_off(0);
+                    // We hold the lock while relaying the transaction,
+                    // in case a previous connection of the same sender
+                    // is still around.
+                    let mut last = last.lock().unwrap();
+                    let result = if seq > *last {
+                        let result = observer.on_start().and_then(|_| {
+                            if !updates.is_empty() {
+                                let updates = updates.into_iter().flatten().map(|u| u.into());
+                                observer.on_updates(Box::new(updates))?;
+                            }
+                            observer.on_commit()
+                        });
+                        if result.is_ok() {
+                            *last = seq;
+                        }
+                        Some(result)
+                    } else {
+                        None
+                    };
+                    (seq, result)
+                }
+                Message::Complete(seq) => {
+                    let mut last = last.lock().unwrap();
+                    let result = if seq > *last {
+                        let result = observer.on_completed();
+                        if result.is_ok() {
+                            *last = seq;
+                        }
+                        Some(result)
+                    } else {
+                        None
+                    };
+                    (seq, result)
+                }
+                Message::Hello(..) | Message::Welcome(..) | Message::Ack(_) => {
+                    error!(
+                        "TcpReceiver({}): received unexpected {} message",
+                        id, message
+                    );
+                    continue;
+                }
+            };
+
+            match result {
+                Some(Err(e)) => {
+                    // We neither acknowledge the event nor remember
+                    // having seen it, but hang up instead, so that the
+                    // sender connects again and replays it.
+                    error!(
+                        "TcpReceiver({}): observer {:?} failed to process event {}: {}",
+                        id, observer, seq, e
+                    );
+                    if let Err(e) = fd.shutdown() {
+                        error!("TcpReceiver({}): failed to shut down socket: {}", id, e);
+                    }
+                    break;
+                }
+                Some(Ok(())) => (),
+                None => debug!("TcpReceiver({}): dropping duplicate event {}", id, seq),
+            }
+            Self::acknowledge(id, reader.get_mut().get_mut(), seq);
+        }
+        Ok(())
+    }
+
+    /// Receive the next message from a `TcpSender`.
+    ///
+    /// `None` is returned once the connection is gone, in which case
+    /// the socket has been shut down.
+    fn receive<R>(id: usize, reader: &mut R, fd: &Fd) -> Option<Message<D>>
+    where
+        R: Read,
+    {
         loop {
-            let mut message: Message<D> = match deserialize_from(&mut reader) {
-                Ok(m) => m,
+            match deserialize_from(&mut *reader) {
+                Ok(message) => return Some(message),
                 Err(e) => {
                     if fd.is_shutdown() {
-                        return Ok(());
+                        return None;
                     }
                     match *e {
                         // It is possible that the sender was actually
-                        // closed and in this case there is nothing more
-                        // for us to do. So return early.
-                        BincodeError::Io(ref e) if e.kind() == ErrorKind::UnexpectedEof => {
+                        // closed or that the connection broke down. In
+                        // either case there is nothing more for us to
+                        // do; the sender will connect again if needed.
+                        BincodeError::Io(ref e) => {
+                            if e.kind() != ErrorKind::UnexpectedEof {
+                                debug!("TcpReceiver({}): failed to receive message: {}", id, e);
+                            }
                             if let Err(e) = fd.shutdown() {
                                 error!("TcpReceiver({}): failed to shut down socket: {}", id, e);
                             }
-                            return Ok(());
+                            return None;
                         }
                         _ => error!("TcpReceiver({}): failed to deserialize message: {}", id, e),
                     }
-                    continue;
                 }
-            };
+            }
+        }
+    }
 
-            let result = match message {
-                Message::Start => observer.on_start(),
-                Message::Updates(ref mut updates) => {
-                    observer.on_updates(Box::new(updates.drain(..).map(|u| u.into())))
-                }
-                Message::UpdateList(ref mut updates) => observer.on_updates(Box::new(
-                    updates.split_off(0).into_iter().flatten().map(|u| u.into()),
-                )),
-                Message::Commit => observer.on_commit(),
-                Message::Complete => observer.on_completed(),
-            };
+    /// Send our header to the sender.
+    ///
+    /// Failure to do so is not fatal by itself: the sender will just
+    /// connect again.
+    fn send_header(id: usize, stream: &mut Stream, header: &Header) {
+        let data = serialize(header).unwrap();
+        if let Err(e) = stream.write_all(&data).and_then(|_| stream.flush()) {
+            debug!("TcpReceiver({}): failed to send header: {}", id, e);
+        }
+    }
 
-            if let Err(e) = result {
-                error!(
-                    "TcpReceiver({}): observer {:?} failed to process {} event: {}",
-                    id, observer, message, e
-                );
-            }
+    /// Tell the sender which compression to use and acknowledge all
+    /// events up to and including the given sequence number.
+    ///
+    /// Failure to do so is not fatal by itself: the sender will just
+    /// connect again.
+    fn welcome(id: usize, stream: &mut Stream, compression: Compression, seq: u64) {
+        let welcome = serialize(&Message::<()>::Welcome(compression.id(), seq)).unwrap();
+        if let Err(e) = stream.write_all(&welcome).and_then(|_| stream.flush()) {
+            debug!("TcpReceiver({}): failed to send welcome message: {}", id, e);
         }
     }
 
-    /// Retrieve the address we are listening on.
-    pub fn addr(&self) -> &SocketAddr {
-        trace!("TcpReceiver({})::addr: {}", self.id, &self.addr);
-        &self.addr
+    /// Acknowledge all events up to and including the given sequence
+    /// number.
+    ///
+    /// Failure to do so is not fatal: the sender will just send the
+    /// events again.
+    fn acknowledge(id: usize, stream: &mut Stream, seq: u64) {
+        let ack = serialize(&Message::<()>::Ack(seq)).unwrap();
+        if let Err(e) = stream.write_all(&ack).and_then(|_| stream.flush()) {
+            debug!(
+                "TcpReceiver({}): failed to acknowledge event {}: {}",
+                id, seq, e
+            );
+        }
     }
 }
 
-impl<T, D> Drop for TcpReceiver<T, D>
+impl<T, D> Drop for Receiver<T, D>
 where
     T: Debug + Send,
     D: Debug + Send,
@@ -320,7 +601,7 @@ where
     }
 }
 
-impl<T, D> Observable<T, String> for TcpReceiver<T, D>
+impl<T, D> Observable<T, String> for Receiver<T, D>
 where
     T: Debug + Send + 'static,
     D: Debug + Send,
@@ -347,14 +628,253 @@ where
     }
 }
 
+/// The receiving end of a TCP channel has an address
+/// and streams data to an observer.
+///
+/// Transactions are only relayed once committed and every transaction
+/// is acknowledged to the sender. Transactions received again after the
+/// sender reconnected are dropped.
+#[derive(Debug)]
+pub struct TcpReceiver<T, D>
+where
+    T: Debug + Send,
+    D: Debug + Send,
+{
+    /// The address we are listening on.
+    addr: SocketAddr,
+    /// The receiver doing the actual work.
+    receiver: Receiver<T, D>,
+}
+
+/// `T` - type received from the network.  This type is not required to implement `Deserialize`.
+/// `D` - a "wrapper" type that implements `Deserialize` and that can be converted into `T`.
+///
+/// `T` and `D` can be the same type.
+///
+/// Using two separate type arguments supports the use case when `Deserialize` implementation
+/// resides outside the crate that declares `T` and is defined over a wrapper type, without
+/// introducing a separate filter to perform the conversion.
+impl<T, D> TcpReceiver<T, D>
+where
+    T: Send + Debug + 'static,
+    D: DeserializeOwned + Into<T> + Send + Debug,
+{
+    /// Create a new TCP receiver with no observer.
+    ///
+    /// `addr` may have a port set (by setting it to 0). In such a case
+    /// the system will assign a port that is free. To retrieve this
+    /// assigned port (in the form of the full `SocketAddr`), use the
+    /// `addr` method.
+    ///
+    /// Connections from senders running a program with a schema other
+    /// than the given one are rejected.
+    pub fn new<A>(addr: A, schema: SchemaHash) -> Result<Self, String>
+    where
+        A: ToSocketAddrs,
+    {
+        Self::create(addr, schema, Listener::Tcp)
+    }
+
+    /// Create a new TCP receiver with no observer that only accepts
+    /// TLS secured connections from senders authenticating themselves
+    /// with a certificate signed by the authority in the given
+    /// credentials.
+    ///
+    /// See `new` for details on `addr` and `schema`.
+    #[cfg(feature = "tls")]
+    pub fn with_tls<A>(addr: A, schema: SchemaHash, tls: &TlsCfg) -> Result<Self, String>
+    where
+        A: ToSocketAddrs,
+    {
+        let acceptor = Arc::new(
+            Acceptor::new(tls).map_err(|e| format!("failed to load TLS credentials: {}", e))?,
+        );
+        Self::create(addr, schema, |listener| Listener::Tls(listener, acceptor))
+    }
+
+    /// Create a new TCP receiver, accepting connections on the
+    /// `Listener` created by `listener` from the bound socket.
+    fn create<A, F>(addr: A, schema: SchemaHash, listener: F) -> Result<Self, String>
+    where
+        A: ToSocketAddrs,
+        F: FnOnce(TcpListener) -> Listener,
+    {
+        let id = Id::<()>::new().get();
+        trace!("TcpReceiver({})::new", id);
+
+        let tcp_listener =
+            TcpListener::bind(addr).map_err(|e| format!("failed to bind TCP socket: {}", e))?;
+        // We want to allow for auto-assigned ports, by letting the user
+        // specify a `SocketAddr` with port 0. In this case, after
+        // actually binding to an address, we need to update the port we
+        // got assigned in `addr`, but for simplicity we just copy the
+        // entire thing.
+        let addr = tcp_listener
+            .local_addr()
+            .map_err(|e| format!("failed to inquire local address: {}", e))?;
+
+        Ok(Self {
+            addr,
+            receiver: Receiver::new(id, listener(tcp_listener), schema),
+        })
+    }
+
+    /// Set the compression senders are to use for connections accepted
+    /// from now on. By default, and with senders that do not support
+    /// it, data is sent uncompressed.
+    pub fn set_compression(&mut self, compression: Compression) {
+        trace!(
+            "TcpReceiver({})::set_compression({:?})",
+            self.receiver.id,
+            compression
+        );
+        self.receiver.set_compression(compression)
+    }
+
+    /// Retrieve the address we are listening on.
+    pub fn addr(&self) -> &SocketAddr {
+        trace!("TcpReceiver({})::addr: {}", self.receiver.id, &self.addr);
+        &self.addr
+    }
+}
+
+impl<T, D> Observable<T, String> for TcpReceiver<T, D>
+where
+    T: Debug + Send + 'static,
+    D: Debug + Send,
+{
+    type Subscription = ();
+
+    /// An observer subscribes to the receiving end of a TCP channel to
+    /// listen to incoming data.
+    fn subscribe(
+        &mut self,
+        observer: ObserverBox<T, String>,
+    ) -> Result<Self::Subscription, ObserverBox<T, String>> {
+        self.receiver.subscribe(observer)
+    }
+
+    /// Unsubscribe a previously subscribed `Observer` based on a
+    /// subscription.
+    fn unsubscribe(&mut self, subscription: &Self::Subscription) -> Option<ObserverBox<T, String>> {
+        self.receiver.unsubscribe(subscription)
+    }
+}
+
+/// The receiving end of a channel over a Unix domain socket, for
+/// senders on the same machine.
+///
+/// It provides the same guarantees as a `TcpReceiver`. The socket file
+/// is removed when the receiver is dropped.
+#[derive(Debug)]
+pub struct UdsReceiver<T, D>
+where
+    T: Debug + Send,
+    D: Debug + Send,
+{
+    /// The path of the socket we are listening on.
+    path: PathBuf,
+    /// The receiver doing the actual work.
+    receiver: Receiver<T, D>,
+}
+
+/// See `TcpReceiver` for the meaning of `T` and `D`.
+impl<T, D> UdsReceiver<T, D>
+where
+    T: Send + Debug + 'static,
+    D: DeserializeOwned + Into<T> + Send + Debug,
+{
+    /// Create a new Unix domain socket receiver with no observer,
+    /// listening on the given path.
+    ///
+    /// The path must not exist yet. See `TcpReceiver::new` for details
+    /// on `schema`.
+    pub fn new<P>(path: P, schema: SchemaHash) -> Result<Self, String>
+    where
+        P: Into<PathBuf>,
+    {
+        let id = Id::<()>::new().get();
+        let path = path.into();
+        trace!("UdsReceiver({})::new({})", id, path.display());
+
+        let listener = UnixListener::bind(&path).map_err(|e| {
+            format!(
+                "failed to bind Unix domain socket {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+
+        Ok(Self {
+            path,
+            receiver: Receiver::new(id, Listener::Unix(listener), schema),
+        })
+    }
+
+    /// Set the compression senders are to use for connections accepted
+    /// from now on. By default, and with senders that do not support
+    /// it, data is sent uncompressed.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.receiver.set_compression(compression)
+    }
+
+    /// Retrieve the path we are listening on.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl<T, D> Drop for UdsReceiver<T, D>
+where
+    T: Debug + Send,
+    D: Debug + Send,
+{
+    fn drop(&mut self) {
+        if let Err(e) = remove_file(&self.path) {
+            error!(
+                "failed to remove Unix domain socket {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+impl<T, D> Observable<T, String> for UdsReceiver<T, D>
+where
+    T: Debug + Send + 'static,
+    D: Debug + Send,
+{
+    type Subscription = ();
+
+    /// An observer subscribes to the receiving end of a Unix domain
+    /// socket channel to listen to incoming data.
+    fn subscribe(
+        &mut self,
+        observer: ObserverBox<T, String>,
+    ) -> Result<Self::Subscription, ObserverBox<T, String>> {
+        self.receiver.subscribe(observer)
+    }
+
+    /// Unsubscribe a previously subscribed `Observer` based on a
+    /// subscription.
+    fn unsubscribe(&mut self, subscription: &Self::Subscription) -> Option<ObserverBox<T, String>> {
+        self.receiver.unsubscribe(subscription)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::io::ErrorKind;
     use std::io::Read;
+    use std::mem;
     use std::net::TcpStream;
 
+    use bincode::serialize_into;
+    use tempfile::tempdir;
+
     use test_env_log::test;
 
     use crate::await_expected;
@@ -364,13 +884,13 @@ mod tests {
     /// Drop a `TcpReceiver`.
     #[test]
     fn drop() {
-        let _recv = TcpReceiver::<(), ()>::new("127.0.0.1:0").unwrap();
+        let _recv = TcpReceiver::<(), ()>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
     }
 
     /// Connect to a `TcpReceiver`.
     #[test]
     fn accept() {
-        let recv = TcpReceiver::<(), ()>::new("127.0.0.1:0").unwrap();
+        let recv = TcpReceiver::<(), ()>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
         {
             let _send = TcpStream::connect(recv.addr()).unwrap();
         }
@@ -382,7 +902,8 @@ mod tests {
     fn never_accepted() {
         let test = || {
             let addr = {
-                let recv = TcpReceiver::<(), ()>::new("127.0.0.1:0").unwrap();
+                let recv =
+                    TcpReceiver::<(), ()>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
                 recv.addr().clone()
             };
 
@@ -413,7 +934,7 @@ mod tests {
     #[test]
     fn sender_cleanup() {
         let mut send = {
-            let recv = TcpReceiver::<(), ()>::new("127.0.0.1:0").unwrap();
+            let recv = TcpReceiver::<(), ()>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
             let send = TcpStream::connect(recv.addr()).unwrap();
             send
         };
@@ -428,11 +949,11 @@ mod tests {
     #[test]
     fn multiple_senders() {
         let mock = Arc::new(Mutex::new(MockObserver::new()));
-        let mut recv = TcpReceiver::<u64, u64>::new("127.0.0.1:0").unwrap();
+        let mut recv = TcpReceiver::<u64, u64>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
         let addr = recv.addr();
-        let mut send1 = TcpSender::<u64>::new(*addr).unwrap();
-        let mut send2 = TcpSender::<u64>::new(*addr).unwrap();
-        let mut send3 = TcpSender::<u64>::new(*addr).unwrap();
+        let mut send1 = TcpSender::<u64>::new(*addr, SchemaHash::default()).unwrap();
+        let mut send2 = TcpSender::<u64>::new(*addr, SchemaHash::default()).unwrap();
+        let mut send3 = TcpSender::<u64>::new(*addr, SchemaHash::default()).unwrap();
 
         recv.subscribe(Box::new(mock.clone())).unwrap();
 
@@ -488,4 +1009,165 @@ mod tests {
             assert_eq!(on_commit, 3);
         });
     }
+
+    /// Connect to a receiver, introducing ourselves as `uuid`, and
+    /// check which sequence number it acknowledges.
+    fn connect(addr: &SocketAddr, uuid: Uuid, expected: u64) -> TcpStream {
+        let header = Header::new(SchemaHash::default());
+        let mut stream = TcpStream::connect(addr).unwrap();
+        serialize_into(&mut stream, &header).unwrap();
+        let hello = Message::<u64>::Hello(uuid, vec![Compression::None.id()]);
+        serialize_into(&mut stream, &hello).unwrap();
+        let remote = deserialize_from::<_, Header>(&mut stream).unwrap();
+        assert_eq!(remote, header);
+        let welcome = deserialize_from::<_, Message<u64>>(&mut stream).unwrap();
+        assert_eq!(welcome, Message::Welcome(Compression::None.id(), expected));
+        stream
+    }
+
+    /// Send a transaction with the given sequence number.
+    fn commit(stream: &mut TcpStream, seq: u64) {
+        let messages = vec![
+            Message::Start,
+            Message::Updates(vec![seq]),
+            Message::Commit(seq),
+        ];
+        for message in messages {
+            serialize_into(&mut *stream, &message).unwrap();
+        }
+    }
+
+    /// Send a transaction with the given sequence number and wait for
+    /// its acknowledgement.
+    fn send(stream: &mut TcpStream, seq: u64) {
+        commit(stream, seq);
+        let ack = deserialize_from::<_, Message<u64>>(&mut *stream).unwrap();
+        assert_eq!(ack, Message::Ack(seq));
+    }
+
+    /// Check that a receiver falls back to no compression for senders
+    /// not supporting the one it requests.
+    #[cfg(feature = "compression")]
+    #[test]
+    fn unsupported_compression() {
+        let mut recv = TcpReceiver::<u64, u64>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
+        recv.set_compression(Compression::Zstd);
+
+        let header = Header::new(SchemaHash::default());
+        let mut stream = TcpStream::connect(recv.addr()).unwrap();
+        serialize_into(&mut stream, &header).unwrap();
+        let hello = Message::<u64>::Hello(Uuid::new_v4(), vec![Compression::Lz4.id(), 42]);
+        serialize_into(&mut stream, &hello).unwrap();
+        let _ = deserialize_from::<_, Header>(&mut stream).unwrap();
+        let welcome = deserialize_from::<_, Message<u64>>(&mut stream).unwrap();
+        assert_eq!(welcome, Message::Welcome(Compression::None.id(), 0));
+    }
+
+    /// Check that transactions sent again by a sender after it
+    /// reconnected are acknowledged but not delivered twice.
+    #[test]
+    fn duplicate_transactions() {
+        let mock = Arc::new(Mutex::new(MockObserver::new()));
+        let mut recv = TcpReceiver::<u64, u64>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
+        recv.subscribe(Box::new(mock.clone())).unwrap();
+
+        let uuid = Uuid::new_v4();
+        let mut first = connect(recv.addr(), uuid, 0);
+        send(&mut first, 1);
+        send(&mut first, 2);
+
+        // Pretend the acknowledgement for the second transaction got
+        // lost along with the connection. The sender connects again and
+        // sends it again, along with a new one.
+        mem::drop(first);
+        let mut second = connect(recv.addr(), uuid, 2);
+        send(&mut second, 2);
+        send(&mut second, 3);
+
+        // The receiver keeps remembering what it delivered, however
+        // often the sender connects again.
+        mem::drop(second);
+        let mut stream = connect(recv.addr(), uuid, 3);
+        send(&mut stream, 3);
+
+        // A different sender starts out fresh.
+        let mut stream = connect(recv.addr(), Uuid::new_v4(), 0);
+        send(&mut stream, 1);
+
+        let guard = mock.lock().unwrap();
+        assert_eq!(guard.called_on_start, 4);
+        assert_eq!(guard.called_on_updates, 4);
+        assert_eq!(guard.called_on_commit, 4);
+    }
+
+    /// Check that a transaction the observer failed to process is
+    /// neither acknowledged nor remembered, so that it gets replayed.
+    #[test]
+    fn observer_failure() {
+        /// An observer failing to commit the first transaction.
+        #[derive(Debug, Default)]
+        struct FailFirst {
+            failed: bool,
+            committed: Vec<u64>,
+            updates: Vec<u64>,
+        }
+
+        impl Observer<u64, String> for FailFirst {
+            fn on_start(&mut self) -> Result<(), String> {
+                self.updates.clear();
+                Ok(())
+            }
+
+            fn on_commit(&mut self) -> Result<(), String> {
+                if !self.failed {
+                    self.failed = true;
+                    return Err("failing first commit".to_string());
+                }
+                self.committed.append(&mut self.updates);
+                Ok(())
+            }
+
+            fn on_updates<'a>(
+                &mut self,
+                updates: Box<dyn Iterator<Item = u64> + 'a>,
+            ) -> Result<(), String> {
+                self.updates.extend(updates);
+                Ok(())
+            }
+
+            fn on_completed(&mut self) -> Result<(), String> {
+                Ok(())
+            }
+        }
+
+        let observer = Arc::new(Mutex::new(FailFirst::default()));
+        let mut recv = TcpReceiver::<u64, u64>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
+        recv.subscribe(Box::new(observer.clone())).unwrap();
+
+        let uuid = Uuid::new_v4();
+        let mut stream = connect(recv.addr(), uuid, 0);
+        commit(&mut stream, 1);
+        // Instead of an acknowledgement we expect the receiver to hang
+        // up on us.
+        assert!(deserialize_from::<_, Message<u64>>(&mut stream).is_err());
+
+        let mut stream = connect(recv.addr(), uuid, 0);
+        send(&mut stream, 1);
+        assert_eq!(observer.lock().unwrap().committed, vec![1]);
+    }
+
+    /// Check that only a single `UdsReceiver` can listen on a path and
+    /// that the socket is removed once it is dropped.
+    #[test]
+    fn uds_socket_cleanup() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("socket");
+        {
+            let recv = UdsReceiver::<(), ()>::new(&path, SchemaHash::default()).unwrap();
+            assert_eq!(recv.path(), path.as_path());
+            assert!(path.exists());
+            assert!(UdsReceiver::<(), ()>::new(&path, SchemaHash::default()).is_err());
+        }
+        assert!(!path.exists());
+    }
 }
@@ -1,26 +1,134 @@
+use std::cmp::min;
+use std::convert::TryFrom;
+use std::convert::TryInto;
 use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
 use std::io::BufWriter;
 use std::io::Error;
+use std::io::ErrorKind;
+use std::io::Write;
 use std::net::SocketAddr;
-use std::net::TcpStream;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Condvar;
 use std::sync::Mutex;
 use std::thread::spawn;
 use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
 
+use bincode::deserialize_from;
+use bincode::serialize;
+use bincode::ErrorKind as BincodeError;
+use libc::c_uint;
 use log::debug;
 use log::error;
 use log::trace;
 use serde::Serialize;
 use uid::Id;
+use uuid::Uuid;
 
 use crate::observe::Observer;
+#[cfg(feature = "tls")]
+use crate::schema::TlsCfg;
+use crate::tcp_channel::compress::Compression;
+use crate::tcp_channel::compress::Compressor;
+use crate::tcp_channel::handshake::Header;
+use crate::tcp_channel::handshake::SchemaHash;
+use crate::tcp_channel::message::Message;
 use crate::tcp_channel::socket::Cancelable;
+use crate::tcp_channel::socket::Fd;
+use crate::tcp_channel::socket::ShutdownExt;
 use crate::tcp_channel::socket::Socket;
+use crate::tcp_channel::stream::Stream;
+#[cfg(feature = "tls")]
+use crate::tcp_channel::tls::Connector;
 use crate::tcp_channel::txnbuf::TxnBuf;
 
+/// The time we wait before the first attempt to reconnect.
+const MIN_BACKOFF: Duration = Duration::from_millis(50);
+/// The maximum time we wait between two connection attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+/// The interval in which we check whether the connection broke down
+/// while waiting for acknowledgements.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// The maximum time we wait for outstanding acknowledgements when the
+/// sender is dropped.
+const LINGER: Duration = Duration::from_secs(5);
+
+/// The writer we use for sending data to the receiver.
+type Writer = BufWriter<Compressor<Stream>>;
+/// The transaction buffer, shared with the connection thread.
+type Buffer<T> = Arc<Mutex<TxnBuf<Writer, T>>>;
+/// The connection state, shared with the connection thread.
+type Shared = Arc<(Mutex<State>, Condvar)>;
+
+/// The state of the connection to the receiver.
+#[derive(Debug, Default)]
+struct State {
+    /// Whether the sender has been dropped.
+    stopped: bool,
+    /// Whether we are currently connected to the receiver.
+    connected: bool,
+    /// A fatal error that caused us to give up on connecting.
+    error: Option<String>,
+    /// A cancellation handle we can use for canceling an ongoing
+    /// connect.
+    cancel: Option<Cancelable>,
+    /// The file descriptor of the current connection, if any.
+    fd: Option<Arc<Fd>>,
+}
+
+/// The receiver we connect to.
+#[derive(Debug)]
+enum Target {
+    /// A receiver listening on a TCP socket.
+    Tcp(SocketAddr),
+    /// A receiver listening on a TCP socket and requiring a TLS secured
+    /// connection.
+    #[cfg(feature = "tls")]
+    Tls(SocketAddr, Connector),
+    /// A receiver listening on a Unix domain socket.
+    Unix(PathBuf),
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Target::Tcp(addr) => write!(f, "{}", addr),
+            #[cfg(feature = "tls")]
+            Target::Tls(addr, _) => write!(f, "{}", addr),
+            Target::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// The ways in which an attempt to connect can fail.
+#[derive(Debug)]
+enum Failure {
+    /// A failure after which we retry connecting.
+    Transient(String),
+    /// A failure that retrying is not going to fix.
+    Fatal(String),
+}
+
 /// The sending end of a TCP channel with a specified address and a TCP
 /// connection.
+///
+/// Transactions are retained until the receiver acknowledged them.
+/// Should the connection break down, the sender reconnects (with
+/// exponential backoff) and sends all transactions that have not been
+/// acknowledged again. The receiver uses the sequence numbers of the
+/// transactions to drop those it has seen already, so that each
+/// transaction is delivered exactly once. At most `MAX_UNACKED`
+/// transactions are retained; starting another one fails until the
+/// receiver acknowledged some of them.
+///
+/// Data is compressed using the codec the receiver picked from those
+/// we support during the handshake.
 #[derive(Debug)]
 pub struct TcpSender<T>
 where
@@ -30,12 +138,11 @@ where
     id: usize,
     /// The buffer we use for buffering transactions or pushing them out
     /// over the wire.
-    buffer: Arc<Mutex<TxnBuf<BufWriter<TcpStream>, T>>>,
-    /// A cancellation handle we can use for canceling an ongoing
-    /// connect.
-    cancel: Cancelable,
-    /// The thread attempting to establish a connection to a receiver.
-    thread: Option<JoinHandle<Result<(), String>>>,
+    buffer: Buffer<T>,
+    /// The state of the connection to the receiver.
+    shared: Shared,
+    /// The thread maintaining the connection to the receiver.
+    thread: Option<JoinHandle<()>>,
 }
 
 impl<T> TcpSender<T>
@@ -43,47 +150,315 @@ where
     T: Debug + Send + Serialize + 'static,
 {
     /// Create a new `TcpSender`, connecting to the given address.
-    pub fn new(addr: SocketAddr) -> Result<Self, Error> {
+    ///
+    /// The connection is refused unless the receiver runs a program
+    /// with the given schema.
+    pub fn new(addr: SocketAddr, schema: SchemaHash) -> Result<Self, Error> {
+        Self::create(Target::Tcp(addr), schema)
+    }
+
+    /// Create a new `TcpSender`, connecting to the given address and
+    /// securing the connection with TLS, using the given credentials
+    /// for authenticating both ends.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(addr: SocketAddr, schema: SchemaHash, tls: &TlsCfg) -> Result<Self, Error> {
+        Self::create(Target::Tls(addr, Connector::new(tls)?), schema)
+    }
+
+    /// Create a new `TcpSender` connecting to the given target.
+    fn create(target: Target, schema: SchemaHash) -> Result<Self, Error> {
         let id = Id::<()>::new().get();
-        trace!("TcpSender({})::new({})", id, addr);
+        trace!("TcpSender({})::new({}, {})", id, target, schema);
 
+        let header = Header::new(schema);
         let buffer = Arc::new(Mutex::new(TxnBuf::default()));
-        let socket = Socket::new()?;
-        let cancel = socket.to_cancelable();
-        let thread = Some(Self::connect(id, socket, addr, buffer.clone()));
+        let shared = Arc::new((Mutex::new(State::default()), Condvar::new()));
+        let thread = {
+            let buffer = buffer.clone();
+            let shared = shared.clone();
+            spawn(move || Self::run(id, target, header, buffer, shared))
+        };
 
         Ok(Self {
             id,
             buffer,
-            cancel,
-            thread,
+            shared,
+            thread: Some(thread),
         })
     }
 
-    /// Start a thread attempting to connect to the given address.
+    /// Maintain a connection to the given target until the sender is
+    /// dropped.
+    fn run(id: usize, target: Target, header: Header, buffer: Buffer<T>, shared: Shared) {
+        // The ID identifying us to the receiver across connections.
+        let uuid = Uuid::new_v4();
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            match Self::connect(id, &target, &header, uuid, &buffer, &shared) {
+                Ok(fd) => {
+                    debug!("TcpSender({}): connected to {}", id, target);
+                    backoff = MIN_BACKOFF;
+                    Self::serve(id, &fd, &buffer, &shared);
+                    debug!("TcpSender({}): disconnected from {}", id, target);
+                }
+                Err(Failure::Transient(e)) => debug!("{}", e),
+                Err(Failure::Fatal(e)) => {
+                    error!("{}", e);
+                    let (lock, cvar) = &*shared;
+                    lock.lock().unwrap().error = Some(e);
+                    cvar.notify_all();
+                    return;
+                }
+            }
+            Self::disconnect(id, &buffer, &shared);
+
+            let (lock, cvar) = &*shared;
+            let state = lock.lock().unwrap();
+            let (state, _) = cvar
+                .wait_timeout_while(state, backoff, |state| !state.stopped)
+                .unwrap();
+            if state.stopped {
+                return;
+            }
+            backoff = min(backoff * 2, MAX_BACKOFF);
+        }
+    }
+
+    /// Connect to the given target and hand the resulting connection
+    /// to the buffer, after the receiver told us which transactions it
+    /// has seen already.
     fn connect(
         id: usize,
-        socket: Socket,
-        addr: SocketAddr,
-        buffer: Arc<Mutex<TxnBuf<BufWriter<TcpStream>, T>>>,
-    ) -> JoinHandle<Result<(), String>> {
-        spawn(move || {
-            let stream = socket
-                .connect(&addr)
-                .map_err(|e| format!("TcpSender({}): failed to connect to {}: {}", id, addr, e))?;
-            debug!("TcpSender({}): connected to {}", id, addr);
-
-            let buffer = &mut buffer.lock().unwrap();
-            buffer
-                .set_mode_passthrough(BufWriter::new(stream))
-                .map_err(|e| {
-                    format!(
-                        "TcpSender({}): failed to flush cached transactions: {}",
-                        id, e
-                    )
-                })?;
-            Ok(())
-        })
+        target: &Target,
+        header: &Header,
+        uuid: Uuid,
+        buffer: &Buffer<T>,
+        shared: &Shared,
+    ) -> Result<Arc<Fd>, Failure> {
+        let transient = |what: &str, e: String| {
+            Failure::Transient(format!(
+                "TcpSender({}): failed to {} {}: {}",
+                id, what, target, e
+            ))
+        };
+        let stopped = || Failure::Transient(format!("TcpSender({}): stopped", id));
+
+        let socket = match target {
+            Target::Unix(..) => Socket::new_unix(),
+            _ => Socket::new(),
+        }
+        .map_err(|e| transient("create socket for", e.to_string()))?;
+        {
+            let mut state = shared.0.lock().unwrap();
+            if state.stopped {
+                return Err(stopped());
+            }
+            state.cancel = Some(socket.to_cancelable());
+        }
+
+        let mut stream = match target {
+            Target::Tcp(addr) => socket
+                .connect(addr)
+                .map(Stream::Plain)
+                .map_err(|e| transient("connect to", e.to_string()))?,
+            #[cfg(feature = "tls")]
+            Target::Tls(addr, connector) => socket
+                .connect(addr)
+                .map(|socket| connector.connect(socket))
+                .map_err(|e| transient("connect to", e.to_string()))?,
+            Target::Unix(path) => socket
+                .connect_unix(path)
+                .map(Stream::Unix)
+                .map_err(|e| transient("connect to", e.to_string()))?,
+        };
+        let fd = c_uint::try_from(stream.as_raw_fd()).unwrap();
+        let fd = Arc::new(Fd::new_unowned(fd));
+        {
+            let mut state = shared.0.lock().unwrap();
+            state.cancel = None;
+            if state.stopped {
+                return Err(stopped());
+            }
+            state.fd = Some(fd.clone());
+        }
+
+        let result = stream
+            .handshake()
+            .map_err(|e| {
+                // Failure to authenticate the receiver (or us being
+                // rejected by it) is not going to go away by retrying.
+                if e.kind() == ErrorKind::InvalidData {
+                    Failure::Fatal(format!(
+                        "TcpSender({}): failed to establish TLS session with {}: {}",
+                        id, target, e
+                    ))
+                } else {
+                    transient("establish TLS session with", e.to_string())
+                }
+            })
+            .and_then(|_| {
+                let header = serialize(header).unwrap();
+                // The receiver picks the compression to use from the
+                // codecs we support.
+                let hello = Message::<()>::Hello(uuid, Compression::supported());
+                let hello = serialize(&hello).unwrap();
+                stream
+                    .write_all(&header)
+                    .and_then(|_| stream.write_all(&hello))
+                    .and_then(|_| stream.flush())
+                    .map_err(|e| transient("send hello message to", e.to_string()))
+            })
+            .and_then(|_| match deserialize_from::<_, Header>(&mut stream) {
+                // A receiver running an incompatible program is not
+                // going to change its mind either.
+                Ok(remote) => header.check(&remote).map_err(|e| {
+                    Failure::Fatal(format!(
+                        "TcpSender({}): refusing to connect to {}: {}",
+                        id, target, e
+                    ))
+                }),
+                Err(e) => Err(transient("receive header from", e.to_string())),
+            })
+            .and_then(|_| match deserialize_from(&mut stream) {
+                // We only advertised codecs we support, so a receiver
+                // picking another one is not going to change its mind.
+                Ok(Message::<()>::Welcome(codec, acked)) => Compression::from_id(codec)
+                    .map(|compression| (compression, acked))
+                    .map_err(|e| {
+                        Failure::Fatal(format!(
+                            "TcpSender({}): refusing to connect to {}: receiver requested unsupported compression: {}",
+                            id, target, e
+                        ))
+                    }),
+                Ok(msg) => Err(transient(
+                    "receive welcome message from",
+                    format!("unexpected {} message", msg),
+                )),
+                Err(e) => Err(transient("receive welcome message from", e.to_string())),
+            });
+
+        let (compression, acked) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                // Make sure nobody shuts down the file descriptor once
+                // we closed it.
+                shared.0.lock().unwrap().fd = None;
+                return Err(e);
+            }
+        };
+
+        buffer
+            .lock()
+            .unwrap()
+            .set_writer(BufWriter::new(Compressor::new(stream, compression)), acked)
+            .map_err(|e| transient("send cached transactions to", e))?;
+
+        let (lock, cvar) = &**shared;
+        lock.lock().unwrap().connected = true;
+        cvar.notify_all();
+        Ok(fd)
+    }
+
+    /// Process acknowledgements from the receiver until the connection
+    /// breaks down.
+    fn serve(id: usize, fd: &Fd, buffer: &Buffer<T>, shared: &Shared) {
+        let mut data = Vec::new();
+        let mut pollfds = [libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+        let count = pollfds.len().try_into().unwrap();
+        let timeout = POLL_INTERVAL.as_millis().try_into().unwrap();
+
+        loop {
+            match unsafe { libc::poll(pollfds.as_mut_ptr(), count, timeout) } {
+                -1 => {
+                    let err = Error::last_os_error();
+                    if err.kind() != ErrorKind::Interrupted {
+                        error!("TcpSender({}): failed to poll socket: {}", id, err);
+                        return;
+                    }
+                    continue;
+                }
+                0 => {
+                    // Writes happen outside of this thread, so we check
+                    // periodically whether they failed.
+                    if buffer.lock().unwrap().is_broken() {
+                        return;
+                    }
+                    continue;
+                }
+                _ => (),
+            }
+
+            let mut buffer = buffer.lock().unwrap();
+            let writer = match buffer.writer_mut() {
+                Some(writer) => writer,
+                None => return,
+            };
+            match writer.get_mut().get_mut().read_available(&mut data) {
+                Ok(0) => return,
+                Ok(_) => (),
+                Err(e) => {
+                    debug!("TcpSender({}): failed to receive data: {}", id, e);
+                    return;
+                }
+            }
+
+            let mut slice = data.as_slice();
+            let mut acked = None;
+            loop {
+                let remaining = slice;
+                match deserialize_from(&mut slice) {
+                    Ok(Message::<()>::Ack(seq)) => acked = Some(seq),
+                    Ok(msg) => {
+                        error!("TcpSender({}): received unexpected {} message", id, msg);
+                        return;
+                    }
+                    // We may just have received a partial message.
+                    Err(e) => match *e {
+                        BincodeError::Io(_) => {
+                            slice = remaining;
+                            break;
+                        }
+                        _ => {
+                            error!("TcpSender({}): failed to deserialize message: {}", id, e);
+                            return;
+                        }
+                    },
+                }
+            }
+            let consumed = data.len() - slice.len();
+            let _ = data.drain(..consumed);
+
+            if let Some(seq) = acked {
+                buffer.acknowledge(seq);
+                shared.1.notify_all();
+            }
+            if buffer.is_broken() {
+                return;
+            }
+        }
+    }
+
+    /// Tear down the current connection, if any.
+    fn disconnect(id: usize, buffer: &Buffer<T>, shared: &Shared) {
+        let writer = buffer.lock().unwrap().take_writer();
+        let fd = {
+            let mut state = shared.0.lock().unwrap();
+            state.connected = false;
+            state.fd.take()
+        };
+        // Shut down the socket before closing it, to wake up anybody
+        // still using it.
+        if let Some(fd) = fd {
+            if let Err(e) = fd.shutdown() {
+                debug!("TcpSender({}): failed to shut down socket: {}", id, e);
+            }
+        }
+        drop(writer)
     }
 }
 
@@ -92,17 +467,40 @@ where
     T: Debug,
 {
     /// Block until a connection is established.
+    ///
+    /// An error is reported if we gave up on connecting, which happens
+    /// if the receiver could not be authenticated (or rejected us) or
+    /// if it runs an incompatible program.
     pub fn wait_connected(&mut self) -> Result<(), String> {
-        if let Some(t) = self.thread.take() {
-            match t.join() {
-                Ok(result) => result,
-                Err(e) => Err(format!(
-                    "TcpSender({}) thread has panicked: {:?}",
-                    self.id, e
-                )),
+        let (lock, cvar) = &*self.shared;
+        let state = lock.lock().unwrap();
+        let state = cvar
+            .wait_while(state, |state| {
+                !state.connected && state.error.is_none() && !state.stopped
+            })
+            .unwrap();
+        match &state.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Wait (for a limited amount of time) for the receiver to
+    /// acknowledge all transactions, provided we are connected.
+    fn linger(&self) {
+        let (lock, cvar) = &*self.shared;
+        let deadline = Instant::now() + LINGER;
+        loop {
+            if self.buffer.lock().unwrap().is_acknowledged() {
+                break;
+            }
+
+            let state = lock.lock().unwrap();
+            let now = Instant::now();
+            if !state.connected || now >= deadline {
+                break;
             }
-        } else {
-            Ok(())
+            let _ = cvar.wait_timeout(state, min(deadline - now, POLL_INTERVAL));
         }
     }
 }
@@ -143,16 +541,92 @@ where
     }
 }
 
+/// The sending end of a channel over a Unix domain socket, for feeding
+/// receivers on the same machine without going through the TCP stack.
+///
+/// It provides the same guarantees as a `TcpSender`.
+#[derive(Debug)]
+pub struct UdsSender<T>(TcpSender<T>)
+where
+    T: Debug;
+
+impl<T> UdsSender<T>
+where
+    T: Debug + Send + Serialize + 'static,
+{
+    /// Create a new `UdsSender`, connecting to the Unix domain socket
+    /// at the given path.
+    ///
+    /// The connection is refused unless the receiver runs a program
+    /// with the given schema.
+    pub fn new<P>(path: P, schema: SchemaHash) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        TcpSender::create(Target::Unix(path.into()), schema).map(Self)
+    }
+}
+
+impl<T> UdsSender<T>
+where
+    T: Debug,
+{
+    /// Block until a connection is established.
+    pub fn wait_connected(&mut self) -> Result<(), String> {
+        self.0.wait_connected()
+    }
+}
+
+impl<T, V> Observer<V, String> for UdsSender<T>
+where
+    T: Debug + Send + Serialize + From<V> + 'static,
+    V: Send,
+{
+    fn on_start(&mut self) -> Result<(), String> {
+        Observer::<V, _>::on_start(&mut self.0)
+    }
+
+    fn on_updates<'a>(&mut self, updates: Box<dyn Iterator<Item = V> + 'a>) -> Result<(), String> {
+        self.0.on_updates(updates)
+    }
+
+    fn on_commit(&mut self) -> Result<(), String> {
+        Observer::<V, _>::on_commit(&mut self.0)
+    }
+
+    fn on_completed(&mut self) -> Result<(), String> {
+        Observer::<V, _>::on_completed(&mut self.0)
+    }
+}
+
 impl<T> Drop for TcpSender<T>
 where
     T: Debug,
 {
     fn drop(&mut self) {
-        if let Err(e) = self.cancel.cancel() {
-            error!("failed to cancel connect: {}", e);
+        self.linger();
+
+        {
+            let (lock, cvar) = &*self.shared;
+            let mut state = lock.lock().unwrap();
+            state.stopped = true;
+            if let Some(cancel) = &state.cancel {
+                if let Err(e) = cancel.cancel() {
+                    error!("failed to cancel connect: {}", e);
+                }
+            }
+            if let Some(fd) = &state.fd {
+                if let Err(e) = fd.shutdown() {
+                    error!("failed to shut down TcpSender socket: {}", e);
+                }
+            }
+            cvar.notify_all();
         }
-        if let Err(e) = self.wait_connected() {
-            error!("{}", e);
+
+        if let Some(t) = self.thread.take() {
+            if let Err(e) = t.join() {
+                error!("TcpSender({}) thread has panicked: {:?}", self.id, e);
+            }
         }
     }
 }
@@ -161,6 +635,9 @@ where
 mod tests {
     use super::*;
 
+    use std::net::TcpListener;
+
+    use tempfile::tempdir;
     use test_env_log::test;
 
     use crate::await_expected;
@@ -168,13 +645,14 @@ mod tests {
     use crate::Observable;
     use crate::SharedObserver;
     use crate::TcpReceiver;
+    use crate::UdsReceiver;
 
     /// Connect a `TcpSender` to a `TcpReceiver`.
     #[test]
     fn connect() {
-        let recv = TcpReceiver::<(), ()>::new("127.0.0.1:0").unwrap();
+        let recv = TcpReceiver::<(), ()>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
         {
-            let _send = TcpSender::<()>::new(*recv.addr());
+            let _send = TcpSender::<()>::new(*recv.addr(), SchemaHash::default());
         }
     }
 
@@ -182,9 +660,10 @@ mod tests {
     /// without an `Observer` being subscribed to the `TcpReceiver`.
     #[test]
     fn transmit_updates_no_consumer() {
-        let recv = TcpReceiver::<String, String>::new("127.0.0.1:0").unwrap();
+        let recv =
+            TcpReceiver::<String, String>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
         {
-            let mut send = TcpSender::<String>::new(*recv.addr()).unwrap();
+            let mut send = TcpSender::<String>::new(*recv.addr(), SchemaHash::default()).unwrap();
 
             let send = &mut send as &mut dyn Observer<String, _>;
             send.on_start().unwrap();
@@ -196,8 +675,11 @@ mod tests {
 
     #[test]
     fn delayed_connect() {
-        let mut send = TcpSender::<u64>::new("127.0.0.1:5006".parse().unwrap()).unwrap();
-        let mut recv = TcpReceiver::<u64, u64>::new("127.0.0.1:5006").unwrap();
+        let mut send =
+            TcpSender::<u64>::new("127.0.0.1:5006".parse().unwrap(), SchemaHash::default())
+                .unwrap();
+        let mut recv =
+            TcpReceiver::<u64, u64>::new("127.0.0.1:5006", SchemaHash::default()).unwrap();
         let observer = SharedObserver::new(Mutex::new(MockObserver::new()));
         let _ = recv.subscribe(Box::new(observer.clone())).unwrap();
 
@@ -216,4 +698,163 @@ mod tests {
             assert_eq!(on_updates, 3);
         });
     }
+
+    /// Check that a `TcpSender` reconnects to a `TcpReceiver` that went
+    /// away and delivers the transactions committed in the meantime.
+    #[test]
+    fn reconnect() {
+        fn transmit(send: &mut TcpSender<u64>, updates: Vec<u64>) {
+            let send = send as &mut dyn Observer<u64, _>;
+            send.on_start().unwrap();
+            send.on_updates(Box::new(updates.into_iter())).unwrap();
+            send.on_commit().unwrap();
+        }
+
+        fn await_delivered(observer: &SharedObserver<MockObserver>, commits: usize) {
+            await_expected(|| {
+                let on_commit = observer.lock().unwrap().called_on_commit;
+                assert_eq!(on_commit, commits);
+            });
+        }
+
+        let observer1 = SharedObserver::new(Mutex::new(MockObserver::new()));
+        let mut recv = TcpReceiver::<u64, u64>::new("127.0.0.1:0", SchemaHash::default()).unwrap();
+        let addr = *recv.addr();
+        recv.subscribe(Box::new(observer1.clone())).unwrap();
+
+        let mut send = TcpSender::<u64>::new(addr, SchemaHash::default()).unwrap();
+        let buffer = send.buffer.clone();
+        transmit(&mut send, vec![1, 2]);
+        await_delivered(&observer1, 1);
+        await_expected(|| assert!(buffer.lock().unwrap().is_acknowledged()));
+
+        drop(recv);
+        transmit(&mut send, vec![3, 4, 5]);
+        transmit(&mut send, vec![6]);
+
+        let observer2 = SharedObserver::new(Mutex::new(MockObserver::new()));
+        let mut recv = TcpReceiver::<u64, u64>::new(addr, SchemaHash::default()).unwrap();
+        recv.subscribe(Box::new(observer2.clone())).unwrap();
+
+        await_delivered(&observer2, 2);
+        assert_eq!(observer2.lock().unwrap().called_on_updates, 4);
+        await_expected(|| assert!(buffer.lock().unwrap().is_acknowledged()));
+        assert_eq!(observer1.lock().unwrap().called_on_commit, 1);
+    }
+
+    /// Transmit updates over a Unix domain socket, with the receiver
+    /// only showing up after the sender started connecting.
+    #[test]
+    fn transmit_updates_unix() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("socket");
+        let mut send = UdsSender::<u64>::new(&path, SchemaHash::default()).unwrap();
+        let mut recv = UdsReceiver::<u64, u64>::new(&path, SchemaHash::default()).unwrap();
+        let observer = SharedObserver::new(Mutex::new(MockObserver::new()));
+        recv.subscribe(Box::new(observer.clone())).unwrap();
+
+        let observer_ = &mut send as &mut dyn Observer<u64, _>;
+        observer_.on_start().unwrap();
+        observer_
+            .on_updates(Box::new(vec![1, 2, 3].into_iter()))
+            .unwrap();
+        observer_.on_commit().unwrap();
+        send.wait_connected().unwrap();
+
+        await_expected(|| {
+            let (on_updates, on_commit) = {
+                let mock = observer.lock().unwrap();
+                (mock.called_on_updates, mock.called_on_commit)
+            };
+
+            assert_eq!(on_updates, 3);
+            assert_eq!(on_commit, 1);
+        });
+    }
+
+    /// Check that a `TcpSender` refuses to connect to a receiver running
+    /// a program with a different schema.
+    #[test]
+    fn schema_mismatch() {
+        let schema = SchemaHash::new(vec![("Foo", "bigint")]);
+        let mut recv = TcpReceiver::<u64, u64>::new("127.0.0.1:0", schema).unwrap();
+        let observer = SharedObserver::new(Mutex::new(MockObserver::new()));
+        recv.subscribe(Box::new(observer.clone())).unwrap();
+
+        let schema = SchemaHash::new(vec![("Foo", "string")]);
+        let mut send = TcpSender::<u64>::new(*recv.addr(), schema).unwrap();
+        let observer_ = &mut send as &mut dyn Observer<u64, _>;
+        observer_.on_start().unwrap();
+        observer_
+            .on_updates(Box::new(vec![1, 2, 3].into_iter()))
+            .unwrap();
+        observer_.on_commit().unwrap();
+
+        let error = send.wait_connected().unwrap_err();
+        assert!(error.contains("program schema mismatch"), "{}", error);
+
+        let on_start = observer.lock().unwrap().called_on_start;
+        assert_eq!(on_start, 0);
+    }
+
+    /// Check that a `TcpSender` gives up on a receiver that asks it to
+    /// use a codec it does not support.
+    #[test]
+    fn unsupported_compression() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let receiver = spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let header = deserialize_from::<_, Header>(&mut stream).unwrap();
+            let hello = deserialize_from::<_, Message<()>>(&mut stream).unwrap();
+            match hello {
+                Message::Hello(_, codecs) => assert!(!codecs.contains(&42)),
+                _ => panic!("expected hello message but received {}", hello),
+            }
+            stream.write_all(&serialize(&header).unwrap()).unwrap();
+            let welcome = Message::<()>::Welcome(42, 0);
+            stream.write_all(&serialize(&welcome).unwrap()).unwrap();
+        });
+
+        let mut send = TcpSender::<u64>::new(addr, SchemaHash::default()).unwrap();
+        let error = send.wait_connected().unwrap_err();
+        assert!(error.contains("unknown compression codec 42"), "{}", error);
+        receiver.join().unwrap();
+    }
+
+    /// Check that large transactions make it through a connection
+    /// compressed as requested by the receiver.
+    #[cfg(feature = "compression")]
+    #[test]
+    fn transmit_compressed() {
+        for compression in &[Compression::Lz4, Compression::Zstd] {
+            let schema = SchemaHash::default();
+            let mut recv = TcpReceiver::<u64, u64>::new("127.0.0.1:0", schema).unwrap();
+            recv.set_compression(*compression);
+            let observer = SharedObserver::new(Mutex::new(MockObserver::new()));
+            recv.subscribe(Box::new(observer.clone())).unwrap();
+
+            let mut send = TcpSender::<u64>::new(*recv.addr(), schema).unwrap();
+            let observer_ = &mut send as &mut dyn Observer<u64, _>;
+            for _ in 0..2 {
+                observer_.on_start().unwrap();
+                for i in 0..10 {
+                    let updates = i * 100_000..(i + 1) * 100_000;
+                    observer_.on_updates(Box::new(updates)).unwrap();
+                }
+                observer_.on_commit().unwrap();
+            }
+            send.wait_connected().unwrap();
+
+            await_expected(|| {
+                let (on_updates, on_commit) = {
+                    let mock = observer.lock().unwrap();
+                    (mock.called_on_updates, mock.called_on_commit)
+                };
+
+                assert_eq!(on_updates, 2_000_000);
+                assert_eq!(on_commit, 2);
+            });
+        }
+    }
 }
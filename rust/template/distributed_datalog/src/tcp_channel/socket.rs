@@ -6,7 +6,8 @@
 //! request, but that requires an unnecessary amount of cycles.
 //!
 //! This module provides a way to cancel a connection request issued
-//! earlier by separating socket creation from connection.
+//! earlier by separating socket creation from connection. Both TCP and
+//! Unix domain sockets are supported.
 
 use std::convert::TryFrom;
 use std::convert::TryInto;
@@ -15,14 +16,19 @@ use std::io::ErrorKind;
 use std::mem::forget;
 use std::net::SocketAddr;
 use std::net::TcpStream;
+use std::os::unix::ffi::OsStrExt;
 use std::os::unix::io::AsRawFd;
 use std::os::unix::io::FromRawFd;
 use std::os::unix::io::IntoRawFd;
 use std::os::unix::io::RawFd;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Weak;
+use std::thread::sleep;
+use std::time::Duration;
 
 use log::error;
 
@@ -66,36 +72,86 @@ fn set_nonblocking(fd: RawFd, nonblocking: bool) -> Result<(), Error> {
     cvt(unsafe { libc::ioctl(fd, libc::FIONBIO, &mut nonblocking) }).map(|_| ())
 }
 
+/// Convert a path into a Unix domain socket address.
+fn unix_addr(path: &Path) -> Result<libc::sockaddr_un, Error> {
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+    let bytes = path.as_os_str().as_bytes();
+    // We need to leave room for the terminating NUL byte.
+    if bytes.len() >= addr.sun_path.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("socket path {} is too long", path.display()),
+        ));
+    }
+    for (dst, src) in addr.sun_path.iter_mut().zip(bytes) {
+        *dst = *src as libc::c_char;
+    }
+    Ok(addr)
+}
+
+/// The time we wait before attempting to connect again after the
+/// connection got refused right away.
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Attempt to connect the given socket file descriptor.
 ///
 /// This function will not return until either
 /// 1) the socket was connected successfully
 /// OR
 /// 2) `closable` was closed asynchronously
+fn connect(socket: &Fd, addr: &SocketAddr) -> Result<(), Error> {
+    connect_raw(socket, || into_inner(addr))
+}
+
+/// Attempt to connect the given Unix domain socket file descriptor.
+///
+/// This function behaves like `connect`. Note that a socket path that
+/// does not exist is treated just like a refused connection, as the
+/// path will only be created once somebody listens on it.
+fn connect_unix(socket: &Fd, path: &Path) -> Result<(), Error> {
+    let addr = unix_addr(path)?;
+    connect_raw(socket, || {
+        (
+            &addr as *const _ as *const _,
+            std::mem::size_of_val(&addr) as libc::socklen_t,
+        )
+    })
+}
+
 // This function is derived from the standard library's `connect_timeout`,
 // but without the timeout part, and instead added support for
 // asynchronous cancellation.
-fn connect(socket: &Fd, addr: &SocketAddr) -> Result<(), Error> {
+fn connect_raw<F>(socket: &Fd, addr: F) -> Result<(), Error>
+where
+    F: Fn() -> (*const libc::sockaddr, libc::socklen_t),
+{
     'connect: loop {
+        if socket.is_shutdown() {
+            return Err(Error::from_raw_os_error(libc::ENOTCONN));
+        }
+
         let fd = socket.as_raw_fd();
         set_nonblocking(fd, true)?;
         let r = unsafe {
-            let (addrp, len) = into_inner(addr);
+            let (addrp, len) = addr();
             cvt(libc::connect(fd, addrp, len))
         };
         set_nonblocking(fd, false)?;
 
         match r {
             Ok(_) => return Ok(()),
-            Err(e) => {
-                let os_err = e.raw_os_error();
-                if os_err == Some(libc::ECONNREFUSED) {
+            Err(e) => match e.raw_os_error() {
+                // Unix domain sockets report refused connections (and
+                // a full backlog) right away.
+                Some(libc::ECONNREFUSED) | Some(libc::ENOENT) | Some(libc::EAGAIN) => {
+                    sleep(RETRY_INTERVAL);
                     continue 'connect;
                 }
-                if os_err != Some(libc::EINPROGRESS) {
-                    return Err(e);
-                }
-            }
+                Some(libc::EINPROGRESS) => (),
+                _ => return Err(e),
+            },
         }
 
         let mut pollfds = [libc::pollfd {
@@ -274,8 +330,13 @@ impl Drop for Fd {
 
 /// Create a socket file descriptor.
 fn socket() -> Result<Fd, Error> {
+    socket_in(libc::AF_INET)
+}
+
+/// Create a socket file descriptor in the given domain.
+fn socket_in(domain: libc::c_int) -> Result<Fd, Error> {
     unsafe {
-        let fd = cvt(libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0))?;
+        let fd = cvt(libc::socket(domain, libc::SOCK_STREAM, 0))?;
         let oldflags = cvt(libc::fcntl(fd, libc::F_GETFD, 0))?;
         let _ = cvt(libc::fcntl(fd, libc::F_SETFD, oldflags | libc::O_CLOEXEC))?;
         Ok(Fd::new(fd as libc::c_uint))
@@ -292,17 +353,29 @@ impl Socket {
         Ok(Self(Arc::new(socket()?)))
     }
 
+    /// Create a new `Socket` object for connecting to a Unix domain
+    /// socket.
+    pub fn new_unix() -> Result<Self, Error> {
+        Ok(Self(Arc::new(socket_in(libc::AF_UNIX)?)))
+    }
+
     /// Connect the socket to the given address.
     ///
     /// This function will not return until a connection has been
     /// established or the socket been closed.
     pub fn connect(self, addr: &SocketAddr) -> Result<TcpStream, Error> {
-        connect(&self.0, addr).map(|_| {
-            // It's always safe to unwrap here because we only ever
-            // handed out weak references.
-            let fd = Arc::try_unwrap(self.0).unwrap();
-            unsafe { TcpStream::from_raw_fd(fd.into_raw_fd()) }
-        })
+        connect(&self.0, addr)?;
+        Ok(unsafe { TcpStream::from_raw_fd(self.into_raw_fd()) })
+    }
+
+    /// Connect the socket (created by `new_unix`) to the Unix domain
+    /// socket at the given path.
+    ///
+    /// This function will not return until a connection has been
+    /// established or the socket been closed.
+    pub fn connect_unix(self, path: &Path) -> Result<UnixStream, Error> {
+        connect_unix(&self.0, path)?;
+        Ok(unsafe { UnixStream::from_raw_fd(self.into_raw_fd()) })
     }
 
     /// Retrieve a reference to a file descriptor that can be used to
@@ -313,6 +386,15 @@ impl Socket {
     }
 }
 
+impl IntoRawFd for Socket {
+    fn into_raw_fd(self) -> RawFd {
+        // It's always safe to unwrap here because we only ever handed
+        // out weak references.
+        let fd = Arc::try_unwrap(self.0).unwrap();
+        fd.into_raw_fd()
+    }
+}
+
 #[derive(Debug)]
 pub struct Cancelable(Weak<Fd>);
 
@@ -338,9 +420,10 @@ mod tests {
     use std::io::Read;
     use std::io::Write;
     use std::net::TcpListener;
-    use std::thread::sleep;
+    use std::os::unix::net::UnixListener;
     use std::thread::spawn;
-    use std::time::Duration;
+
+    use tempfile::tempdir;
 
     /// Test the closing on an `Fd`.
     #[test]
@@ -482,4 +565,58 @@ mod tests {
         thread1.join().unwrap();
         thread2.join().unwrap();
     }
+
+    /// Check that we can eventually connect to a Unix domain socket
+    /// that is only created after we started connecting.
+    #[test]
+    fn connect_unix_eventually() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("socket");
+        let copy = path.clone();
+
+        let thread = spawn(move || {
+            sleep(Duration::from_millis(50));
+            let listener = UnixListener::bind(copy).unwrap();
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut data = Vec::new();
+            let _ = socket.read_to_end(&mut data).unwrap();
+            assert_eq!(data.as_slice(), b"success");
+        });
+
+        {
+            let socket = Socket::new_unix().unwrap();
+            let mut stream = socket.connect_unix(&path).unwrap();
+            stream.write_all(b"success").unwrap();
+        }
+
+        thread.join().unwrap();
+    }
+
+    /// Test cancellation of an attempted connect to a Unix domain
+    /// socket that does not exist.
+    #[test]
+    fn cancel_unix() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("socket");
+        let socket = Socket::new_unix().unwrap();
+        let cancelable = socket.to_cancelable();
+
+        let thread = spawn(move || {
+            let err = socket.connect_unix(&path).unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::ENOTCONN));
+        });
+
+        let _ = cancelable.cancel().unwrap();
+
+        thread.join().unwrap();
+    }
+
+    /// Check that we reject socket paths that are too long.
+    #[test]
+    fn unix_path_too_long() {
+        let path = Path::new("/").join("x".repeat(200));
+        let socket = Socket::new_unix().unwrap();
+        let err = socket.connect_unix(&path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
 }
@@ -0,0 +1,127 @@
+//! A module providing the stream underlying a TCP channel, which is
+//! either a plain TCP stream, a Unix domain socket, or (with the `tls`
+//! feature) a TCP stream secured with TLS.
+
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io::Error;
+use std::io::Read;
+use std::io::Result as IoResult;
+use std::io::Write;
+use std::net::TcpStream;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::RawFd;
+use std::os::unix::net::UnixStream;
+
+#[cfg(feature = "tls")]
+use crate::tcp_channel::tls::TlsStream;
+
+/// Perform a single read on the given socket, appending the data to
+/// `buf`.
+fn read_chunk<R>(socket: &mut R, buf: &mut Vec<u8>) -> Result<usize, Error>
+where
+    R: Read,
+{
+    let mut chunk = [0; 4096];
+    let count = socket.read(&mut chunk)?;
+    buf.extend_from_slice(&chunk[..count]);
+    Ok(count)
+}
+
+/// A stream that is either plain TCP, secured with TLS, or a Unix
+/// domain socket.
+pub enum Stream {
+    /// A plain TCP stream.
+    Plain(TcpStream),
+    /// A Unix domain socket stream.
+    Unix(UnixStream),
+    /// A TCP stream secured with TLS.
+    #[cfg(feature = "tls")]
+    Tls(TlsStream),
+}
+
+impl Stream {
+    /// Complete the TLS handshake, if it has not happened yet, which
+    /// includes authenticating the peer.
+    ///
+    /// The handshake is also performed implicitly by the first read or
+    /// write, but doing so explicitly allows for reporting failures
+    /// early on.
+    pub fn handshake(&mut self) -> Result<(), Error> {
+        match self {
+            Stream::Plain(..) | Stream::Unix(..) => Ok(()),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.handshake(),
+        }
+    }
+
+    /// Read the data that is available right away, appending it to
+    /// `buf`.
+    ///
+    /// Only a single read is performed on the underlying socket, i.e.,
+    /// the call blocks only if no data is available at all. The number
+    /// of bytes read from the socket is returned, with zero indicating
+    /// that the peer hung up.
+    pub fn read_available(&mut self, buf: &mut Vec<u8>) -> Result<usize, Error> {
+        match self {
+            Stream::Plain(socket) => read_chunk(socket, buf),
+            Stream::Unix(socket) => read_chunk(socket, buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.read_available(buf),
+        }
+    }
+}
+
+impl AsRawFd for Stream {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Stream::Plain(socket) => socket.as_raw_fd(),
+            Stream::Unix(socket) => socket.as_raw_fd(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.as_raw_fd(),
+        }
+    }
+}
+
+impl Debug for Stream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Stream::Plain(socket) => f.debug_tuple("Plain").field(socket).finish(),
+            Stream::Unix(socket) => f.debug_tuple("Unix").field(socket).finish(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => f.debug_tuple("Tls").field(stream).finish(),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        match self {
+            Stream::Plain(socket) => socket.read(buf),
+            Stream::Unix(socket) => socket.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        match self {
+            Stream::Plain(socket) => socket.write(buf),
+            Stream::Unix(socket) => socket.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match self {
+            Stream::Plain(socket) => socket.flush(),
+            Stream::Unix(socket) => socket.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
@@ -1,41 +1,69 @@
 //! A module providing functionality for buffering of transactions. Such
 //! buffering comes in handy in scenarios where the future receiver of a
-//! transaction is not yet available to process it.
+//! transaction is not yet available to process it, or where the
+//! connection to it broke down before it acknowledged the transaction.
 
 use std::collections::LinkedList;
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::io::Write;
-use std::mem::replace;
+use std::mem::take;
 
 use bincode::serialize_into;
+use log::debug;
 use serde::Serialize;
 
 use crate::observe::Observer;
 use crate::tcp_channel::message::Message;
 
+/// The maximum number of events a `TxnBuf` retains by default while
+/// waiting for the receiver to acknowledge them.
+pub const MAX_UNACKED: usize = 4096;
+
 /// A type representing the updates of a transaction.
 type Transaction<T> = LinkedList<Vec<T>>;
 
+/// An event that has to be acknowledged by the receiver.
+#[derive(Debug)]
+enum Event<T> {
+    /// A committed transaction.
+    Commit(Transaction<T>),
+    /// The `on_completed` event.
+    Complete,
+}
+
 /// A buffer for transactions.
+///
+/// All committed transactions are retained until the receiver
+/// acknowledged them, so that they can be sent again over a new
+/// connection should the current one break down. Each committed
+/// transaction (as well as the `on_completed` event) is assigned a
+/// sequence number, which allows the receiver to detect and drop
+/// transactions it has seen already.
+///
+/// The number of retained events is limited, so that a receiver that
+/// is unreachable for a long time does not make the buffer grow without
+/// bounds. Once the limit is reached, starting a transaction fails.
 #[derive(Debug)]
-pub enum TxnBuf<W, T>
+pub struct TxnBuf<W, T>
 where
     W: Debug,
     T: Debug,
 {
-    /// A list of buffered transactions.
-    Updates {
-        /// We merge all "completed" transactions (i.e., those for which
-        /// we have seen a commit) into a single one.
-        complete: Transaction<T>,
-        /// The transaction currently in progress.
-        ongoing: Option<Transaction<T>>,
-        /// We have received an `on_completed` event.
-        on_completed: bool,
-    },
-    /// A writer is present and we no longer need to buffer
-    /// transactions.
-    Writer(W),
+    /// All events that have not been acknowledged yet, along with their
+    /// sequence numbers, in ascending order.
+    unacked: VecDeque<(u64, Event<T>)>,
+    /// The maximum number of events in `unacked` before we refuse to
+    /// start another transaction.
+    limit: usize,
+    /// The transaction currently in progress.
+    ongoing: Option<Transaction<T>>,
+    /// The sequence number assigned to the most recent event.
+    seq: u64,
+    /// The writer representing the current connection, if any.
+    writer: Option<W>,
+    /// Whether writing to `writer` has failed.
+    broken: bool,
 }
 
 impl<W, T> TxnBuf<W, T>
@@ -43,50 +71,86 @@ where
     W: Debug + Send + Write,
     T: Debug + Send + Serialize,
 {
-    /// Convert the `TxnBuf` into the `Writer` variant.
+    /// Start using the given writer, after sending all events not
+    /// acknowledged as per `acked` over it.
     ///
-    /// An error return indicates a failure to flush all buffered
-    /// transactions. The objects is in an undefined state afterwards.
-    pub fn set_mode_passthrough(&mut self, mut writer: W) -> Result<(), String> {
-        match self {
-            TxnBuf::Updates {
-                complete,
-                ongoing,
-                on_completed,
-            } => {
-                Self::handle_txn(&mut writer, replace(complete, LinkedList::new()))?;
-                Self::handle_partial_txn(&mut writer, ongoing.take())?;
-                if *on_completed {
-                    Self::handle_msg(&mut writer, &Message::<T>::Complete)?;
+    /// An error return indicates a failure to send the events, in which
+    /// case the writer is considered broken.
+    pub fn set_writer(&mut self, writer: W, acked: u64) -> Result<(), String> {
+        self.acknowledge(acked);
+        let writer = self.writer.get_or_insert(writer);
+        let result = Self::replay(writer, &mut self.unacked, &mut self.ongoing);
+        self.broken = result.is_err();
+        result
+    }
+
+    /// Send all the given unacknowledged events as well as the
+    /// transaction in progress, if any.
+    fn replay(
+        writer: &mut W,
+        unacked: &mut VecDeque<(u64, Event<T>)>,
+        ongoing: &mut Option<Transaction<T>>,
+    ) -> Result<(), String> {
+        for (seq, event) in unacked.iter_mut() {
+            match event {
+                Event::Commit(txn) => {
+                    Self::handle_msg(writer, &Message::Start)?;
+                    Self::handle_txn(writer, txn)?;
+                    Self::handle_msg(writer, &Message::Commit(*seq))?;
                 }
-                writer.flush().map_err(|e| e.to_string())?;
-                *self = TxnBuf::Writer(writer);
-                Ok(())
+                Event::Complete => Self::handle_msg(writer, &Message::Complete(*seq))?,
             }
-            TxnBuf::Writer(..) => panic!("TxnBuf is already a Writer variant"),
         }
+
+        if let Some(txn) = ongoing {
+            // If there is a partial transaction that means that we
+            // received a transaction start and potentially updates,
+            // but no commit yet.
+            Self::handle_msg(writer, &Message::Start)?;
+            Self::handle_txn(writer, txn)?;
+        }
+        writer.flush().map_err(|e| e.to_string())
     }
 
-    /// Send a full transaction.
-    fn handle_txn(writer: &mut W, txn: Transaction<T>) -> Result<(), String> {
-        if !txn.is_empty() {
-            Self::handle_msg(writer, &Message::<T>::Start)?;
-            Self::handle_msg(writer, &Message::UpdateList(txn))?;
-            Self::handle_msg(writer, &Message::<T>::Commit)?;
+    /// Stop using the current writer, if any, and return it.
+    pub fn take_writer(&mut self) -> Option<W> {
+        self.broken = false;
+        self.writer.take()
+    }
+
+    /// Retrieve the current writer, if any.
+    pub fn writer_mut(&mut self) -> Option<&mut W> {
+        self.writer.as_mut()
+    }
+
+    /// Check whether writing to the current writer has failed.
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    /// Drop all events up to and including the given sequence number,
+    /// as the receiver has acknowledged them.
+    pub fn acknowledge(&mut self, acked: u64) {
+        while let Some((seq, _)) = self.unacked.front() {
+            if *seq > acked {
+                break;
+            }
+            let _ = self.unacked.pop_front();
         }
-        Ok(())
     }
 
-    /// Send a partial transaction.
-    fn handle_partial_txn(writer: &mut W, txn: Option<Transaction<T>>) -> Result<(), String> {
-        if let Some(updates) = txn {
-            // If there is a partial transaction that means that we
-            // received a transaction start and potentially updates, but
-            // no commit yet.
-            Self::handle_msg(writer, &Message::<T>::Start)?;
-            if !updates.is_empty() {
-                Self::handle_msg(writer, &Message::UpdateList(updates))?;
+    /// Send the updates of a (partial) transaction.
+    fn handle_txn(writer: &mut W, txn: &mut Transaction<T>) -> Result<(), String> {
+        if !txn.is_empty() {
+            // We need to retain the updates, but do not want to require
+            // them to be cloneable. So temporarily move them into the
+            // message.
+            let msg = Message::UpdateList(take(txn));
+            let result = Self::handle_msg(writer, &msg);
+            if let Message::UpdateList(updates) = msg {
+                *txn = updates;
             }
+            result?;
         }
         Ok(())
     }
@@ -95,20 +159,62 @@ where
     fn handle_msg(writer: &mut W, msg: &Message<T>) -> Result<(), String> {
         serialize_into(writer, msg).map_err(|e| e.to_string())
     }
+
+    /// Send a message over the current connection, if any, and flush it
+    /// if requested.
+    ///
+    /// Failure to do so is not an error from the perspective of the
+    /// observer, as all events are sent again over the next connection.
+    fn send(&mut self, msg: &Message<T>, flush: bool) {
+        if let (Some(writer), false) = (&mut self.writer, self.broken) {
+            let result = Self::handle_msg(writer, msg).and_then(|_| {
+                if flush {
+                    writer.flush().map_err(|e| e.to_string())
+                } else {
+                    Ok(())
+                }
+            });
+
+            if let Err(e) = result {
+                debug!("failed to send {} message: {}", msg, e);
+                self.broken = true;
+            }
+        }
+    }
 }
 
-impl<W, T> Default for TxnBuf<W, T>
+impl<W, T> TxnBuf<W, T>
 where
     W: Debug,
     T: Debug,
 {
-    fn default() -> Self {
-        TxnBuf::Updates {
-            complete: LinkedList::default(),
+    /// Create a buffer retaining at most `limit` events that have not
+    /// been acknowledged.
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            unacked: VecDeque::new(),
+            limit,
             ongoing: None,
-            on_completed: false,
+            seq: 0,
+            writer: None,
+            broken: false,
         }
     }
+
+    /// Check whether all committed transactions have been acknowledged.
+    pub fn is_acknowledged(&self) -> bool {
+        self.unacked.is_empty()
+    }
+}
+
+impl<W, T> Default for TxnBuf<W, T>
+where
+    W: Debug,
+    T: Debug,
+{
+    fn default() -> Self {
+        Self::with_limit(MAX_UNACKED)
+    }
 }
 
 impl<W, T> Observer<T, String> for TxnBuf<W, T>
@@ -118,65 +224,54 @@ where
 {
     /// Perform some action before data starts coming in.
     fn on_start(&mut self) -> Result<(), String> {
-        match self {
-            TxnBuf::Updates { ongoing, .. } => {
-                if ongoing.is_none() {
-                    *ongoing = Some(LinkedList::new());
-                } else {
-                    panic!("received multiple on_start events")
-                }
-            }
-            TxnBuf::Writer(writer) => Self::handle_msg(writer, &Message::<T>::Start)?,
+        if self.unacked.len() >= self.limit {
+            return Err(format!(
+                "{} events are waiting to be acknowledged by the receiver",
+                self.unacked.len()
+            ));
+        }
+
+        if self.ongoing.is_none() {
+            self.ongoing = Some(LinkedList::new());
+        } else {
+            panic!("received multiple on_start events")
         }
+
+        self.send(&Message::Start, false);
         Ok(())
     }
 
     /// Send a series of items over the TCP channel.
     fn on_updates<'a>(&mut self, updates: Box<dyn Iterator<Item = T> + 'a>) -> Result<(), String> {
-        match self {
-            TxnBuf::Updates { ongoing, .. } => {
-                if let Some(transaction) = ongoing {
-                    transaction.push_back(updates.collect())
-                } else {
-                    panic!("on_updates was not preceded by an on_start event")
-                }
-            }
-            TxnBuf::Writer(writer) => {
-                Self::handle_msg(writer, &Message::Updates(updates.collect()))?
-            }
+        if self.ongoing.is_none() {
+            panic!("on_updates was not preceded by an on_start event")
+        }
+
+        let msg = Message::Updates(updates.collect());
+        self.send(&msg, false);
+
+        if let (Some(txn), Message::Updates(updates)) = (&mut self.ongoing, msg) {
+            txn.push_back(updates)
         }
         Ok(())
     }
 
     /// Flush the TCP stream and signal the commit.
     fn on_commit(&mut self) -> Result<(), String> {
-        match self {
-            TxnBuf::Updates {
-                complete, ongoing, ..
-            } => {
-                if let Some(transaction) = ongoing {
-                    complete.append(transaction);
-                    *ongoing = None
-                } else {
-                    panic!("on_commit was not preceded by an on_start event")
-                }
-            }
-            TxnBuf::Writer(writer) => {
-                Self::handle_msg(writer, &Message::<T>::Commit)?;
-                writer.flush().map_err(|e| e.to_string())?
-            }
+        if let Some(txn) = self.ongoing.take() {
+            self.seq += 1;
+            self.unacked.push_back((self.seq, Event::Commit(txn)));
+            self.send(&Message::Commit(self.seq), true);
+            Ok(())
+        } else {
+            panic!("on_commit was not preceded by an on_start event")
         }
-        Ok(())
     }
 
     fn on_completed(&mut self) -> Result<(), String> {
-        match self {
-            TxnBuf::Updates { on_completed, .. } => *on_completed = true,
-            TxnBuf::Writer(writer) => {
-                Self::handle_msg(writer, &Message::<T>::Complete)?;
-                writer.flush().map_err(|e| e.to_string())?;
-            }
-        }
+        self.seq += 1;
+        self.unacked.push_back((self.seq, Event::Complete));
+        self.send(&Message::Complete(self.seq), true);
         Ok(())
     }
 }
@@ -187,6 +282,21 @@ mod tests {
 
     use bincode::deserialize_from;
 
+    /// Check that the given writer contains exactly the expected
+    /// messages.
+    fn check(buf: &[u8], expected: Vec<Message<u64>>) {
+        let mut slice = buf;
+        for expected in expected {
+            let msg = deserialize_from::<_, Message<u64>>(&mut slice).unwrap();
+            assert_eq!(msg, expected);
+        }
+
+        // Make sure we did not have any additional messages
+        // in the reader.
+        let result = deserialize_from::<_, Message<u64>>(&mut slice);
+        assert!(result.is_err(), result)
+    }
+
     /// Test caching of transactions in a `TxnBuf`.
     #[test]
     fn transaction_caching() {
@@ -196,42 +306,27 @@ mod tests {
         {
             let mut buffer = TxnBuf::default();
             f(&mut buffer).unwrap();
-            buffer.set_mode_passthrough(Vec::new()).unwrap();
-
-            match buffer {
-                TxnBuf::Writer(buf) => {
-                    let mut slice = buf.as_slice();
-                    for expected in expected {
-                        let msg = deserialize_from::<_, Message<u64>>(&mut slice).unwrap();
-                        assert_eq!(msg, expected);
-                    }
-
-                    // Make sure we did not have any additional messages
-                    // in the reader.
-                    let result = deserialize_from::<_, Message<u64>>(&mut slice);
-                    assert!(result.is_err(), result)
-                }
-                TxnBuf::Updates { .. } => unreachable!(),
-            }
+            buffer.set_writer(Vec::new(), 0).unwrap();
+            check(&buffer.take_writer().unwrap(), expected);
         }
 
         test(vec![Message::Start], |buffer| buffer.on_start());
 
-        test(vec![], |buffer| {
+        test(vec![Message::Start, Message::Commit(1)], |buffer| {
             buffer.on_start()?;
             buffer.on_commit()?;
             Ok(())
         });
 
-        let updates = vec![vec![1, 2], vec![3], vec![4, 5, 6]]
-            .into_iter()
-            .collect();
         let expected = vec![
             Message::Start,
-            Message::UpdateList(updates),
-            Message::Commit,
+            Message::UpdateList(vec![vec![1, 2], vec![3]].into_iter().collect()),
+            Message::Commit(1),
+            Message::Complete(2),
+            Message::Start,
+            Message::UpdateList(vec![vec![4, 5, 6]].into_iter().collect()),
+            Message::Commit(3),
             Message::Start,
-            Message::Complete,
         ];
         test(expected, |buffer| {
             buffer.on_start()?;
@@ -248,4 +343,75 @@ mod tests {
             Ok(())
         });
     }
+
+    /// Check that transactions are retained until acknowledged and sent
+    /// again over a new writer.
+    #[test]
+    fn transaction_retention() {
+        let mut buffer = TxnBuf::<Vec<u8>, u64>::default();
+        buffer.set_writer(Vec::new(), 0).unwrap();
+
+        for i in 1..=3 {
+            buffer.on_start().unwrap();
+            buffer.on_updates(Box::new(vec![i].into_iter())).unwrap();
+            buffer.on_commit().unwrap();
+        }
+
+        let expected = (1..=3)
+            .flat_map(|i| {
+                vec![
+                    Message::Start,
+                    Message::Updates(vec![i]),
+                    Message::Commit(i),
+                ]
+            })
+            .collect();
+        check(&buffer.take_writer().unwrap(), expected);
+
+        // The connection broke down after the receiver acknowledged the
+        // first transaction. The remaining ones are sent again.
+        buffer.acknowledge(1);
+        buffer.set_writer(Vec::new(), 1).unwrap();
+
+        let expected = (2..=3)
+            .flat_map(|i| {
+                vec![
+                    Message::Start,
+                    Message::UpdateList(vec![vec![i]].into_iter().collect()),
+                    Message::Commit(i),
+                ]
+            })
+            .collect();
+        check(&buffer.take_writer().unwrap(), expected);
+        assert!(!buffer.is_acknowledged());
+
+        buffer.set_writer(Vec::new(), 3).unwrap();
+        check(&buffer.take_writer().unwrap(), vec![]);
+        assert!(buffer.is_acknowledged());
+    }
+
+    /// Check that the buffer refuses to start transactions while it is
+    /// full, and accepts them again once the receiver acknowledged some.
+    #[test]
+    fn transaction_limit() {
+        let mut buffer = TxnBuf::<Vec<u8>, u64>::with_limit(2);
+        for _ in 0..2 {
+            buffer.on_start().unwrap();
+            buffer.on_commit().unwrap();
+        }
+
+        assert!(buffer.on_start().is_err());
+        buffer.set_writer(Vec::new(), 1).unwrap();
+        buffer.on_start().unwrap();
+        buffer.on_commit().unwrap();
+        assert!(buffer.on_start().is_err());
+
+        let expected = vec![
+            Message::Start,
+            Message::Commit(2),
+            Message::Start,
+            Message::Commit(3),
+        ];
+        check(&buffer.take_writer().unwrap(), expected);
+    }
 }
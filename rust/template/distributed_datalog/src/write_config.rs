@@ -0,0 +1,25 @@
+//! A module containing traits that abstract away the publishing of
+//! a configuration.
+
+use crate::schema::Member;
+use crate::schema::SysCfg;
+
+/// A client to some form of membership service that allows for
+/// (de)registering members.
+pub trait WriteMembers {
+    /// Register the given member with the service.
+    ///
+    /// Registration is expected to be tied to the lifetime of the
+    /// client, if the service supports such a concept.
+    fn register(&self, member: &Member) -> Result<(), String>;
+
+    /// Remove the given member from the service.
+    fn unregister(&self, member: &Member) -> Result<(), String>;
+}
+
+/// A client to some form of configuration manager that allows for
+/// publishing a new configuration.
+pub trait WriteConfig {
+    /// Replace the current system configuration with the given one.
+    fn set_config(&self, config: &SysCfg) -> Result<(), String>;
+}
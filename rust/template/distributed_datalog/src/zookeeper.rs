@@ -2,19 +2,28 @@
 
 #[cfg(test)]
 use std::env::var_os;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::from_slice;
+use serde_json::to_vec;
 
 use zookeeper::Acl;
 use zookeeper::CreateMode;
 use zookeeper::ZkError;
 
+use crate::schema::Member;
 use crate::schema::Members;
 use crate::schema::SysCfg;
 use crate::ReadConfig;
 use crate::ReadMembers;
+use crate::WriteConfig;
+use crate::WriteMembers;
 
 pub use zookeeper::WatchedEvent;
 pub use zookeeper::Watcher;
@@ -27,6 +36,30 @@ const D3LOG_KEY: &str = "/d3log";
 const CONFIG_KEY: &str = "/d3log/config";
 const MEMBERS_KEY: &str = "/d3log/members";
 
+/// A `Watcher` signaling changes to watched nodes through a channel,
+/// suitable for driving a `Controller`.
+#[derive(Debug)]
+pub struct ChannelWatcher(Mutex<Sender<()>>);
+
+impl ChannelWatcher {
+    /// Create a new watcher along with the receiving end of the
+    /// channel it signals changes through.
+    pub fn new() -> (Self, Receiver<()>) {
+        let (sender, receiver) = channel();
+        (Self(Mutex::new(sender)), receiver)
+    }
+}
+
+impl Watcher for ChannelWatcher {
+    fn handle(&self, event: WatchedEvent) {
+        // Events without a path concern the connection state, not
+        // the data we are interested in.
+        if event.path.is_some() {
+            let _ = self.0.lock().unwrap().send(());
+        }
+    }
+}
+
 /// Connect to a ZooKeeper instance comprised of the given set of servers.
 ///
 /// Note that each server address should contain the port the
@@ -48,17 +81,7 @@ where
         .map_err(|e| format!("failed to connect to ZooKeeper: {}", e))?;
 
     // Create the d3log root key to be able to watch for updates to it.
-    let result = zk.create(
-        D3LOG_KEY,
-        vec![b'\0'],
-        Acl::open_unsafe().clone(),
-        CreateMode::Persistent,
-    );
-    match result {
-        Ok(_) | Err(ZkError::NodeExists) => (),
-        Err(e) => return Err(format!("failed to create {} node: {}", D3LOG_KEY, e)),
-    };
-
+    ensure(&zk, D3LOG_KEY, vec![b'\0'])?;
     renew_watch(&zk)?;
     Ok(zk)
 }
@@ -86,6 +109,39 @@ fn from_env() -> Result<ZooKeeper, String> {
     connect(endpoints.split(','), watcher)
 }
 
+/// Create a persistent node with the given data, if it does not exist
+/// yet.
+fn ensure(zk: &ZooKeeper, key: &str, data: Vec<u8>) -> Result<(), String> {
+    let result = zk.create(
+        key,
+        data,
+        Acl::open_unsafe().clone(),
+        CreateMode::Persistent,
+    );
+    match result {
+        Ok(_) | Err(ZkError::NodeExists) => Ok(()),
+        Err(e) => Err(format!("failed to create {} node: {}", key, e)),
+    }
+}
+
+/// Serialize some data item for storage in a ZooKeeper instance.
+fn serialize<D>(data: &D) -> Result<Vec<u8>, String>
+where
+    D: Serialize,
+{
+    to_vec(data).map_err(|e| format!("failed to serialize data: {}", e))
+}
+
+/// Retrieve the key at which the given member is registered.
+fn member_key(member: &Member) -> String {
+    // The addresses of members reachable through a Unix domain socket
+    // contain slashes, which would make for a nested key. The key is
+    // only used for identifying the member (its data are stored in the
+    // node itself), so escaping them is good enough.
+    let addr = member.addr().to_string().replace('/', "%2F");
+    format!("{}/{}", MEMBERS_KEY, addr)
+}
+
 /// Read and deserialize some data item from a ZooKeeper instance.
 fn read<D>(zk: &ZooKeeper, key: &str) -> Result<D, String>
 where
@@ -99,14 +155,54 @@ where
         Ok((json, _)) => {
             from_slice(&json).map_err(|e| format!("failed to deserialize data from {}: {}", key, e))
         }
-        Err(ZkError::NoNode) => Ok(D::default()),
+        Err(ZkError::NoNode) => {
+            // Reading a node that does not exist does not leave a
+            // watch behind, so explicitly watch for its creation.
+            let _ = zk
+                .exists(key, watch)
+                .map_err(|e| format!("failed to set watch on {}: {}", key, e))?;
+            Ok(D::default())
+        }
         Err(e) => Err(format!("failed to retrieve data from {}: {}", key, e)),
     }
 }
 
+/// Members are comprised of the ones stored (as a serialized
+/// `Members` object) in the members node itself and the ones
+/// registered as (ephemeral) children of it.
 impl ReadMembers for ZooKeeper {
     fn members(&self) -> Result<Members, String> {
-        read(self, MEMBERS_KEY)
+        let members = read::<Members>(self, MEMBERS_KEY)?;
+        let watch = true;
+
+        let children = match self.get_children(MEMBERS_KEY, watch) {
+            Ok(children) => children,
+            Err(ZkError::NoNode) => Vec::new(),
+            Err(e) => {
+                return Err(format!(
+                    "failed to retrieve children of {}: {}",
+                    MEMBERS_KEY, e
+                ))
+            }
+        };
+
+        children
+            .into_iter()
+            .try_fold(members, |mut members, child| {
+                let key = format!("{}/{}", MEMBERS_KEY, child);
+                match self.get_data(&key, false) {
+                    Ok((json, _)) => {
+                        let member = from_slice(&json).map_err(|e| {
+                            format!("failed to deserialize data from {}: {}", key, e)
+                        })?;
+                        let _ = members.insert(member);
+                    }
+                    // The member may have vanished in the meantime.
+                    Err(ZkError::NoNode) => (),
+                    Err(e) => return Err(format!("failed to retrieve data from {}: {}", key, e)),
+                }
+                Ok(members)
+            })
     }
 }
 
@@ -116,9 +212,50 @@ impl ReadConfig for ZooKeeper {
     }
 }
 
+/// Members are registered as ephemeral nodes, meaning that they vanish
+/// once the session of the client that registered them ends.
+impl WriteMembers for ZooKeeper {
+    fn register(&self, member: &Member) -> Result<(), String> {
+        ensure(self, MEMBERS_KEY, serialize(&Members::new())?)?;
+
+        let key = member_key(member);
+        let result = self.create(
+            &key,
+            serialize(member)?,
+            Acl::open_unsafe().clone(),
+            CreateMode::Ephemeral,
+        );
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("failed to create {} node: {}", key, e)),
+        }
+    }
+
+    fn unregister(&self, member: &Member) -> Result<(), String> {
+        let key = member_key(member);
+        match self.delete(&key, None) {
+            Ok(()) | Err(ZkError::NoNode) => Ok(()),
+            Err(e) => Err(format!("failed to remove {}: {}", key, e)),
+        }
+    }
+}
+
+impl WriteConfig for ZooKeeper {
+    fn set_config(&self, config: &SysCfg) -> Result<(), String> {
+        ensure(self, CONFIG_KEY, serialize(&SysCfg::new())?)?;
+
+        let _ = self
+            .set_data(CONFIG_KEY, serialize(config)?, None)
+            .map_err(|e| format!("failed to update {}: {}", CONFIG_KEY, e))?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use maplit::btreemap;
     use serial_test_derive::serial;
+    use uuid::Uuid;
 
     use super::*;
 
@@ -139,4 +276,40 @@ mod tests {
         }
         assert_eq!(zk.members().unwrap(), Members::new())
     }
+
+    #[test]
+    #[serial(zookeeper)]
+    fn register_members() {
+        let zk = match from_env() {
+            Ok(zk) => zk,
+            Err(_) if var_os("IS_CI_RUN").is_none() => return,
+            Err(e) => panic!(e),
+        };
+
+        for addr in &["127.0.0.1:1", "unix:/run/d3log/node0.sock"] {
+            let member = Member::new(addr.parse().unwrap());
+            zk.register(&member).unwrap();
+            assert!(zk.members().unwrap().contains(&member));
+
+            zk.unregister(&member).unwrap();
+            assert!(!zk.members().unwrap().contains(&member));
+        }
+    }
+
+    #[test]
+    #[serial(zookeeper)]
+    fn publish_config() {
+        let zk = match from_env() {
+            Ok(zk) => zk,
+            Err(_) if var_os("IS_CI_RUN").is_none() => return,
+            Err(e) => panic!(e),
+        };
+
+        let config = btreemap! { Uuid::new_v4() => btreemap! {} };
+        zk.set_config(&config).unwrap();
+        assert_eq!(zk.config().unwrap(), config);
+
+        zk.set_config(&SysCfg::new()).unwrap();
+        assert_eq!(zk.config().unwrap(), SysCfg::new());
+    }
 }
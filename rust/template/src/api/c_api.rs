@@ -4,100 +4,538 @@
 use crate::{
     api::HDDlog,
     record::{Record, UpdCmd},
-    update_handler::{ExternCCallback, ExternCUpdateHandler, NullUpdateHandler},
+    update_handler::{BatchUpdateHandler, ExternCUpdateHandler, NullUpdateHandler, SubscriptionId},
 };
+use cmd_parser::{Command, CommandReader, ParseError, ReadError};
 use differential_datalog::{
     ddval::DDValue,
     program::{IdxId, RelId},
     record::IntoRecord,
-    DDlog, DeltaMap,
+    DDlog, DDlogError, DeltaMap, ErrorKind, ReplayFormat, UpdateThreads,
 };
 use std::{
+    cell::RefCell,
     collections::BTreeMap,
     ffi::{CStr, CString},
+    fmt::{self, Display, Formatter},
     fs::File,
+    io,
     os::raw,
-    ptr,
+    ptr, slice,
     sync::{Arc, Mutex},
 };
 
 #[cfg(feature = "flatbuf")]
-use std::{mem, slice};
+use std::mem;
 
 #[cfg(feature = "flatbuf")]
-use super::flatbuf;
+use crate::flatbuf;
 #[cfg(feature = "flatbuf")]
-use super::flatbuf::FromFlatBuffer;
+use crate::flatbuf::FromFlatBuffer;
 
 #[cfg(unix)]
 use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
 #[cfg(windows)]
 use std::os::windows::io::{FromRawHandle, IntoRawHandle, RawHandle};
 
-#[no_mangle]
-pub unsafe extern "C" fn ddlog_get_table_id(tname: *const raw::c_char) -> libc::size_t {
-    if tname.is_null() {
-        return libc::size_t::max_value();
+// Types used in the signatures of C API functions.  The C header (`ddlog.h`)
+// is generated from these signatures by cbindgen; types marked with
+// `cbindgen:ignore` are declared in `cbindgen.toml` instead, as their C
+// definitions differ from the Rust ones.
+
+/// Opaque handle to an instance of DDlog program.
+///
+/// cbindgen:ignore
+#[allow(non_camel_case_types)]
+pub type ddlog_prog = *const HDDlog;
+
+/// A set of changes to DDlog relations.
+///
+/// cbindgen:ignore
+#[allow(non_camel_case_types)]
+pub type ddlog_delta = DeltaMap<DDValue>;
+
+/// Unique DDlog table identifier
+#[allow(non_camel_case_types)]
+pub type table_id = libc::size_t;
+
+/// Unique DDlog index identifier
+#[allow(non_camel_case_types)]
+pub type index_id = libc::size_t;
+
+/// Callback invoked for every record added to or removed from an output
+/// relation.  Takes the following arguments:
+/// - `arg`    - opaque user-defined value
+/// - `table`  - table being modified
+/// - `rec`    - record that has been inserted or deleted
+/// - `weight` - change in the multiplicity of the record
+#[allow(non_camel_case_types)]
+pub type ddlog_update_callback = Option<
+    extern "C" fn(arg: libc::uintptr_t, table: table_id, rec: *const Record, weight: libc::ssize_t),
+>;
+
+/// Callback invoked with all changes made to an output relation by a
+/// transaction.  Takes the following arguments:
+/// - `arg`         - opaque user-defined value
+/// - `table`       - table being modified
+/// - `updates`     - array of records inserted into or deleted from the table
+/// - `num_updates` - number of elements in `updates`
+#[allow(non_camel_case_types)]
+pub type ddlog_batch_callback = Option<
+    extern "C" fn(
+        arg: libc::uintptr_t,
+        table: table_id,
+        updates: *const ddlog_record_update,
+        num_updates: libc::size_t,
+    ),
+>;
+
+/// Callback invoked with all changes made to an output relation by a
+/// transaction, serialized to a FlatBuffer.  Takes the following arguments:
+/// - `arg`   - opaque user-defined value
+/// - `table` - table being modified
+/// - `buf`   - FlatBuffer containing the changes
+/// - `size`  - size of the FlatBuffer in bytes
+#[allow(non_camel_case_types)]
+pub type ddlog_flatbuf_callback = Option<
+    extern "C" fn(arg: libc::uintptr_t, table: table_id, buf: *const u8, size: libc::size_t),
+>;
+
+/// Error codes.  Functions in this library signal failure through their
+/// return value (typically, -1 or NULL); the code and a human-readable
+/// description of the error can then be retrieved using
+/// `ddlog_last_error_code()` and `ddlog_last_error()`.
+///
+/// The numeric values of error codes are stable across DDlog releases.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ddlog_error_code {
+    /// The last call succeeded.
+    DDLOG_OK = 0,
+    /// NULL pointer or otherwise malformed argument.
+    DDLOG_ERR_INVALID_ARGUMENT = 1,
+    /// Unknown relation, or a relation of the wrong kind, e.g., an input
+    /// relation where an output relation is expected.
+    DDLOG_ERR_UNKNOWN_TABLE = 2,
+    DDLOG_ERR_UNKNOWN_INDEX = 3,
+    /// Insertion of a value whose primary key already exists.
+    DDLOG_ERR_DUPLICATE_KEY = 4,
+    /// Deletion or modification of a key that does not exist.
+    DDLOG_ERR_KEY_NOT_FOUND = 5,
+    /// The operation requires a transaction, but none was started.
+    DDLOG_ERR_NO_TRANSACTION = 6,
+    DDLOG_ERR_TRANSACTION_IN_PROGRESS = 7,
+    /// A record does not match the type of its relation.
+    DDLOG_ERR_INVALID_RECORD = 8,
+    /// A DDlog worker thread failed or stopped responding.
+    DDLOG_ERR_WORKER_FAILURE = 9,
+    DDLOG_ERR_IO = 10,
+    /// The program handle is in use by another thread.
+    DDLOG_ERR_BUSY = 11,
+    /// The operation is not supported by this build or configuration of the
+    /// program, e.g., FlatBuffers functions in a library compiled without
+    /// FlatBuffers support.
+    DDLOG_ERR_UNSUPPORTED = 12,
+    DDLOG_ERR_OTHER = 13,
+    /// Text passed to `ddlog_parse_record()` or `ddlog_parse_commands()` is
+    /// not valid DDlog syntax.  The error message contains the line and
+    /// column of the error.
+    DDLOG_ERR_PARSE = 14,
+}
+
+use ddlog_error_code::*;
+
+/// Error reported through the C API.
+#[derive(Clone, Debug)]
+pub struct CError {
+    code: ddlog_error_code,
+    message: String,
+}
+
+impl CError {
+    fn new<S>(code: ddlog_error_code, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            code,
+            message: message.into(),
+        }
     }
+}
 
-    let table_str = CStr::from_ptr(tname).to_str().unwrap();
-    match HDDlog::get_table_id(table_str) {
-        Ok(relid) => relid as libc::size_t,
-        Err(_) => libc::size_t::max_value(),
+impl Display for CError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
     }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn ddlog_get_table_name(tid: libc::size_t) -> *const raw::c_char {
-    match HDDlog::get_table_cname(tid) {
-        Ok(name) => name.as_ptr(),
-        Err(_) => ptr::null(),
+impl From<DDlogError> for CError {
+    fn from(e: DDlogError) -> Self {
+        let code = match e.kind() {
+            ErrorKind::InvalidArgument => DDLOG_ERR_INVALID_ARGUMENT,
+            ErrorKind::UnknownRelation => DDLOG_ERR_UNKNOWN_TABLE,
+            ErrorKind::UnknownIndex => DDLOG_ERR_UNKNOWN_INDEX,
+            ErrorKind::DuplicateKey => DDLOG_ERR_DUPLICATE_KEY,
+            ErrorKind::KeyNotFound => DDLOG_ERR_KEY_NOT_FOUND,
+            ErrorKind::NoTransaction => DDLOG_ERR_NO_TRANSACTION,
+            ErrorKind::TransactionInProgress => DDLOG_ERR_TRANSACTION_IN_PROGRESS,
+            ErrorKind::InvalidRecord => DDLOG_ERR_INVALID_RECORD,
+            ErrorKind::WorkerFailure => DDLOG_ERR_WORKER_FAILURE,
+            ErrorKind::Unsupported => DDLOG_ERR_UNSUPPORTED,
+            ErrorKind::Other => DDLOG_ERR_OTHER,
+        };
+        Self::new(code, e.message())
     }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn ddlog_get_index_id(iname: *const raw::c_char) -> libc::size_t {
-    if iname.is_null() {
-        return libc::size_t::max_value();
+/// Errors that are not classified by the Rust API, e.g., ones returned by
+/// the program's `run` function.
+impl From<String> for CError {
+    fn from(message: String) -> Self {
+        Self::new(DDLOG_ERR_OTHER, message)
     }
+}
 
-    let index_str = CStr::from_ptr(iname).to_str().unwrap();
-    match HDDlog::get_index_id(index_str) {
-        Ok(idxid) => idxid as libc::size_t,
-        Err(_) => libc::size_t::max_value(),
+impl From<io::Error> for CError {
+    fn from(e: io::Error) -> Self {
+        Self::new(DDLOG_ERR_IO, e.to_string())
     }
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn ddlog_get_index_name(iid: libc::size_t) -> *const raw::c_char {
-    match HDDlog::get_index_cname(iid) {
-        Ok(name) => name.as_ptr(),
-        Err(_) => ptr::null(),
+impl From<ParseError> for CError {
+    fn from(e: ParseError) -> Self {
+        Self::new(DDLOG_ERR_PARSE, e.to_string())
     }
 }
 
+thread_local! {
+    /// The error reported by the last failed C API call made by this
+    /// thread, with the name of the failed function prepended to the
+    /// message.
+    static LAST_ERROR: RefCell<Option<(ddlog_error_code, CString)>> = RefCell::new(None);
+}
+
+/// Forget the last error of the calling thread.  Invoked at the start of
+/// every C API function, so that `ddlog_last_error_code()` reflects the
+/// outcome of the last call.
+pub(crate) fn clear_last_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+fn set_last_error(func: &str, e: &CError) {
+    // Interior NUL characters cannot be represented in a C string.
+    let message = format!("{}(): {}", func, e.message).replace('\0', "\\0");
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((e.code, message)));
+}
+
+/// Report that `func` failed with error `e`: store the error as the last
+/// error of the calling thread and print it via the `print_err` callback
+/// of `prog`.  Returns `ret`, the value that `func` returns on failure.
+pub(crate) fn report_error<T, E>(prog: &HDDlog, func: &str, e: E, ret: T) -> T
+where
+    E: Into<CError>,
+{
+    let e = e.into();
+    prog.eprintln(&format!("{}(): error: {}", func, e));
+    set_last_error(func, &e);
+    ret
+}
+
+/// Report that `func` was invoked with a NULL or otherwise invalid `arg`.
+/// Returns `ret`.
+pub(crate) fn null_argument<T>(func: &str, arg: &str, ret: T) -> T {
+    let e = CError::new(
+        DDLOG_ERR_INVALID_ARGUMENT,
+        format!("invalid or NULL argument '{}'", arg),
+    );
+    set_last_error(func, &e);
+    ret
+}
+
+/// Convert a string argument of `func` into a `&str`, reporting an error if
+/// it is NULL or not valid UTF-8.
+unsafe fn str_argument<'a>(func: &str, arg: &str, s: *const raw::c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return null_argument(func, arg, None);
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Some(s),
+        Err(e) => {
+            let e = CError::new(DDLOG_ERR_INVALID_ARGUMENT, format!("'{}': {}", arg, e));
+            set_last_error(func, &e);
+            None
+        }
+    }
+}
+
+/// Store the outcome of `func` in the last error of the calling thread,
+/// without printing it.  Used by functions that do not take a program
+/// handle.
+fn check<T, E>(func: &str, result: Result<T, E>) -> Option<T>
+where
+    E: Into<CError>,
+{
+    result.map_err(|e| set_last_error(func, &e.into())).ok()
+}
+
+/// Returns the code of the error reported by the last `ddlog_XXX()` call
+/// made by the calling thread, or `DDLOG_OK` if the call succeeded.
+///
+/// Every function in this library (except functions that manipulate
+/// records, e.g., `ddlog_bool()`, `ddlog_get_int()`, etc.) resets the error
+/// state of the calling thread on entry.  Error state is thread-local, so
+/// it is safe to call this function while other threads use the same DDlog
+/// program.
+#[no_mangle]
+pub extern "C" fn ddlog_last_error_code() -> ddlog_error_code {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(DDLOG_OK, |(code, _)| *code))
+}
+
+/// Returns a null-terminated description of the error reported by the last
+/// `ddlog_XXX()` call made by the calling thread, or NULL if the call
+/// succeeded.  The message starts with the name of the failed function, e.g.,
+/// "ddlog_transaction_commit(): no transaction in progress".
+///
+/// The string is owned by DDlog and must not be deallocated by the caller.
+/// It remains valid until the next `ddlog_XXX()` call in the same thread.
+#[no_mangle]
+pub extern "C" fn ddlog_last_error() -> *const raw::c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |(_, message)| message.as_ptr())
+    })
+}
+
+/// Get DDlog table id by name.  The table name is a null-terminated UTF8
+/// string.
+///
+/// NOTE: for tables declared outside of the main DDlog module, fully qualified
+/// module names must be used, e.g., the fully qualified name of a table named
+/// "Pod" declared inside the "k8spolicy" module is "k8spolicy.Pod".
+///
+/// On error, returns -1.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_get_table_id(tname: *const raw::c_char) -> table_id {
+    clear_last_error();
+    str_argument("ddlog_get_table_id", "tname", tname)
+        .and_then(|table_str| check("ddlog_get_table_id", HDDlog::get_table_id(table_str)))
+        .map_or(table_id::max_value(), |relid| relid as table_id)
+}
+
+/// Get DDlog table name from id.
+///
+/// Returns a null-terminated UTF8 string on success or NULL on error.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_get_table_name(tid: table_id) -> *const raw::c_char {
+    clear_last_error();
+    check("ddlog_get_table_name", HDDlog::get_table_cname(tid))
+        .map_or(ptr::null(), |name| name.as_ptr())
+}
+
+/// Get DDlog index id by name.  The index name is a null-terminated UTF8
+/// string.
+///
+/// NOTE: for indexes declared outside of the main DDlog module, fully qualified
+/// module names must be used.
+///
+/// On error, returns -1.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_get_index_id(iname: *const raw::c_char) -> index_id {
+    clear_last_error();
+    str_argument("ddlog_get_index_id", "iname", iname)
+        .and_then(|index_str| check("ddlog_get_index_id", HDDlog::get_index_id(index_str)))
+        .map_or(index_id::max_value(), |idxid| idxid as index_id)
+}
+
+/// Get DDlog index name from id.
+///
+/// Returns a null-terminated UTF8 string on success or NULL on error.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_get_index_name(iid: index_id) -> *const raw::c_char {
+    clear_last_error();
+    check("ddlog_get_index_name", HDDlog::get_index_cname(iid))
+        .map_or(ptr::null(), |name| name.as_ptr())
+}
+
+/// Create an instance of DDlog program.
+///
+/// `workers` is the number of DDlog worker threads that will be
+/// allocated to run the program.  While any positive integer value is
+/// valid, values larger than the number of cores in the system are
+/// likely to hurt the performance.
+///
+/// `do_store` - set to true to store the copy of output tables inside DDlog.
+/// When set, the client can use the following APIs to retrieve the contents of
+/// tables:
+///      - `ddlog_dump_ovsdb_delta_tables()`
+///      - `ddlog_dump_table()`
+/// This has a cost in terms of memory and CPU.  In addition, the current implementation
+/// serializes all writes to its internal copies of tables, introducing contention
+/// when `workers > 1`.  Therefore, this flag should be set to `false` if the
+/// client prefers to use DDlog in streaming mode, via the callback mechanism
+/// (see below).
+///
+/// `cb` - callback to be invoked for every record added to or removed from an
+/// output relation.  DDlog guarantees that the callback can only be invoked in
+/// two situations: (1) from the `ddlog_run()` function, as the DDlog program
+/// is initialized with static records (if any), (2) from
+/// `ddlog_transaction_commit()`, as DDlog applies updates performed by the
+/// transaction.
+///   The `cb` function takes the following arguments:
+///     - `arg`    - opaque used-defined value
+///     - `table`  - table being modified
+///     - `rec`    - record that has been inserted or deleted
+///     - `weight` - change in the multiplicity of the record.  The same record can
+///                  be inserted (callback invoked with positive weight) and deleted
+///                  (callback invoked with negative weight) multiple times during
+///                  transaction commit.  In order to determine how the membership of
+///                  the value in the relation was changed by the transaction, sum up
+///                  all its weights.  The result of +1 means that the record was
+///                  inserted; -1 - record was deleted; 0 - record's membership
+///                  did not change.
+/// `init_state` - when not NULL, DDlog will store a pointer to `ddlog_delta`
+/// containing initial snapshot of output relations at this address.  The caller
+/// is responsible for freeing this delta, e.g., using `ddlog_free_delta()`.
+///
+/// IMPORTANT: Thread safety: DDlog invokes the callback from its worker threads
+/// without any serialization to avoid contention. Hence, if the `workers` argument
+/// is greater than 1, then `cb` must be prepared to handle concurrent invocations
+/// from multiple threads.
+///
+/// IMPORTANT: DDlog does not guarantee that callback is invoked at most once for
+/// each record and for each transaction.  Depending on your specific dataflow,
+/// it is possible that DDlog will, e.g., create and then delete a record within a
+/// transaction, and invoke `cb` both times.
+///
+/// Setting `cb` to NULL disables notifications.
+///
+/// `print_err_msg` - callback to redirect diagnostic messages to.  Before
+/// returning an error, functions in this API invoke this callback to print
+/// error explanation.
+///
+/// Setting `print_err_msg` to NULL causes ddlog to print to `stderr`.
+///
+/// Returns a program handle to be used in subsequent calls to
+/// `ddlog_transaction_start()`,
+/// `ddlog_transaction_commit()`, etc., or NULL in case of error.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_run(
     workers: raw::c_uint,
     do_store: bool,
-    cb: Option<ExternCCallback>,
+    cb: ddlog_update_callback,
+    cb_arg: libc::uintptr_t,
+    print_err_msg: Option<extern "C" fn(msg: *const raw::c_char)>,
+    init_state: *mut *mut ddlog_delta,
+) -> ddlog_prog {
+    run(
+        "ddlog_run",
+        workers,
+        do_store,
+        UpdateThreads::Single,
+        cb,
+        cb_arg,
+        print_err_msg,
+        init_state,
+    )
+}
+
+/// Like `ddlog_run()`, but invokes `cb` from a pool of `update_threads`
+/// threads rather than from a single thread, so that a slow callback does
+/// not stall the program as much.  Tables are sharded among the threads:
+/// all changes to a table are delivered by the same thread, in order, but
+/// changes to different tables may be delivered concurrently, and `cb`
+/// must be prepared to handle that.  Changes are still delivered before
+/// `ddlog_transaction_commit()` returns.
+///
+/// `update_threads` of 0 or 1 is equivalent to `ddlog_run()`.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_run_with_update_threads(
+    workers: raw::c_uint,
+    do_store: bool,
+    update_threads: raw::c_uint,
+    cb: ddlog_update_callback,
+    cb_arg: libc::uintptr_t,
+    print_err_msg: Option<extern "C" fn(msg: *const raw::c_char)>,
+    init_state: *mut *mut ddlog_delta,
+) -> ddlog_prog {
+    let update_threads = if update_threads > 1 {
+        UpdateThreads::Pool {
+            threads: update_threads as usize,
+        }
+    } else {
+        UpdateThreads::Single
+    };
+    run(
+        "ddlog_run_with_update_threads",
+        workers,
+        do_store,
+        update_threads,
+        cb,
+        cb_arg,
+        print_err_msg,
+        init_state,
+    )
+}
+
+/// Like `ddlog_run()`, but invokes `cb` asynchronously from a separate
+/// thread: `ddlog_transaction_commit()` returns without waiting for `cb` to
+/// see the changes, as long as at most `queue_size` changes are pending,
+/// so that a slow callback does not stall the program.  Changes are
+/// delivered in order.  Use `ddlog_flush_updates()` to wait until `cb` has
+/// seen all committed changes; `ddlog_stop()` does so before it returns.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_run_async(
+    workers: raw::c_uint,
+    do_store: bool,
+    queue_size: libc::size_t,
+    cb: ddlog_update_callback,
+    cb_arg: libc::uintptr_t,
+    print_err_msg: Option<extern "C" fn(msg: *const raw::c_char)>,
+    init_state: *mut *mut ddlog_delta,
+) -> ddlog_prog {
+    run(
+        "ddlog_run_async",
+        workers,
+        do_store,
+        UpdateThreads::Async { queue_size },
+        cb,
+        cb_arg,
+        print_err_msg,
+        init_state,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn run(
+    func: &str,
+    workers: raw::c_uint,
+    do_store: bool,
+    update_threads: UpdateThreads,
+    cb: ddlog_update_callback,
     cb_arg: libc::uintptr_t,
-    print_err: Option<extern "C" fn(msg: *const raw::c_char)>,
-    init_state: *mut *mut DeltaMap<DDValue>,
-) -> *const HDDlog {
+    print_err_msg: Option<extern "C" fn(msg: *const raw::c_char)>,
+    init_state: *mut *mut ddlog_delta,
+) -> ddlog_prog {
+    clear_last_error();
     let result = if let Some(f) = cb {
         HDDlog::do_run(
             workers as usize,
             do_store,
+            update_threads,
             ExternCUpdateHandler::new(f, cb_arg),
-            print_err,
+            print_err_msg,
         )
     } else {
         HDDlog::do_run(
             workers as usize,
             do_store,
+            update_threads,
             NullUpdateHandler::new(),
-            print_err,
+            print_err_msg,
         )
     };
 
@@ -109,81 +547,187 @@ pub unsafe extern "C" fn ddlog_run(
             Arc::into_raw(Arc::new(hddlog))
         }
         Err(err) => {
-            HDDlog::print_err(print_err, &format!("ddlog_run() failed: {}", err));
+            HDDlog::print_err(print_err_msg, &format!("{}() failed: {}", func, err));
+            set_last_error(func, &err.into());
             ptr::null()
         }
     }
 }
 
+/// Record commands issued to DDlog via this API in a file.
+///
+/// This is a debugging feature used to record DDlog commands issued through
+/// functions in this file in a DDlog command file that can later be replayed
+/// through the CLI interface.
+///
+/// `fd` - file descriptor.  Passing -1 in this argument instructs DDlog to stop
+/// recording. The caller is responsible for opening and closing the file.  They
+/// can inject additional commands, e.g., `echo` to the file.
+///
+/// IMPORTANT: this function is _not_ thread-safe and must not be invoked
+/// concurrently with other functions in this API.
 #[no_mangle]
 #[cfg(unix)]
-pub unsafe extern "C" fn ddlog_record_commands(prog: *const HDDlog, fd: RawFd) -> raw::c_int {
-    if prog.is_null() {
-        return -1;
-    }
-    let mut prog = Arc::from_raw(prog);
-
-    let file = if fd == -1 {
-        None
-    } else {
-        Some(File::from_raw_fd(fd))
-    };
+pub unsafe extern "C" fn ddlog_record_commands(prog: ddlog_prog, fd: RawFd) -> raw::c_int {
+    record_commands(prog, fd, ReplayFormat::Text, "ddlog_record_commands")
+}
 
-    let res = match Arc::get_mut(&mut prog) {
-        Some(prog) => {
-            let mut old_file = file.map(Mutex::new);
-            prog.record_commands(&mut old_file);
-            /* Convert the old file into FD to prevent it from closing.
-             * It is the caller's responsibility to close the file when
-             * they are done with it. */
-            old_file.map(|m| m.into_inner().unwrap().into_raw_fd());
-            0
-        }
-        None => -1,
-    };
+/// cbindgen:ignore
+#[no_mangle]
+#[cfg(windows)]
+pub unsafe extern "C" fn ddlog_record_commands(prog: ddlog_prog, fd: raw::c_int) -> raw::c_int {
+    record_commands(prog, fd, ReplayFormat::Text, "ddlog_record_commands")
+}
 
-    Arc::into_raw(prog);
-    res
+/// Like `ddlog_record_commands()`, but records commands in a compact binary
+/// format that is faster to write and to replay than the text format.  The CLI
+/// detects the format of its input automatically, and its `--convert-replay`
+/// option converts between the two formats.
+///
+/// Binary recordings cannot be mixed with text: do not use
+/// `ddlog_dump_input_snapshot()` or inject commands into the file.
+///
+/// IMPORTANT: this function is _not_ thread-safe and must not be invoked
+/// concurrently with other functions in this API.
+#[no_mangle]
+#[cfg(unix)]
+pub unsafe extern "C" fn ddlog_record_commands_binary(prog: ddlog_prog, fd: RawFd) -> raw::c_int {
+    record_commands(
+        prog,
+        fd,
+        ReplayFormat::Binary,
+        "ddlog_record_commands_binary",
+    )
 }
 
+/// cbindgen:ignore
 #[no_mangle]
 #[cfg(windows)]
-pub unsafe extern "C" fn ddlog_record_commands(prog: *const HDDlog, fd: raw::c_int) -> raw::c_int {
+pub unsafe extern "C" fn ddlog_record_commands_binary(
+    prog: ddlog_prog,
+    fd: raw::c_int,
+) -> raw::c_int {
+    record_commands(
+        prog,
+        fd,
+        ReplayFormat::Binary,
+        "ddlog_record_commands_binary",
+    )
+}
+
+/// Start recording commands in `format` to the file with descriptor `fd`,
+/// or stop recording if `fd` is -1, on behalf of the API function
+/// `function`.
+unsafe fn record_commands(
+    prog: ddlog_prog,
+    fd: raw::c_int,
+    format: ReplayFormat,
+    function: &str,
+) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument(function, "prog", -1);
     }
     let mut prog = Arc::from_raw(prog);
 
     let file = if fd == -1 {
         None
     } else {
-        // Convert file descriptor to file handle on Windows.
-        let handle = libc::get_osfhandle(fd);
-        Some(File::from_raw_handle(handle as RawHandle))
+        Some(file_from_fd(fd))
     };
 
     let res = match Arc::get_mut(&mut prog) {
         Some(prog) => {
             let mut old_file = file.map(Mutex::new);
-            prog.record_commands(&mut old_file);
+            let res = prog
+                .record_commands_as(&mut old_file, format)
+                .map(|_| 0)
+                .unwrap_or_else(|e| report_error(prog, function, e, -1));
             /* Convert the old file into FD to prevent it from closing.
              * It is the caller's responsibility to close the file when
              * they are done with it. */
-            old_file.map(|m| m.into_inner().unwrap().into_raw_handle());
-            0
+            if let Some(old_file) = old_file {
+                release_file(old_file.into_inner().unwrap());
+            }
+            res
+        }
+        None => {
+            let e = CError::new(DDLOG_ERR_BUSY, "the program is in use by another thread");
+            report_error(&prog, function, e, -1)
         }
-        None => -1,
     };
 
     Arc::into_raw(prog);
     res
 }
 
+/// Wrap the file descriptor `fd`, which remains owned by the caller, in a
+/// `File`.
+#[cfg(unix)]
+unsafe fn file_from_fd(fd: raw::c_int) -> File {
+    File::from_raw_fd(fd)
+}
+
+#[cfg(windows)]
+unsafe fn file_from_fd(fd: raw::c_int) -> File {
+    // Convert file descriptor to file handle on Windows.
+    let handle = libc::get_osfhandle(fd);
+    File::from_raw_handle(handle as RawHandle)
+}
+
+/// Give up ownership of a file created by `file_from_fd` without closing
+/// it.
+#[cfg(unix)]
+fn release_file(file: File) {
+    let _ = file.into_raw_fd();
+}
+
+#[cfg(windows)]
+fn release_file(file: File) {
+    let _ = file.into_raw_handle();
+}
+
+/// Dump current snapshot of input tables to a file in a format suitable
+/// for replay debugging.
+///
+/// This function is intended to be used in conjunction with
+/// `ddlog_record_commands`.  It is useful if one wants to start recording
+/// after the program has been running for some time, or if the current log is
+/// full and needs to be rotated out.  Simply calling `ddlog_record_commands`
+/// with a new file descriptor at this point will generate an incomplete log that
+/// will not reflect the state of input tables at the time recording is starting.
+/// Such a log cannot be replayed in a meaningful way.
+///
+/// Instead, we would like to start replay from the current input state (which
+/// is in essence a compressed representation of the entire previous execution
+/// history).  This can be achieved by first calling this function to store a
+/// snapshot of input tables, followed by `ddlog_record_commands()` to continue
+/// recording subsequent commands to the same file.
+///
+/// This function generates input snapshot in the following format:
+///
+/// ```text
+/// insert Table1[val1],
+/// insert Table1[val2],
+/// insert Table2[val3],
+/// insert Table2[val4],
+/// ...
+/// ```
+///
+/// NOTE: it does not wrap its output in a transaction.  The caller is
+/// responsible for injecting `start;` and `commit;` commands, if necessary.
+///
+/// `fd` - valid writable file descriptor.  The caller is responsible for opening
+/// and closing the file.
 #[no_mangle]
 #[cfg(unix)]
-pub unsafe extern "C" fn ddlog_dump_input_snapshot(prog: *const HDDlog, fd: RawFd) -> raw::c_int {
-    if prog.is_null() || fd < 0 {
-        return -1;
+pub unsafe extern "C" fn ddlog_dump_input_snapshot(prog: ddlog_prog, fd: RawFd) -> raw::c_int {
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_dump_input_snapshot", "prog", -1);
+    }
+    if fd < 0 {
+        return null_argument("ddlog_dump_input_snapshot", "fd", -1);
     }
 
     let prog = Arc::from_raw(prog);
@@ -191,24 +735,23 @@ pub unsafe extern "C" fn ddlog_dump_input_snapshot(prog: *const HDDlog, fd: RawF
     let res = prog
         .dump_input_snapshot(&mut file)
         .map(|_| 0)
-        .unwrap_or_else(|e| {
-            prog.eprintln(&format!("ddlog_dump_input_snapshot: error: {}", e));
-            -1
-        });
+        .unwrap_or_else(|e| report_error(&prog, "ddlog_dump_input_snapshot", e, -1));
 
     file.into_raw_fd();
     Arc::into_raw(prog);
     res
 }
 
+/// cbindgen:ignore
 #[no_mangle]
 #[cfg(windows)]
-pub unsafe extern "C" fn ddlog_dump_input_snapshot(
-    prog: *const HDDlog,
-    fd: raw::c_int,
-) -> raw::c_int {
-    if prog.is_null() || fd < 0 {
-        return -1;
+pub unsafe extern "C" fn ddlog_dump_input_snapshot(prog: ddlog_prog, fd: raw::c_int) -> raw::c_int {
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_dump_input_snapshot", "prog", -1);
+    }
+    if fd < 0 {
+        return null_argument("ddlog_dump_input_snapshot", "fd", -1);
     }
 
     let prog = &*prog;
@@ -218,19 +761,27 @@ pub unsafe extern "C" fn ddlog_dump_input_snapshot(
     let res = prog
         .dump_input_snapshot(&mut file)
         .map(|_| 0)
-        .unwrap_or_else(|e| {
-            prog.eprintln(&format!("ddlog_dump_input_snapshot: error: {}", e));
-            -1
-        });
+        .unwrap_or_else(|e| report_error(prog, "ddlog_dump_input_snapshot", e, -1));
 
     file.into_raw_handle();
     res
 }
 
+/// Stops the program; deallocates all resources, invalidates the handle.
+///
+/// All concurrent calls using the handle must complete before calling this
+/// function.
+///
+/// On success, returns `0`; on error, returns `-1` and prints error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// IMPORTANT: this function is _not_ thread-safe and must not be invoked
+/// concurrently with other functions in this API.
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_stop(prog: *const HDDlog) -> raw::c_int {
+pub unsafe extern "C" fn ddlog_stop(prog: ddlog_prog) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_stop", "prog", -1);
     }
     /* Prevents closing of the old descriptor. */
     ddlog_record_commands(prog, -1);
@@ -238,12 +789,22 @@ pub unsafe extern "C" fn ddlog_stop(prog: *const HDDlog) -> raw::c_int {
     let prog = Arc::from_raw(prog);
     match Arc::try_unwrap(prog) {
         Ok(HDDlog {
-            prog, print_err, ..
+            prog,
+            print_err,
+            async_updates,
+            ..
         }) => prog
             .into_inner()
             .map(|mut p| {
-                p.stop().map(|_| 0).unwrap_or_else(|e| {
+                let result = p.stop().and_then(|_| match async_updates {
+                    Some(handler) => handler
+                        .flush()
+                        .map_err(|e| DDlogError::new(ErrorKind::WorkerFailure, e)),
+                    None => Ok(()),
+                });
+                result.map(|_| 0).unwrap_or_else(|e| {
                     HDDlog::print_err(print_err, &format!("ddlog_stop(): error: {}", e));
+                    set_last_error("ddlog_stop", &e.into());
                     -1
                 })
             })
@@ -252,82 +813,125 @@ pub unsafe extern "C" fn ddlog_stop(prog: *const HDDlog) -> raw::c_int {
                     print_err,
                     &format!("ddlog_stop(): error acquiring lock: {}", e),
                 );
+                let e = CError::new(
+                    DDLOG_ERR_WORKER_FAILURE,
+                    format!("error acquiring lock: {}", e),
+                );
+                set_last_error("ddlog_stop", &e);
                 -1
             }),
         Err(pref) => {
             pref.eprintln("ddlog_stop(): cannot extract value from Arc");
+            let e = CError::new(DDLOG_ERR_BUSY, "the program is in use by another thread");
+            set_last_error("ddlog_stop", &e);
             -1
         }
     }
 }
 
+/// Start a transaction.
+///
+/// On success, returns `0`; on error, returns `-1` and prints error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// This function will fail if another transaction is in progress.
+///
+/// Within a transaction, updates to input relations are buffered until
+/// `ddlog_transaction_commit()` is called.
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_transaction_start(prog: *const HDDlog) -> raw::c_int {
+pub unsafe extern "C" fn ddlog_transaction_start(prog: ddlog_prog) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_transaction_start", "prog", -1);
     }
     let prog = &*prog;
 
-    prog.transaction_start().map(|_| 0).unwrap_or_else(|e| {
-        prog.eprintln(&format!("ddlog_transaction_start(): error: {}", e));
-        -1
-    })
+    prog.transaction_start()
+        .map(|_| 0)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_transaction_start", e, -1))
 }
 
+/// Commit a transaction; propagate all buffered changes through all
+/// rules in the program and update all output relations and returns
+/// the set of changes.  On error, returns `NULL` and prints error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// This function will fail if there is no transaction in progress.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_transaction_commit_dump_changes(
-    prog: *const HDDlog,
-) -> *mut DeltaMap<DDValue> {
+    prog: ddlog_prog,
+) -> *mut ddlog_delta {
+    clear_last_error();
     if prog.is_null() {
-        return ptr::null_mut();
+        return null_argument(
+            "ddlog_transaction_commit_dump_changes",
+            "prog",
+            ptr::null_mut(),
+        );
     }
     let prog = &*prog;
 
     prog.transaction_commit_dump_changes()
         .map(|delta| Box::into_raw(Box::new(delta)))
         .unwrap_or_else(|e| {
-            prog.eprintln(&format!(
-                "ddlog_transaction_commit_dump_changes: error: {}",
-                e
-            ));
-            ptr::null_mut()
+            report_error(
+                prog,
+                "ddlog_transaction_commit_dump_changes",
+                e,
+                ptr::null_mut(),
+            )
         })
 }
 
 #[repr(C)]
 pub struct ddlog_record_update {
-    table: libc::size_t,
-    rec: *mut Record,
-    w: libc::ssize_t,
+    pub table: table_id,
+    pub rec: *mut Record,
+    pub weight: libc::ssize_t,
 }
 
+/// Commit a transaction; propagate all buffered changes through all
+/// rules in the program and update all output relations.  Once all
+/// updates are finished, returns an array of changes to output relations.
+/// Each record occurs in the array at most once.
+///
+/// NOTE: The array returned by this function is owned by DDlog and must be
+/// deallocated (along with all it contents) using the
+/// `ddlog_free_record_updates()` function.
+///
+/// On success, returns `0`; on error, returns `-1` and prints error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// This function will fail if there is no transaction in progress.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_transaction_commit_dump_changes_as_array(
-    prog: *const HDDlog,
-    changes: *mut *const ddlog_record_update,
+    prog: ddlog_prog,
+    changes: *mut *mut ddlog_record_update,
     num_changes: *mut libc::size_t,
 ) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_transaction_commit_dump_changes_as_array", "prog", -1);
     }
     let prog = &*prog;
 
     do_transaction_commit_dump_changes_as_array(prog, changes, num_changes)
         .map(|_| 0)
         .unwrap_or_else(|e| {
-            prog.eprintln(&format!(
-                "ddlog_transaction_commit_dump_changes_as_array: error: {}",
-                e
-            ));
-            -1
+            report_error(
+                prog,
+                "ddlog_transaction_commit_dump_changes_as_array",
+                e,
+                -1,
+            )
         })
 }
 
 unsafe fn do_transaction_commit_dump_changes_as_array(
     prog: &HDDlog,
-    changes: *mut *const ddlog_record_update,
+    changes: *mut *mut ddlog_record_update,
     num_changes: *mut libc::size_t,
-) -> Result<(), String> {
+) -> Result<(), DDlogError> {
     let updates = prog.transaction_commit_dump_changes()?;
     let mut size = 0;
     for (_, delta) in updates.as_ref().iter() {
@@ -342,22 +946,30 @@ unsafe fn do_transaction_commit_dump_changes_as_array(
             change_vec.push(ddlog_record_update {
                 table: rel,
                 rec: Box::into_raw(Box::new(val.into_record())),
-                w,
+                weight: w,
             });
         }
     }
 
-    *changes = change_vec.as_ptr();
+    *changes = change_vec.as_mut_ptr();
     std::mem::forget(change_vec);
 
     Ok(())
 }
 
+/// Deallocate array of updates returned by
+/// `ddlog_transaction_commit_dump_changes_as_array()`.  Both `changes` and
+/// `num_changes` arguments must be equal to the values returned by a successful
+/// call `ddlog_transaction_commit_dump_changes_as_array`.
+///
+/// This function invalidates record handles stored in the `changes` array;
+/// they must not be accessed after the call.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_free_record_updates(
     changes: *mut ddlog_record_update,
     num_changes: libc::size_t,
 ) {
+    clear_last_error();
     // Assume that vector's capacity is equal to its length.
     let changes_vec: Vec<ddlog_record_update> =
         Vec::from_raw_parts(changes, num_changes as usize, num_changes as usize);
@@ -366,24 +978,60 @@ pub unsafe extern "C" fn ddlog_free_record_updates(
     }
 }
 
+/// Same as `ddlog_transaction_commit_dump_changes`, but serializes changes to a
+/// FlatBuffer.  On success, returns pointer to FlatBuffer, size and capacity of
+/// the buffer, and offset where valid data starts inside the buffer.
 #[cfg(feature = "flatbuf")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_transaction_commit_dump_changes_to_flatbuf(
-    prog: *const HDDlog,
-    buf: *mut *const u8,
+    prog: ddlog_prog,
+    buf: *mut *mut u8,
     buf_size: *mut libc::size_t,
     buf_capacity: *mut libc::size_t,
     buf_offset: *mut libc::size_t,
 ) -> raw::c_int {
-    if prog.is_null() || buf_size.is_null() || buf_capacity.is_null() || buf_offset.is_null() {
-        return -1;
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument(
+            "ddlog_transaction_commit_dump_changes_to_flatbuf",
+            "prog",
+            -1,
+        );
+    }
+    if buf.is_null() {
+        return null_argument(
+            "ddlog_transaction_commit_dump_changes_to_flatbuf",
+            "buf",
+            -1,
+        );
+    }
+    if buf_size.is_null() {
+        return null_argument(
+            "ddlog_transaction_commit_dump_changes_to_flatbuf",
+            "buf_size",
+            -1,
+        );
+    }
+    if buf_capacity.is_null() {
+        return null_argument(
+            "ddlog_transaction_commit_dump_changes_to_flatbuf",
+            "buf_capacity",
+            -1,
+        );
+    }
+    if buf_offset.is_null() {
+        return null_argument(
+            "ddlog_transaction_commit_dump_changes_to_flatbuf",
+            "buf_offset",
+            -1,
+        );
     }
     let prog = &*prog;
 
     prog.transaction_commit_dump_changes()
         .map(|changes| {
-            let (fbvec, fboffset) = flatbuf::updates_to_flatbuf(&changes);
-            *buf = fbvec.as_ptr();
+            let (mut fbvec, fboffset) = flatbuf::updates_to_flatbuf(&changes);
+            *buf = fbvec.as_mut_ptr();
             *buf_size = fbvec.len() as libc::size_t;
             *buf_capacity = fbvec.capacity() as libc::size_t;
             *buf_offset = fboffset as libc::size_t;
@@ -391,61 +1039,105 @@ pub unsafe extern "C" fn ddlog_transaction_commit_dump_changes_to_flatbuf(
             0
         })
         .unwrap_or_else(|e| {
-            prog.eprintln(&format!(
-                "ddlog_transaction_commit_dump_changes_to_flatbuf: error: {}",
-                e
-            ));
-            -1
+            report_error(
+                prog,
+                "ddlog_transaction_commit_dump_changes_to_flatbuf",
+                e,
+                -1,
+            )
         })
 }
 
+/// cbindgen:ignore
 #[cfg(not(feature = "flatbuf"))]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_transaction_commit_dump_changes_to_flatbuf(
-    prog: *const HDDlog,
-    _buf: *mut *const u8,
+    prog: ddlog_prog,
+    _buf: *mut *mut u8,
     _buf_size: *mut libc::size_t,
     _buf_capacity: *mut libc::size_t,
     _buf_offset: *mut libc::size_t,
 ) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument(
+            "ddlog_transaction_commit_dump_changes_to_flatbuf",
+            "prog",
+            -1,
+        );
     }
 
     let prog = &*prog;
-    prog.eprintln("ddlog_transaction_commit_dump_changes_to_flatbuf(): error: DDlog was compiled without FlatBuffers support");
-
-    -1
+    let e = CError::new(
+        DDLOG_ERR_UNSUPPORTED,
+        "DDlog was compiled without FlatBuffers support",
+    );
+    report_error(
+        prog,
+        "ddlog_transaction_commit_dump_changes_to_flatbuf",
+        e,
+        -1,
+    )
 }
 
+/// Perform a query serialized in a flatbuf; return result in another flatbuf.
+///
+/// The FlatBuffer schema is auto-generated from DDlog code and is stored in
+/// `flatbuf/flatbuf.rs`.
+///
+/// `buf` - pointer to FlatBuffer of size `n` containing the query.
+///
+/// The following values returned by the function describe the flatbuffer
+/// containing response to the query.
+/// `resbuf` - address where the function will write the pointer to the reply
+/// flatbuf.
+/// `resbuf_size` - address to store the size of the generated flatbuf.
+/// `resbuf_capacity` - address to store generated flatbuf capacity.
+/// `resbuf_offset` - address to store data offset inside the returned buffer.
+///
+/// On success, returns `0`. On error, returns a negative value and
+/// writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// Like `ddlog_dump_index()`, this function is thread-safe.
 #[cfg(feature = "flatbuf")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_query_index_from_flatbuf(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     buf: *const u8,
     n: libc::size_t,
-    resbuf: *mut *const u8,
+    resbuf: *mut *mut u8,
     resbuf_size: *mut libc::size_t,
     resbuf_capacity: *mut libc::size_t,
     resbuf_offset: *mut libc::size_t,
 ) -> raw::c_int {
-    if prog.is_null()
-        || buf.is_null()
-        || resbuf.is_null()
-        || resbuf_size.is_null()
-        || resbuf_capacity.is_null()
-        || resbuf_offset.is_null()
-    {
-        return -1;
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_query_index_from_flatbuf", "prog", -1);
+    }
+    if buf.is_null() {
+        return null_argument("ddlog_query_index_from_flatbuf", "buf", -1);
+    }
+    if resbuf.is_null() {
+        return null_argument("ddlog_query_index_from_flatbuf", "resbuf", -1);
+    }
+    if resbuf_size.is_null() {
+        return null_argument("ddlog_query_index_from_flatbuf", "resbuf_size", -1);
+    }
+    if resbuf_capacity.is_null() {
+        return null_argument("ddlog_query_index_from_flatbuf", "resbuf_capacity", -1);
+    }
+    if resbuf_offset.is_null() {
+        return null_argument("ddlog_query_index_from_flatbuf", "resbuf_offset", -1);
     }
 
     let prog = &*prog;
 
     flatbuf::query_from_flatbuf(slice::from_raw_parts(buf, n))
+        .map_err(|e| DDlogError::new(ErrorKind::InvalidRecord, e))
         .and_then(|(idxid, key)| {
             prog.query_index(idxid, key).map(|res| {
-                let (fbvec, fboffset) = flatbuf::idx_values_to_flatbuf(idxid, res.iter());
-                *resbuf = fbvec.as_ptr();
+                let (mut fbvec, fboffset) = flatbuf::idx_values_to_flatbuf(idxid, res.iter());
+                *resbuf = fbvec.as_mut_ptr();
                 *resbuf_size = fbvec.len() as libc::size_t;
                 *resbuf_capacity = fbvec.capacity() as libc::size_t;
                 *resbuf_offset = fboffset as libc::size_t;
@@ -453,44 +1145,58 @@ pub unsafe extern "C" fn ddlog_query_index_from_flatbuf(
                 0
             })
         })
-        .unwrap_or_else(|e| {
-            prog.eprintln(&format!("ddlog_query_index_from_flatbuf(): error: {}", e));
-            -1
-        })
+        .unwrap_or_else(|e| report_error(prog, "ddlog_query_index_from_flatbuf", e, -1))
 }
 
+/// cbindgen:ignore
 #[cfg(not(feature = "flatbuf"))]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_query_index_from_flatbuf(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     _buf: *const u8,
     _n: libc::size_t,
-    _resbuf: *mut *const u8,
+    _resbuf: *mut *mut u8,
     _resbuf_size: *mut libc::size_t,
     _resbuf_capacity: *mut libc::size_t,
     _resbuf_offset: *mut libc::size_t,
 ) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_query_index_from_flatbuf", "prog", -1);
     }
 
     let prog = &*prog;
-    prog.eprintln(
-        "ddlog_query_index_from_flatbuf(): error: DDlog was compiled without FlatBuffers support",
+    let e = CError::new(
+        DDLOG_ERR_UNSUPPORTED,
+        "DDlog was compiled without FlatBuffers support",
     );
-
-    -1
+    report_error(prog, "ddlog_query_index_from_flatbuf", e, -1)
 }
 
+/// Enumerates the entire contents of an index.
+/// Note that an index over a multiset will still enumerate records only once.
+///
+/// `idxid` - id of the index to dump.
+/// `cb` - callback invoked for each record in the index.
+/// `cb_arg` - opaque handle passed to each `cb invocation`.
+///
+/// On success, returns `0`. On error, returns a negative value and
+/// writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// This function is thread-safe: it can be invoked concurrently with other
+/// queries and with functions that modify the program, e.g.,
+/// `ddlog_apply_updates()`.  Queries wait for a transaction being committed
+/// to propagate through the dataflow and observe its outcome.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_dump_index(
-    prog: *const HDDlog,
-    idxid: libc::size_t,
+    prog: ddlog_prog,
+    idxid: index_id,
     cb: Option<extern "C" fn(arg: libc::uintptr_t, rec: *const Record)>,
     cb_arg: libc::uintptr_t,
 ) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_dump_index", "prog", -1);
     }
     let prog = &*prog;
 
@@ -503,22 +1209,33 @@ pub unsafe extern "C" fn ddlog_dump_index(
             };
             0
         })
-        .unwrap_or_else(|e| {
-            prog.eprintln(&format!("ddlog_dump_index: error: {}", e));
-            -1
-        })
+        .unwrap_or_else(|e| report_error(prog, "ddlog_dump_index", e, -1))
 }
 
+/// Query index by key.
+///
+/// `idxid` - id of the index to dump.
+/// `key` - query key.
+///     NOTE: the caller keeps ownership of `key` after the call and
+///     must deallocate it usin `ddlog_free()`.
+/// `cb` - callback invoked for each returned record.
+/// `cb_arg` - opaque handle passed to each `cb invocation`.
+///
+/// On success, returns `0`. On error, returns a negative value and
+/// writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// Like `ddlog_dump_index()`, this function is thread-safe.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_query_index(
-    prog: *const HDDlog,
-    idxid: libc::size_t,
+    prog: ddlog_prog,
+    idxid: index_id,
     key: *const Record,
     cb: Option<extern "C" fn(arg: libc::uintptr_t, rec: *const Record)>,
     cb_arg: libc::uintptr_t,
 ) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_query_index", "prog", -1);
     }
     let prog = &*prog;
 
@@ -531,207 +1248,659 @@ pub unsafe extern "C" fn ddlog_query_index(
             }
             0
         })
-        .unwrap_or_else(|e| {
-            prog.eprintln(&format!("ddlog_dump_index: error: {}", e));
-            -1
-        })
+        .unwrap_or_else(|e| report_error(prog, "ddlog_query_index", e, -1))
 }
 
+/// Dump all values in an index to a flatbuf.
+///
+/// The FlatBuffer schema is auto-generated from DDlog code and is stored in
+/// `flatbuf/flatbuf.rs`.
+///
+/// `idxid` - id of the index to dump.
+///
+/// The following values returned by the function describe the flatbuffer
+/// containing response to the query.
+/// `resbuf` - address where the function will write the pointer to the reply
+/// flatbuf.
+/// `resbuf_size` - address to store the size of the generated flatbuf.
+/// `resbuf_capacity` - address to store generated flatbuf capacity.
+/// `resbuf_offset` - address to store data offset inside the returned buffer.
+///
+/// On success, returns `0`. On error, returns a negative value and
+/// writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// Like `ddlog_dump_index()`, this function is thread-safe.
 #[cfg(feature = "flatbuf")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_dump_index_to_flatbuf(
-    prog: *const HDDlog,
-    idxid: libc::size_t,
-    resbuf: *mut *const u8,
+    prog: ddlog_prog,
+    idxid: index_id,
+    resbuf: *mut *mut u8,
     resbuf_size: *mut libc::size_t,
     resbuf_capacity: *mut libc::size_t,
     resbuf_offset: *mut libc::size_t,
 ) -> raw::c_int {
-    if prog.is_null()
-        || resbuf.is_null()
-        || resbuf_size.is_null()
-        || resbuf_capacity.is_null()
-        || resbuf_offset.is_null()
-    {
-        return -1;
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_dump_index_to_flatbuf", "prog", -1);
+    }
+    if resbuf.is_null() {
+        return null_argument("ddlog_dump_index_to_flatbuf", "resbuf", -1);
+    }
+    if resbuf_size.is_null() {
+        return null_argument("ddlog_dump_index_to_flatbuf", "resbuf_size", -1);
+    }
+    if resbuf_capacity.is_null() {
+        return null_argument("ddlog_dump_index_to_flatbuf", "resbuf_capacity", -1);
+    }
+    if resbuf_offset.is_null() {
+        return null_argument("ddlog_dump_index_to_flatbuf", "resbuf_offset", -1);
     }
     let prog = &*prog;
 
     prog.dump_index(idxid as IdxId)
         .map(|res| {
-            let (fbvec, fboffset) = flatbuf::idx_values_to_flatbuf(idxid, res.iter());
-            *resbuf = fbvec.as_ptr();
+            let (mut fbvec, fboffset) = flatbuf::idx_values_to_flatbuf(idxid, res.iter());
+            *resbuf = fbvec.as_mut_ptr();
             *resbuf_size = fbvec.len() as libc::size_t;
             *resbuf_capacity = fbvec.capacity() as libc::size_t;
             *resbuf_offset = fboffset as libc::size_t;
             mem::forget(fbvec);
             0
         })
-        .unwrap_or_else(|e| {
-            prog.eprintln(&format!("ddlog_dump_index_to_flatbuf(): error: {}", e));
-            -1
-        })
+        .unwrap_or_else(|e| report_error(prog, "ddlog_dump_index_to_flatbuf", e, -1))
 }
 
+/// cbindgen:ignore
 #[cfg(not(feature = "flatbuf"))]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_dump_index_to_flatbuf(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     _idxid: libc::size_t,
-    _resbuf: *mut *const u8,
+    _resbuf: *mut *mut u8,
     _resbuf_size: *mut libc::size_t,
     _resbuf_capacity: *mut libc::size_t,
     _resbuf_offset: *mut libc::size_t,
 ) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_dump_index_to_flatbuf", "prog", -1);
     }
 
     let prog = &*prog;
-    prog.eprintln(
-        "ddlog_dump_index_to_flatbuf(): error: DDlog was compiled without FlatBuffers support",
+    let e = CError::new(
+        DDLOG_ERR_UNSUPPORTED,
+        "DDlog was compiled without FlatBuffers support",
     );
-
-    -1
+    report_error(prog, "ddlog_dump_index_to_flatbuf", e, -1)
 }
 
+/// Deallocate a FlatBuffer returned by
+/// `ddlog_transaction_commit_dump_changes_to_flatbuf`.  Must be called once for
+/// each buffer returned by a successful invocation of
+/// `ddlog_transaction_commit_dump_changes_to_flatbuf`.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_flatbuf_free(
     buf: *mut u8,
     buf_size: libc::size_t,
     buf_capacity: libc::size_t,
 ) {
+    clear_last_error();
     Vec::from_raw_parts(buf, buf_size as usize, buf_capacity as usize);
 }
 
+/// Commit a transaction; propagate all buffered changes through all
+/// rules in the program and update all output relations.
+///
+/// On success, returns `0`; on error, returns `-1` and prints error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// This function will fail if there is no transaction in progress.
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_transaction_commit(prog: *const HDDlog) -> raw::c_int {
+pub unsafe extern "C" fn ddlog_transaction_commit(prog: ddlog_prog) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_transaction_commit", "prog", -1);
     }
     let prog = &*prog;
 
-    prog.transaction_commit().map(|_| 0).unwrap_or_else(|e| {
-        prog.eprintln(&format!("ddlog_transaction_commit(): error: {}", e));
-        -1
-    })
+    prog.transaction_commit()
+        .map(|_| 0)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_transaction_commit", e, -1))
 }
 
+/// Wait until the update callback has seen all changes committed so far.
+/// Only a program started with `ddlog_run_async()` delivers changes after
+/// `ddlog_transaction_commit()` returns; for other programs, this function
+/// returns immediately.
+///
+/// On success, returns `0`; on error, returns `-1` and prints error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_transaction_rollback(prog: *const HDDlog) -> raw::c_int {
+pub unsafe extern "C" fn ddlog_flush_updates(prog: ddlog_prog) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_flush_updates", "prog", -1);
     }
     let prog = &*prog;
 
-    prog.transaction_rollback().map(|_| 0).unwrap_or_else(|e| {
-        prog.eprintln(&format!("ddlog_transaction_rollback(): error: {}", e));
-        -1
-    })
+    prog.flush_updates()
+        .map(|_| 0)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_flush_updates", e, -1))
+}
+
+/// Discard all buffered updates and abort the current transaction.
+///
+/// On success, returns `0`; on error, returns `-1` and prints error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// This function will fail if there is no transaction in progress.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_transaction_rollback(prog: ddlog_prog) -> raw::c_int {
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_transaction_rollback", "prog", -1);
+    }
+    let prog = &*prog;
+
+    prog.transaction_rollback()
+        .map(|_| 0)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_transaction_rollback", e, -1))
 }
 
+/// Apply updates to DDlog tables.  See the ddlog_cmd API below.
+///
+/// On success, returns `0`. On error, returns a negative value and
+/// writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+///
+/// Whether the function succeeds or fails, it consumes all commands in
+/// the `upds` array (but not the array itself), so they can no longer be
+/// accessed by the caller after the function returns.
+///
+/// This function fails non-atomically: upon a failure, it may have applied
+/// a _subset_ of input commands (one exception is when `ddlog_apply_updates`
+/// is called with a single command, in which case it leaves the database
+/// unmodified in case of a failure).  Use `ddlog_transaction_rollback()` to
+/// bring the database back to a known state, specifically the state where it
+/// was before the start of the transaction.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_apply_updates(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     upds: *const *mut UpdCmd,
     n: libc::size_t,
 ) -> raw::c_int {
-    if prog.is_null() || upds.is_null() {
-        return -1;
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_apply_updates", "prog", -1);
+    }
+    if upds.is_null() {
+        return null_argument("ddlog_apply_updates", "upds", -1);
     }
     let prog = Arc::from_raw(prog);
 
     let res = prog
         .apply_updates((0..n).map(|i| Box::from_raw(*upds.add(i))))
         .map(|_| 0)
-        .unwrap_or_else(|e| {
-            prog.eprintln(&format!("ddlog_apply_updates(): error: {}", e));
-            -1
-        });
+        .unwrap_or_else(|e| report_error(&prog, "ddlog_apply_updates", e, -1));
     Arc::into_raw(prog);
     res
 }
 
+/// Apply updates, serialized into a FlatBuffer, to DDlog tables.
+///
+/// The FlatBuffer schema is auto-generated from DDlog code and is stored in
+/// `flatbuf/flatbuf.rs`.
+///
+/// `buf` - pointer to FlatBuffer of size `n`.
+///
+/// On success, returns `0`. On error, returns a negative value and
+/// writes error message (see `print_err_msg` parameter to `ddlog_run()`).
 #[cfg(feature = "flatbuf")]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_apply_updates_from_flatbuf(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     buf: *const u8,
     n: libc::size_t,
 ) -> raw::c_int {
-    if prog.is_null() || buf.is_null() {
-        return -1;
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_apply_updates_from_flatbuf", "prog", -1);
+    }
+    if buf.is_null() {
+        return null_argument("ddlog_apply_updates_from_flatbuf", "buf", -1);
     }
     let prog = &*prog;
 
     prog.apply_updates_from_flatbuf(slice::from_raw_parts(buf, n))
         .map(|_| 0)
-        .unwrap_or_else(|e| {
-            prog.eprintln(&format!("ddlog_apply_updates_from_flatbuf(): error: {}", e));
-            -1
-        })
+        .unwrap_or_else(|e| report_error(prog, "ddlog_apply_updates_from_flatbuf", e, -1))
 }
 
+/// cbindgen:ignore
 #[cfg(not(feature = "flatbuf"))]
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_apply_updates_from_flatbuf(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     _buf: *const u8,
     _n: libc::size_t,
 ) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_apply_updates_from_flatbuf", "prog", -1);
     }
 
     let prog = &*prog;
-    prog.eprintln(&"ddlog_apply_updates_from_flatbuf(): error: DDlog was compiled without FlatBuffers support");
+    let e = CError::new(
+        DDLOG_ERR_UNSUPPORTED,
+        "DDlog was compiled without FlatBuffers support",
+    );
+    report_error(prog, "ddlog_apply_updates_from_flatbuf", e, -1)
+}
+
+/// Name of the input in parse errors reported by `ddlog_parse_record()` and
+/// `ddlog_parse_commands()`.
+const PARSE_SOURCE: &str = "<text>";
+
+/// Parse a record written in DDlog syntax, e.g., `Foo{1, "bar"}` or
+/// `Foo{.x = 1, .y = "bar"}`.  `text` is a null-terminated UTF8 string.
+///
+/// This is a faster alternative to constructing the record one field at a
+/// time using the record API below.  The record is not type checked until
+/// it is used in a command, e.g., `ddlog_insert_cmd()`.
+///
+/// On success, returns a new record, which must be either deallocated using
+/// `ddlog_free()` or passed to a function that takes its ownership.  On error,
+/// returns NULL; the error message returned by `ddlog_last_error()` contains
+/// the line and column at which parsing failed.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_parse_record(text: *const raw::c_char) -> *mut Record {
+    clear_last_error();
+    let text = match str_argument("ddlog_parse_record", "text", text) {
+        Some(text) => text,
+        None => return ptr::null_mut(),
+    };
 
-    -1
+    check(
+        "ddlog_parse_record",
+        cmd_parser::parse_record(PARSE_SOURCE, text),
+    )
+    .map_or(ptr::null_mut(), |rec| Box::into_raw(Box::new(rec)))
 }
 
+/// Parse a sequence of update commands written in the syntax of the DDlog
+/// command-line interface, e.g.:
+///
+/// ```text
+/// insert Foo(1, "bar"),
+/// delete Foo(2, "baz"),
+/// modify Bar 5 <- Bar{.x = 6};
+/// ```
+///
+/// Only `insert`, `insert_or_update`, `delete`, `delete_key`, and `modify`
+/// commands, separated by `,` or `;`, and comments are allowed; other
+/// commands, e.g., `start` or `commit`, are reported as errors.  Relations
+/// are referred to by name, as in `ddlog_get_table_id()`.
+///
+/// `text` - null-terminated UTF8 string containing the commands.
+///
+/// `cmds` - on success, receives an array of commands, which can be passed
+///     to `ddlog_apply_updates()`.  The array must be deallocated with
+///     `ddlog_free_commands()`.
+///
+/// `num_cmds` - on success, receives the number of commands in `cmds`.
+///
+/// On success, returns `0`.  On error, returns `-1`; the error message
+/// returned by `ddlog_last_error()` contains the line and column at which
+/// parsing failed.
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_clear_relation(
-    prog: *const HDDlog,
-    table: libc::size_t,
+pub unsafe extern "C" fn ddlog_parse_commands(
+    text: *const raw::c_char,
+    cmds: *mut *mut *mut UpdCmd,
+    num_cmds: *mut libc::size_t,
 ) -> raw::c_int {
+    clear_last_error();
+    let text = match str_argument("ddlog_parse_commands", "text", text) {
+        Some(text) => text,
+        None => return -1,
+    };
+    if cmds.is_null() {
+        return null_argument("ddlog_parse_commands", "cmds", -1);
+    }
+    if num_cmds.is_null() {
+        return null_argument("ddlog_parse_commands", "num_cmds", -1);
+    }
+
+    check("ddlog_parse_commands", parse_commands(text)).map_or(-1, |upds| {
+        let upds: Box<[*mut UpdCmd]> = upds
+            .into_iter()
+            .map(|upd| Box::into_raw(Box::new(upd)))
+            .collect();
+        *num_cmds = upds.len();
+        *cmds = Box::into_raw(upds) as *mut *mut UpdCmd;
+        0
+    })
+}
+
+fn parse_commands(text: &str) -> Result<Vec<UpdCmd>, CError> {
+    let mut reader = CommandReader::new(PARSE_SOURCE, text.as_bytes());
+    let mut upds = Vec::new();
+    loop {
+        match reader.next_command() {
+            Ok(Some(Command::Update(upd, _))) => upds.push(upd),
+            Ok(Some(Command::Comment)) => {}
+            Ok(Some(_)) => {
+                return Err(CError::new(
+                    DDLOG_ERR_PARSE,
+                    format!(
+                        "{}:{}: not an update command: '{}'",
+                        reader.source(),
+                        reader.line(),
+                        String::from_utf8_lossy(reader.command_text())
+                    ),
+                ))
+            }
+            Ok(None) => break,
+            Err(ReadError::Parse(e)) => return Err(e.into()),
+            Err(ReadError::Io(e)) => return Err(e.into()),
+        }
+    }
+    reader.finish()?;
+    Ok(upds)
+}
+
+/// Deallocate an array of commands returned by `ddlog_parse_commands()`.
+/// `num_cmds` must be equal to the number of commands returned along with
+/// the array.
+///
+/// Only the array is deallocated, not the commands in it, as they are
+/// normally consumed by `ddlog_apply_updates()`.  Commands that have not
+/// been applied must be deallocated using `ddlog_free_cmd()`.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_free_commands(cmds: *mut *mut UpdCmd, num_cmds: libc::size_t) {
+    clear_last_error();
+    if cmds.is_null() {
+        return;
+    }
+    drop(Box::from_raw(slice::from_raw_parts_mut(cmds, num_cmds)));
+}
+
+/// Remove all records from an input relation.
+///
+/// Fails if there is no transaction in progress.
+///
+/// On success, returns `0`. On error, returns a negative value and
+/// writes error message (see `print_err_msg` parameter to `ddlog_run()`).
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_clear_relation(prog: ddlog_prog, table: table_id) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_clear_relation", "prog", -1);
     }
     let prog = &*prog;
 
-    prog.clear_relation(table).map(|_| 0).unwrap_or_else(|e| {
-        prog.eprintln(&format!("ddlog_clear_relation(): error: {}", e));
-        -1
-    })
+    prog.clear_relation(table)
+        .map(|_| 0)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_clear_relation", e, -1))
 }
 
+/// Dump the content of an output table by invoking `cb` for each value
+/// in the table.
+///
+/// `cb` returns `true` to allow enumeration to continue or `false` to
+/// abort the dump.
+///
+/// `cb_arg` is an opaque argument passed to each invocation.
+///
+/// Requires that `hprog` was created by calling `ddlog_run()` with
+/// `do_store` flag set to `true`.  Fails otherwise.
+///
+/// The `rec` argument of the callback function is a borrowed reference
+/// that is only valid for the duration of the callback.
+///
+/// The content of the table returned by this function represents
+/// database state after the last committed transaction.
+///
+/// This function is thread-safe: it can be invoked concurrently with
+/// `ddlog_dump_table()` and with functions that modify the program, e.g.,
+/// `ddlog_apply_updates()`.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_dump_table(
-    prog: *const HDDlog,
-    table: libc::size_t,
+    prog: ddlog_prog,
+    table: table_id,
     cb: Option<extern "C" fn(arg: libc::uintptr_t, rec: *const Record, w: libc::ssize_t) -> bool>,
     cb_arg: libc::uintptr_t,
 ) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_dump_table", "prog", -1);
     }
     let prog = &*prog;
 
     let f = cb.map(|f| move |rec: &Record, w: isize| f(cb_arg, rec, w as libc::ssize_t));
 
-    prog.dump_table(table, f).map(|_| 0).unwrap_or_else(|e| {
-        prog.eprintln(&format!("ddlog_dump_table(): error: {}", e));
-        -1
-    })
+    prog.dump_table(table, f)
+        .map(|_| 0)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_dump_table", e, -1))
 }
 
+/// Subscribe to changes to an output table.
+///
+/// `cb` is invoked for every record inserted into (positive `weight`) or
+/// deleted from (negative `weight`) the table, with `cb_arg` as its first
+/// argument.  Unlike the callback passed to `ddlog_run()`, which receives
+/// changes to all output tables, `cb` only receives changes to `table`.
+/// Any number of subscribers can be registered for the same table.
+///
+/// The subscription takes effect at the start of the next transaction.
+/// Callbacks are invoked from a DDlog thread during
+/// `ddlog_transaction_commit()`; the `rec` argument is a borrowed reference
+/// that is only valid for the duration of the callback.
+///
+/// On success, returns a non-negative subscription id to be passed to
+/// `ddlog_unsubscribe()`.  On error, returns -1 and writes error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_enable_cpu_profiling(
-    prog: *const HDDlog,
-    enable: bool,
-) -> raw::c_int {
+pub unsafe extern "C" fn ddlog_subscribe(
+    prog: ddlog_prog,
+    table: table_id,
+    cb: ddlog_update_callback,
+    cb_arg: libc::uintptr_t,
+) -> i64 {
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_subscribe", "prog", -1);
+    }
+    let cb = match cb {
+        Some(cb) => cb,
+        None => return null_argument("ddlog_subscribe", "cb", -1),
+    };
+    let prog = &*prog;
+
+    let handler = Box::new(ExternCUpdateHandler::new(cb, cb_arg));
+    prog.subscribe(table, handler)
+        .map(|id| id as i64)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_subscribe", e, -1))
+}
+
+/// Like `ddlog_subscribe()`, but subscribes to changes to the contents of an
+/// index, i.e., `cb` is only invoked for records that are added to or
+/// removed from the index.  `table` passed to `cb` is the id of the table
+/// that the index is built on.  Fails if the index is not built on an output
+/// table.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_subscribe_index(
+    prog: ddlog_prog,
+    index: index_id,
+    cb: ddlog_update_callback,
+    cb_arg: libc::uintptr_t,
+) -> i64 {
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_subscribe_index", "prog", -1);
+    }
+    let cb = match cb {
+        Some(cb) => cb,
+        None => return null_argument("ddlog_subscribe_index", "cb", -1),
+    };
+    let prog = &*prog;
+
+    let handler = Box::new(ExternCUpdateHandler::new(cb, cb_arg));
+    prog.subscribe_index(index, handler)
+        .map(|id| id as i64)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_subscribe_index", e, -1))
+}
+
+/// Like `ddlog_subscribe()`, but delivers changes in batches: once a
+/// transaction commits, `cb` is invoked with an array of all records
+/// inserted into or deleted from `table` by the transaction, rather than
+/// once for every record.  Each record occurs in the array at most once.  `cb`
+/// is not invoked for transactions that do not modify `table` or that fail.
+///
+/// Batching saves one callback invocation per record, but not the cost of
+/// copying: every change in the batch is converted to a freshly allocated
+/// record before `cb` is invoked.  Use `ddlog_subscribe_flatbuf()` to
+/// serialize the batch without the conversion.
+///
+/// The `updates` array and the records in it are owned by DDlog and are only
+/// valid for the duration of the callback; the callback must not deallocate
+/// them.
+///
+/// On success, returns a non-negative subscription id to be passed to
+/// `ddlog_unsubscribe()`.  On error, returns -1; the error can be retrieved
+/// using `ddlog_last_error()`.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_subscribe_batch(
+    prog: ddlog_prog,
+    table: table_id,
+    cb: ddlog_batch_callback,
+    cb_arg: libc::uintptr_t,
+) -> i64 {
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_subscribe_batch", "prog", -1);
+    }
+    let cb = match cb {
+        Some(cb) => cb,
+        None => return null_argument("ddlog_subscribe_batch", "cb", -1),
+    };
+    let prog = &*prog;
+
+    let handler = BatchUpdateHandler::new(move |relid: RelId, delta: BTreeMap<DDValue, isize>| {
+        let (records, weights): (Vec<Record>, Vec<isize>) = delta
+            .into_iter()
+            .map(|(val, w)| (val.into_record(), w))
+            .unzip();
+        // The records are borrowed by the callback.
+        let updates: Vec<ddlog_record_update> = records
+            .iter()
+            .zip(weights)
+            .map(|(rec, weight)| ddlog_record_update {
+                table: relid,
+                rec: rec as *const Record as *mut Record,
+                weight,
+            })
+            .collect();
+        cb(cb_arg, relid, updates.as_ptr(), updates.len());
+    });
+    prog.subscribe(table, Box::new(handler))
+        .map(|id| id as i64)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_subscribe_batch", e, -1))
+}
+
+/// Like `ddlog_subscribe_batch()`, but serializes each batch of changes to a
+/// FlatBuffer, in the same format as
+/// `ddlog_transaction_commit_dump_changes_to_flatbuf()`.  The buffer is
+/// serialized directly from DDlog values, without converting them to records.
+///
+/// The buffer is owned by DDlog and is only valid for the duration of the
+/// callback.
+///
+/// Fails with `DDLOG_ERR_UNSUPPORTED` if DDlog was compiled without
+/// FlatBuffers support.
+#[cfg(feature = "flatbuf")]
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_subscribe_flatbuf(
+    prog: ddlog_prog,
+    table: table_id,
+    cb: ddlog_flatbuf_callback,
+    cb_arg: libc::uintptr_t,
+) -> i64 {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_subscribe_flatbuf", "prog", -1);
+    }
+    let cb = match cb {
+        Some(cb) => cb,
+        None => return null_argument("ddlog_subscribe_flatbuf", "cb", -1),
+    };
+    let prog = &*prog;
+
+    let handler = BatchUpdateHandler::new(move |relid: RelId, delta: BTreeMap<DDValue, isize>| {
+        let (fbvec, fboffset) = flatbuf::updates_to_flatbuf(&DeltaMap::singleton(relid, delta));
+        let buf = &fbvec[fboffset..];
+        cb(cb_arg, relid, buf.as_ptr(), buf.len());
+    });
+    prog.subscribe(table, Box::new(handler))
+        .map(|id| id as i64)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_subscribe_flatbuf", e, -1))
+}
+
+/// cbindgen:ignore
+#[cfg(not(feature = "flatbuf"))]
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_subscribe_flatbuf(
+    prog: ddlog_prog,
+    _table: table_id,
+    _cb: ddlog_flatbuf_callback,
+    _cb_arg: libc::uintptr_t,
+) -> i64 {
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_subscribe_flatbuf", "prog", -1);
+    }
+
+    let prog = &*prog;
+    let e = CError::new(
+        DDLOG_ERR_UNSUPPORTED,
+        "DDlog was compiled without FlatBuffers support",
+    );
+    report_error(prog, "ddlog_subscribe_flatbuf", e, -1)
+}
+
+/// Cancel a subscription created by `ddlog_subscribe()`,
+/// `ddlog_subscribe_index()`, `ddlog_subscribe_batch()`, or
+/// `ddlog_subscribe_flatbuf()`.  The callback stops being invoked at the
+/// start of the next transaction.
+///
+/// On success, returns `0`. On error, returns -1.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_unsubscribe(prog: ddlog_prog, subscription: i64) -> raw::c_int {
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_unsubscribe", "prog", -1);
+    }
+    if subscription < 0 {
+        return null_argument("ddlog_unsubscribe", "subscription", -1);
+    }
+    let prog = &*prog;
+
+    prog.unsubscribe(subscription as SubscriptionId)
+        .map(|_| 0)
+        .unwrap_or_else(|e| report_error(prog, "ddlog_unsubscribe", e, -1))
+}
+
+/// Controls recording of differential operator runtimes.  When enabled,
+/// DDlog records each activation of every operator and prints the
+/// per-operator CPU usage summary in the profile.  When disabled, the
+/// recording stops, but the previously accumulated profile is preserved.
+///
+/// Recording CPU events can be expensive in large dataflows and is
+/// therefore disabled by default.
+#[no_mangle]
+pub unsafe extern "C" fn ddlog_enable_cpu_profiling(prog: ddlog_prog, enable: bool) -> raw::c_int {
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_enable_cpu_profiling", "prog", -1);
     }
     let prog = &*prog;
 
@@ -739,13 +1908,23 @@ pub unsafe extern "C" fn ddlog_enable_cpu_profiling(
     0
 }
 
+/// Controls recording of timely operator runtimes. When enabled,
+/// DDlog receives timely dataflow events and writes them out to a CSV file
+/// where they can be queried later for useful information about program
+/// execution. This is particularly useful for DDlog programs running with
+/// multiple workers. When disabled, the recording stops, but the previously
+/// accumulated profile is preserved.
+///
+/// Recording timely events can be expensive in large dataflows and is
+/// therefore disabled by default.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_enable_timely_profiling(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     enable: bool,
 ) -> raw::c_int {
+    clear_last_error();
     if prog.is_null() {
-        return -1;
+        return null_argument("ddlog_enable_timely_profiling", "prog", -1);
     }
     let prog = &*prog;
 
@@ -753,10 +1932,14 @@ pub unsafe extern "C" fn ddlog_enable_timely_profiling(
     0
 }
 
+/// Returns DDlog program runtime profile as a C string.
+///
+/// The returned string must be deallocated using `ddlog_string_free()`.
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_profile(prog: *const HDDlog) -> *const raw::c_char {
+pub unsafe extern "C" fn ddlog_profile(prog: ddlog_prog) -> *mut raw::c_char {
+    clear_last_error();
     if prog.is_null() {
-        return ptr::null();
+        return null_argument("ddlog_profile", "prog", ptr::null_mut());
     }
     let prog = &*prog;
 
@@ -764,13 +1947,20 @@ pub unsafe extern "C" fn ddlog_profile(prog: *const HDDlog) -> *const raw::c_cha
     CString::new(profile)
         .map(CString::into_raw)
         .unwrap_or_else(|e| {
-            prog.eprintln(&format!("Failed to convert profile string to C: {}", e));
-            ptr::null_mut()
+            let e = CError::new(
+                DDLOG_ERR_OTHER,
+                format!("failed to convert profile string to C: {}", e),
+            );
+            report_error(prog, "ddlog_profile", e, ptr::null_mut())
         })
 }
 
+/// Deallocate a C string returned by DDlog
+/// (currently only applicable to the string returned by `ddlog_profile()` and
+/// `ddlog_dump_record()`).
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_string_free(s: *mut raw::c_char) {
+    clear_last_error();
     if s.is_null() {
         return;
     }
@@ -778,16 +1968,22 @@ pub unsafe extern "C" fn ddlog_string_free(s: *mut raw::c_char) {
     CString::from_raw(s);
 }
 
+/// Creates an empty delta.
 #[no_mangle]
-pub extern "C" fn ddlog_new_delta() -> *mut DeltaMap<DDValue> {
+pub extern "C" fn ddlog_new_delta() -> *mut ddlog_delta {
+    clear_last_error();
     Box::into_raw(Box::new(DeltaMap::new()))
 }
 
+/// Retrieve changes to a specific table.
+///
+/// Returns a delta that only contains changes to one table.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_delta_get_table(
-    delta: *const DeltaMap<DDValue>,
-    table: libc::size_t,
-) -> *mut DeltaMap<DDValue> {
+    delta: *const ddlog_delta,
+    table: table_id,
+) -> *mut ddlog_delta {
+    clear_last_error();
     let res = DeltaMap::singleton(
         table,
         (&*delta)
@@ -799,12 +1995,18 @@ pub unsafe extern "C" fn ddlog_delta_get_table(
     Box::into_raw(Box::new(res))
 }
 
+/// Enumerate changes in this delta.
+///
+/// Invokes `cb` for each record in `delta`.
+///
+/// `cb_arg` is an opaque handle passed to each `cb invocation`.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_delta_enumerate(
-    delta: *const DeltaMap<DDValue>,
-    cb: Option<ExternCCallback>,
+    delta: *const ddlog_delta,
+    cb: ddlog_update_callback,
     cb_arg: libc::uintptr_t,
 ) {
+    clear_last_error();
     if let Some(f) = cb {
         for (table_id, table_data) in (&*delta).as_ref().iter() {
             for (val, weight) in table_data.iter() {
@@ -820,35 +2022,53 @@ pub unsafe extern "C" fn ddlog_delta_enumerate(
     };
 }
 
+/// Remove changes to the specified table from `delta`.
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_delta_clear_table(
-    delta: *mut DeltaMap<DDValue>,
-    table: libc::size_t,
-) {
+pub unsafe extern "C" fn ddlog_delta_clear_table(delta: *mut ddlog_delta, table: table_id) {
+    clear_last_error();
     (&mut *delta).clear_rel(table as RelId);
 }
 
+/// Remove changes to the specified table from `delta` and return them as a separate
+/// delta.  The caller is responsible for deallocating the new delta.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_delta_remove_table(
-    delta: *mut DeltaMap<DDValue>,
-    table: libc::size_t,
-) -> *mut DeltaMap<DDValue> {
+    delta: *mut ddlog_delta,
+    table: table_id,
+) -> *mut ddlog_delta {
+    clear_last_error();
     Box::into_raw(Box::new(DeltaMap::singleton(
         table,
         (&mut *delta).clear_rel(table as RelId),
     )))
 }
 
+/// Clear changes to all tables.  Leaves `delta` empty.
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_delta_clear(delta: *mut DeltaMap<DDValue>) {
+pub unsafe extern "C" fn ddlog_delta_clear(delta: *mut ddlog_delta) {
+    clear_last_error();
     (&mut *delta).as_mut().clear();
 }
 
+/// Adds the contents of `new_delta` to `delta`.
+///
+/// Example 1: `delta` does not contain record `r`; `new_delta` contains record
+/// `r` with weight `w`.  `r` gets added to `delta` with weight `w`.
+///
+/// Example 2: `delta` contains record `r` with weight `w`; `new_delta` does
+/// not contain `r`.  The state of `r` in `delta` does not change.
+///
+/// Example 3: `delta` contains record `r` with weight `w`; `new_delta`
+/// contains the same record with weight `-w`. The two changes
+/// cancel out, and the record gets removed from `delta`.
+///
+/// Example 4: `delta` and `new_delta` contain `r` with weights `w1` and `w2`.
+/// While this operation is well-defined and will update the weight of `r` to
+/// the sum of the two weights, it should only occur when working with multisets
+/// or streams.
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_delta_union(
-    delta: *mut DeltaMap<DDValue>,
-    new_delta: *const DeltaMap<DDValue>,
-) {
+pub unsafe extern "C" fn ddlog_delta_union(delta: *mut ddlog_delta, new_delta: *const ddlog_delta) {
+    clear_last_error();
     for (table_id, table_data) in (&*new_delta).as_ref().iter() {
         for (val, weight) in table_data.iter() {
             (&mut *delta).update(*table_id, val, *weight);
@@ -856,8 +2076,10 @@ pub unsafe extern "C" fn ddlog_delta_union(
     }
 }
 
+/// Deallocate delta.  Invalidates the pointer.
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_free_delta(delta: *mut DeltaMap<DDValue>) {
+pub unsafe extern "C" fn ddlog_free_delta(delta: *mut ddlog_delta) {
+    clear_last_error();
     if !delta.is_null() {
         // Deallocate the DeltaMap
         Box::from_raw(delta);
@@ -1,6 +1,48 @@
 fn main() {
     #[cfg(not(windows))]
     libtool();
+
+    #[cfg(feature = "c_api")]
+    c_header();
+}
+
+/* Generate the C API header from `extern "C"` declarations in Rust sources.
+ *
+ * The header is written to `$OUT_DIR/ddlog.h`.  The copy of the header
+ * shipped with the crate (`ddlog.h`) is only overwritten when the
+ * `DDLOG_UPDATE_HEADER` environment variable is set; the `c_header` test
+ * checks that the two are identical.
+ */
+#[cfg(feature = "c_api")]
+fn c_header() {
+    use std::env;
+    use std::path::Path;
+
+    const SOURCES: &[&str] = &[
+        "src/api/c_api.rs",
+        "src/ovsdb_api.rs",
+        "differential_datalog/src/record/mod.rs",
+    ];
+
+    let topdir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=DDLOG_UPDATE_HEADER");
+
+    let config = cbindgen::Config::from_file(Path::new(&topdir).join("cbindgen.toml"))
+        .expect("failed to read cbindgen.toml");
+    let mut builder = cbindgen::Builder::new().with_config(config);
+    for src in SOURCES {
+        println!("cargo:rerun-if-changed={}", src);
+        builder = builder.with_src(Path::new(&topdir).join(src));
+    }
+    let header = builder.generate().expect("failed to generate C header");
+
+    header.write_to_file(Path::new(&out_dir).join("ddlog.h"));
+    if env::var_os("DDLOG_UPDATE_HEADER").is_some() {
+        header.write_to_file(Path::new(&topdir).join("ddlog.h"));
+    }
 }
 
 #[cfg(not(windows))]
@@ -23,7 +65,6 @@ fn libtool() {
     println!("cargo:rerun-if-changed=src/lib.rs");
     println!("cargo:rerun-if-changed=src/main.rs");
     println!("cargo:rerun-if-changed=src/ovsdb.rs");
-    println!("cargo:rerun-if-changed=src/update_handler.rs");
 
     let lib = "libdatalog_example_ddlog";
 
@@ -455,6 +455,176 @@ where
     }
 }
 
+impl<'a> FromFlatBuffer<fb::__Record<'a>> for record::Record {
+    fn from_flatbuf(r: fb::__Record<'a>) -> Response<Self> {
+        let fields = || -> Response<Vec<record::Record>> {
+            r.fields().map_or(Ok(Vec::new()), |fields| {
+                FBIter::from_vector(fields)
+                    .map(record::Record::from_flatbuf)
+                    .collect()
+            })
+        };
+        let name = || -> Response<record::Name> {
+            r.name()
+                .map(|name| std::borrow::Cow::from(name.to_string()))
+                .ok_or_else(|| {
+                    format!("Record::from_flatbuf: invalid buffer: failed to extract name")
+                })
+        };
+        let string_val = || -> Response<String> {
+            r.string_val().map(|s| s.to_string()).ok_or_else(|| {
+                format!("Record::from_flatbuf: invalid buffer: failed to extract string_val")
+            })
+        };
+        match r.kind() {
+            fb::__RecordKind::Bool => Ok(record::Record::Bool(r.bool_val())),
+            fb::__RecordKind::Int => {
+                let v = r.int_val().ok_or_else(|| {
+                    format!("Record::from_flatbuf: invalid buffer: failed to extract int_val")
+                })?;
+                let bytes = v.bytes().ok_or_else(|| {
+                    format!("Record::from_flatbuf: invalid buffer: failed to extract bytes")
+                })?;
+                let sign = if v.sign() {
+                    num::bigint::Sign::Plus
+                } else {
+                    num::bigint::Sign::Minus
+                };
+                Ok(record::Record::Int(num::bigint::BigInt::from_bytes_be(
+                    sign, bytes,
+                )))
+            }
+            fb::__RecordKind::Float => Ok(record::Record::Float(OrderedFloat(r.float_val()))),
+            fb::__RecordKind::Double => Ok(record::Record::Double(OrderedFloat(r.double_val()))),
+            fb::__RecordKind::String => Ok(record::Record::String(string_val()?)),
+            fb::__RecordKind::Serialized => Ok(record::Record::Serialized(name()?, string_val()?)),
+            fb::__RecordKind::Tuple => Ok(record::Record::Tuple(fields()?)),
+            fb::__RecordKind::Array => Ok(record::Record::Array(
+                record::CollectionKind::Unknown,
+                fields()?,
+            )),
+            fb::__RecordKind::Vector => Ok(record::Record::Array(
+                record::CollectionKind::Vector,
+                fields()?,
+            )),
+            fb::__RecordKind::Set => Ok(record::Record::Array(
+                record::CollectionKind::Set,
+                fields()?,
+            )),
+            fb::__RecordKind::Map => Ok(record::Record::Array(
+                record::CollectionKind::Map,
+                fields()?,
+            )),
+            fb::__RecordKind::PosStruct => Ok(record::Record::PosStruct(name()?, fields()?)),
+            fb::__RecordKind::NamedStruct => {
+                let field_names = r.field_names().ok_or_else(|| {
+                    format!("Record::from_flatbuf: invalid buffer: failed to extract field_names")
+                })?;
+                let fields = fields()?;
+                if field_names.len() != fields.len() {
+                    return Err(format!(
+                        "Record::from_flatbuf: invalid buffer: {} field names for {} fields",
+                        field_names.len(),
+                        fields.len()
+                    ));
+                }
+                Ok(record::Record::NamedStruct(
+                    name()?,
+                    FBIter::from_vector(field_names)
+                        .map(|name| std::borrow::Cow::from(name.to_string()))
+                        .zip(fields.into_iter())
+                        .collect(),
+                ))
+            }
+        }
+    }
+}
+
+impl<'b> ToFlatBuffer<'b> for record::Record {
+    type Target = fbrt::WIPOffset<fb::__Record<'b>>;
+
+    fn to_flatbuf(&self, fbb: &mut fbrt::FlatBufferBuilder<'b>) -> Self::Target {
+        fn fields<'c>(
+            fbb: &mut fbrt::FlatBufferBuilder<'c>,
+            recs: &[record::Record],
+        ) -> Option<fbrt::WIPOffset<fbrt::Vector<'c, fbrt::ForwardsUOffset<fb::__Record<'c>>>>>
+        {
+            let recs: Vec<_> = recs.iter().map(|rec| rec.to_flatbuf(fbb)).collect();
+            Some(fbb.create_vector(recs.as_slice()))
+        }
+
+        let mut args = fb::__RecordArgs::default();
+        match self {
+            record::Record::Bool(b) => {
+                args.kind = fb::__RecordKind::Bool;
+                args.bool_val = *b;
+            }
+            record::Record::Int(i) => {
+                let (sign, bytes) = i.to_bytes_be();
+                let bytes = fbb.create_vector(bytes.as_slice());
+                args.kind = fb::__RecordKind::Int;
+                args.int_val = Some(fb::__BigInt::create(
+                    fbb,
+                    &fb::__BigIntArgs {
+                        sign: sign != num::bigint::Sign::Minus,
+                        bytes: Some(bytes),
+                    },
+                ));
+            }
+            record::Record::Float(f) => {
+                args.kind = fb::__RecordKind::Float;
+                args.float_val = **f;
+            }
+            record::Record::Double(d) => {
+                args.kind = fb::__RecordKind::Double;
+                args.double_val = **d;
+            }
+            record::Record::String(s) => {
+                args.kind = fb::__RecordKind::String;
+                args.string_val = Some(fbb.create_string(s));
+            }
+            record::Record::Serialized(format, s) => {
+                args.kind = fb::__RecordKind::Serialized;
+                args.name = Some(fbb.create_string(format));
+                args.string_val = Some(fbb.create_string(s));
+            }
+            record::Record::Tuple(recs) => {
+                args.kind = fb::__RecordKind::Tuple;
+                args.fields = fields(fbb, recs);
+            }
+            record::Record::Array(kind, recs) => {
+                args.kind = match kind {
+                    record::CollectionKind::Unknown => fb::__RecordKind::Array,
+                    record::CollectionKind::Vector => fb::__RecordKind::Vector,
+                    record::CollectionKind::Set => fb::__RecordKind::Set,
+                    record::CollectionKind::Map => fb::__RecordKind::Map,
+                };
+                args.fields = fields(fbb, recs);
+            }
+            record::Record::PosStruct(name, recs) => {
+                args.kind = fb::__RecordKind::PosStruct;
+                args.name = Some(fbb.create_string(name));
+                args.fields = fields(fbb, recs);
+            }
+            record::Record::NamedStruct(name, named_recs) => {
+                let names: Vec<_> = named_recs
+                    .iter()
+                    .map(|(name, _)| fbb.create_string(name))
+                    .collect();
+                let recs: Vec<_> = named_recs
+                    .iter()
+                    .map(|(_, rec)| rec.to_flatbuf(fbb))
+                    .collect();
+                args.kind = fb::__RecordKind::NamedStruct;
+                args.name = Some(fbb.create_string(name));
+                args.fields = Some(fbb.create_vector(recs.as_slice()));
+                args.field_names = Some(fbb.create_vector(names.as_slice()));
+            }
+        }
+        fb::__Record::create(fbb, &args)
+    }
+}
+
 // Wrapper type, so we can implement traits for it.
 pub struct DDValueUpdate(pub Update<DDValue>);
 
@@ -464,11 +634,34 @@ impl<'a> FromFlatBuffer<fb::__Command<'a>> for DDValueUpdate {
         let val_table = cmd.val().ok_or_else(|| {
             format!("Update::from_flatbuf: invalid buffer: failed to extract value")
         })?;
-        let val = relval_from_flatbuf(relid, val_table)?;
-        match cmd.weight() {
-            1 => Ok(DDValueUpdate(Update::Insert { relid, v: val })),
-            (-1) => Ok(DDValueUpdate(Update::DeleteValue { relid, v: val })),
-            w => Err(format!("Update::from_flatbuf: non-unit weight {}", w)),
+        match cmd.kind() {
+            fb::__CommandKind::Update => {
+                let val = relval_from_flatbuf(relid, val_table)?;
+                match cmd.weight() {
+                    1 => Ok(DDValueUpdate(Update::Insert { relid, v: val })),
+                    (-1) => Ok(DDValueUpdate(Update::DeleteValue { relid, v: val })),
+                    w => Err(format!("Update::from_flatbuf: non-unit weight {}", w)),
+                }
+            }
+            fb::__CommandKind::InsertOrUpdate => {
+                let val = relval_from_flatbuf(relid, val_table)?;
+                Ok(DDValueUpdate(Update::InsertOrUpdate { relid, v: val }))
+            }
+            fb::__CommandKind::DeleteKey => {
+                let key = relkey_from_flatbuf(relid, val_table)?;
+                Ok(DDValueUpdate(Update::DeleteKey { relid, k: key }))
+            }
+            fb::__CommandKind::Modify => {
+                let key = relkey_from_flatbuf(relid, val_table)?;
+                let mutator = cmd.mutator().ok_or_else(|| {
+                    format!("Update::from_flatbuf: invalid buffer: failed to extract mutator")
+                })?;
+                Ok(DDValueUpdate(Update::Modify {
+                    relid,
+                    k: key,
+                    m: std::sync::Arc::new(record::Record::from_flatbuf(mutator)?),
+                }))
+            }
         }
     }
 }
@@ -488,6 +681,8 @@ impl<'b> ToFlatBuffer<'b> for FBDDValue<'_> {
                 relid: self.0 as u64,
                 val_type,
                 val: Some(v),
+                kind: fb::__CommandKind::Update,
+                mutator: None,
             },
         )
     }
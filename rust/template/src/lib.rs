@@ -43,6 +43,7 @@ use differential_datalog::record::IntoRecord;
 use differential_datalog::record::RelIdentifier;
 use differential_datalog::record::UpdCmd;
 use differential_datalog::DDlogConvert;
+use differential_datalog::DDlogProgram;
 use num_traits::cast::FromPrimitive;
 use num_traits::identities::One;
 use once_cell::sync::Lazy;
@@ -51,7 +52,7 @@ use fnv::FnvHashMap;
 
 pub mod api;
 pub mod ovsdb_api;
-pub mod update_handler;
+pub use differential_datalog::update_handler;
 
 use crate::api::updcmd2upd;
 
@@ -75,11 +76,97 @@ impl DDlogConvert for DDlogConverter {
         indexid2name(idxId)
     }
 
+    fn relation_types() -> Vec<(&'static str, &'static str)> {
+        RELIDMAP
+            .iter()
+            .map(|(rel, name)| (*name, RELTYPEMAP[rel]))
+            .collect()
+    }
+
     fn updcmd2upd(upd_cmd: &UpdCmd) -> ::std::result::Result<program::Update<DDValue>, String> {
         updcmd2upd(upd_cmd)
     }
 }
 
+/// Program-specific functionality used by `api::HDDlog`, forwarded to the
+/// generated functions and tables below.
+impl DDlogProgram for DDlogConverter {
+    type UpdateSerializer = UpdateSerializer;
+
+    fn prog(update_cb: Box<dyn program::CBFn>) -> program::Program {
+        prog(update_cb)
+    }
+
+    fn relname2id(name: &str) -> Option<program::RelId> {
+        Relations::try_from(name)
+            .ok()
+            .map(|rel| rel as program::RelId)
+    }
+
+    fn indexname2id(name: &str) -> Option<program::IdxId> {
+        Indexes::try_from(name)
+            .ok()
+            .map(|idx| idx as program::IdxId)
+    }
+
+    #[cfg(feature = "c_api")]
+    fn relid2cname(relId: program::RelId) -> Option<&'static ::std::ffi::CStr> {
+        relid2cname(relId)
+    }
+
+    #[cfg(feature = "c_api")]
+    fn indexid2cname(idxId: program::IdxId) -> Option<&'static ::std::ffi::CStr> {
+        indexid2cname(idxId)
+    }
+
+    fn input_relations() -> Vec<program::RelId> {
+        INPUT_RELIDMAP
+            .keys()
+            .map(|rel| *rel as program::RelId)
+            .collect()
+    }
+
+    fn is_output_relation(relId: program::RelId) -> bool {
+        Relations::try_from(relId).map_or(false, |rel| rel.is_output())
+    }
+
+    fn relation_type_id(relId: program::RelId) -> Option<TypeId> {
+        Relations::try_from(relId).ok().map(|rel| rel.type_id())
+    }
+
+    fn indexes() -> Vec<program::IdxId> {
+        IDXIDMAP.keys().map(|idx| *idx as program::IdxId).collect()
+    }
+
+    fn index_arrid(idxId: program::IdxId) -> Option<program::ArrId> {
+        Indexes::try_from(idxId).ok().map(indexes2arrid)
+    }
+
+    fn idxkey_from_record(
+        idxId: program::IdxId,
+        rec: &record::Record,
+    ) -> ::std::result::Result<DDValue, String> {
+        let idx = Indexes::try_from(idxId).map_err(|()| format!("unknown index {}", idxId))?;
+        idxkey_from_record(idx, rec)
+    }
+
+    #[cfg(feature = "flatbuf")]
+    fn updates_from_flatbuf(
+        buf: &[u8],
+    ) -> ::std::result::Result<Vec<program::Update<DDValue>>, String> {
+        use crate::flatbuf::FromFlatBuffer;
+
+        flatbuf::updates_from_flatbuf(buf)?
+            .map(|cmd| flatbuf::DDValueUpdate::from_flatbuf(cmd).map(|x| x.0))
+            .collect()
+    }
+
+    #[cfg(feature = "flatbuf")]
+    fn query_from_flatbuf(buf: &[u8]) -> ::std::result::Result<(program::IdxId, DDValue), String> {
+        flatbuf::query_from_flatbuf(buf)
+    }
+}
+
 /* Wrapper around `Update<DDValue>` type that implements `Serialize` and `Deserialize`
  * traits.  It is currently only used by the distributed_ddlog crate in order to
  * serialize updates before sending them over the network and deserializing them on the
@@ -313,6 +400,8 @@ pub static INPUT_RELIDMAP: Lazy<FnvHashMap<Relations, &'static str>> =
     Lazy::new(FnvHashMap::default);
 pub static OUTPUT_RELIDMAP: Lazy<FnvHashMap<Relations, &'static str>> =
     Lazy::new(FnvHashMap::default);
+pub static RELTYPEMAP: Lazy<FnvHashMap<Relations, &'static str>> =
+    Lazy::new(FnvHashMap::default);
 
 pub fn indexid2name(_iid: program::IdxId) -> Option<&'static str> {
     panic!("indexid2name not implemented")
@@ -5,7 +5,11 @@
 #![allow(dead_code, non_snake_case, clippy::match_like_matches_macro)]
 
 use std::convert::TryFrom;
+use std::io;
 use std::io::stdout;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
 use std::io::Write;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -21,6 +25,9 @@ use differential_datalog::program::*;
 use differential_datalog::record::*;
 use differential_datalog::DDlog;
 use differential_datalog::DeltaMap;
+use differential_datalog::{read_binary_header, read_frame, update_frame};
+use differential_datalog::{write_binary_header, write_frame};
+use differential_datalog::{RecordReplay, ReplayFormat, ReplayFrame};
 use num_traits::cast::ToPrimitive;
 use rustop::opts;
 
@@ -42,7 +49,7 @@ fn handle_cmd(
         Ok(())
     })
     .and(match cmd {
-        Command::Start => hddlog.transaction_start(),
+        Command::Start => hddlog.transaction_start().map_err(String::from),
         Command::LogLevel(level) => {
             log_set_default_callback(
                 Some(Box::new(move |level, msg| {
@@ -70,7 +77,8 @@ fn handle_cmd(
                 })
             } else {
                 hddlog.transaction_commit()
-            };
+            }
+            .map_err(String::from);
 
             #[cfg(feature = "profile")]
             {
@@ -83,7 +91,7 @@ fn handle_cmd(
             res
         }
         Command::Comment => Ok(()),
-        Command::Rollback => hddlog.transaction_rollback(),
+        Command::Rollback => hddlog.transaction_rollback().map_err(String::from),
         Command::Timestamp => {
             println!("Timestamp: {}", start_time.elapsed().whole_nanoseconds());
             Ok(())
@@ -104,7 +112,7 @@ fn handle_cmd(
 
         Command::Dump(None) => {
             let _ = hddlog.db.as_ref().map(|db| {
-                db.lock()
+                db.read()
                     .unwrap()
                     .format_as_sets::<DDlogConverter>(&mut stdout())
             });
@@ -124,7 +132,7 @@ fn handle_cmd(
             let _ = hddlog
                 .db
                 .as_ref()
-                .map(|db| db.lock().unwrap().format_rel_as_set(relid, &mut stdout()));
+                .map(|db| db.read().unwrap().format_rel_as_set(relid, &mut stdout()));
             Ok(())
         }
         Command::Clear(rname) => {
@@ -138,7 +146,7 @@ fn handle_cmd(
                     return (Err(err), interactive);
                 }
             };
-            hddlog.clear_relation(relid)
+            hddlog.clear_relation(relid).map_err(String::from)
         }
         Command::Exit => {
             return (Ok(()), false);
@@ -171,8 +179,11 @@ fn handle_cmd(
         Command::QueryIndex(idx, key) => Indexes::try_from(idx.as_str())
             .map_err(|_| format!("Unknown index {}", idx))
             .and_then(|idxid| {
-                idxkey_from_record(idxid, &key)
-                    .and_then(|keyval| hddlog.query_index(idxid as IdxId, keyval))
+                idxkey_from_record(idxid, &key).and_then(|keyval| {
+                    hddlog
+                        .query_index(idxid as IdxId, keyval)
+                        .map_err(String::from)
+                })
             })
             .map(|vals| {
                 for val in vals.into_iter() {
@@ -181,12 +192,19 @@ fn handle_cmd(
             }),
         Command::DumpIndex(idx) => Indexes::try_from(idx.as_str())
             .map_err(|_| format!("Unknown index {}", idx))
-            .and_then(|idxid| hddlog.dump_index(idxid as IdxId))
+            .and_then(|idxid| hddlog.dump_index(idxid as IdxId).map_err(String::from))
             .map(|vals| {
                 for val in vals.into_iter() {
                     let _ = writeln!(stdout(), "{}", val.clone().into_record());
                 }
             }),
+        // `cmd_parser` executes the contents of included files instead of
+        // passing `include` commands to us, so we only get to see one if it
+        // was constructed by other means.
+        Command::Include(path) => Err(format!(
+            "include of {} must be expanded by the command parser",
+            path.display()
+        )),
     });
     match resp {
         Ok(_) => (Ok(()), true),
@@ -211,7 +229,9 @@ fn dump_delta(delta: &DeltaMap<DDValue>) {
 
 fn apply_updates(hddlog: &HDDlog, upds: &mut Vec<Update<DDValue>>) -> Response<()> {
     if !upds.is_empty() {
-        hddlog.apply_valupdates(upds.drain(..))
+        hddlog
+            .apply_valupdates(upds.drain(..))
+            .map_err(String::from)
     } else {
         Ok(())
     }
@@ -224,10 +244,10 @@ fn is_upd_cmd(c: &Command) -> bool {
     }
 }
 
-fn run(mut hddlog: HDDlog, print_deltas: bool) -> Result<(), String> {
+fn run(mut hddlog: HDDlog, print_deltas: bool, keep_going: bool) -> Result<(), String> {
     let upds = Arc::new(Mutex::new(Vec::new()));
     let start_time = Instant::now();
-    interact(|cmd, interactive| {
+    let handle = |cmd: Command, interactive: bool| {
         handle_cmd(
             start_time,
             &hddlog,
@@ -236,9 +256,129 @@ fn run(mut hddlog: HDDlog, print_deltas: bool) -> Result<(), String> {
             &mut upds.lock().unwrap(),
             cmd,
         )
-    })?;
+    };
 
-    hddlog.stop()
+    if stdin_is_tty() {
+        let completer =
+            CommandCompleter::new(RELIDMAP.values().cloned(), IDXIDMAP.values().cloned());
+        interact_with(completer, keep_going, handle)?;
+    } else {
+        let stdin = BufReader::with_capacity(READ_BUFFER_SIZE, io::stdin());
+        let (format, stdin) =
+            ReplayFormat::detect(stdin).map_err(|e| format!("Failed to read stdin: {}", e))?;
+        match format {
+            ReplayFormat::Text => read_commands(STDIN, stdin, keep_going, handle)?,
+            ReplayFormat::Binary => replay_binary(stdin, &hddlog, &upds, handle)?,
+        }
+    }
+
+    hddlog.stop().map_err(String::from)
+}
+
+/// Execute commands recorded in the binary replay format.
+fn replay_binary<R, F>(
+    mut reader: R,
+    hddlog: &HDDlog,
+    upds: &Mutex<Vec<Update<DDValue>>>,
+    handle: F,
+) -> Result<(), String>
+where
+    R: BufRead,
+    F: Fn(Command, bool) -> (Result<(), String>, bool),
+{
+    let read_error = |e| format!("Failed to read stdin: {}", e);
+    read_binary_header(&mut reader).map_err(read_error)?;
+
+    while let Some(frame) = read_frame::<_, UpdateSerializer>(&mut reader).map_err(read_error)? {
+        let cmd = match frame {
+            ReplayFrame::Start => Command::Start,
+            ReplayFrame::Commit(record_delta) => Command::Commit(record_delta),
+            ReplayFrame::Rollback => Command::Rollback,
+            ReplayFrame::Clear(relid) => {
+                let rname =
+                    relid2name(relid).ok_or_else(|| format!("Unknown input relation {}", relid))?;
+                Command::Clear(rname.to_string())
+            }
+            ReplayFrame::Update(upd, last) => {
+                let mut upds = upds.lock().unwrap();
+                upds.push(upd.into());
+                if last {
+                    apply_updates(hddlog, &mut upds)?;
+                }
+                continue;
+            }
+            ReplayFrame::Text(text) => {
+                read_commands(STDIN, text.as_bytes(), false, &handle)?;
+                continue;
+            }
+        };
+        let (result, cont) = handle(cmd, false);
+        result?;
+        if !cont {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Convert a recorded session read from `input` to `format`, writing the
+/// result to stdout.
+fn convert_replay<R>(input: R, format: ReplayFormat) -> Result<(), String>
+where
+    R: BufRead,
+{
+    let io_error = |e| format!("Failed to convert replay file: {}", e);
+    let stdout = stdout();
+    let mut output = BufWriter::new(stdout.lock());
+
+    let (input_format, mut input) = ReplayFormat::detect(input).map_err(io_error)?;
+    match (input_format, format) {
+        (ReplayFormat::Text, ReplayFormat::Text) | (ReplayFormat::Binary, ReplayFormat::Binary) => {
+            let _ = io::copy(&mut input, &mut output).map_err(io_error)?;
+        }
+        (ReplayFormat::Binary, ReplayFormat::Text) => {
+            read_binary_header(&mut input).map_err(io_error)?;
+            while let Some(frame) =
+                read_frame::<_, UpdateSerializer>(&mut input).map_err(io_error)?
+            {
+                output
+                    .record_frame::<DDlogConverter, _>(frame)
+                    .map_err(io_error)?;
+            }
+        }
+        (ReplayFormat::Text, ReplayFormat::Binary) => {
+            write_binary_header(&mut output).map_err(io_error)?;
+            let mut reader = CommandReader::new(STDIN, input);
+            while let Some(cmd) = reader.next_command().map_err(|e| e.to_string())? {
+                let frame = match cmd {
+                    Command::Comment => continue,
+                    Command::Start => ReplayFrame::Start,
+                    Command::Commit(record_delta) => ReplayFrame::Commit(record_delta),
+                    Command::Rollback => ReplayFrame::Rollback,
+                    Command::Update(upd, last) => {
+                        update_frame::<DDlogConverter, UpdateSerializer>(&updcmd2upd(&upd)?, last)
+                    }
+                    // Unknown relations are reported when the file is replayed.
+                    Command::Clear(rname) => match Relations::try_from(rname.as_str()) {
+                        Ok(rid) => ReplayFrame::Clear(rid as RelId),
+                        Err(_) => text_frame(&reader),
+                    },
+                    _ => text_frame(&reader),
+                };
+                write_frame(&mut output, &frame).map_err(io_error)?;
+            }
+            reader.finish().map_err(|e| e.to_string())?;
+        }
+    }
+    output.flush().map_err(io_error)
+}
+
+/// Store the last command read by `reader` in a binary replay file as text.
+fn text_frame<R>(reader: &CommandReader<R>) -> ReplayFrame<UpdateSerializer> {
+    ReplayFrame::Text(format!(
+        "{}\n",
+        String::from_utf8_lossy(reader.command_text())
+    ))
 }
 
 #[allow(clippy::redundant_closure)]
@@ -252,6 +392,8 @@ fn main() -> Result<(), String> {
         opt print:bool=true, desc:"Backwards compatibility. The value of this flag is ignored.";                                    // --no-print
         opt trace:bool=false, desc:"Trace updates to output relations to stderr.";                                                  // --trace
         opt workers:usize=1, short:'w', desc:"The number of worker threads. Default is 1.";                                         // --workers or -w
+        opt keep_going:bool=false, desc:"Report invalid commands and skip to the next ';' instead of exiting.";                     // --keep-going
+        opt convert_replay:Option<String>, desc:"Convert commands read from stdin to the given format ('text' or 'binary') and write them to stdout without running the program."; // --convert-replay
     };
     let (args, rest) = parser.parse_or_exit();
 
@@ -259,6 +401,11 @@ fn main() -> Result<(), String> {
         return Err("Invalid command line arguments; try -h for help".to_string());
     }
 
+    if let Some(format) = args.convert_replay {
+        let stdin = BufReader::with_capacity(READ_BUFFER_SIZE, io::stdin());
+        return convert_replay(stdin, format.parse()?);
+    }
+
     fn record_upd(table: usize, rec: &Record, w: isize) {
         eprintln!(
             "{}({:+}) {:?} {}",
@@ -276,7 +423,7 @@ fn main() -> Result<(), String> {
             if args.init_snapshot {
                 dump_delta(&init_output);
             }
-            run(hddlog, args.delta)
+            run(hddlog, args.delta, args.keep_going)
         }
         Err(err) => Err(format!("Failed to run differential datalog: {}", err)),
     }
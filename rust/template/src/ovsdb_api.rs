@@ -6,6 +6,7 @@ use std::convert::TryFrom;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
 use std::ptr;
+use std::str::Utf8Error;
 use std::sync;
 
 use ddlog_ovsdb_adapter::*;
@@ -14,40 +15,49 @@ use differential_datalog::program::*;
 use differential_datalog::record::{IntoRecord, Record, UpdCmd};
 use differential_datalog::record_upd_cmds;
 use differential_datalog::DDlog;
+use differential_datalog::DDlogError;
 use differential_datalog::DeltaMap;
+use differential_datalog::ErrorKind;
 
-use crate::api::{updcmd2upd, HDDlog};
+use crate::api::{
+    clear_last_error, ddlog_delta, ddlog_prog, null_argument, report_error, updcmd2upd, HDDlog,
+};
 use crate::DDlogConverter;
 use crate::Relations;
 
-/// Parse OVSDB JSON <table-updates> value into DDlog commands; apply commands to a DDlog program.
+/// Parse OVSDB JSON <table-updates> value into DDlog commands; apply
+/// commands to a DDlog program.
 ///
 /// Must be called in the context of a transaction.
 ///
-/// `prefix` contains is the prefix to be added to JSON table names, e.g, `OVN_Southbound_` or
-/// `OVN_Northbound_` for OVN southbound and northbound database updates.
-///
-/// `updates` is the JSON string, e.g.:
+/// `prefix` contains is the prefix to be added to JSON table names, e.g,
+/// `OVN_Southbound.` or `OVN_Northbound.` for OVN southbound and
+/// northbound database updates.
 ///
+/// `updates` is the JSON string, e.g.,
+/// ```text
 /// {"Logical_Switch":{"ffe8d84e-b4a0-419e-b865-19f151eed878":{"new":{"acls":["set",[]],"dns_records":["set",[]],"external_ids":["map",[]],"load_balancer":["set",[]],"name":"lsw0","other_config":["map",[]],"ports":["set",[]],"qos_rules":["set",[]]}}}}
-///
-///
+/// ```
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_apply_ovsdb_updates(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     prefix: *const c_char,
     updates: *const c_char,
 ) -> c_int {
-    if prog.is_null() || prefix.is_null() || updates.is_null() {
-        return -1;
-    };
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_apply_ovsdb_updates", "prog", -1);
+    }
+    if prefix.is_null() {
+        return null_argument("ddlog_apply_ovsdb_updates", "prefix", -1);
+    }
+    if updates.is_null() {
+        return null_argument("ddlog_apply_ovsdb_updates", "updates", -1);
+    }
     let prog = sync::Arc::from_raw(prog);
     let res = apply_updates(&prog, prefix, updates)
         .map(|_| 0)
-        .unwrap_or_else(|e| {
-            prog.eprintln(&format!("ddlog_apply_ovsdb_updates(): error: {}", e));
-            -1
-        });
+        .unwrap_or_else(|e| report_error(&prog, "ddlog_apply_ovsdb_updates", e, -1));
     sync::Arc::into_raw(prog);
     res
 }
@@ -56,48 +66,71 @@ fn apply_updates(
     prog: &sync::Arc<HDDlog>,
     prefix: *const c_char,
     updates_str: *const c_char,
-) -> Result<(), String> {
+) -> Result<(), DDlogError> {
+    let invalid_argument = |e: Utf8Error| {
+        DDlogError::new(
+            ErrorKind::InvalidArgument,
+            format!("invalid UTF8 string in prefix: {}", e),
+        )
+    };
     let prefix: &str = unsafe { CStr::from_ptr(prefix) }
         .to_str()
-        .map_err(|e| format!("invalid UTF8 string in prefix: {}", e))?;
+        .map_err(invalid_argument)?;
     let updates_str: &str = unsafe { CStr::from_ptr(updates_str) }
         .to_str()
-        .map_err(|e| format!("invalid UTF8 string in prefix: {}", e))?;
-    let commands = cmds_from_table_updates_str(prefix, updates_str)?;
+        .map_err(invalid_argument)?;
+    let invalid_record = |e: String| DDlogError::new(ErrorKind::InvalidRecord, e);
+    let commands = cmds_from_table_updates_str(prefix, updates_str).map_err(invalid_record)?;
 
     let updates: Result<Vec<Update<DDValue>>, String> =
         commands.iter().map(|c| updcmd2upd(c)).collect();
-    prog.apply_valupdates(updates?.into_iter())
+    prog.apply_valupdates(updates.map_err(invalid_record)?.into_iter())
 }
 
-/// Dump OVSDB Delta-Plus, Delta-Minus, and Delta-Update tables as a sequence of OVSDB
-/// commands in JSON format.
+/// Dump Delta-Plus, Delta-Minus, and Delta-Update tables for OVSDB table
+/// `table` declared in DDlog module `module`, as a sequence of OVSDB insert,
+/// delete, and update commands in JSON format.
 ///
-/// On success, returns `0` and stores a pointer to JSON string in `json`.  This pointer must be
-/// later deallocated by calling `ddlog_free_json()`
+/// `delta` - DDlog delta containing the latest snapshot of `table`.
+/// `module` - a fully qualified name of a module.
 ///
-/// On error, returns a negative number and writes error message to stderr.
+/// On success, returns `0` and stores a pointer to JSON string in
+/// `json`.  This pointer must be later deallocated by calling
+/// `ddlog_free_json()`
+///
+/// On error, returns a negative number and writes error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_dump_ovsdb_delta_tables(
-    prog: *const HDDlog,
-    delta: *const DeltaMap<DDValue>,
+    prog: ddlog_prog,
+    delta: *const ddlog_delta,
     module: *const c_char,
     table: *const c_char,
     json: *mut *mut c_char,
 ) -> c_int {
-    if json.is_null() || prog.is_null() || delta.is_null() || module.is_null() || table.is_null() {
-        return -1;
-    };
+    clear_last_error();
+    if json.is_null() {
+        return null_argument("ddlog_dump_ovsdb_delta_tables", "json", -1);
+    }
+    if prog.is_null() {
+        return null_argument("ddlog_dump_ovsdb_delta_tables", "prog", -1);
+    }
+    if delta.is_null() {
+        return null_argument("ddlog_dump_ovsdb_delta_tables", "delta", -1);
+    }
+    if module.is_null() {
+        return null_argument("ddlog_dump_ovsdb_delta_tables", "module", -1);
+    }
+    if table.is_null() {
+        return null_argument("ddlog_dump_ovsdb_delta_tables", "table", -1);
+    }
     let prog = sync::Arc::from_raw(prog);
     let res = match dump_delta(&*delta, module, table) {
         Ok(json_string) => {
             *json = json_string.into_raw();
             0
         }
-        Err(e) => {
-            prog.eprintln(&format!("ddlog_dump_ovsdb_delta_tables(): error: {}", e));
-            -1
-        }
+        Err(e) => report_error(&prog, "ddlog_dump_ovsdb_delta_tables", e, -1),
     };
     sync::Arc::into_raw(prog);
     res
@@ -181,19 +214,32 @@ fn dump_delta(
     Ok(unsafe { CString::from_vec_unchecked(cmds.join(",").into_bytes()) })
 }
 
+/// Serializes table record 'rec' into a JSON object for an OVSDB insert,
+/// update, or delete operation, respectively, to apply to OVSDB table 'table'.
+///
+/// On success, returns `0` and stores a pointer to JSON string in
+/// `json`.  This pointer must be later deallocated by calling
+/// `ddlog_free_json()`
+///
+/// On error, returns a negative number and writes error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_into_ovsdb_insert_str(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     table: *const c_char,
     rec: *const Record,
     json: *mut *mut c_char,
 ) -> c_int {
-    if prog.is_null() || table.is_null() {
-        return -1;
-    };
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_into_ovsdb_insert_str", "prog", -1);
+    }
+    if table.is_null() {
+        return null_argument("ddlog_into_ovsdb_insert_str", "table", -1);
+    }
     let rec = match rec.as_ref() {
         Some(record) => record,
-        _ => return -1,
+        _ => return null_argument("ddlog_into_ovsdb_insert_str", "rec", -1),
     };
     let prog = sync::Arc::from_raw(prog);
     let res = match into_insert_str(table, rec) {
@@ -201,10 +247,7 @@ pub unsafe extern "C" fn ddlog_into_ovsdb_insert_str(
             *json = json_string.into_raw();
             0
         }
-        Err(e) => {
-            prog.eprintln(&format!("ddlog_into_insert_str(): error: {}", e));
-            -1
-        }
+        Err(e) => report_error(&prog, "ddlog_into_ovsdb_insert_str", e, -1),
     };
     sync::Arc::into_raw(prog);
     res
@@ -218,19 +261,32 @@ fn into_insert_str(table: *const c_char, rec: &Record) -> Result<CString, String
         .map(|s| unsafe { CString::from_vec_unchecked(s.into_bytes()) })
 }
 
+/// Serializes table record 'rec' into a JSON object for an OVSDB insert,
+/// update, or delete operation, respectively, to apply to OVSDB table 'table'.
+///
+/// On success, returns `0` and stores a pointer to JSON string in
+/// `json`.  This pointer must be later deallocated by calling
+/// `ddlog_free_json()`
+///
+/// On error, returns a negative number and writes error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
 #[no_mangle]
-pub unsafe extern "C" fn ddlog_into_osvdb_delete_str(
-    prog: *const HDDlog,
+pub unsafe extern "C" fn ddlog_into_ovsdb_delete_str(
+    prog: ddlog_prog,
     table: *const c_char,
     rec: *const Record,
     json: *mut *mut c_char,
 ) -> c_int {
-    if prog.is_null() || table.is_null() {
-        return -1;
-    };
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_into_ovsdb_delete_str", "prog", -1);
+    }
+    if table.is_null() {
+        return null_argument("ddlog_into_ovsdb_delete_str", "table", -1);
+    }
     let rec = match rec.as_ref() {
         Some(record) => record,
-        _ => return -1,
+        _ => return null_argument("ddlog_into_ovsdb_delete_str", "rec", -1),
     };
     let prog = sync::Arc::from_raw(prog);
     let res = match into_delete_str(table, rec) {
@@ -238,10 +294,7 @@ pub unsafe extern "C" fn ddlog_into_osvdb_delete_str(
             *json = json_string.into_raw();
             0
         }
-        Err(e) => {
-            prog.eprintln(&format!("ddlog_into_delete_str(): error: {}", e));
-            -1
-        }
+        Err(e) => report_error(&prog, "ddlog_into_ovsdb_delete_str", e, -1),
     };
     sync::Arc::into_raw(prog);
     res
@@ -255,19 +308,32 @@ fn into_delete_str(table: *const c_char, rec: &Record) -> Result<CString, String
         .map(|s| unsafe { CString::from_vec_unchecked(s.into_bytes()) })
 }
 
+/// Serializes table record 'rec' into a JSON object for an OVSDB insert,
+/// update, or delete operation, respectively, to apply to OVSDB table 'table'.
+///
+/// On success, returns `0` and stores a pointer to JSON string in
+/// `json`.  This pointer must be later deallocated by calling
+/// `ddlog_free_json()`
+///
+/// On error, returns a negative number and writes error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_into_ovsdb_update_str(
-    prog: *const HDDlog,
+    prog: ddlog_prog,
     table: *const c_char,
     rec: *const Record,
     json: *mut *mut c_char,
 ) -> c_int {
-    if prog.is_null() || table.is_null() {
-        return -1;
-    };
+    clear_last_error();
+    if prog.is_null() {
+        return null_argument("ddlog_into_ovsdb_update_str", "prog", -1);
+    }
+    if table.is_null() {
+        return null_argument("ddlog_into_ovsdb_update_str", "table", -1);
+    }
     let rec = match rec.as_ref() {
         Some(record) => record,
-        _ => return -1,
+        _ => return null_argument("ddlog_into_ovsdb_update_str", "rec", -1),
     };
     let prog = sync::Arc::from_raw(prog);
     let res = match into_update_str(table, rec) {
@@ -275,10 +341,7 @@ pub unsafe extern "C" fn ddlog_into_ovsdb_update_str(
             *json = json_string.into_raw();
             0
         }
-        Err(e) => {
-            prog.eprintln(&format!("ddlog_into_update_str(): error: {}", e));
-            -1
-        }
+        Err(e) => report_error(&prog, "ddlog_into_ovsdb_update_str", e, -1),
     };
     sync::Arc::into_raw(prog);
     res
@@ -292,27 +355,49 @@ fn into_update_str(table: *const c_char, rec: &Record) -> Result<CString, String
         .map(|s| unsafe { CString::from_vec_unchecked(s.into_bytes()) })
 }
 
+/// Dump output table for OVSDB table `table` declared in DDlog module
+/// `module`, as a sequence of OVSDB insert and delete commands in JSON format.
+///
+/// `delta` - DDlog delta containing the latest snapshot of `table`.
+/// `module` - a fully qualified name of a module.
+///
+/// On success, returns `0` and stores a pointer to JSON string in
+/// `json`.  This pointer must be later deallocated by calling
+/// `ddlog_free_json()`
+///
+/// On error, returns a negative number and writes error message
+/// (see `print_err_msg` parameter to `ddlog_run()`).
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_dump_ovsdb_output_table(
-    prog: *const HDDlog,
-    delta: *const DeltaMap<DDValue>,
+    prog: ddlog_prog,
+    delta: *const ddlog_delta,
     module: *const c_char,
     table: *const c_char,
     json: *mut *mut c_char,
 ) -> c_int {
-    if json.is_null() || prog.is_null() || delta.is_null() || module.is_null() || table.is_null() {
-        return -1;
-    };
+    clear_last_error();
+    if json.is_null() {
+        return null_argument("ddlog_dump_ovsdb_output_table", "json", -1);
+    }
+    if prog.is_null() {
+        return null_argument("ddlog_dump_ovsdb_output_table", "prog", -1);
+    }
+    if delta.is_null() {
+        return null_argument("ddlog_dump_ovsdb_output_table", "delta", -1);
+    }
+    if module.is_null() {
+        return null_argument("ddlog_dump_ovsdb_output_table", "module", -1);
+    }
+    if table.is_null() {
+        return null_argument("ddlog_dump_ovsdb_output_table", "table", -1);
+    }
     let prog = sync::Arc::from_raw(prog);
     let res = match dump_output(&*delta, module, table) {
         Ok(json_string) => {
             *json = json_string.into_raw();
             0
         }
-        Err(e) => {
-            prog.eprintln(&format!("ddlog_dump_ovsdb_output_table(): error: {}", e));
-            -1
-        }
+        Err(e) => report_error(&prog, "ddlog_dump_ovsdb_output_table", e, -1),
     };
     sync::Arc::into_raw(prog);
     res
@@ -360,6 +445,7 @@ fn dump_output(
 /// Deallocates strings returned by other functions in this API.
 #[no_mangle]
 pub unsafe extern "C" fn ddlog_free_json(str: *mut c_char) {
+    clear_last_error();
     if str.is_null() {
         return;
     }
@@ -0,0 +1,18 @@
+#![cfg(feature = "c_api")]
+
+// Checks that the C API header shipped with the crate is identical to the
+// header that `build.rs` generates from the Rust sources.
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn c_header_up_to_date() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/ddlog.h"));
+    let shipped = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("ddlog.h"))
+        .expect("failed to read ddlog.h");
+    assert!(
+        generated == shipped,
+        "ddlog.h does not match the C API; regenerate it by running `DDLOG_UPDATE_HEADER=1 cargo build`"
+    );
+}
@@ -0,0 +1,15 @@
+// Checks that DDlog handles can be shared between threads without any
+// `unsafe impl`s.
+
+use datalog_example_ddlog::api::HDDlog;
+use differential_datalog::program::{QueryHandle, RunningProgram};
+
+fn assert_send<T: Send>() {}
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn hddlog_is_send_sync() {
+    assert_send_sync::<HDDlog>();
+    assert_send::<RunningProgram>();
+    assert_send_sync::<QueryHandle>();
+}
@@ -0,0 +1,216 @@
+use std::sync::{Arc, Mutex};
+
+use datalog_example_ddlog::update_handler::{
+    MTUpdateHandler, PoolUpdateHandler, ST_CBFn, UpdateHandler,
+};
+use differential_datalog::ddval::{DDValConvert, DDValue};
+use differential_datalog::program::RelId;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Event {
+    BeforeCommit,
+    Update(RelId, DDValue, isize),
+    AfterCommit(bool),
+}
+
+/// Events received by the handlers of a pool, tagged with the index of the
+/// worker that received them.
+type Log = Arc<Mutex<Vec<(usize, Event)>>>;
+
+/// Handler that logs the events it receives.
+#[derive(Clone, Debug)]
+struct Recorder {
+    worker: usize,
+    log: Log,
+}
+
+impl UpdateHandler for Recorder {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let handler = self.clone();
+        Box::new(move |relid, v, w| {
+            handler
+                .log
+                .lock()
+                .unwrap()
+                .push((handler.worker, Event::Update(relid, v.clone(), w)))
+        })
+    }
+    fn before_commit(&self) {
+        self.log
+            .lock()
+            .unwrap()
+            .push((self.worker, Event::BeforeCommit));
+    }
+    fn after_commit(&self, success: bool) {
+        self.log
+            .lock()
+            .unwrap()
+            .push((self.worker, Event::AfterCommit(success)));
+    }
+}
+
+fn pool(threads: usize) -> (PoolUpdateHandler, Log) {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log2 = log.clone();
+    let pool = PoolUpdateHandler::new(threads, move |worker| {
+        Box::new(Recorder {
+            worker,
+            log: log2.clone(),
+        }) as Box<dyn UpdateHandler>
+    });
+    (pool, log)
+}
+
+fn value(x: u64) -> DDValue {
+    x.into_ddvalue()
+}
+
+/// Updates to a relation are handled by a single worker in order, and all
+/// of them are handled by the time `after_commit` returns.
+#[test]
+fn pool_shards_by_relation() {
+    let (pool, log) = pool(3);
+    assert_eq!(pool.threads(), 3);
+
+    pool.before_commit();
+    let mut cb = pool.mt_update_cb();
+    for x in 0..100 {
+        for relid in 0..6 {
+            cb(relid, &value(x), 1);
+        }
+    }
+    pool.after_commit(true);
+
+    let log = log.lock().unwrap().clone();
+    assert_eq!(log.len(), 6 * 100 + 2 * 3);
+    for worker in 0..3 {
+        let events: Vec<Event> = log
+            .iter()
+            .filter(|(w, _)| *w == worker)
+            .map(|(_, e)| e.clone())
+            .collect();
+        assert_eq!(events.first(), Some(&Event::BeforeCommit));
+        assert_eq!(events.last(), Some(&Event::AfterCommit(true)));
+        for relid in 0..6 {
+            let updates: Vec<Event> = events
+                .iter()
+                .filter(|e| matches!(e, Event::Update(r, _, _) if *r == relid))
+                .cloned()
+                .collect();
+            let expected: Vec<Event> = if relid % 3 == worker {
+                (0..100)
+                    .map(|x| Event::Update(relid, value(x), 1))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            assert_eq!(updates, expected);
+        }
+    }
+}
+
+/// Updates sent by different threads through clones of the callback are
+/// all handled before `after_commit` returns, in every transaction.
+#[test]
+fn pool_multiple_senders() {
+    let (pool, log) = pool(2);
+
+    for transaction in 0..10 {
+        pool.before_commit();
+        let senders: Vec<_> = (0..4)
+            .map(|relid| {
+                let mut cb = pool.mt_update_cb();
+                std::thread::spawn(move || {
+                    for x in 0..50 {
+                        cb(relid, &value(x), 1);
+                    }
+                })
+            })
+            .collect();
+        for sender in senders {
+            sender.join().unwrap();
+        }
+        pool.after_commit(true);
+
+        let updates = log
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, e)| matches!(e, Event::Update(..)))
+            .count();
+        assert_eq!(updates, (transaction + 1) * 4 * 50);
+    }
+}
+
+#[test]
+fn pool_has_at_least_one_thread() {
+    let (pool, log) = pool(0);
+    assert_eq!(pool.threads(), 1);
+
+    pool.before_commit();
+    pool.update_cb()(1, &value(1), -1);
+    pool.after_commit(false);
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            (0, Event::BeforeCommit),
+            (0, Event::Update(1, value(1), -1)),
+            (0, Event::AfterCommit(false)),
+        ]
+    );
+}
+
+/// Handler that panics when it receives an update.
+#[derive(Debug)]
+struct Panicker;
+
+impl UpdateHandler for Panicker {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        Box::new(|relid, _, _| panic!("update to relation {}", relid))
+    }
+    fn before_commit(&self) {}
+    fn after_commit(&self, _success: bool) {}
+}
+
+/// A worker that terminates does not stall `after_commit` or prevent the
+/// other workers from handling their relations.  Updates to its relations
+/// are dropped.
+#[test]
+fn pool_survives_dead_worker() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let log2 = log.clone();
+    let pool = PoolUpdateHandler::new(2, move |worker| {
+        if worker == 0 {
+            Box::new(Panicker) as Box<dyn UpdateHandler>
+        } else {
+            Box::new(Recorder {
+                worker,
+                log: log2.clone(),
+            }) as Box<dyn UpdateHandler>
+        }
+    });
+
+    let mut cb = pool.mt_update_cb();
+    pool.before_commit();
+    cb(0, &value(1), 1);
+    cb(1, &value(2), 1);
+    pool.after_commit(true);
+
+    pool.before_commit();
+    cb(0, &value(4), 1);
+    cb(1, &value(3), 1);
+    pool.update_cb()(0, &value(5), 1);
+    pool.after_commit(true);
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            (1, Event::BeforeCommit),
+            (1, Event::Update(1, value(2), 1)),
+            (1, Event::AfterCommit(true)),
+            (1, Event::BeforeCommit),
+            (1, Event::Update(1, value(3), 1)),
+            (1, Event::AfterCommit(true)),
+        ]
+    );
+}
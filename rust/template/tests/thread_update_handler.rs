@@ -0,0 +1,225 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use datalog_example_ddlog::update_handler::{
+    MTUpdateHandler, ST_CBFn, ThreadUpdateHandler, UpdateHandler,
+};
+use differential_datalog::ddval::{DDValConvert, DDValue};
+use differential_datalog::program::RelId;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Event {
+    BeforeCommit,
+    Update(RelId, DDValue, isize),
+    AfterCommit(bool),
+}
+
+type Log = Arc<Mutex<Vec<Event>>>;
+
+/// Handler that logs the events it receives.  If `gate` is set, every
+/// update waits for a token from it.
+#[derive(Clone, Debug)]
+struct Recorder {
+    log: Log,
+    gate: Option<Arc<Mutex<Receiver<()>>>>,
+}
+
+impl UpdateHandler for Recorder {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        let handler = self.clone();
+        Box::new(move |relid, v, w| {
+            if let Some(gate) = &handler.gate {
+                gate.lock().unwrap().recv().unwrap();
+            }
+            handler
+                .log
+                .lock()
+                .unwrap()
+                .push(Event::Update(relid, v.clone(), w))
+        })
+    }
+    fn before_commit(&self) {
+        self.log.lock().unwrap().push(Event::BeforeCommit);
+    }
+    fn after_commit(&self, success: bool) {
+        self.log.lock().unwrap().push(Event::AfterCommit(success));
+    }
+}
+
+fn value(x: u64) -> DDValue {
+    x.into_ddvalue()
+}
+
+fn transaction(n: u64) -> Vec<Event> {
+    let mut events = vec![Event::BeforeCommit];
+    events.extend((0..10).map(|x| Event::Update(0, value(n * 10 + x), 1)));
+    events.push(Event::AfterCommit(true));
+    events
+}
+
+fn commit(handler: &ThreadUpdateHandler, n: u64) {
+    handler.before_commit();
+    let mut cb = handler.mt_update_cb();
+    for x in 0..10 {
+        cb(0, &value(n * 10 + x), 1);
+    }
+    handler.after_commit(true);
+}
+
+#[test]
+fn async_commits_are_handled_in_order() {
+    let log = Log::default();
+    let log2 = log.clone();
+    let (gate_tx, gate_rx) = channel();
+    let gate = Arc::new(Mutex::new(gate_rx));
+    let handler = ThreadUpdateHandler::new_async(100, move || {
+        Box::new(Recorder {
+            log: log2,
+            gate: Some(gate),
+        }) as Box<dyn UpdateHandler>
+    });
+
+    // Commits return before the worker handles any update.
+    for n in 0..3 {
+        commit(&handler, n);
+    }
+    assert_eq!(handler.last_commit(), 3);
+    assert!(log.lock().unwrap().len() <= 1);
+
+    for _ in 0..20 {
+        gate_tx.send(()).unwrap();
+    }
+    handler.wait_for_commit(2).unwrap();
+    let expected: Vec<Event> = (0..2).flat_map(transaction).collect();
+    assert_eq!(log.lock().unwrap()[..expected.len()], expected[..]);
+
+    for _ in 0..10 {
+        gate_tx.send(()).unwrap();
+    }
+    handler.flush().unwrap();
+    let expected: Vec<Event> = (0..3).flat_map(transaction).collect();
+    assert_eq!(*log.lock().unwrap(), expected);
+}
+
+#[test]
+fn async_queue_is_bounded() {
+    let log = Log::default();
+    let log2 = log.clone();
+    let (gate_tx, gate_rx) = channel();
+    let gate = Arc::new(Mutex::new(gate_rx));
+    let handler = ThreadUpdateHandler::new_async(2, move || {
+        Box::new(Recorder {
+            log: log2,
+            gate: Some(gate),
+        }) as Box<dyn UpdateHandler>
+    });
+
+    let sent = Arc::new(AtomicUsize::new(0));
+    let sender = {
+        let mut cb = handler.mt_update_cb();
+        let sent = sent.clone();
+        thread::spawn(move || {
+            for x in 0..10 {
+                cb(0, &value(x), 1);
+                let _ = sent.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+    };
+
+    // One update is being handled and two are queued; the sender blocks on
+    // the next one.
+    thread::sleep(Duration::from_millis(100));
+    assert!(sent.load(Ordering::SeqCst) <= 4);
+
+    for _ in 0..10 {
+        gate_tx.send(()).unwrap();
+    }
+    sender.join().unwrap();
+    handler.after_commit(true);
+    handler.flush().unwrap();
+    assert_eq!(log.lock().unwrap().len(), 11);
+}
+
+#[test]
+fn sync_commits_are_handled_on_return() {
+    let log = Log::default();
+    let log2 = log.clone();
+    let handler = ThreadUpdateHandler::new(move || {
+        Box::new(Recorder {
+            log: log2,
+            gate: None,
+        }) as Box<dyn UpdateHandler>
+    });
+
+    commit(&handler, 0);
+    assert_eq!(handler.last_commit(), 1);
+    assert_eq!(*log.lock().unwrap(), transaction(0));
+    handler.wait_for_commit(1).unwrap();
+}
+
+#[test]
+fn dropping_a_clone_keeps_worker() {
+    let log = Log::default();
+    let log2 = log.clone();
+    let handler = ThreadUpdateHandler::new_async(10, move || {
+        Box::new(Recorder {
+            log: log2,
+            gate: None,
+        }) as Box<dyn UpdateHandler>
+    });
+
+    // Clones share the worker, which stops when the last of them is dropped.
+    drop(handler.clone());
+    commit(&handler, 0);
+    handler.wait_for_commit(1).unwrap();
+    assert_eq!(*log.lock().unwrap(), transaction(0));
+}
+
+/// Handler that panics on the first update.
+#[derive(Debug)]
+struct Panicker;
+
+impl UpdateHandler for Panicker {
+    fn update_cb(&self) -> Box<dyn ST_CBFn> {
+        Box::new(|_, _, _| panic!("update handler failed"))
+    }
+    fn before_commit(&self) {}
+    fn after_commit(&self, _success: bool) {}
+}
+
+#[test]
+fn wait_fails_if_worker_terminates() {
+    let handler =
+        ThreadUpdateHandler::new_async(10, || Box::new(Panicker) as Box<dyn UpdateHandler>);
+    handler.update_cb()(0, &value(0), 1);
+    handler.after_commit(true);
+    assert!(handler.wait_for_commit(1).is_err());
+}
+
+#[test]
+fn sync_commit_returns_if_worker_terminates() {
+    let handler = ThreadUpdateHandler::new(|| Box::new(Panicker) as Box<dyn UpdateHandler>);
+    handler.update_cb()(0, &value(0), 1);
+    handler.after_commit(true);
+    assert!(handler.flush().is_err());
+}
+
+/// Notifications sent after the worker has terminated are dropped.
+#[test]
+fn updates_after_worker_terminates_are_dropped() {
+    let handler = ThreadUpdateHandler::new(|| Box::new(Panicker) as Box<dyn UpdateHandler>);
+    let mut cb = handler.mt_update_cb();
+    handler.before_commit();
+    cb(0, &value(0), 1);
+    handler.after_commit(true);
+    assert!(handler.flush().is_err());
+
+    handler.before_commit();
+    cb(0, &value(1), 1);
+    handler.update_cb()(0, &value(2), 1);
+    handler.after_commit(true);
+    assert!(handler.flush().is_err());
+}
@@ -184,8 +184,8 @@ templateFiles =
         , (dir </> "src/api/mod.rs"             , $(embedFile "rust/template/src/api/mod.rs"))
         , (dir </> "src/api/c_api.rs"           , $(embedFile "rust/template/src/api/c_api.rs"))
         , (dir </> "src/ovsdb_api.rs"           , $(embedFile "rust/template/src/ovsdb_api.rs"))
-        , (dir </> "src/update_handler.rs"      , $(embedFile "rust/template/src/update_handler.rs"))
         , (dir </> "ddlog.h"                    , $(embedFile "rust/template/ddlog.h"))
+        , (dir </> "cbindgen.toml"              , $(embedFile "rust/template/cbindgen.toml"))
         , (dir </> "ddlog_ovsdb_test.c"         , $(embedFile "rust/template/ddlog_ovsdb_test.c"))
         ]
     where dir = rustProjectDir
@@ -200,6 +200,8 @@ rustLibFiles =
         , (dir </> "differential_datalog/src/ddval/mod.rs"                , $(embedFile "rust/template/differential_datalog/src/ddval/mod.rs"))
         , (dir </> "differential_datalog/src/ddval/ddvalue.rs"            , $(embedFile "rust/template/differential_datalog/src/ddval/ddvalue.rs"))
         , (dir </> "differential_datalog/src/ddval/ddval_convert.rs"      , $(embedFile "rust/template/differential_datalog/src/ddval/ddval_convert.rs"))
+        , (dir </> "differential_datalog/src/error.rs"                    , $(embedFile "rust/template/differential_datalog/src/error.rs"))
+        , (dir </> "differential_datalog/src/hddlog.rs"                   , $(embedFile "rust/template/differential_datalog/src/hddlog.rs"))
         , (dir </> "differential_datalog/src/lib.rs"                      , $(embedFile "rust/template/differential_datalog/src/lib.rs"))
         , (dir </> "differential_datalog/src/profile.rs"                  , $(embedFile "rust/template/differential_datalog/src/profile.rs"))
         , (dir </> "differential_datalog/src/profile_statistics.rs"       , $(embedFile "rust/template/differential_datalog/src/profile_statistics.rs"))
@@ -213,21 +215,31 @@ rustLibFiles =
         , (dir </> "differential_datalog/src/record/arrays.rs"            , $(embedFile "rust/template/differential_datalog/src/record/arrays.rs"))
         , (dir </> "differential_datalog/src/replay.rs"                   , $(embedFile "rust/template/differential_datalog/src/replay.rs"))
         , (dir </> "differential_datalog/src/test_record.rs"              , $(embedFile "rust/template/differential_datalog/src/test_record.rs"))
+        , (dir </> "differential_datalog/src/update_handler.rs"           , $(embedFile "rust/template/differential_datalog/src/update_handler.rs"))
         , (dir </> "differential_datalog/src/valmap.rs"                   , $(embedFile "rust/template/differential_datalog/src/valmap.rs"))
         , (dir </> "differential_datalog/src/variable.rs"                 , $(embedFile "rust/template/differential_datalog/src/variable.rs"))
         , (dir </> "differential_datalog_test/Cargo.toml"                 , $(embedFile "rust/template/differential_datalog_test/Cargo.toml"))
         , (dir </> "differential_datalog_test/lib.rs"                     , $(embedFile "rust/template/differential_datalog_test/lib.rs"))
         , (dir </> "differential_datalog_test/test_value.rs"              , $(embedFile "rust/template/differential_datalog_test/test_value.rs"))
         , (dir </> "cmd_parser/Cargo.toml"                                , $(embedFile "rust/template/cmd_parser/Cargo.toml"))
+        , (dir </> "cmd_parser/completion.rs"                             , $(embedFile "rust/template/cmd_parser/completion.rs"))
+        , (dir </> "cmd_parser/error.rs"                                  , $(embedFile "rust/template/cmd_parser/error.rs"))
+        , (dir </> "cmd_parser/include.rs"                                , $(embedFile "rust/template/cmd_parser/include.rs"))
+        , (dir </> "cmd_parser/interactive.rs"                            , $(embedFile "rust/template/cmd_parser/interactive.rs"))
         , (dir </> "cmd_parser/lib.rs"                                    , $(embedFile "rust/template/cmd_parser/lib.rs"))
         , (dir </> "cmd_parser/parse.rs"                                  , $(embedFile "rust/template/cmd_parser/parse.rs"))
+        , (dir </> "cmd_parser/reader.rs"                                 , $(embedFile "rust/template/cmd_parser/reader.rs"))
         , (dir </> "distributed_datalog/Cargo.toml"                       , $(embedFile "rust/template/distributed_datalog/Cargo.toml"))
+        , (dir </> "distributed_datalog/benches/compression.rs"           , $(embedFile "rust/template/distributed_datalog/benches/compression.rs"))
         , (dir </> "distributed_datalog/src/assign.rs"                    , $(embedFile "rust/template/distributed_datalog/src/assign.rs"))
         , (dir </> "distributed_datalog/src/accumulate/mod.rs"            , $(embedFile "rust/template/distributed_datalog/src/accumulate/mod.rs"))
         , (dir </> "distributed_datalog/src/accumulate/accumulator.rs"    , $(embedFile "rust/template/distributed_datalog/src/accumulate/accumulator.rs"))
         , (dir </> "distributed_datalog/src/accumulate/observer.rs"       , $(embedFile "rust/template/distributed_datalog/src/accumulate/observer.rs"))
         , (dir </> "distributed_datalog/src/accumulate/test.rs"           , $(embedFile "rust/template/distributed_datalog/src/accumulate/test.rs"))
         , (dir </> "distributed_datalog/src/accumulate/txndistributor.rs" , $(embedFile "rust/template/distributed_datalog/src/accumulate/txndistributor.rs"))
+        , (dir </> "distributed_datalog/src/config_file.rs"               , $(embedFile "rust/template/distributed_datalog/src/config_file.rs"))
+        , (dir </> "distributed_datalog/src/controller.rs"                , $(embedFile "rust/template/distributed_datalog/src/controller.rs"))
+        , (dir </> "distributed_datalog/src/hash.rs"                      , $(embedFile "rust/template/distributed_datalog/src/hash.rs"))
         , (dir </> "distributed_datalog/src/instantiate.rs"               , $(embedFile "rust/template/distributed_datalog/src/instantiate.rs"))
         , (dir </> "distributed_datalog/src/lib.rs"                       , $(embedFile "rust/template/distributed_datalog/src/lib.rs"))
         , (dir </> "distributed_datalog/src/observe/mod.rs"               , $(embedFile "rust/template/distributed_datalog/src/observe/mod.rs"))
@@ -241,14 +253,24 @@ rustLibFiles =
         , (dir </> "distributed_datalog/src/sinks/mod.rs"                 , $(embedFile "rust/template/distributed_datalog/src/sinks/mod.rs"))
         , (dir </> "distributed_datalog/src/sources/file.rs"              , $(embedFile "rust/template/distributed_datalog/src/sources/file.rs"))
         , (dir </> "distributed_datalog/src/sources/mod.rs"               , $(embedFile "rust/template/distributed_datalog/src/sources/mod.rs"))
+        , (dir </> "distributed_datalog/src/tcp_channel/compress.rs"      , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/compress.rs"))
+        , (dir </> "distributed_datalog/src/tcp_channel/handshake.rs"     , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/handshake.rs"))
         , (dir </> "distributed_datalog/src/tcp_channel/message.rs"       , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/message.rs"))
         , (dir </> "distributed_datalog/src/tcp_channel/mod.rs"           , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/mod.rs"))
         , (dir </> "distributed_datalog/src/tcp_channel/receiver.rs"      , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/receiver.rs"))
         , (dir </> "distributed_datalog/src/tcp_channel/sender.rs"        , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/sender.rs"))
         , (dir </> "distributed_datalog/src/tcp_channel/socket.rs"        , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/socket.rs"))
+        , (dir </> "distributed_datalog/src/tcp_channel/stream.rs"        , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/stream.rs"))
+        , (dir </> "distributed_datalog/src/tcp_channel/test_certs/ca.pem" , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/test_certs/ca.pem"))
+        , (dir </> "distributed_datalog/src/tcp_channel/test_certs/cert.pem" , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/test_certs/cert.pem"))
+        , (dir </> "distributed_datalog/src/tcp_channel/test_certs/key.pem" , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/test_certs/key.pem"))
+        , (dir </> "distributed_datalog/src/tcp_channel/test_certs/rogue_cert.pem" , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/test_certs/rogue_cert.pem"))
+        , (dir </> "distributed_datalog/src/tcp_channel/test_certs/rogue_key.pem" , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/test_certs/rogue_key.pem"))
+        , (dir </> "distributed_datalog/src/tcp_channel/tls.rs"           , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/tls.rs"))
         , (dir </> "distributed_datalog/src/tcp_channel/txnbuf.rs"        , $(embedFile "rust/template/distributed_datalog/src/tcp_channel/txnbuf.rs"))
         , (dir </> "distributed_datalog/src/test.rs"                      , $(embedFile "rust/template/distributed_datalog/src/test.rs"))
         , (dir </> "distributed_datalog/src/txnmux.rs"                    , $(embedFile "rust/template/distributed_datalog/src/txnmux.rs"))
+        , (dir </> "distributed_datalog/src/write_config.rs"              , $(embedFile "rust/template/distributed_datalog/src/write_config.rs"))
         , (dir </> "distributed_datalog/src/zookeeper.rs"                 , $(embedFile "rust/template/distributed_datalog/src/zookeeper.rs"))
         , (dir </> "ovsdb/Cargo.toml"                                     , $(embedFile "rust/template/ovsdb/Cargo.toml"))
         , (dir </> "ovsdb/lib.rs"                                         , $(embedFile "rust/template/ovsdb/lib.rs"))
@@ -1205,6 +1227,7 @@ mkDDValueFromRecord d@DatalogProgram{..} =
     mkRelIdMapC d                                                                                   $$
     mkInputRelIdMap d                                                                               $$
     mkOutputRelIdMap d                                                                              $$
+    mkRelTypeMap d                                                                                  $$
     mkIndexesTryFromStr d                                                                           $$
     mkIndexesTryFromIdxId d                                                                         $$
     mkIdxId2Name d                                                                                  $$
@@ -1426,6 +1449,26 @@ mkOutputRelIdMap prog =
                   c_api = False
                 }
 
+mkRelTypeMap :: DatalogProgram -> Doc
+mkRelTypeMap prog =
+    createLazyStatic lazy_static
+    where
+        mapKey rel = "Relations::" <> (rnameFlat . name $ rel)
+        -- Expand typedefs, including those of nested fields and type
+        -- arguments, so that the description reflects the structure of
+        -- the type and not just its name.
+        mapValue rel = pp $ show $ render $ pp $ typeExpand prog rel
+        entries = [(mapKey rel, mapValue rel) | rel <- M.elems (progRelations prog)]
+        lazy_static =
+            LazyStatic
+                { staticName = "RELTYPEMAP",
+                  staticDoc = Just "A map of `Relations`s to the type of their values as an `&'static str`",
+                  keyType = "Relations",
+                  valueType = "&'static str",
+                  staticEntries = entries,
+                  c_api = False
+                }
+
 -- Convert string to `enum Indexes`
 mkIndexesTryFromStr :: DatalogProgram -> Doc
 mkIndexesTryFromStr d =
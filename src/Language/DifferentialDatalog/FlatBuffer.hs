@@ -63,10 +63,6 @@ module Language.DifferentialDatalog.FlatBuffer
     )
 where
 
--- FIXME: support `DeleteKey` and `Modify` commands.  The former require collecting
--- primary key types; the latter requires adding support for type-erased value
--- representation (aka `Record`).
-
 import Prelude hiding ((<>), readFile, writeFile)
 import qualified Data.Map as M
 import Data.List
@@ -195,11 +191,41 @@ compileFlatBufferSchema d prog_name =
     "    key: __Value;"                                                         $$
     "}"                                                                         $$
     ""                                                                          $$
+    "// Type-erased DDlog value (see `differential_datalog::record::Record`)."  $$
+    "// Used to describe partial-record mutators in `Modify` commands."        $$
+    "enum __RecordKind: uint8 {"                                                $$
+    "    Bool, Int, Float, Double, String, Serialized, Tuple,"                 $$
+    "    Array, Vector, Set, Map, PosStruct, NamedStruct"                       $$
+    "}"                                                                         $$
+    "table __Record {"                                                          $$
+    "    kind: __RecordKind;"                                                   $$
+    "    bool_val: bool;"                                                       $$
+    "    int_val: __BigInt;"                                                    $$
+    "    float_val: float;"                                                     $$
+    "    double_val: double;"                                                   $$
+    "    // String value or serialized value."                                 $$
+    "    string_val: string;"                                                   $$
+    "    // Struct constructor name or serialization format."                  $$
+    "    name: string;"                                                         $$
+    "    // Tuple or collection elements or struct fields."                    $$
+    "    fields: [__Record];"                                                   $$
+    "    // Field names of a `NamedStruct`."                                    $$
+    "    field_names: [string];"                                                $$
+    "}"                                                                         $$
+    ""                                                                          $$
     "// DDlog commands"                                                         $$
+    "//"                                                                        $$
+    "// `Update` commands insert (`weight` = 1) or delete (`weight` = -1) `val`;" $$
+    "// `InsertOrUpdate` inserts `val`, replacing any existing value with the"  $$
+    "// same key; `DeleteKey` deletes the value whose key is `val`; `Modify`"    $$
+    "// applies `mutator` to the value whose key is `val`."                     $$
+    "enum __CommandKind: uint8 { Update, InsertOrUpdate, DeleteKey, Modify }"   $$
     "table __Command {"                                                         $$
     "   weight: int64;"                                                         $$
     "   relid: uint64 =" <+> default_relid <> ";"                               $$
     "   val:  __Value;"                                                         $$
+    "   kind: __CommandKind = Update;"                                          $$
+    "   mutator: __Record;"                                                     $$
     "}"                                                                         $$
     "table __Commands {"                                                        $$
     "   commands: [__Command];"                                                 $$
@@ -397,6 +423,7 @@ progRustTypesToSerialize :: (?d::DatalogProgram) => [Type]
 progRustTypesToSerialize =
     nub $
     concatMap relTypesToSerialize progIORelations ++
+    concatMap keyTypesToSerialize progKeyedRelations ++
     concatMap idxTypesToSerialize (M.elems $ progIndexes ?d) ++
     concatMap (relTypesToSerialize . idxRelation ?d) (M.elems $ progIndexes ?d)
 
@@ -414,6 +441,7 @@ progValTypes :: (?d::DatalogProgram) => [Type]
 progValTypes =
     nub $
     map (typeNormalizeForFlatBuf . relType) progIORelations ++
+    map (typeNormalizeForFlatBuf . relKeyType') progKeyedRelations ++
     map (typeNormalizeForFlatBuf . idxKeyType) (M.elems $ progIndexes ?d) ++
     map (typeNormalizeForFlatBuf . relType . idxRelation ?d) (M.elems $ progIndexes ?d)
 
@@ -422,6 +450,17 @@ progIORelations =
     filter (\rel -> elem (relRole rel) [RelInput, RelOutput])
            $ M.elems $ progRelations ?d
 
+-- Input relations with primary keys, which can be targeted by `DeleteKey` and
+-- `Modify` commands.
+progKeyedRelations :: (?d::DatalogProgram) => [Relation]
+progKeyedRelations =
+    filter (\rel -> relRole rel == RelInput && isJust (relPrimaryKey rel))
+           $ M.elems $ progRelations ?d
+
+-- Primary key type of a relation in 'progKeyedRelations'.
+relKeyType' :: (?d::DatalogProgram) => Relation -> Type
+relKeyType' rel = typeNormalize ?d $ fromJust $ relKeyType ?d rel
+
 -- Types used in relation declaration (possibly, recursively), for which serialization
 -- logic must be generated.
 relTypesToSerialize :: (?d::DatalogProgram) => Relation -> [Type]
@@ -431,6 +470,13 @@ relTypesToSerialize Relation{..} =
                                          -- generate a table for it even if it's a primitive
                                          -- type.
 
+-- Types used in the primary key of a relation (possibly, recursively), for which
+-- serialization logic must be generated.
+keyTypesToSerialize :: (?d::DatalogProgram) => Relation -> [Type]
+keyTypesToSerialize rel =
+    execState (typeSubtypes [] $ relKeyType' rel)
+              [relKeyType' rel]
+
 -- Types used in index declaration (possibly, recursively), for which serialization
 -- logic must be generated.
 idxTypesToSerialize :: (?d::DatalogProgram) => Index -> [Type]
@@ -1052,6 +1098,7 @@ mkJavaUpdateBuilder = ("ddlog" </> ?prog_name </> updateBuilderClass <.> "java",
     "package ddlog." <> pp ?prog_name <> ";"                            $$
     "import ddlogapi.DDlogAPI;"                                         $$
     "import ddlogapi.DDlogException;"                                   $$
+    "import ddlogapi.DDlogRecord;"                                      $$
     "import com.google.flatbuffers.*;"                                  $$
     "public class" <+> pp updateBuilderClass
                    <+> "extends" <+> pp builderClass                    $$
@@ -1077,43 +1124,128 @@ mkJavaUpdateBuilder = ("ddlog" </> ?prog_name </> updateBuilderClass <.> "java",
                "    this.finished = true;"                              $$
                "    hddlog.applyUpdatesFromFlatBuf(fbbuilder.dataBuffer());" $$
                "}"                                                      $$
+               mk_mutator                                               $$
                (vcat $ map mk_command_constructors
                      $ filter ((== RelInput) . relRole)
                      $ M.elems $ progRelations ?d)))
     where
+    -- Serialize a type-erased mutator for a 'Modify' command into a '__Record' table.
+    mk_mutator :: Doc
+    mk_mutator =
+        let record = jFBPackage <> ".__Record"
+            rkind k = jFBPackage <> ".__RecordKind." <> k
+            elems size get = "fields = new int[r." <> size <> "()];"                      $$
+                             "for (int i = 0; i < fields.length; i++)"                  $$
+                             "    fields[i] = mutator(r." <> get <> "(i));" in
+        "private int mutator(DDlogRecord r) throws DDlogException {"                    $$
+        (nest' $
+         "int kind;"                                                                    $$
+         "boolean boolVal = false;"                                                     $$
+         "int intVal = 0;"                                                              $$
+         "float floatVal = 0;"                                                          $$
+         "double doubleVal = 0;"                                                        $$
+         "int stringVal = 0;"                                                           $$
+         "int name = 0;"                                                                $$
+         "int[] fields = null;"                                                         $$
+         "int[] fieldNames = null;"                                                     $$
+         "if (r.isBool()) {"                                                            $$
+         "    kind =" <+> rkind "Bool" <> ";"                                           $$
+         "    boolVal = r.getBoolean();"                                                $$
+         "} else if (r.isInt()) {"                                                      $$
+         "    kind =" <+> rkind "Int" <> ";"                                            $$
+         "    java.math.BigInteger v = r.getInt();"                                     $$
+         "    intVal =" <+> jFBPackage <> ".__BigInt.create__BigInt(fbbuilder, v.signum() >= 0," <+>
+                            jFBPackage <> ".__BigInt.createBytesVector(fbbuilder, v.abs().toByteArray()));" $$
+         "} else if (r.isFloat()) {"                                                    $$
+         "    kind =" <+> rkind "Float" <> ";"                                          $$
+         "    floatVal = r.getFloat();"                                                 $$
+         "} else if (r.isDouble()) {"                                                   $$
+         "    kind =" <+> rkind "Double" <> ";"                                         $$
+         "    doubleVal = r.getDouble();"                                               $$
+         "} else if (r.isString()) {"                                                   $$
+         "    kind =" <+> rkind "String" <> ";"                                         $$
+         "    stringVal = fbbuilder.createString(r.getString());"                       $$
+         "} else if (r.isTuple()) {"                                                    $$
+         "    kind =" <+> rkind "Tuple" <> ";"                                          $$
+         (nest' $ elems "getTupleSize" "getTupleField")                                 $$
+         "} else if (r.isVector()) {"                                                   $$
+         "    kind =" <+> rkind "Vector" <> ";"                                         $$
+         (nest' $ elems "getVectorSize" "getVectorField")                               $$
+         "} else if (r.isSet()) {"                                                      $$
+         "    kind =" <+> rkind "Set" <> ";"                                            $$
+         (nest' $ elems "getSetSize" "getSetField")                                     $$
+         "} else if (r.isMap()) {"                                                      $$
+         "    // Map entries are stored as key-value tuples."                          $$
+         "    kind =" <+> rkind "Map" <> ";"                                            $$
+         "    fields = new int[r.getMapSize()];"                                        $$
+         "    for (int i = 0; i < fields.length; i++) {"                                $$
+         "        int[] kv = new int[]{ mutator(r.getMapKey(i)), mutator(r.getMapValue(i)) };" $$
+         "        fields[i] =" <+> record <> ".create__Record(fbbuilder," <+> rkind "Tuple" <> ", false, 0, 0, 0, 0, 0," <+>
+                                   record <> ".createFieldsVector(fbbuilder, kv), 0);" $$
+         "    }"                                                                        $$
+         "} else if (r.isStruct()) {"                                                   $$
+         "    name = fbbuilder.createString(r.getStructName());"                        $$
+         (nest' $ elems "getStructSize" "getStructField")                               $$
+         "    if (r.isNamedStruct()) {"                                                 $$
+         "        kind =" <+> rkind "NamedStruct" <> ";"                                $$
+         "        fieldNames = new int[fields.length];"                                 $$
+         "        for (int i = 0; i < fields.length; i++)"                              $$
+         "            fieldNames[i] = fbbuilder.createString(r.getFieldName(i));"       $$
+         "    } else {"                                                                 $$
+         "        kind =" <+> rkind "PosStruct" <> ";"                                  $$
+         "    }"                                                                        $$
+         "} else {"                                                                     $$
+         "    throw new DDlogException(\"Cannot serialize mutator \" + r);"            $$
+         "}"                                                                            $$
+         "int fieldsVec = fields == null ? 0 :" <+> record <> ".createFieldsVector(fbbuilder, fields);" $$
+         "int fieldNamesVec = fieldNames == null ? 0 :" <+> record <> ".createFieldNamesVector(fbbuilder, fieldNames);" $$
+         "return" <+> record <> ".create__Record(fbbuilder, kind, boolVal, intVal, floatVal, doubleVal, stringVal, name, fieldsVec, fieldNamesVec);") $$
+        "}"
+
     mk_command_constructors :: Relation -> Doc
     mk_command_constructors rel =
-        mk_command_constructor "Insert" rel $$
-        mk_command_constructor "Delete" rel
-
-    mk_command_constructor cmd rel@Relation{..} =
-        let lcmd = pp $ (toLower $ head cmd) : tail cmd in
-        if typeHasUniqueConstructor relType
-           then -- Relation type has a unique constructor (e.g., it's a struct with a
+        mk_command_constructor "insert" "Update" "1" False rel (relType rel) $$
+        mk_command_constructor "delete" "Update" "-1" False rel (relType rel) $$
+        if isJust $ relPrimaryKey rel
+           then mk_command_constructor "insert_or_update" "InsertOrUpdate" "1" False rel (relType rel) $$
+                mk_command_constructor "delete_key" "DeleteKey" "-1" False rel (relKeyType' rel) $$
+                mk_command_constructor "modify" "Modify" "1" True rel (relKeyType' rel)
+           else empty
+
+    -- Method that adds a command of the specified kind to the builder.  'val_type'
+    -- is the type of the value stored in the command: relation type or the type of
+    -- its primary key.  'Modify' commands take an additional 'DDlogRecord' argument
+    -- that describes the mutator.
+    mk_command_constructor :: Doc -> Doc -> Doc -> Bool -> Relation -> Type -> Doc
+    mk_command_constructor lcmd kind weight with_mutator rel val_type =
+        let mutator_arg = if with_mutator then ["DDlogRecord mutator"] else []
+            mutator = if with_mutator then "mutator(mutator)" else "0"
+            cmd cons = "int cmd =" <+> jFBCallConstructor "__Command"
+                                       [ weight
+                                       , (pp $ relIdentifier ?d rel)
+                                       , jFBPackage <> ".__Value." <> typeTableName val_type
+                                       , jConvCreateTable val_type cons
+                                       , jFBPackage <> ".__CommandKind." <> kind
+                                       , mutator] <> ";"
+            throws = if with_mutator then " throws DDlogException" else empty in
+        if typeHasUniqueConstructor val_type
+           then -- Value type has a unique constructor (e.g., it's a struct with a
                 -- unique constructor, a tuple or a primitive type).
-                let args = case typ' ?d $ typeNormalizeForFlatBuf relType of
+                let args = case typ' ?d $ typeNormalizeForFlatBuf val_type of
                                 TStruct{..} -> map (\a -> jConvTypeW a <+> pp (name a)) $ consArgs $ typeCons !! 0
                                 TTuple{..}  -> mapIdx (\a i -> jConvTypeW a <+> "a" <> pp i) typeTupArgs
-                                _           -> [jConvTypeW relType <+> "v"] in
-                "public void" <+> lcmd <> "_" <> mkRelId rel <> "(" <> commaSep args <> ")" $$
-                (braces' $ "int cmd =" <+> jFBCallConstructor "__Command"
-                                           [ if cmd == "Insert" then "1" else "-1"
-                                           , (pp $ relIdentifier ?d rel)
-                                           , jFBPackage <> ".__Value." <> typeTableName relType
-                                           , jConvCreateTable relType Nothing] <> ";"  $$
+                                _           -> [jConvTypeW val_type <+> "v"] in
+                "public void" <+> lcmd <> "_" <> mkRelId rel <> "(" <> commaSep (args ++ mutator_arg) <> ")" <> throws $$
+                (braces' $ cmd Nothing $$
                            "this.commands.add(Integer.valueOf(cmd));")
-           else -- Relation type is a struct with multiple constructors
+           else -- Value type is a struct with multiple constructors
                 vcat $
                 map (\c@Constructor{..} ->
                      "public void" <+> lcmd <> "_" <> mkRelId rel <> "_" <> (pp $ legalize $ name c) <>
-                         "(" <> (commaSep $ map (\a -> jConvTypeW a <+> pp (name a)) consArgs) <> ")" $$
-                     (braces' $ "int cmd =" <+> jFBCallConstructor "__Command"
-                                                [ if cmd == "Insert" then "1" else "-1"
-                                                , (pp $ relIdentifier ?d rel)
-                                                , jFBPackage <> ".__Value." <> typeTableName relType
-                                                , jConvCreateTable relType (Just c)] <> ";"  $$
+                         "(" <> (commaSep $ map (\a -> jConvTypeW a <+> pp (name a)) consArgs ++ mutator_arg) <> ")" <> throws $$
+                     (braces' $ cmd (Just c) $$
                                 "this.commands.add(Integer.valueOf(cmd));"))
-                    $ typeCons $ typ' ?d $ typeNormalizeForFlatBuf relType
+                    $ typeCons $ typ' ?d $ typeNormalizeForFlatBuf val_type
 
 -- Class with methods to query DDlog indexes.
 mkJavaQuery :: (?d::DatalogProgram, ?prog_name::String) => (FilePath, Doc)
@@ -1478,6 +1610,12 @@ rustValueFromFlatbuf =
     "        _ => Err(format!(\"DDValue::relval_from_flatbuf: invalid relid {}\", relid))"      $$
     "    }"                                                                                     $$
     "}"                                                                                         $$
+    "fn relkey_from_flatbuf(relid: program::RelId, v: fbrt::Table) -> Response<DDValue> {"      $$
+    "    match relid {"                                                                         $$
+    (nest' $ nest' $ vcat key_enums)                                                            $$
+    "        _ => Err(format!(\"DDValue::relkey_from_flatbuf: relation {} does not have a primary key\", relid))" $$
+    "    }"                                                                                     $$
+    "}"                                                                                         $$
     "fn idxkey_from_flatbuf(idxid: program::IdxId, v: fbrt::Table) -> Response<DDValue> {"      $$
     "    match idxid {"                                                                         $$
     (nest' $ nest' $ vcat idx_enums)                                                            $$
@@ -1511,6 +1649,11 @@ rustValueFromFlatbuf =
                      pp (relIdentifier ?d rel) <+> "=> Ok(" <>
                          "<" <> R.mkType ?d Nothing relType <> ">::from_flatbuf(fb::" <> typeTableName relType <> "::init_from_table(v))?.into_ddvalue()),")
                     progIORelations
+    key_enums = map (\rel ->
+                     let t = relKeyType' rel in
+                     pp (relIdentifier ?d rel) <+> "=> Ok(" <>
+                         "<" <> R.mkType ?d Nothing t <> ">::from_flatbuf(fb::" <> typeTableName t <> "::init_from_table(v))?.into_ddvalue()),")
+                    progKeyedRelations
     idx_enums = map (\idx@Index{} ->
                      let t = idxKeyType idx in
                      pp (idxIdentifier ?d idx) <+> "=> Ok(" <>
@@ -53,6 +53,7 @@ module Language.DifferentialDatalog.Type(
     checkTypesMatch,
     typesMatch,
     typeNormalize,
+    typeExpand,
     typeSubstTypeArgs,
     ConsTree(..),
     consTreeEmpty,
@@ -521,6 +522,30 @@ typeNormalize' d t =
          _                  -> error $ "Type.typeNormalize': unexpected type " ++ show t'
     where t' = typ'' d t
 
+-- | Expand all typedef's in a type, including those of struct fields,
+-- tuple elements and type arguments, so that the result describes the
+-- complete structure of the type.  A recursive occurrence of a typedef
+-- that is already being expanded is left as is.
+typeExpand :: (WithType a) => DatalogProgram -> a -> Type
+typeExpand d x = typeExpand' d [] $ typ x
+
+typeExpand' :: DatalogProgram -> [String] -> Type -> Type
+typeExpand' d seen t =
+    case t of
+         TUser{..} | elem typeName seen
+                            -> t{typeArgs = map (typeExpand' d seen) typeArgs}
+         TUser{..}          -> let tdef = getType d typeName in
+                               case tdefType tdef of
+                                    Nothing -> tOpaque typeName $ map (typeExpand' d seen) typeArgs
+                                    Just t' -> typeExpand' d (typeName:seen)
+                                               $ typeSubstTypeArgs (M.fromList $ zip (tdefArgs tdef) typeArgs) t'
+         TOpaque{..}        -> t{typeArgs = map (typeExpand' d seen) typeArgs}
+         TStruct{..}        -> t{typeCons = map (\c -> c{consArgs = map (\a -> a{fieldType = typeExpand' d seen $ fieldType a}) $ consArgs c}) typeCons}
+         TTuple{..}         -> t{typeTupArgs = map (typeExpand' d seen) typeTupArgs}
+         TFunction{..}      -> t{ typeFuncArgs = map (\a -> a{atypeType = typeExpand' d seen $ typ a}) typeFuncArgs
+                                , typeRetType = typeExpand' d seen typeRetType}
+         _                  -> t
+
 -- User-defined types that appear in type expression
 typeUserTypes :: Type -> [String]
 typeUserTypes = nub . typeUserTypes'
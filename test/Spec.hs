@@ -55,6 +55,7 @@ import Language.DifferentialDatalog.Parse
 import Language.DifferentialDatalog.Module
 import Language.DifferentialDatalog.Syntax
 import Language.DifferentialDatalog.Validate
+import Language.DifferentialDatalog.Type
 import Language.DifferentialDatalog.Compile
 import Language.DifferentialDatalog.FlatBuffer
 
@@ -104,7 +105,9 @@ allTests progress = do
                then []
                else generatedTests progress dlFile refFile logFile
             | (dlFile, refFile, logFile) <- inFiles]
-    return $ testGroup "ddlog tests" [parser_tests, generated_tests]
+    let type_tests = testGroup "type tests" $
+          [ testCase "expand nested typedefs" typeExpandTest ]
+    return $ testGroup "ddlog tests" [parser_tests, generated_tests, type_tests]
 
 generatedTests :: Bool -> FilePath -> Maybe FilePath -> Maybe FilePath -> [TestTree]
 generatedTests progress dlFile refFile refLogFile = do
@@ -133,6 +136,23 @@ unitTests dir = do
     testGroup "unit tests" $
           [ testCase (takeBaseName dir) $ unitTest dir ]
 
+-- Check that the type reported for a relation, which distributed nodes
+-- hash to determine whether they run the same program, reflects changes
+-- to typedefs nested in the relation's type.
+typeExpandTest :: IO ()
+typeExpandTest = do
+    let program field = "typedef Inner = Inner{x: " ++ field ++ "}\n" ++
+                        "typedef Outer = Outer{id: bigint, inner: Inner}\n" ++
+                        "input relation Foo[Outer]\n"
+    let expanded field = do
+            (_, d, _) <- parseValidate "./test/types.dl" False $ program field
+            let [rel] = filter ((== "Foo") . relName) $ M.elems $ progRelations d
+            return $ show $ typeExpand d rel
+    t32 <- expanded "bit<32>"
+    t64 <- expanded "bit<64>"
+    assertBool ("nested field missing from expanded type " ++ t32) $ "inner: Inner{x: bit<32>}" `isInfixOf` t32
+    assertBool ("expanded type does not reflect the nested field: " ++ t64) $ t32 /= t64
+
 parseValidate :: FilePath -> Bool -> String -> IO ([DatalogModule], DatalogProgram, M.Map ModuleName (Doc, Doc, Doc))
 parseValidate file java program = do
     (modules, d, rs_code) <- parseDatalogProgram [takeDirectory file, "lib"] True program file
@@ -5,13 +5,14 @@ mod tests {
     use differential_datalog::program::Update;
     use differential_datalog::record::*;
     use std::borrow::Cow;
+    use std::convert::TryFrom;
 
     #[test]
     fn start_stop() -> Result<(), String> {
         let (prog, _) = HDDlog::run(1, false, |_, _: &Record, _| {});
 
         // the update consists of inserting a single bool
-        let table = HDDlog::get_table_id("Rin").unwrap();
+        let table = Relations::try_from("Rin").unwrap();
         let b = Record::Bool(false);
         let rec = Record::PosStruct(Cow::from("Rin"), [b].to_vec());
         let mut updates = Vec::new();
@@ -25,6 +26,682 @@ mod tests {
         prog.apply_valupdates(updates.into_iter())?;
         prog.transaction_commit_dump_changes()?;
 
-        prog.stop()
+        prog.stop().map_err(String::from)
+    }
+}
+
+/// Tests of subscriptions to output relations and indexes via the C API.
+#[cfg(test)]
+mod subscription_tests {
+    use api_ddlog::api::*;
+    use differential_datalog::record::*;
+    use std::ffi::CString;
+    use std::ptr;
+    use std::slice;
+    use std::sync::Mutex;
+
+    /// Changes delivered to a callback: table, record fields, weight.
+    type Log = Mutex<Vec<(table_id, Vec<bool>, isize)>>;
+
+    fn fields(rec: &Record) -> Vec<bool> {
+        match rec {
+            Record::PosStruct(_, fields) => fields.iter().filter_map(Record::as_bool).collect(),
+            Record::NamedStruct(_, fields) => {
+                fields.iter().filter_map(|(_, v)| v.as_bool()).collect()
+            }
+            _ => panic!("unexpected record {:?}", rec),
+        }
+    }
+
+    extern "C" fn log_update(
+        arg: libc::uintptr_t,
+        table: table_id,
+        rec: *const Record,
+        weight: libc::ssize_t,
+    ) {
+        let log = unsafe { &*(arg as *const Log) };
+        let rec = unsafe { &*rec };
+        log.lock().unwrap().push((table, fields(rec), weight));
+    }
+
+    unsafe fn table(name: &str) -> table_id {
+        ddlog_get_table_id(CString::new(name).unwrap().as_ptr())
+    }
+
+    unsafe fn index(name: &str) -> index_id {
+        ddlog_get_index_id(CString::new(name).unwrap().as_ptr())
+    }
+
+    /// Insert `values` into `Rin` in a single transaction.
+    unsafe fn insert(prog: ddlog_prog, values: &[bool]) {
+        let rin = table("Rin");
+        let cons = CString::new("Rin").unwrap();
+        let cmds: Vec<_> = values
+            .iter()
+            .map(|b| {
+                let field = ddlog_bool(*b);
+                ddlog_insert_cmd(rin, ddlog_struct(cons.as_ptr(), &field, 1))
+            })
+            .collect();
+        assert_eq!(ddlog_transaction_start(prog), 0);
+        assert_eq!(ddlog_apply_updates(prog, cmds.as_ptr(), cmds.len()), 0);
+        assert_eq!(ddlog_transaction_commit(prog), 0);
+    }
+
+    unsafe fn run() -> ddlog_prog {
+        let prog = ddlog_run(1, false, None, 0, None, ptr::null_mut());
+        assert!(!prog.is_null());
+        prog
+    }
+
+    #[test]
+    fn subscribe_unsubscribe() {
+        unsafe {
+            let prog = run();
+            let (rout, rpair) = (table("Rout"), table("Rpair"));
+            let (rout_log, rpair_log) = (Log::default(), Log::default());
+
+            let rout_sub =
+                ddlog_subscribe(prog, rout, Some(log_update), &rout_log as *const _ as _);
+            let rpair_sub =
+                ddlog_subscribe(prog, rpair, Some(log_update), &rpair_log as *const _ as _);
+            assert!(rout_sub >= 0 && rpair_sub >= 0);
+            assert_ne!(rout_sub, rpair_sub);
+
+            insert(prog, &[true]);
+            assert_eq!(*rout_log.lock().unwrap(), vec![(rout, vec![true], 1)]);
+            assert_eq!(
+                *rpair_log.lock().unwrap(),
+                vec![(rpair, vec![true, false], 1)]
+            );
+
+            assert_eq!(ddlog_unsubscribe(prog, rout_sub), 0);
+            assert_eq!(ddlog_unsubscribe(prog, rout_sub), -1);
+            assert_ne!(ddlog_last_error_code(), ddlog_error_code::DDLOG_OK);
+
+            insert(prog, &[false]);
+            assert_eq!(rout_log.lock().unwrap().len(), 1);
+            assert_eq!(
+                rpair_log.lock().unwrap()[1..],
+                [(rpair, vec![false, true], 1)]
+            );
+
+            /* Only output relations can be subscribed to. */
+            let rin = table("Rin");
+            assert_eq!(
+                ddlog_subscribe(prog, rin, Some(log_update), &rout_log as *const _ as _),
+                -1
+            );
+            assert_eq!(
+                ddlog_last_error_code(),
+                ddlog_error_code::DDLOG_ERR_UNKNOWN_TABLE
+            );
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    #[test]
+    fn subscribe_index() {
+        unsafe {
+            let prog = run();
+            let rpair = table("Rpair");
+            let log = Log::default();
+
+            let sub = ddlog_subscribe_index(
+                prog,
+                index("Rpair_by_a"),
+                Some(log_update),
+                &log as *const _ as _,
+            );
+            assert!(sub >= 0);
+
+            /* `Rpair(true, false)` does not match the index pattern. */
+            insert(prog, &[true, false]);
+            assert_eq!(*log.lock().unwrap(), vec![(rpair, vec![false, true], 1)]);
+
+            assert_eq!(
+                ddlog_subscribe_index(prog, 1000, Some(log_update), &log as *const _ as _),
+                -1
+            );
+            assert_eq!(
+                ddlog_last_error_code(),
+                ddlog_error_code::DDLOG_ERR_UNKNOWN_INDEX
+            );
+
+            assert_eq!(ddlog_unsubscribe(prog, sub), 0);
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    /// Subscriber that cancels its own subscription when it receives its
+    /// first update.
+    struct OneShot {
+        prog: ddlog_prog,
+        subscription: Mutex<i64>,
+        log: Log,
+    }
+
+    extern "C" fn unsubscribe_on_update(
+        arg: libc::uintptr_t,
+        table: table_id,
+        rec: *const Record,
+        weight: libc::ssize_t,
+    ) {
+        let subscriber = unsafe { &*(arg as *const OneShot) };
+        let mut subscription = subscriber.subscription.lock().unwrap();
+        if *subscription >= 0 {
+            assert_eq!(
+                unsafe { ddlog_unsubscribe(subscriber.prog, *subscription) },
+                0
+            );
+            *subscription = -1;
+        }
+        log_update(&subscriber.log as *const _ as _, table, rec, weight);
+    }
+
+    /// A subscription cancelled during a transaction receives the rest of
+    /// the transaction, but not subsequent ones.
+    #[test]
+    fn unsubscribe_during_commit() {
+        unsafe {
+            let prog = run();
+            let rout = table("Rout");
+            let subscriber = OneShot {
+                prog,
+                subscription: Mutex::new(-1),
+                log: Log::default(),
+            };
+            let sub = ddlog_subscribe(
+                prog,
+                rout,
+                Some(unsubscribe_on_update),
+                &subscriber as *const _ as _,
+            );
+            assert!(sub >= 0);
+            *subscriber.subscription.lock().unwrap() = sub;
+
+            insert(prog, &[false, true]);
+            assert_eq!(*subscriber.subscription.lock().unwrap(), -1);
+            let mut log = subscriber.log.lock().unwrap().clone();
+            log.sort();
+            assert_eq!(log, vec![(rout, vec![false], 1), (rout, vec![true], 1)]);
+
+            assert_eq!(ddlog_transaction_start(prog), 0);
+            assert_eq!(ddlog_clear_relation(prog, table("Rin")), 0);
+            assert_eq!(ddlog_transaction_commit(prog), 0);
+            assert_eq!(subscriber.log.lock().unwrap().len(), 2);
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    /// Batches delivered to a callback: table, and the record fields and
+    /// weight of every change in the batch.
+    type BatchLog = Mutex<Vec<(table_id, Vec<(Vec<bool>, isize)>)>>;
+
+    extern "C" fn log_batch(
+        arg: libc::uintptr_t,
+        table: table_id,
+        updates: *const ddlog_record_update,
+        num_updates: libc::size_t,
+    ) {
+        let log = unsafe { &*(arg as *const BatchLog) };
+        let updates = unsafe { slice::from_raw_parts(updates, num_updates) };
+        let mut batch: Vec<_> = updates
+            .iter()
+            .map(|upd| {
+                assert_eq!(upd.table, table);
+                (fields(unsafe { &*upd.rec }), upd.weight)
+            })
+            .collect();
+        batch.sort();
+        log.lock().unwrap().push((table, batch));
+    }
+
+    #[test]
+    fn subscribe_batch() {
+        unsafe {
+            let prog = run();
+            let rpair = table("Rpair");
+            let log = BatchLog::default();
+
+            let sub = ddlog_subscribe_batch(prog, rpair, Some(log_batch), &log as *const _ as _);
+            assert!(sub >= 0);
+
+            insert(prog, &[true, false]);
+            assert_eq!(
+                *log.lock().unwrap(),
+                vec![(rpair, vec![(vec![false, true], 1), (vec![true, false], 1)])]
+            );
+
+            /* Transactions that do not modify the table are not reported. */
+            insert(prog, &[]);
+            assert_eq!(log.lock().unwrap().len(), 1);
+
+            assert_eq!(ddlog_transaction_start(prog), 0);
+            assert_eq!(ddlog_clear_relation(prog, table("Rin")), 0);
+            assert_eq!(ddlog_transaction_commit(prog), 0);
+            assert_eq!(
+                log.lock().unwrap()[1..],
+                [(
+                    rpair,
+                    vec![(vec![false, true], -1), (vec![true, false], -1)]
+                )]
+            );
+
+            assert_eq!(ddlog_unsubscribe(prog, sub), 0);
+            insert(prog, &[true]);
+            assert_eq!(log.lock().unwrap().len(), 2);
+
+            assert_eq!(
+                ddlog_subscribe_batch(prog, table("Rin"), Some(log_batch), &log as *const _ as _),
+                -1
+            );
+            assert_eq!(
+                ddlog_last_error_code(),
+                ddlog_error_code::DDLOG_ERR_UNKNOWN_TABLE
+            );
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    /// FlatBuffers delivered to a callback: table and number of changes.
+    type FlatbufLog = Mutex<Vec<(table_id, usize)>>;
+
+    #[cfg(feature = "flatbuf")]
+    extern "C" fn log_flatbuf(
+        arg: libc::uintptr_t,
+        table: table_id,
+        buf: *const u8,
+        size: libc::size_t,
+    ) {
+        let log = unsafe { &*(arg as *const FlatbufLog) };
+        let buf = unsafe { slice::from_raw_parts(buf, size) };
+        let changes = api_ddlog::flatbuf::updates_from_flatbuf(buf)
+            .unwrap()
+            .count();
+        log.lock().unwrap().push((table, changes));
+    }
+
+    #[cfg(not(feature = "flatbuf"))]
+    extern "C" fn log_flatbuf(
+        _arg: libc::uintptr_t,
+        _table: table_id,
+        _buf: *const u8,
+        _size: libc::size_t,
+    ) {
+        unreachable!()
+    }
+
+    #[test]
+    fn subscribe_flatbuf() {
+        unsafe {
+            let prog = run();
+            let rout = table("Rout");
+            let log = FlatbufLog::default();
+
+            let sub = ddlog_subscribe_flatbuf(prog, rout, Some(log_flatbuf), &log as *const _ as _);
+            if cfg!(feature = "flatbuf") {
+                assert!(sub >= 0);
+                insert(prog, &[true, false]);
+                insert(prog, &[]);
+                assert_eq!(*log.lock().unwrap(), vec![(rout, 2)]);
+                assert_eq!(ddlog_unsubscribe(prog, sub), 0);
+            } else {
+                assert_eq!(sub, -1);
+                assert_eq!(
+                    ddlog_last_error_code(),
+                    ddlog_error_code::DDLOG_ERR_UNSUPPORTED
+                );
+            }
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    /// With `update_threads`, changes are delivered by a pool of threads, and
+    /// all of them are delivered by the time the transaction commits.
+    #[test]
+    fn update_threads() {
+        unsafe {
+            let log = Log::default();
+            let prog = ddlog_run_with_update_threads(
+                1,
+                false,
+                2,
+                Some(log_update),
+                &log as *const _ as _,
+                None,
+                ptr::null_mut(),
+            );
+            assert!(!prog.is_null());
+            let (rout, rpair) = (table("Rout"), table("Rpair"));
+
+            insert(prog, &[true]);
+            let mut changes = log.lock().unwrap().clone();
+            changes.sort();
+            let mut expected = vec![(rout, vec![true], 1), (rpair, vec![true, false], 1)];
+            expected.sort();
+            assert_eq!(changes, expected);
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    /// `ddlog_run_async` delivers changes in order once they are flushed.
+    #[test]
+    fn run_async() {
+        unsafe {
+            let log = Log::default();
+            let prog = ddlog_run_async(
+                1,
+                false,
+                16,
+                Some(log_update),
+                &log as *const _ as _,
+                None,
+                ptr::null_mut(),
+            );
+            assert!(!prog.is_null());
+            let rout = table("Rout");
+
+            insert(prog, &[true]);
+            insert(prog, &[false]);
+            assert_eq!(ddlog_flush_updates(prog), 0);
+            let changes: Vec<_> = log
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(table, _, _)| *table == rout)
+                .cloned()
+                .collect();
+            assert_eq!(changes, vec![(rout, vec![true], 1), (rout, vec![false], 1)]);
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+}
+
+/// Tests that C API functions report the class of each failure via
+/// `ddlog_last_error_code()`.
+#[cfg(test)]
+mod error_code_tests {
+    use api_ddlog::api::ddlog_error_code::*;
+    use api_ddlog::api::*;
+    use differential_datalog::record::*;
+    use std::ffi::CString;
+    use std::ptr;
+
+    unsafe fn table(name: &str) -> table_id {
+        ddlog_get_table_id(CString::new(name).unwrap().as_ptr())
+    }
+
+    unsafe fn record(cons: &str, values: &[bool]) -> *mut Record {
+        let cons = CString::new(cons).unwrap();
+        let fields: Vec<_> = values.iter().map(|b| ddlog_bool(*b)).collect();
+        ddlog_struct(cons.as_ptr(), fields.as_ptr(), fields.len())
+    }
+
+    /// Apply a single command and return the resulting error code.
+    unsafe fn apply(prog: ddlog_prog, cmd: *mut UpdCmd) -> ddlog_error_code {
+        ddlog_apply_updates(prog, &cmd, 1);
+        ddlog_last_error_code()
+    }
+
+    unsafe fn run() -> ddlog_prog {
+        let prog = ddlog_run(1, false, None, 0, None, ptr::null_mut());
+        assert!(!prog.is_null());
+        prog
+    }
+
+    #[test]
+    fn transaction() {
+        unsafe {
+            let prog = run();
+
+            assert_eq!(ddlog_transaction_commit(prog), -1);
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_NO_TRANSACTION);
+
+            assert_eq!(ddlog_transaction_start(prog), 0);
+            assert_eq!(ddlog_last_error_code(), DDLOG_OK);
+            assert_eq!(ddlog_transaction_start(prog), -1);
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_TRANSACTION_IN_PROGRESS);
+
+            assert_eq!(ddlog_transaction_rollback(prog), 0);
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    #[test]
+    fn unknown_table_and_index() {
+        unsafe {
+            let prog = run();
+
+            assert!(ddlog_get_table_name(1000).is_null());
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_UNKNOWN_TABLE);
+            assert!(ddlog_get_index_name(1000).is_null());
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_UNKNOWN_INDEX);
+
+            assert_eq!(ddlog_transaction_start(prog), 0);
+            assert_eq!(
+                apply(prog, ddlog_insert_cmd(1000, ddlog_bool(true))),
+                DDLOG_ERR_UNKNOWN_TABLE
+            );
+            assert_eq!(ddlog_clear_relation(prog, 1000), -1);
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_UNKNOWN_TABLE);
+            assert_eq!(ddlog_transaction_rollback(prog), 0);
+
+            assert_eq!(ddlog_dump_index(prog, 1000, None, 0), -1);
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_UNKNOWN_INDEX);
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    #[test]
+    fn primary_key() {
+        unsafe {
+            let prog = run();
+            let rkey = table("Rkey");
+
+            assert_eq!(ddlog_transaction_start(prog), 0);
+            assert_eq!(
+                apply(prog, ddlog_insert_cmd(rkey, record("Rkey", &[true, false]))),
+                DDLOG_OK
+            );
+            assert_eq!(
+                apply(prog, ddlog_insert_cmd(rkey, record("Rkey", &[true, true]))),
+                DDLOG_ERR_DUPLICATE_KEY
+            );
+            assert_eq!(
+                apply(prog, ddlog_delete_key_cmd(rkey, ddlog_bool(false))),
+                DDLOG_ERR_KEY_NOT_FOUND
+            );
+            assert_eq!(ddlog_transaction_rollback(prog), 0);
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    #[test]
+    fn invalid_record_and_argument() {
+        unsafe {
+            let prog = run();
+            let rin = table("Rin");
+
+            assert_eq!(ddlog_transaction_start(prog), 0);
+            /* `Rin` does not have a field of type `bit<64>`. */
+            assert_eq!(
+                apply(prog, ddlog_insert_cmd(rin, ddlog_u64(1))),
+                DDLOG_ERR_INVALID_RECORD
+            );
+            assert_eq!(ddlog_transaction_rollback(prog), 0);
+
+            assert_eq!(ddlog_unsubscribe(prog, 1000), -1);
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_INVALID_ARGUMENT);
+            assert_eq!(ddlog_transaction_start(ptr::null()), -1);
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_INVALID_ARGUMENT);
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    #[test]
+    fn unsupported() {
+        unsafe {
+            let prog = run();
+
+            assert_eq!(ddlog_transaction_start(prog), 0);
+            /* `Rin` does not have a primary key. */
+            assert_eq!(
+                apply(
+                    prog,
+                    ddlog_insert_or_update_cmd(table("Rin"), record("Rin", &[true]))
+                ),
+                DDLOG_ERR_UNSUPPORTED
+            );
+            assert_eq!(ddlog_transaction_rollback(prog), 0);
+
+            /* The program was started with `do_store` set to `false`. */
+            assert_eq!(ddlog_dump_table(prog, table("Rout"), None, 0), -1);
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_UNSUPPORTED);
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+}
+
+/// Tests of `ddlog_parse_commands()` and `ddlog_free_commands()`.
+#[cfg(test)]
+mod parse_commands_tests {
+    use api_ddlog::api::ddlog_error_code::*;
+    use api_ddlog::api::*;
+    use differential_datalog::record::*;
+    use std::borrow::Cow;
+    use std::ffi::CStr;
+    use std::ffi::CString;
+    use std::ptr;
+    use std::slice;
+    use std::sync::Mutex;
+
+    /// Parse `text`; returns the commands on success or the error message.
+    unsafe fn parse(text: &str) -> Result<(*mut *mut UpdCmd, usize), String> {
+        let text = CString::new(text).unwrap();
+        let mut cmds = ptr::null_mut();
+        let mut num_cmds = 0;
+        if ddlog_parse_commands(text.as_ptr(), &mut cmds, &mut num_cmds) == 0 {
+            assert_eq!(ddlog_last_error_code(), DDLOG_OK);
+            Ok((cmds, num_cmds))
+        } else {
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_PARSE);
+            assert!(cmds.is_null());
+            let message = CStr::from_ptr(ddlog_last_error());
+            Err(message.to_str().unwrap().to_string())
+        }
+    }
+
+    extern "C" fn log_record(arg: libc::uintptr_t, rec: *const Record, _w: libc::ssize_t) -> bool {
+        let log = unsafe { &*(arg as *const Mutex<Vec<Record>>) };
+        log.lock().unwrap().push(unsafe { &*rec }.clone());
+        true
+    }
+
+    /// Commands parsed from text can be applied to the program.
+    #[test]
+    fn parse_and_apply() {
+        unsafe {
+            let prog = ddlog_run(1, true, None, 0, None, ptr::null_mut());
+            assert!(!prog.is_null());
+
+            let (cmds, num_cmds) =
+                parse("insert Rin(true),\n# comment\ninsert Rin(.b = false);").unwrap();
+            let rel = || RelIdentifier::RelName(Cow::from("Rin"));
+            assert_eq!(
+                slice::from_raw_parts(cmds, num_cmds)
+                    .iter()
+                    .map(|cmd| (**cmd).clone())
+                    .collect::<Vec<_>>(),
+                vec![
+                    UpdCmd::Insert(
+                        rel(),
+                        Record::PosStruct(Cow::from("Rin"), vec![Record::Bool(true)])
+                    ),
+                    UpdCmd::Insert(
+                        rel(),
+                        Record::NamedStruct(
+                            Cow::from("Rin"),
+                            vec![(Cow::from("b"), Record::Bool(false))]
+                        )
+                    )
+                ]
+            );
+
+            assert_eq!(ddlog_transaction_start(prog), 0);
+            assert_eq!(ddlog_apply_updates(prog, cmds, num_cmds), 0);
+            ddlog_free_commands(cmds, num_cmds);
+            assert_eq!(ddlog_transaction_commit(prog), 0);
+
+            let rout = ddlog_get_table_id(CString::new("Rout").unwrap().as_ptr());
+            let log = Mutex::new(Vec::new());
+            assert_eq!(
+                ddlog_dump_table(prog, rout, Some(log_record), &log as *const _ as _),
+                0
+            );
+            assert_eq!(log.into_inner().unwrap().len(), 2);
+
+            assert_eq!(ddlog_stop(prog), 0);
+        }
+    }
+
+    /// Commands that are not applied are deallocated individually.
+    #[test]
+    fn free_unapplied() {
+        unsafe {
+            let (cmds, num_cmds) = parse(
+                "delete Rin(true), delete_key Rkey true; modify Rkey true <- Rkey{.v = true};",
+            )
+            .unwrap();
+            assert_eq!(num_cmds, 3);
+            for cmd in slice::from_raw_parts(cmds, num_cmds) {
+                ddlog_free_cmd(*cmd);
+            }
+            ddlog_free_commands(cmds, num_cmds);
+
+            /* Empty input. */
+            let (cmds, num_cmds) = parse("# nothing to do\n").unwrap();
+            assert_eq!(num_cmds, 0);
+            ddlog_free_commands(cmds, num_cmds);
+
+            ddlog_free_cmd(ptr::null_mut());
+            ddlog_free_commands(ptr::null_mut(), 0);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        unsafe {
+            let e = parse("insert Rin(true),\ninsert Rin(.b = );").unwrap_err();
+            assert!(
+                e.starts_with("ddlog_parse_commands(): <text>:2:1:"),
+                "{}",
+                e
+            );
+
+            let e = parse("insert Rin(true);\ncommit;").unwrap_err();
+            assert!(e.contains("not an update command"), "{}", e);
+
+            let e = parse("insert Rin(true)").unwrap_err();
+            assert!(e.contains("incomplete command"), "{}", e);
+
+            let text = CString::new("insert Rin(true);").unwrap();
+            let mut num_cmds = 0;
+            assert_eq!(
+                ddlog_parse_commands(text.as_ptr(), ptr::null_mut(), &mut num_cmds),
+                -1
+            );
+            assert_eq!(ddlog_last_error_code(), DDLOG_ERR_INVALID_ARGUMENT);
+        }
     }
 }
@@ -124,7 +124,7 @@ fn main() -> Result<(), String> {
 
     // `Record` type
 
-    let relid_word1 = HDDlog::get_table_id("Word1").unwrap() as RelId;
+    let relid_word1 = HDDlog::get_table_id("Word1").unwrap();
 
     // `UpdCmd` is a dynamically typed representaion of a DDlog command.
     // It takes a vector or `Record`'s, which represent dynamically typed
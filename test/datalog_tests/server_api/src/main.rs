@@ -1,9 +1,7 @@
 use std::path::PathBuf;
-use std::sync::Mutex;
 use std::thread::park;
 
 use env_logger::init;
-use once_cell::sync::Lazy;
 use log::debug;
 use log::info;
 use log::log_enabled;
@@ -11,18 +9,16 @@ use log::Level::Info;
 use serde_json::to_string as to_json;
 use structopt::StructOpt;
 
+use distributed_datalog::config_file::ConfigFile;
 use distributed_datalog::instantiate;
 use distributed_datalog::simple_assign;
 use distributed_datalog::zookeeper::connect;
-use distributed_datalog::zookeeper::renew_watch;
-use distributed_datalog::zookeeper::WatchedEvent;
-use distributed_datalog::zookeeper::ZooKeeper;
+use distributed_datalog::zookeeper::ChannelWatcher;
 use distributed_datalog::Addr;
+use distributed_datalog::Controller;
 use distributed_datalog::Member;
 use distributed_datalog::Members;
-use distributed_datalog::ReadConfig;
-use distributed_datalog::ReadMembers;
-use distributed_datalog::Realization;
+use distributed_datalog::Reconciler;
 
 use server_api_ddlog::api::HDDlog;
 use server_api_test::config;
@@ -69,6 +65,19 @@ enum Opts {
         #[structopt(long)]
         nodes: Vec<String>,
     },
+
+    /// Read the configuration from a (JSON or TOML) file, reconfiguring
+    /// whenever it changes.
+    #[structopt(name = "file")]
+    File {
+        /// The address of the current member being instantiated.
+        #[structopt(long)]
+        member: Addr,
+
+        /// The path to the configuration file.
+        #[structopt(long, default_value = "d3log.toml", parse(from_os_str))]
+        config: PathBuf,
+    },
 }
 
 fn manual(
@@ -113,71 +122,33 @@ fn manual(
     Ok(())
 }
 
-fn reconfigure(member: &Addr, zookeeper: &ZooKeeper) -> Result<Vec<Realization<HDDlog>>, String> {
-    let members = zookeeper.members()?;
-    let sys_cfg = zookeeper.config()?;
+fn zookeeper(member: Addr, nodes: Vec<String>) -> Result<(), String> {
+    let (watcher, changes) = ChannelWatcher::new();
+    let zookeeper = connect(nodes.iter(), watcher)?;
 
-    let assignment = simple_assign(sys_cfg.keys(), members.iter())
-        .ok_or_else(|| format!("failed to find an node:member assignment"))?;
+    let mut reconciler = Reconciler::<HDDlog>::new(member.clone());
+    let mut controller = Controller::new(zookeeper, Member::new(member), |sys_cfg, assignment| {
+        debug!("Using assignment: {:?}", assignment);
+        reconciler.reconcile(sys_cfg, assignment)
+    })?;
 
-    let realization = instantiate::<HDDlog>(sys_cfg, member, &assignment)?;
-    Ok(realization)
-}
-
-fn reconfig_state(member: &Addr, state: &Mutex<State>) -> Result<(), String> {
-    info!("Reconfiguring...");
-    let mut guard = state.lock().unwrap();
-    // Tear down the old realization before re-instantiating
-    // everything.
-    drop(guard.realization.take());
-
-    if let Some(zookeeper) = &mut guard.zookeeper {
-        // It is not clear why, but we seemingly need to "renew" the
-        // watch we already have...
-        renew_watch(zookeeper)?;
-
-        match reconfigure(&member, zookeeper) {
-            Ok(new) => {
-                guard.realization.replace(new);
-                Ok(())
-            }
-            Err(e) => Err(format!("failed to reconfigure: {}", e)),
-        }
-    } else {
-        Ok(())
-    }
+    println!("Configuration is running. Stop with Ctrl-C.");
+    controller.run(changes);
+    Ok(())
 }
 
-#[derive(Default)]
-struct State {
-    zookeeper: Option<ZooKeeper>,
-    realization: Option<Vec<Realization<HDDlog>>>,
-}
+fn file(member: Addr, config: PathBuf) -> Result<(), String> {
+    let config = ConfigFile::new(config);
+    let (_watcher, changes) = config.watch()?;
 
-impl State {
-    const fn new() -> Self {
-        Self {
-            zookeeper: None,
-            realization: None,
-        }
-    }
-}
+    let mut reconciler = Reconciler::<HDDlog>::new(member.clone());
+    let mut controller = Controller::new(config, Member::new(member), |sys_cfg, assignment| {
+        debug!("Using assignment: {:?}", assignment);
+        reconciler.reconcile(sys_cfg, assignment)
+    })?;
 
-fn zookeeper(member: Addr, nodes: Vec<String>) -> Result<(), String> {
-    static STATE: Lazy<Mutex<State>> = Lazy::new(|| Mutex::new(State::new()));
-
-    let watch = move |event: WatchedEvent| {
-        debug!("Watch event: {:?}", event);
-        if event.path.is_some() {
-            let _ = reconfig_state(&member, &STATE);
-        }
-    };
-    let zookeeper = connect(nodes.iter(), watch)?;
-    STATE.lock().unwrap().zookeeper = Some(zookeeper);
-
-    reconfig_state(&member, &STATE)?;
     println!("Configuration is running. Stop with Ctrl-C.");
-    park();
+    controller.run(changes);
     Ok(())
 }
 
@@ -194,5 +165,6 @@ fn main() -> Result<(), String> {
             output,
         } => manual(member, members, input1, input2, output),
         Opts::ZooKeeper { member, nodes } => zookeeper(member, nodes),
+        Opts::File { member, config } => file(member, config),
     }
 }
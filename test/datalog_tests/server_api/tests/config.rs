@@ -1,18 +1,29 @@
+use std::fs;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
 use std::io::Write;
+use std::path::Path;
 
+use maplit::btreemap;
 use maplit::btreeset;
 
+use serde_json::json;
+
 use tempfile::NamedTempFile;
 use test_env_log::test;
 
 use distributed_datalog::await_expected;
+use distributed_datalog::config_file::ConfigFile;
 use distributed_datalog::instantiate;
+use distributed_datalog::rendezvous_assign;
 use distributed_datalog::simple_assign;
 use distributed_datalog::Addr;
+use distributed_datalog::Controller;
 use distributed_datalog::Member;
+use distributed_datalog::Reconciler;
+use distributed_datalog::RelCfg;
+use distributed_datalog::SysCfg;
 
 use server_api_ddlog::api::HDDlog;
 use server_api_test::config;
@@ -66,3 +77,235 @@ fn instantiate_configuration_end_to_end() -> Result<(), String> {
 
     Ok(())
 }
+
+/// Instantiate a configuration under an assignment computed by means of
+/// rendezvous hashing, which spreads the nodes over all members.
+#[test]
+fn instantiate_rendezvous_assignment_end_to_end() -> Result<(), String> {
+    const SERVER_API_1_P1IN: &'static [u8] = include_bytes!("../data/server_api_1_p1in.dat");
+    const SERVER_API_2_P2IN: &'static [u8] = include_bytes!("../data/server_api_2_p2in.dat");
+    const SERVER_API_3_P3OUT: &'static str =
+        include_str!("../data/server_api_3_p3out.dump.expected");
+
+    let mut file1 = NamedTempFile::new().unwrap();
+    file1.write_all(SERVER_API_1_P1IN).unwrap();
+    let path1 = file1.into_temp_path();
+
+    let mut file2 = NamedTempFile::new().unwrap();
+    file2.write_all(SERVER_API_2_P2IN).unwrap();
+    let path2 = file2.into_temp_path();
+
+    let (mut file3, path3) = NamedTempFile::new().unwrap().into_parts();
+
+    let node1 = Addr::Ip("127.0.0.1:5008".parse().unwrap());
+    let node2 = Addr::Ip("127.0.0.1:5009".parse().unwrap());
+    let node3 = Addr::Ip("127.0.0.1:5010".parse().unwrap());
+
+    let members = btreeset! {
+        Member::new(node1.clone()),
+        Member::new(node2.clone()),
+    };
+
+    // With fewer members than nodes there is no assignment.
+    let sys_cfg = config(path1.as_ref(), path2.as_ref(), path3.as_ref());
+    assert_eq!(rendezvous_assign(sys_cfg.keys(), members.iter()), None);
+
+    let mut members = members;
+    let _ = members.insert(Member::new(node3.clone()));
+    let assignment = rendezvous_assign(sys_cfg.keys(), members.iter()).unwrap();
+    let _realizations = [node1, node2, node3]
+        .iter()
+        .map(|node| instantiate::<HDDlog>(sys_cfg.clone(), node, &assignment))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    await_expected(move || {
+        let mut string = String::new();
+        let _ = file3.seek(SeekFrom::Start(0)).unwrap();
+        let _ = file3.read_to_string(&mut string).unwrap();
+
+        assert_eq!(string, SERVER_API_3_P3OUT);
+    });
+
+    Ok(())
+}
+
+/// Bring up a configuration without any file sources and then add them
+/// to the running system by means of a `Reconciler`.
+#[test]
+fn reconcile_configuration_end_to_end() -> Result<(), String> {
+    const SERVER_API_1_P1IN: &'static [u8] = include_bytes!("../data/server_api_1_p1in.dat");
+    const SERVER_API_2_P2IN: &'static [u8] = include_bytes!("../data/server_api_2_p2in.dat");
+    const SERVER_API_3_P3OUT: &'static str =
+        include_str!("../data/server_api_3_p3out.dump.expected");
+
+    let mut file1 = NamedTempFile::new().unwrap();
+    file1.write_all(SERVER_API_1_P1IN).unwrap();
+    let path1 = file1.into_temp_path();
+
+    let mut file2 = NamedTempFile::new().unwrap();
+    file2.write_all(SERVER_API_2_P2IN).unwrap();
+    let path2 = file2.into_temp_path();
+
+    let (mut file3, path3) = NamedTempFile::new().unwrap().into_parts();
+
+    let node1 = Addr::Ip("127.0.0.1:5003".parse().unwrap());
+    let node2 = Addr::Ip("127.0.0.1:5004".parse().unwrap());
+    let node3 = Addr::Ip("127.0.0.1:5005".parse().unwrap());
+
+    let members = btreeset! {
+        Member::new(node1.clone()),
+        Member::new(node2.clone()),
+        Member::new(node3.clone()),
+    };
+
+    let sys_cfg = config(path1.as_ref(), path2.as_ref(), path3.as_ref());
+    let no_sources = sys_cfg
+        .iter()
+        .map(|(uuid, node_cfg)| {
+            let node_cfg = node_cfg
+                .iter()
+                .map(|(rel_id, rel_cfgs)| {
+                    let rel_cfgs = rel_cfgs
+                        .iter()
+                        .filter(|rel_cfg| match rel_cfg {
+                            RelCfg::Source(..) => false,
+                            _ => true,
+                        })
+                        .cloned()
+                        .collect();
+                    (*rel_id, rel_cfgs)
+                })
+                .collect();
+            (*uuid, node_cfg)
+        })
+        .collect::<SysCfg>();
+
+    let assignment = simple_assign(sys_cfg.keys(), members.iter()).unwrap();
+    let mut reconcilers = vec![
+        Reconciler::<HDDlog>::new(node1),
+        Reconciler::<HDDlog>::new(node2),
+        Reconciler::<HDDlog>::new(node3),
+    ];
+
+    reconcilers
+        .iter_mut()
+        .try_for_each(|reconciler| reconciler.reconcile(&no_sources, &assignment))?;
+
+    let uuid1 = sys_cfg.keys().next().unwrap();
+    let realization1 = reconcilers[0].realization(uuid1).unwrap();
+    assert!(!realization1.contains_file_source(path1.to_path_buf()));
+
+    reconcilers
+        .iter_mut()
+        .try_for_each(|reconciler| reconciler.reconcile(&sys_cfg, &assignment))?;
+
+    let realization1 = reconcilers[0].realization(uuid1).unwrap();
+    assert!(realization1.contains_file_source(path1.to_path_buf()));
+
+    await_expected(move || {
+        let mut string = String::new();
+        let _ = file3.seek(SeekFrom::Start(0)).unwrap();
+        let _ = file3.read_to_string(&mut string).unwrap();
+
+        assert_eq!(string, SERVER_API_3_P3OUT);
+    });
+
+    Ok(())
+}
+
+/// Assignments putting more than one node on a member are rejected by
+/// both `instantiate` and `Reconciler`, as the nodes would have to share
+/// the member's receiver.
+#[test]
+fn reject_multiple_nodes_per_member() {
+    let node1 = Addr::Ip("127.0.0.1:5006".parse().unwrap());
+    let node2 = Addr::Ip("127.0.0.1:5007".parse().unwrap());
+
+    let sys_cfg = config(
+        Path::new("input1.dat"),
+        Path::new("input2.dat"),
+        Path::new("output.dump"),
+    );
+    let mut uuids = sys_cfg.keys();
+    let assignment = btreemap! {
+        *uuids.next().unwrap() => node1.clone(),
+        *uuids.next().unwrap() => node2.clone(),
+        *uuids.next().unwrap() => node2.clone(),
+    };
+
+    assert!(instantiate::<HDDlog>(sys_cfg.clone(), &node2, &assignment).is_err());
+
+    let mut reconciler = Reconciler::<HDDlog>::new(node2);
+    assert!(reconciler.reconcile(&sys_cfg, &assignment).is_err());
+    assert!(sys_cfg
+        .keys()
+        .all(|uuid| reconciler.realization(uuid).is_none()));
+}
+
+/// Run a `Controller` realizing configurations by means of a
+/// `Reconciler` for every member listed in a configuration file. A
+/// configuration with more nodes than members is not realized until
+/// enough members join.
+#[test]
+fn control_configuration_end_to_end() -> Result<(), String> {
+    const SERVER_API_1_P1IN: &'static [u8] = include_bytes!("../data/server_api_1_p1in.dat");
+    const SERVER_API_2_P2IN: &'static [u8] = include_bytes!("../data/server_api_2_p2in.dat");
+    const SERVER_API_3_P3OUT: &'static str =
+        include_str!("../data/server_api_3_p3out.dump.expected");
+
+    let mut file1 = NamedTempFile::new().unwrap();
+    file1.write_all(SERVER_API_1_P1IN).unwrap();
+    let path1 = file1.into_temp_path();
+
+    let mut file2 = NamedTempFile::new().unwrap();
+    file2.write_all(SERVER_API_2_P2IN).unwrap();
+    let path2 = file2.into_temp_path();
+
+    let (mut file3, path3) = NamedTempFile::new().unwrap().into_parts();
+
+    let members = vec![
+        Member::new(Addr::Ip("127.0.0.1:5011".parse().unwrap())),
+        Member::new(Addr::Ip("127.0.0.1:5012".parse().unwrap())),
+        Member::new(Addr::Ip("127.0.0.1:5013".parse().unwrap())),
+    ];
+    let sys_cfg = config(path1.as_ref(), path2.as_ref(), path3.as_ref());
+    let config_file = NamedTempFile::new().unwrap().into_temp_path();
+    let write_config = |members: &[Member]| {
+        let contents = json!({ "members": members, "config": sys_cfg });
+        fs::write(&config_file, contents.to_string()).unwrap();
+    };
+
+    let controller = |member: &Member| {
+        let mut reconciler = Reconciler::<HDDlog>::new(member.addr().clone());
+        Controller::new(
+            ConfigFile::new(config_file.to_path_buf()),
+            member.clone(),
+            move |sys_cfg, assignment| reconciler.reconcile(sys_cfg, assignment),
+        )
+    };
+
+    write_config(&members[..2]);
+    let mut controllers = members[..2]
+        .iter()
+        .map(&controller)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(controllers
+        .iter_mut()
+        .all(|controller| controller.reconfigure().is_err()));
+
+    write_config(&members);
+    controllers.push(controller(&members[2])?);
+    controllers
+        .iter_mut()
+        .try_for_each(|controller| controller.reconfigure())?;
+
+    await_expected(move || {
+        let mut string = String::new();
+        let _ = file3.seek(SeekFrom::Start(0)).unwrap();
+        let _ = file3.read_to_string(&mut string).unwrap();
+
+        assert_eq!(string, SERVER_API_3_P3OUT);
+    });
+
+    Ok(())
+}
@@ -11,12 +11,14 @@ use distributed_datalog::await_expected;
 use distributed_datalog::DDlogServer as DDlogServerT;
 use distributed_datalog::Observable;
 use distributed_datalog::Observer;
+use distributed_datalog::SchemaHash;
 use distributed_datalog::SharedObserver;
 use distributed_datalog::TcpReceiver;
 use distributed_datalog::TcpSender;
 use distributed_datalog::TxnMux;
 use distributed_datalog::UpdatesObservable as UpdatesObservableT;
 
+use server_api_ddlog::DDlogConverter;
 use server_api_ddlog::UpdateSerializer;
 use server_api_ddlog::api::updcmd2upd;
 use server_api_ddlog::api::HDDlog;
@@ -105,8 +107,9 @@ fn single_delta_tcp() -> Result<(), String> {
         observable: &mut UpdatesObservable,
         observer: SharedObserver<DDlogServer>,
     ) -> Result<Box<dyn Any>, String> {
-        let mut recv = TcpReceiver::<Update<DDValue>, UpdateSerializer>::new("127.0.0.1:0").unwrap();
-        let send = TcpSender::<UpdateSerializer>::new(*recv.addr()).unwrap();
+        let schema = SchemaHash::of::<DDlogConverter>();
+        let mut recv = TcpReceiver::<Update<DDValue>, UpdateSerializer>::new("127.0.0.1:0", schema).unwrap();
+        let send = TcpSender::<UpdateSerializer>::new(*recv.addr(), schema).unwrap();
 
         let _ = recv.subscribe(Box::new(observer)).unwrap();
         let _ = observable.subscribe(Box::new(send)).unwrap();
@@ -237,11 +240,12 @@ fn multi_transaction_tcp() -> Result<(), String> {
         let mut mux = TxnMux::new();
         let _ = mux.subscribe(Box::new(observer)).unwrap();
 
-        let recv1 = TcpReceiver::<Update<DDValue>, UpdateSerializer>::new("127.0.0.1:0").unwrap();
-        let send1 = TcpSender::<UpdateSerializer>::new(*recv1.addr()).unwrap();
+        let schema = SchemaHash::of::<DDlogConverter>();
+        let recv1 = TcpReceiver::<Update<DDValue>, UpdateSerializer>::new("127.0.0.1:0", schema).unwrap();
+        let send1 = TcpSender::<UpdateSerializer>::new(*recv1.addr(), schema).unwrap();
 
-        let recv2 = TcpReceiver::<Update<DDValue>, UpdateSerializer>::new("127.0.0.1:0").unwrap();
-        let send2 = TcpSender::<UpdateSerializer>::new(*recv2.addr()).unwrap();
+        let recv2 = TcpReceiver::<Update<DDValue>, UpdateSerializer>::new("127.0.0.1:0", schema).unwrap();
+        let send2 = TcpSender::<UpdateSerializer>::new(*recv2.addr(), schema).unwrap();
 
         let _ = observable1.subscribe(Box::new(send1)).unwrap();
         let _ = observable2.subscribe(Box::new(send2)).unwrap();
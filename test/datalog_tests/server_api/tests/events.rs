@@ -12,6 +12,7 @@ use distributed_datalog::DDlogServer as DDlogServerT;
 use distributed_datalog::MockObserver as Mock;
 use distributed_datalog::Observable;
 use distributed_datalog::Observer;
+use distributed_datalog::SchemaHash;
 use distributed_datalog::SharedObserver;
 use distributed_datalog::TcpReceiver;
 use distributed_datalog::TcpSender;
@@ -19,6 +20,7 @@ use distributed_datalog::UpdatesObservable as UpdatesObservableT;
 
 use server_api_ddlog::api::updcmd2upd;
 use server_api_ddlog::api::HDDlog;
+use server_api_ddlog::DDlogConverter;
 use server_api_ddlog::UpdateSerializer;
 use server_api_ddlog::Relations::*;
 
@@ -293,8 +295,9 @@ fn setup_tcp() -> (DDlogServer, UpdatesObservable, MockObserver, Box<dyn Any>) {
     let observer = SharedObserver::new(Mutex::new(Mock::new()));
     let mut stream = server.add_stream(btreeset! {server_api_1_P1Out as usize});
 
-    let mut recv = TcpReceiver::<Update<DDValue>, UpdateSerializer>::new("127.0.0.1:0").unwrap();
-    let send = TcpSender::<UpdateSerializer>::new(*recv.addr()).unwrap();
+    let schema = SchemaHash::of::<DDlogConverter>();
+    let mut recv = TcpReceiver::<Update<DDValue>, UpdateSerializer>::new("127.0.0.1:0", schema).unwrap();
+    let send = TcpSender::<UpdateSerializer>::new(*recv.addr(), schema).unwrap();
 
     let _ = stream.subscribe(Box::new(send)).unwrap();
     let _ = recv.subscribe(Box::new(observer.clone())).unwrap();
@@ -40,16 +40,50 @@ mod tests {
         // Check that realization has tcp receiver.
         assert_eq!(realization.add_tcp_receiver(&addr), Ok(()));
 
-        let source_id = realization.get_tcp_receiver_id();
+        let source_id = realization.get_tcp_receiver_id(&addr);
         // Check that TxnMux is subscribed to tcp receiver.
         assert!(realization.txn_subscription_exists(source_id));
         // Check that Realization contains the tcp receiver.
-        assert!(realization.contains_tcp_receiver());
+        assert!(realization.contains_tcp_receiver(&addr));
 
         // Check that removing the tcp receiver succeeds.
-        assert_eq!(realization.remove_source(&Source::TcpReceiver), Ok(()));
+        assert_eq!(
+            realization.remove_source(&Source::Receiver(Addr::Ip(addr))),
+            Ok(())
+        );
         // Check that the realization no longer contains the tcp receiver.
-        assert!(!realization.contains_tcp_receiver());
+        assert!(!realization.contains_tcp_receiver(&addr));
+    }
+
+    #[test]
+    fn add_and_then_remove_multiple_tcp_receivers_from_realization() {
+        let mut realization = Realization::<HDDlog>::new();
+
+        let addr1 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 5011);
+        let addr2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 5012);
+        // Check that realization accepts tcp receivers on both addresses.
+        assert_eq!(realization.add_tcp_receiver(&addr1), Ok(()));
+        assert_eq!(
+            realization.add_source(&Source::Receiver(Addr::Ip(addr2))),
+            Ok(())
+        );
+
+        // Check that both are tracked individually.
+        let source_id1 = realization.get_tcp_receiver_id(&addr1);
+        let source_id2 = realization.get_tcp_receiver_id(&addr2);
+        assert_ne!(source_id1, source_id2);
+        assert!(realization.txn_subscription_exists(source_id1));
+        assert!(realization.txn_subscription_exists(source_id2));
+
+        // Check that removing one tcp receiver leaves the other in place.
+        assert_eq!(
+            realization.remove_source(&Source::Receiver(Addr::Ip(addr1))),
+            Ok(())
+        );
+        assert!(!realization.contains_tcp_receiver(&addr1));
+        assert!(!realization.txn_subscription_exists(source_id1));
+        assert!(realization.contains_tcp_receiver(&addr2));
+        assert!(realization.txn_subscription_exists(source_id2));
     }
 
     #[test]
@@ -64,7 +98,7 @@ mod tests {
         rel_ids.insert(3);
 
         let mut server = DDlogServer::new(None, HashMap::new());
-        let sink = Sink::TcpSender(Addr::Ip(addr));
+        let sink = Sink::Sender(Addr::Ip(addr));
 
         // Check that realization successfully adds tcp sender sink.
         assert_eq!(